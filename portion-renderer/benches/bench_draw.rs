@@ -43,6 +43,21 @@ fn from_elem(c: &mut Criterion) {
             p.draw(&pixels, *bounds);
         })
     });
+    group.bench_with_input(BenchmarkId::new("draw_solid_rect", "data_vec"), &"", |b, _| {
+        let mut p = PortionRenderer::<u8>::new_ex(
+            1000, 1000, 10, 10, PixelFormatEnum::RGBA8888
+        );
+        let red = p.create_object_from_color(
+            1, Rect { x: 0, y: 0, w: 500, h: 400 },
+            PIXEL_RED,
+        );
+        let mut by = 1;
+        b.iter(|| {
+            p.move_object_x_by(red, by);
+            by = -by;
+            p.draw_all_layers();
+        });
+    });
     group.bench_with_input(BenchmarkId::new("draw_tilted_rect", "data_vec"), &"", |b, _| {
         let mut p = PortionRenderer::<u8>::new_ex(
             1000, 1000, 10, 10, PixelFormatEnum::RGBA8888
@@ -57,6 +72,24 @@ fn from_elem(c: &mut Criterion) {
             p.force_draw_all_layers();
         });
     });
+    #[cfg(feature = "multithreaded")]
+    group.bench_with_input(BenchmarkId::new("draw_tilted_rect_parallel", "data_vec"), &"", |b, _| {
+        let mut p = PortionRenderer::<u8>::new_ex(
+            1000, 1000, 10, 10, PixelFormatEnum::RGBA8888
+        );
+        let red = p.create_object_from_color(
+            1, Rect { x: 0, y: 0, w: 500, h: 400 },
+            PIXEL_RED,
+        );
+        p.set_object_rotation(red, 45f32);
+        p.move_object_x_by(red, 200);
+        let mut by = 1;
+        b.iter(|| {
+            p.move_object_x_by(red, by);
+            by = -by;
+            p.draw_all_layers_parallel();
+        });
+    });
 }
 
 criterion_group!(benches, from_elem);
@@ -0,0 +1,31 @@
+use minifb::{Key, Window, WindowOptions};
+
+use portion_renderer::bounds::Rect;
+use portion_renderer::present_minifb::MinifbAdapter;
+use portion_renderer::{PortionRenderer, PIXEL_RED};
+
+const WIDTH: u32 = 640;
+const HEIGHT: u32 = 480;
+
+fn main() {
+    let mut window = Window::new(
+        "portion-renderer", WIDTH as usize, HEIGHT as usize,
+        WindowOptions::default(),
+    ).expect("Failed to open window");
+    window.set_target_fps(60);
+
+    let mut p = PortionRenderer::<u8>::new(WIDTH, HEIGHT);
+    let red = p.create_object_from_color(0, Rect { x: 0, y: 200, w: 40, h: 40 }, PIXEL_RED);
+    let mut adapter = MinifbAdapter::new(&p);
+    let mut x = 0;
+    let mut by = 4;
+    while window.is_open() && !window.is_key_down(Key::Escape) {
+        if x + by < 0 || x + by + 40 > WIDTH as i32 {
+            by = -by;
+        }
+        x += by;
+        p.move_object_x_by(red, by);
+        p.draw_all_layers();
+        adapter.update_window(&mut p, &mut window).expect("Failed to update window");
+    }
+}
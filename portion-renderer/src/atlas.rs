@@ -0,0 +1,178 @@
+use super::*;
+
+/// the transparent pixels between regions, so that sampling
+/// the edge of a region with a filter doesnt blend in its neighbor
+const PADDING: u32 = 1;
+
+/// packs many small textures into one, so that objects can share one
+/// texture and each draw their own region of it. eg:
+///
+/// ```
+/// # use portion_renderer::*;
+/// let mut p = PortionRenderer::<u8>::new(16, 16);
+/// let mut atlas = TextureAtlas::new(64, 64, &PixelFormatEnum::RGBA8888);
+/// let player = atlas.add(&[255; 8 * 8 * 4], 8, 8).unwrap();
+/// let coin = atlas.add(&[128; 4 * 4 * 4], 4, 4).unwrap();
+/// let texture = p.create_texture(atlas.into_texture());
+/// p.create_object_from_atlas(0, Rect { x: 0, y: 0, w: 8, h: 8 }, texture, player);
+/// p.create_object_from_atlas(0, Rect { x: 8, y: 0, w: 4, h: 4 }, texture, coin);
+/// ```
+pub struct TextureAtlas<T> {
+    texture: Texture<T>,
+    indices_per_pixel: u32,
+    /// rows of regions, filled from left to right
+    shelves: Vec<Shelf>,
+}
+
+struct Shelf {
+    y: u32,
+    height: u32,
+    /// where the next region on this shelf goes
+    x: u32,
+}
+
+impl<T: Default + Clone> TextureAtlas<T> {
+    /// an empty atlas of width x height pixels in the format
+    pub fn new(width: u32, height: u32, format: &PixelFormatEnum) -> TextureAtlas<T> {
+        let indices_per_pixel = format.indices_per_pixel();
+        let len = checked_buffer_len(width, height, indices_per_pixel)
+            .expect("Atlas dimensions overflow usize");
+        TextureAtlas {
+            texture: Texture::new(vec![T::default(); len], width, height),
+            indices_per_pixel,
+            shelves: vec![],
+        }
+    }
+}
+
+impl<T: Clone> TextureAtlas<T> {
+    /// copies the pixels of a width x height texture into the atlas, and
+    /// returns the region of the atlas that they are in, or None if
+    /// there is no room left. panics if pixels is not width x height pixels
+    pub fn add(&mut self, pixels: &[T], width: u32, height: u32) -> Option<Rect> {
+        let ipp = self.indices_per_pixel as usize;
+        let expected = width as usize * height as usize * ipp;
+        if pixels.len() != expected {
+            panic!("Cannot add a {}x{} texture to the atlas because it has {} values instead of {}", width, height, pixels.len(), expected);
+        }
+        let region = self.allocate(width, height)?;
+        let row_len = width as usize * ipp;
+        for (row, src) in pixels.chunks_exact(row_len.max(1)).enumerate() {
+            let start = red_index(region.x, region.y + row as u32, self.texture.width, self.indices_per_pixel);
+            self.texture.data[start..start + row_len].clone_from_slice(src);
+        }
+        Some(region)
+    }
+
+    /// the shortest shelf that the region fits on, or a new shelf below the others
+    fn allocate(&mut self, width: u32, height: u32) -> Option<Rect> {
+        let atlas_width = self.texture.width;
+        if width == 0 || height == 0 || width > atlas_width {
+            return None;
+        }
+        let shelf = self.shelves.iter_mut()
+            .filter(|shelf| height <= shelf.height && shelf.x + width <= atlas_width)
+            .min_by_key(|shelf| shelf.height);
+        let shelf = match shelf {
+            Some(shelf) => shelf,
+            None => {
+                let y = self.shelves.last().map(|s| s.y + s.height + PADDING).unwrap_or(0);
+                if y + height > self.texture.height {
+                    return None;
+                }
+                self.shelves.push(Shelf { y, height, x: 0 });
+                self.shelves.last_mut().unwrap()
+            }
+        };
+        let region = Rect { x: shelf.x, y: shelf.y, w: width, h: height };
+        shelf.x += width + PADDING;
+        Some(region)
+    }
+
+    pub fn texture(&self) -> &Texture<T> {
+        &self.texture
+    }
+
+    /// the packed texture, eg: for create_texture
+    pub fn into_texture(self) -> Texture<T> {
+        self.texture
+    }
+}
+
+/// the regions of a sprite sheet that is split into cells of
+/// cell_width x cell_height, from left to right and then top to
+/// bottom. partial cells at the right and bottom are left out
+pub fn grid_regions(width: u32, height: u32, cell_width: u32, cell_height: u32) -> Vec<Rect> {
+    if cell_width == 0 || cell_height == 0 {
+        return vec![];
+    }
+    let (columns, rows) = (width / cell_width, height / cell_height);
+    (0..rows).flat_map(|row| (0..columns).map(move |column| Rect {
+        x: column * cell_width,
+        y: row * cell_height,
+        w: cell_width,
+        h: cell_height,
+    })).collect()
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// creates an object that draws the region of a shared texture, eg: a
+    /// region from TextureAtlas::add, or a cell of a sprite sheet from grid_regions.
+    /// the region is stretched over the bounds, see set_object_source_rect
+    pub fn create_object_from_atlas(
+        &mut self, layer_index: u32, bounds: Rect, texture_index: usize, region: Rect,
+    ) -> usize {
+        let object_index = self.create_object_from_texture_index(layer_index, bounds, texture_index);
+        self.set_object_source_rect(object_index, Some(region));
+        object_index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regions_are_packed_without_overlapping() {
+        let mut atlas = TextureAtlas::<u8>::new(8, 8, &PixelFormatEnum::RGBA8888);
+        let red = atlas.add(&[255, 0, 0, 255].repeat(4 * 2), 4, 2).unwrap();
+        let green = atlas.add(&[0, 255, 0, 255].repeat(3), 3, 1).unwrap();
+        let blue = atlas.add(&[0, 0, 255, 255].repeat(8 * 3), 8, 3).unwrap();
+        assert_eq!(red, Rect { x: 0, y: 0, w: 4, h: 2 });
+        // fits next to red on the same shelf, after the padding
+        assert_eq!(green, Rect { x: 5, y: 0, w: 3, h: 1 });
+        assert_eq!(blue, Rect { x: 0, y: 3, w: 8, h: 3 });
+        assert_eq!(atlas.add(&[0; 4 * 4 * 4], 4, 4), None);
+
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let texture = p.create_texture(atlas.into_texture());
+        let objects = [
+            p.create_object_from_atlas(0, Rect { x: 4, y: 1, w: 4, h: 2 }, texture, red),
+            p.create_object_from_atlas(0, Rect { x: 0, y: 0, w: 3, h: 1 }, texture, green),
+            p.create_object_from_atlas(0, Rect { x: 0, y: 4, w: 8, h: 3 }, texture, blue),
+        ];
+        let sources: Vec<Option<Rect>> = objects.iter().map(|o| p.get_object(*o).unwrap().source).collect();
+        assert_eq!(sources, vec![Some(red), Some(green), Some(blue)]);
+        p.draw_all_layers();
+        // each object only shows its own region, and none of the padding
+        let assert_map = [
+            'g', 'g', 'g', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'r', 'r', 'r', 'r',
+            'x', 'x', 'x', 'x', 'r', 'r', 'r', 'r',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'b', 'b', 'b', 'b', 'b', 'b', 'b', 'b',
+            'b', 'b', 'b', 'b', 'b', 'b', 'b', 'b',
+            'b', 'b', 'b', 'b', 'b', 'b', 'b', 'b',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+        ];
+        testing::assert_pixels_in_map(&p, &assert_map, 8);
+    }
+
+    #[test]
+    fn grid_regions_skip_partial_cells() {
+        let regions = grid_regions(10, 4, 4, 2);
+        assert_eq!(regions.len(), 4);
+        assert_eq!(regions[1], Rect { x: 4, y: 0, w: 4, h: 2 });
+        assert_eq!(regions[3], Rect { x: 4, y: 2, w: 4, h: 2 });
+    }
+}
@@ -0,0 +1,99 @@
+use super::*;
+
+/// how drawing writes to the pixel buffer. the renderer decides which
+/// pixels of which objects get drawn where, and then writes them
+/// with these, so that eg: a SIMD version, or one that also writes to a
+/// staging buffer for a GPU upload, can be swapped in with set_backend.
+/// dest is always part of the pixel buffer, in the renderer's pixel
+/// format, and its length is a multiple of the indices per pixel.
+/// only set_pixel has to be implemented, the others default to the
+/// same as SoftwareBackend
+pub trait RenderBackend: Send + Sync {
+    /// writes pixel to the one pixel dest
+    fn set_pixel(&self, dest: &mut [u8], pixel: RgbaPixel);
+
+    /// writes pixel to every pixel of dest, eg: a row of a color object
+    fn fill_span(&self, dest: &mut [u8], pixel: RgbaPixel) {
+        fill_pixels(dest, pixel);
+    }
+
+    /// copies src to dest, which are the same length,
+    /// eg: an opaque row of a texture, or of the clear buffer
+    fn copy_row(&self, dest: &mut [u8], src: &[u8]) {
+        dest.copy_from_slice(src);
+    }
+
+    /// blends pixel into the one pixel dest,
+    /// where weight 1.0 fully replaces dest
+    fn blend(&self, dest: &mut [u8], pixel: RgbaPixel, weight: f32) {
+        blend_pixel_into(dest, pixel, weight);
+    }
+}
+
+/// writes the pixels with plain slice copies, which is the default
+#[derive(Debug, Default, Copy, Clone)]
+pub struct SoftwareBackend;
+
+impl RenderBackend for SoftwareBackend {
+    #[inline(always)]
+    fn set_pixel(&self, dest: &mut [u8], pixel: RgbaPixel) {
+        dest[0] = pixel.r;
+        dest[1] = pixel.g;
+        dest[2] = pixel.b;
+        dest[3] = pixel.a;
+    }
+}
+
+impl<T> PortionRenderer<T> {
+    /// what drawing writes pixels with from now on. the pixels that
+    /// were already drawn are left as they are
+    pub fn set_backend(&mut self, backend: impl RenderBackend + 'static) {
+        self.backend = Box::new(backend);
+    }
+
+    pub fn backend(&self) -> &dyn RenderBackend {
+        &*self.backend
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// counts the pixels it writes, and writes them like SoftwareBackend
+    struct CountingBackend(Arc<AtomicUsize>);
+
+    impl RenderBackend for CountingBackend {
+        fn set_pixel(&self, dest: &mut [u8], pixel: RgbaPixel) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            SoftwareBackend.set_pixel(dest, pixel);
+        }
+
+        fn fill_span(&self, dest: &mut [u8], pixel: RgbaPixel) {
+            self.0.fetch_add(dest.len() / 4, Ordering::Relaxed);
+            fill_pixels(dest, pixel);
+        }
+
+        fn copy_row(&self, dest: &mut [u8], src: &[u8]) {
+            self.0.fetch_add(dest.len() / 4, Ordering::Relaxed);
+            dest.copy_from_slice(src);
+        }
+    }
+
+    #[test]
+    fn drawing_writes_through_the_backend() {
+        let written = Arc::new(AtomicUsize::new(0));
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.set_backend(CountingBackend(written.clone()));
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 4, h: 2 }, PIXEL_RED);
+        let half_transparent = vec![255, 255, 255, 255, 0, 0, 0, 0];
+        p.create_object_from_texture(1, Rect { x: 4, y: 4, w: 2, h: 1 }, half_transparent, 2, 1);
+        p.draw_all_layers();
+        assert_eq!(written.load(Ordering::Relaxed), 8 + 1);
+        assert_eq!(RgbaPixel::from(&p[(3, 1)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(4, 4)]), PIXEL_WHITE);
+        assert_eq!(RgbaPixel::from(&p[(5, 4)]), PIXEL_BLANK);
+    }
+}
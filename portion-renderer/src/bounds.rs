@@ -1,9 +1,13 @@
+use std::borrow::Cow;
 use std::cmp;
 use super::Matrix;
 
 pub static EMPTY_RECT: Rect = Rect { x: 0, y: 0, w: 0, h: 0 };
+pub static EMPTY_OFFSET_RECT: OffsetRect = OffsetRect { x: 0, y: 0, w: 0, h: 0 };
 
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[repr(C)]
 pub struct Rect {
     pub x: u32,
     pub y: u32,
@@ -11,13 +15,26 @@ pub struct Rect {
     pub h: u32,
 }
 
+/// like a Rect, but the position can be negative, ie:
+/// it can be partially or completely off of the top/left of the screen
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct OffsetRect {
+    pub x: i32,
+    pub y: i32,
+    pub w: u32,
+    pub h: u32,
+}
+
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Point {
     pub x: f32,
     pub y: f32,
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Vector {
     pub x: f32,
     pub y: f32,
@@ -28,6 +45,7 @@ pub struct Vector {
 /// able to draw a direct line between A and C, but
 /// rather you have to cross B first.
 #[derive(Copy, Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TiltedRect {
     pub ax: f32,
     pub ay: f32,
@@ -42,9 +60,50 @@ pub struct TiltedRect {
     pub bounding_rect: Rect,
 }
 
+/// a circle shape, mostly useful for round sprites so that
+/// the corners of their rectangular bounds are not considered
+/// part of the object.
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CircleBounds {
+    pub cx: f32,
+    pub cy: f32,
+    pub radius: f32,
+    pub bounding_rect: Rect,
+}
+
+/// a convex polygon, eg: for hex tiles. the points can
+/// be in either clockwise or counter clockwise order, but the
+/// polygon must be convex for contains/spans to be correct.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PolygonBounds {
+    pub points: Vec<Point>,
+    pub bounding_rect: Rect,
+}
+
+/// the shape of an object. This is an enum rather than a
+/// `Box<dyn ...>` because Intersects::intersection is generic,
+/// and so the bounds traits are not object safe.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Bounds {
+    Rect(Rect),
+    Tilted(TiltedRect),
+    Circle(CircleBounds),
+    Polygon(PolygonBounds),
+}
+
 pub trait Contains {
     fn contains(&self, x: f32, y: f32) -> bool;
     fn contains_u32(&self, x: u32, y: u32) -> bool;
+
+    /// how much of the pixel centered at (x, y) is inside
+    /// of the shape, from 0.0 to 1.0. by default a pixel
+    /// is either entirely inside or entirely outside.
+    fn coverage(&self, x: f32, y: f32) -> f32 {
+        if self.contains(x, y) { 1.0 } else { 0.0 }
+    }
 }
 
 pub trait GetRectangularBounds {
@@ -75,6 +134,65 @@ pub fn should_skip_point(skip_regions: &Vec<Rect>, x: u32, y: u32) -> bool {
     false
 }
 
+/// true if any of the regions contain a pixel of row y between [x_start, x_end)
+pub fn row_intersects_any(regions: &[Rect], y: u32, x_start: u32, x_end: u32) -> bool {
+    regions.iter().any(|rect| {
+        y >= rect.y && y < rect.y + rect.h &&
+            rect.x < x_end && x_start < rect.x + rect.w
+    })
+}
+
+/// the regions that need to be skipped while drawing within some bounds,
+/// sorted by x so that the visible spans of a row can be found
+/// with a single pass instead of checking every pixel
+#[derive(Debug, Clone, Default)]
+pub struct RowClipper {
+    regions: Vec<Rect>,
+}
+
+impl RowClipper {
+    /// only keeps the regions that intersect [min_x, max_x) x [min_y, max_y)
+    pub fn new(regions: &[Rect], min_x: u32, max_x: u32, min_y: u32, max_y: u32) -> RowClipper {
+        let mut regions: Vec<Rect> = regions.iter().filter(|r| {
+            r.x < max_x && min_x < r.x + r.w &&
+                r.y < max_y && min_y < r.y + r.h
+        }).copied().collect();
+        regions.sort_by_key(|r| r.x);
+        RowClipper { regions }
+    }
+
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty()
+    }
+
+    /// fills out with the disjoint [start, end) spans of row y
+    /// between [x_start, x_end) that are not covered by any region
+    pub fn visible_spans(&self, y: u32, x_start: u32, x_end: u32, out: &mut Vec<(u32, u32)>) {
+        out.clear();
+        let mut cursor = x_start;
+        for r in self.regions.iter() {
+            if r.x >= x_end {
+                break;
+            }
+            if y < r.y || y >= r.y + r.h {
+                continue;
+            }
+            let covered_end = (r.x + r.w).min(x_end);
+            if covered_end <= cursor {
+                continue;
+            }
+            if r.x > cursor {
+                out.push((cursor, r.x));
+            }
+            cursor = covered_end;
+        }
+        if cursor < x_end {
+            out.push((cursor, x_end));
+        }
+    }
+}
+
 pub fn sorted_values(a: &Point, b: &Point, c: &Point) -> [[f32; 3]; 2] {
     let mut x = [a.x, b.x, c.x];
     let mut y = [a.y, b.y, c.y];
@@ -113,6 +231,599 @@ pub fn get_largest_y(points: &[&Point; 4]) -> f32 {
     if y_01 > y_23 { y_01 } else { y_23 }
 }
 
+/// the smallest rectangle that contains all 4 points.
+/// parts that are at negative coordinates are cut off.
+pub fn outer_bounds_of(points: &[&Point; 4]) -> Rect {
+    let [x_min, x_max] = [
+        get_smallest_x(points),
+        get_largest_x(points),
+    ];
+    let [y_min, y_max] = [
+        get_smallest_y(points),
+        get_largest_y(points),
+    ];
+    if x_max < 0.0 || y_max < 0.0 {
+        return EMPTY_RECT;
+    }
+
+    // casting a negative float to u32 gives 0
+    let [
+        x_min, x_max,
+        y_min, y_max
+    ] = [
+        x_min as u32, x_max as u32,
+        y_min as u32, y_max as u32,
+    ];
+    Rect { x: x_min, y: y_min, w: x_max - x_min + 1, h: y_max - y_min + 1 }
+}
+
+#[inline(always)]
+fn lerp_at_x(a: &Point, b: &Point, x: f32) -> Point {
+    let t = (x - a.x) / (b.x - a.x);
+    Point { x, y: a.y + (b.y - a.y) * t }
+}
+
+#[inline(always)]
+fn lerp_at_y(a: &Point, b: &Point, y: f32) -> Point {
+    let t = (y - a.y) / (b.y - a.y);
+    Point { x: a.x + (b.x - a.x) * t, y }
+}
+
+/// one step of the sutherland-hodgman algorithm:
+/// `inside` returns a value >= 0 for points that should be kept,
+/// and `cut` returns the point where an edge crosses the clipping line
+fn clip_polygon<I, C>(polygon: &[Point], inside: I, cut: C) -> Vec<Point>
+    where I: Fn(&Point) -> f32,
+          C: Fn(&Point, &Point) -> Point,
+{
+    let mut out = vec![];
+    if polygon.is_empty() {
+        return out;
+    }
+    let mut previous = &polygon[polygon.len() - 1];
+    for current in polygon.iter() {
+        let current_inside = inside(current) >= 0.0;
+        let previous_inside = inside(previous) >= 0.0;
+        if current_inside {
+            if !previous_inside {
+                out.push(cut(previous, current));
+            }
+            out.push(*current);
+        } else if previous_inside {
+            out.push(cut(previous, current));
+        }
+        previous = current;
+    }
+    out
+}
+
+/// the range of x values on row y whose pixels are inside
+/// of the convex polygon, as [x_start, x_end).
+/// if no pixels on this row are inside, then x_start == x_end
+pub fn polygon_spans(polygon: &[Point], y: u32) -> (u32, u32) {
+    // small tolerance so that pixels exactly on an
+    // edge are considered inside, same as contains()
+    const EPSILON: f32 = 0.0001;
+    let y = y as f32;
+    let mut min_x = f32::MAX;
+    let mut max_x = f32::MIN;
+    for i in 0..polygon.len() {
+        let p1 = &polygon[i];
+        let p2 = &polygon[(i + 1) % polygon.len()];
+        let (top, bottom) = if p1.y < p2.y { (p1, p2) } else { (p2, p1) };
+        if y < top.y - EPSILON || y > bottom.y + EPSILON {
+            continue;
+        }
+        let (x1, x2) = if bottom.y - top.y < EPSILON {
+            // horizontal edge, the whole edge is on this row
+            (top.x, bottom.x)
+        } else {
+            let x = lerp_at_y(top, bottom, y).x;
+            (x, x)
+        };
+        min_x = min_x.min(x1).min(x2);
+        max_x = max_x.max(x1).max(x2);
+    }
+
+    if min_x > max_x {
+        return (0, 0);
+    }
+    let start = (min_x - EPSILON).ceil().max(0.0);
+    let end = (max_x + EPSILON).floor() + 1.0;
+    if end <= start {
+        return (0, 0);
+    }
+    (start as u32, end as u32)
+}
+
+/// the fraction of the 1x1 pixel square centered at (x, y)
+/// that is inside of the convex polygon, in either winding order
+pub fn polygon_coverage(polygon: &[Point], x: f32, y: f32) -> f32 {
+    if polygon.len() < 3 {
+        return 0.0;
+    }
+    // twice the signed area tells us the winding order, so that
+    // inside is always a positive value below
+    let mut doubled_area = 0.0;
+    for i in 0..polygon.len() {
+        let a = &polygon[i];
+        let b = &polygon[(i + 1) % polygon.len()];
+        doubled_area += a.x * b.y - b.x * a.y;
+    }
+    let winding = if doubled_area < 0.0 { -1.0 } else { 1.0 };
+
+    let pixel = [
+        Point { x: x - 0.5, y: y - 0.5 },
+        Point { x: x + 0.5, y: y - 0.5 },
+        Point { x: x + 0.5, y: y + 0.5 },
+        Point { x: x - 0.5, y: y + 0.5 },
+    ];
+    let mut clipped = pixel.to_vec();
+    let mut fully_inside = true;
+    for i in 0..polygon.len() {
+        let a = polygon[i];
+        let b = polygon[(i + 1) % polygon.len()];
+        let inside = |p: &Point| winding * ((b.x - a.x) * (p.y - a.y) - (b.y - a.y) * (p.x - a.x));
+        if pixel.iter().all(|p| inside(p) >= 0.0) {
+            continue;
+        }
+        fully_inside = false;
+        clipped = clip_polygon(&clipped, inside, |p1, p2| {
+            let (i1, i2) = (inside(p1), inside(p2));
+            let t = i1 / (i1 - i2);
+            Point { x: p1.x + (p2.x - p1.x) * t, y: p1.y + (p2.y - p1.y) * t }
+        });
+        if clipped.is_empty() {
+            return 0.0;
+        }
+    }
+    if fully_inside {
+        return 1.0;
+    }
+
+    let mut doubled_area = 0.0;
+    for i in 0..clipped.len() {
+        let a = &clipped[i];
+        let b = &clipped[(i + 1) % clipped.len()];
+        doubled_area += a.x * b.y - b.x * a.y;
+    }
+    (doubled_area.abs() / 2.0).min(1.0)
+}
+
+/// returns the part of the convex polygon that is inside
+/// of the rectangle. the polygon is empty if they dont overlap
+pub fn clip_polygon_to_rect(polygon: &[Point], r: &Rect) -> Vec<Point> {
+    if r.w == 0 || r.h == 0 {
+        return vec![];
+    }
+    let x0 = r.x as f32;
+    let y0 = r.y as f32;
+    let x1 = (r.x + r.w - 1) as f32;
+    let y1 = (r.y + r.h - 1) as f32;
+    let polygon = clip_polygon(polygon, |p| p.x - x0, |a, b| lerp_at_x(a, b, x0));
+    let polygon = clip_polygon(&polygon, |p| x1 - p.x, |a, b| lerp_at_x(a, b, x1));
+    let polygon = clip_polygon(&polygon, |p| p.y - y0, |a, b| lerp_at_y(a, b, y0));
+    clip_polygon(&polygon, |p| y1 - p.y, |a, b| lerp_at_y(a, b, y1))
+}
+
+/// the smallest rectangle that contains every point of an
+/// already clipped polygon, limited to `outer`
+fn tight_bounds_of(polygon: &[Point], outer: &Rect) -> Option<Rect> {
+    if polygon.is_empty() {
+        return None;
+    }
+    let mut min_x = f32::MAX;
+    let mut max_x = f32::MIN;
+    let mut min_y = f32::MAX;
+    let mut max_y = f32::MIN;
+    for p in polygon.iter() {
+        if p.x < min_x { min_x = p.x; }
+        if p.x > max_x { max_x = p.x; }
+        if p.y < min_y { min_y = p.y; }
+        if p.y > max_y { max_y = p.y; }
+    }
+    // round outwards the same way that the bounding_rect does
+    // so that we never report a smaller region than what gets drawn
+    let tight = Rect {
+        x: min_x as u32,
+        y: min_y as u32,
+        w: max_x as u32 - min_x as u32 + 1,
+        h: max_y as u32 - min_y as u32 + 1,
+    };
+    tight.intersection(*outer)
+}
+
+impl Rect {
+    #[inline(always)]
+    pub fn is_empty(&self) -> bool {
+        self.w == 0 || self.h == 0
+    }
+
+    #[inline(always)]
+    pub fn area(&self) -> u64 {
+        self.w as u64 * self.h as u64
+    }
+
+    /// the smallest rectangle that contains both rectangles.
+    /// empty rectangles are ignored
+    pub fn union(&self, other: &Rect) -> Rect {
+        if other.is_empty() {
+            return *self;
+        }
+        if self.is_empty() {
+            return *other;
+        }
+        let x1 = cmp::min(self.x, other.x);
+        let y1 = cmp::min(self.y, other.y);
+        let x2 = cmp::max(self.x + self.w, other.x + other.w);
+        let y2 = cmp::max(self.y + self.h, other.y + other.h);
+        Rect { x: x1, y: y1, w: x2 - x1, h: y2 - y1 }
+    }
+
+    /// moves the rectangle by (dx, dy). any part that
+    /// would end up at a negative coordinate gets cut off
+    pub fn translate(&self, dx: i32, dy: i32) -> Rect {
+        let moved = OffsetRect {
+            x: self.x as i32 + dx,
+            y: self.y as i32 + dy,
+            w: self.w,
+            h: self.h,
+        };
+        moved.visible_bounds()
+    }
+
+    /// grows the rectangle by `by` pixels on every side.
+    /// the top/left side stops growing at 0
+    pub fn inflate(&self, by: u32) -> Rect {
+        let x = self.x.saturating_sub(by);
+        let y = self.y.saturating_sub(by);
+        Rect {
+            x, y,
+            w: self.x + self.w + by - x,
+            h: self.y + self.h + by - y,
+        }
+    }
+
+    /// returns the part of this rectangle that is inside of bounds.
+    /// if they dont overlap, the returned rectangle is empty
+    pub fn clamp_to(&self, bounds: &Rect) -> Rect {
+        match self.intersection(*bounds) {
+            Some(r) => r,
+            None => Rect {
+                x: cmp::min(cmp::max(self.x, bounds.x), bounds.x + bounds.w),
+                y: cmp::min(cmp::max(self.y, bounds.y), bounds.y + bounds.h),
+                w: 0,
+                h: 0,
+            },
+        }
+    }
+
+    /// the parts of this rectangle that are not inside of other,
+    /// as at most 4 disjoint rectangles: the full width strips
+    /// above and below other, then the parts left and right of it
+    pub fn subtract(&self, other: &Rect) -> Vec<Rect> {
+        let overlap = match self.intersection(*other) {
+            Some(overlap) => overlap,
+            None => return if self.is_empty() { vec![] } else { vec![*self] },
+        };
+        let mut out = Vec::with_capacity(4);
+        if overlap.y > self.y {
+            out.push(Rect { x: self.x, y: self.y, w: self.w, h: overlap.y - self.y });
+        }
+        let self_bottom = self.y + self.h;
+        let overlap_bottom = overlap.y + overlap.h;
+        if self_bottom > overlap_bottom {
+            out.push(Rect { x: self.x, y: overlap_bottom, w: self.w, h: self_bottom - overlap_bottom });
+        }
+        if overlap.x > self.x {
+            out.push(Rect { x: self.x, y: overlap.y, w: overlap.x - self.x, h: overlap.h });
+        }
+        let self_right = self.x + self.w;
+        let overlap_right = overlap.x + overlap.w;
+        if self_right > overlap_right {
+            out.push(Rect { x: overlap_right, y: overlap.y, w: self_right - overlap_right, h: overlap.h });
+        }
+        out
+    }
+
+    /// splits the rectangle into a grid of cols x rows rectangles,
+    /// returned row by row. if the rectangle does not divide evenly
+    /// the last column/row gets the remainder.
+    pub fn split(&self, cols: u32, rows: u32) -> Vec<Rect> {
+        let cols = cmp::max(1, cmp::min(cols, self.w));
+        let rows = cmp::max(1, cmp::min(rows, self.h));
+        let col_w = self.w / cols;
+        let row_h = self.h / rows;
+        let mut out = Vec::with_capacity((cols * rows) as usize);
+        for j in 0..rows {
+            let y = self.y + j * row_h;
+            let h = if j == rows - 1 { self.y + self.h - y } else { row_h };
+            for i in 0..cols {
+                let x = self.x + i * col_w;
+                let w = if i == cols - 1 { self.x + self.w - x } else { col_w };
+                out.push(Rect { x, y, w, h });
+            }
+        }
+        out
+    }
+}
+
+impl CircleBounds {
+    pub fn new(cx: f32, cy: f32, radius: f32) -> CircleBounds {
+        let mut c = CircleBounds { cx, cy, radius, bounding_rect: EMPTY_RECT };
+        c.bounding_rect = c.outer_bounds();
+        c
+    }
+
+    /// the largest circle that fits inside of the rect
+    pub fn from_rect(r: Rect) -> CircleBounds {
+        let size = cmp::min(r.w, r.h) as f32;
+        CircleBounds::new(
+            r.x as f32 + (r.w as f32 - 1.0) / 2.0,
+            r.y as f32 + (r.h as f32 - 1.0) / 2.0,
+            size / 2.0,
+        )
+    }
+
+    /// the range of x values on row y whose pixels
+    /// are inside of the circle, as [x_start, x_end).
+    /// if no pixels on this row are inside, then x_start == x_end
+    pub fn spans(&self, y: u32) -> (u32, u32) {
+        let dy = y as f32 - self.cy;
+        let remaining = self.radius * self.radius - dy * dy;
+        if remaining < 0.0 {
+            return (0, 0);
+        }
+        let dx = remaining.sqrt();
+        let start = (self.cx - dx).ceil().max(0.0);
+        let end = (self.cx + dx).floor() + 1.0;
+        if end <= start {
+            return (0, 0);
+        }
+        (start as u32, end as u32)
+    }
+
+    #[inline(always)]
+    pub fn shift_bounds_x(&mut self, by: i32) {
+        self.cx += by as f32;
+        self.bounding_rect = self.outer_bounds();
+    }
+
+    #[inline(always)]
+    pub fn shift_bounds_y(&mut self, by: i32) {
+        self.cy += by as f32;
+        self.bounding_rect = self.outer_bounds();
+    }
+
+    /// the rectangular bounds of the on screen part of the circle
+    pub fn outer_bounds(&self) -> Rect {
+        let max_x = (self.cx + self.radius).floor();
+        let max_y = (self.cy + self.radius).floor();
+        if max_x < 0.0 || max_y < 0.0 || self.radius < 0.0 {
+            return EMPTY_RECT;
+        }
+        let min_x = (self.cx - self.radius).ceil().max(0.0) as u32;
+        let min_y = (self.cy - self.radius).ceil().max(0.0) as u32;
+        Rect {
+            x: min_x,
+            y: min_y,
+            w: max_x as u32 + 1 - min_x,
+            h: max_y as u32 + 1 - min_y,
+        }
+    }
+}
+
+impl PolygonBounds {
+    pub fn new(points: Vec<Point>) -> PolygonBounds {
+        let mut p = PolygonBounds { points, bounding_rect: EMPTY_RECT };
+        p.bounding_rect = p.outer_bounds();
+        p
+    }
+
+    /// a polygon with `sides` equal sides whose corners are all
+    /// `radius` away from the center. the first corner is directly
+    /// to the right of the center, so 6 sides gives a pointy-side hexagon
+    pub fn regular(cx: f32, cy: f32, radius: f32, sides: u32) -> PolygonBounds {
+        let step = 2.0 * std::f32::consts::PI / sides as f32;
+        let points = (0..sides).map(|i| {
+            let angle = step * i as f32;
+            Point { x: cx + radius * angle.cos(), y: cy + radius * angle.sin() }
+        }).collect();
+        PolygonBounds::new(points)
+    }
+
+    /// the range of x values on row y whose pixels
+    /// are inside of the polygon, as [x_start, x_end).
+    /// if no pixels on this row are inside, then x_start == x_end
+    #[inline(always)]
+    pub fn spans(&self, y: u32) -> (u32, u32) {
+        polygon_spans(&self.points, y)
+    }
+
+    /// returns the part of this polygon that is inside
+    /// the rectangle. the polygon is empty if they dont overlap
+    #[inline(always)]
+    pub fn clip_to_rect(&self, r: &Rect) -> Vec<Point> {
+        clip_polygon_to_rect(&self.points, r)
+    }
+
+    pub fn shift_bounds_x(&mut self, by: i32) {
+        for p in self.points.iter_mut() {
+            p.x += by as f32;
+        }
+        self.bounding_rect = self.outer_bounds();
+    }
+
+    pub fn shift_bounds_y(&mut self, by: i32) {
+        for p in self.points.iter_mut() {
+            p.y += by as f32;
+        }
+        self.bounding_rect = self.outer_bounds();
+    }
+
+    /// the rectangular bounds of the on screen part of the polygon
+    pub fn outer_bounds(&self) -> Rect {
+        if self.points.is_empty() {
+            return EMPTY_RECT;
+        }
+        let mut min_x = f32::MAX;
+        let mut max_x = f32::MIN;
+        let mut min_y = f32::MAX;
+        let mut max_y = f32::MIN;
+        for p in self.points.iter() {
+            if p.x < min_x { min_x = p.x; }
+            if p.x > max_x { max_x = p.x; }
+            if p.y < min_y { min_y = p.y; }
+            if p.y > max_y { max_y = p.y; }
+        }
+        if max_x < 0.0 || max_y < 0.0 {
+            return EMPTY_RECT;
+        }
+        // casting a negative float to u32 gives 0
+        let [min_x, max_x, min_y, max_y] = [
+            min_x as u32, max_x as u32,
+            min_y as u32, max_y as u32,
+        ];
+        Rect { x: min_x, y: min_y, w: max_x - min_x + 1, h: max_y - min_y + 1 }
+    }
+}
+
+impl Bounds {
+    /// the range of x values on row y whose pixels
+    /// are inside of the shape, as [x_start, x_end).
+    /// if no pixels on this row are inside, then x_start == x_end
+    #[inline(always)]
+    pub fn spans(&self, y: u32) -> (u32, u32) {
+        match self {
+            Bounds::Rect(r) => if y >= r.y && y < r.y + r.h {
+                (r.x, r.x + r.w)
+            } else { (0, 0) },
+            Bounds::Tilted(t) => t.spans(y),
+            Bounds::Circle(c) => c.spans(y),
+            Bounds::Polygon(p) => p.spans(y),
+        }
+    }
+
+    /// true if this shape covers its entire rectangular bounds
+    #[inline(always)]
+    pub fn is_rect(&self) -> bool {
+        matches!(self, Bounds::Rect(_))
+    }
+
+    /// the shape of the pixels that drawing this shape can write to.
+    /// nearest neighbor sampling rounds, so pixels up to half a pixel
+    /// outside of a rotated rect can still sample from the texture
+    pub fn footprint(&self) -> Cow<'_, Bounds> {
+        match self {
+            Bounds::Tilted(t) => Cow::Owned(Bounds::Tilted(t.grown(0.5))),
+            shape => Cow::Borrowed(shape),
+        }
+    }
+
+    /// the parts of within that this shape covers. a rectangle gives
+    /// at most one rect, other shapes give one rect per row so that
+    /// whatever is underneath their corners is not considered covered
+    pub fn covered_rects(&self, within: &Rect) -> Vec<Rect> {
+        let overlap = match self.intersection(*within) {
+            Some(overlap) => overlap,
+            None => return vec![],
+        };
+        if self.is_rect() {
+            return vec![overlap];
+        }
+        let mut out = vec![];
+        for y in overlap.y..overlap.y + overlap.h {
+            let (x_start, x_end) = self.spans(y);
+            let x_start = x_start.max(overlap.x);
+            let x_end = x_end.min(overlap.x + overlap.w);
+            if x_start < x_end {
+                out.push(Rect { x: x_start, y, w: x_end - x_start, h: 1 });
+            }
+        }
+        out
+    }
+
+    /// shifts every non rectangular shape. rectangles are not shifted
+    /// because they cannot represent a negative position. instead
+    /// the owner should replace the rect with its new visible bounds
+    pub fn shift_bounds_x(&mut self, by: i32) {
+        match self {
+            Bounds::Rect(_) => {}
+            Bounds::Tilted(t) => t.shift_bounds_x(by),
+            Bounds::Circle(c) => c.shift_bounds_x(by),
+            Bounds::Polygon(p) => p.shift_bounds_x(by),
+        }
+    }
+
+    /// see shift_bounds_x
+    pub fn shift_bounds_y(&mut self, by: i32) {
+        match self {
+            Bounds::Rect(_) => {}
+            Bounds::Tilted(t) => t.shift_bounds_y(by),
+            Bounds::Circle(c) => c.shift_bounds_y(by),
+            Bounds::Polygon(p) => p.shift_bounds_y(by),
+        }
+    }
+
+    /// applies the matrix to this shape, where the matrix operates on
+    /// coordinates that are relative to the top left of `position`.
+    /// rectangles and tilted rectangles are treated as being
+    /// the entire position, so transforming them always gives a TiltedRect
+    pub fn transformed(&self, position: OffsetRect, matrix: &Matrix) -> Bounds {
+        let (px, py) = (position.x as f32, position.y as f32);
+        let transform_point = |p: &Point| {
+            let (x, y) = matrix.mul_point(p.x - px, p.y - py);
+            Point { x: x + px, y: y + py }
+        };
+        match self {
+            Bounds::Rect(_) | Bounds::Tilted(_) => Bounds::Tilted(
+                TiltedRect::from_offset_bounds_and_matrix(position, *matrix)
+            ),
+            Bounds::Circle(c) => {
+                let center = transform_point(&Point { x: c.cx, y: c.cy });
+                // the radius grows with however much the matrix scales
+                let (ox, oy) = matrix.mul_point(0.0, 0.0);
+                let (ux, uy) = matrix.mul_point(1.0, 0.0);
+                let scale = ((ux - ox) * (ux - ox) + (uy - oy) * (uy - oy)).sqrt();
+                Bounds::Circle(CircleBounds::new(center.x, center.y, c.radius * scale))
+            }
+            Bounds::Polygon(p) => Bounds::Polygon(PolygonBounds::new(
+                p.points.iter().map(transform_point).collect()
+            )),
+        }
+    }
+}
+
+impl OffsetRect {
+    /// returns the part of this rect that has non-negative coordinates.
+    /// if all of it is off screen, then the returned rect has no area
+    pub fn visible_bounds(&self) -> Rect {
+        let (clip_x, clip_y) = self.clipped_by();
+        let x = if self.x < 0 { 0 } else { self.x as u32 };
+        let y = if self.y < 0 { 0 } else { self.y as u32 };
+        Rect {
+            x, y,
+            w: self.w.saturating_sub(clip_x),
+            h: self.h.saturating_sub(clip_y),
+        }
+    }
+
+    /// returns how many columns and rows of this rect
+    /// are cut off by the left and top edges
+    #[inline(always)]
+    pub fn clipped_by(&self) -> (u32, u32) {
+        let clip_x = if self.x < 0 { (0 - self.x as i64) as u32 } else { 0 };
+        let clip_y = if self.y < 0 { (0 - self.y as i64) as u32 } else { 0 };
+        (clip_x, clip_y)
+    }
+}
+
+impl From<Rect> for OffsetRect {
+    fn from(r: Rect) -> Self {
+        OffsetRect { x: r.x as i32, y: r.y as i32, w: r.w, h: r.h }
+    }
+}
+
 impl Point {
     #[inline(always)]
     pub fn transform_by(&mut self, matrix: &Matrix) {
@@ -126,53 +837,163 @@ impl TiltedRect {
     /// given an original, non-rotated rectangle. create a tilted rect bounds
     /// via the original bounds and the desired transformation matrix.
     pub fn from_bounds_and_matrix(bounds: Rect, matrix: Matrix) -> TiltedRect {
+        TiltedRect::from_offset_bounds_and_matrix(bounds.into(), matrix)
+    }
+
+    /// same as from_bounds_and_matrix, but the original bounds can be off screen.
+    /// the corners of a tilted rect are the centers of its corner pixels, so
+    /// the outer edges of the pixels are transformed, and then moved back in
+    /// by half a pixel. that way a matrix that scales covers as many whole
+    /// pixels as it should, rather than scaling the distance between pixel centers
+    pub fn from_offset_bounds_and_matrix(bounds: OffsetRect, matrix: Matrix) -> TiltedRect {
         let x = bounds.x as f32;
         let y = bounds.y as f32;
-        let max_x = bounds.w as f32 - 1.0;
-        let max_y = bounds.h as f32 - 1.0;
-        let mut a = Point { x: 0.0, y: 0.0 };
-        let mut b = Point { x: max_x, y: 0.0 };
+        let max_x = bounds.w as f32 - 0.5;
+        let max_y = bounds.h as f32 - 0.5;
+        let mut a = Point { x: -0.5, y: -0.5 };
+        let mut b = Point { x: max_x, y: -0.5 };
         let mut c = Point { x: max_x, y: max_y };
-        let mut d = Point { x: 0.0, y: max_y };
+        let mut d = Point { x: -0.5, y: max_y };
         a.transform_by(&matrix);
         b.transform_by(&matrix);
         c.transform_by(&matrix);
         d.transform_by(&matrix);
-        a.x += x;
-        a.y += y;
-        b.x += x;
-        b.y += y;
-        c.x += x;
-        c.y += y;
-        d.x += x;
-        d.y += y;
-        // println!("A: {:?}", a);
-        // println!("B: {:?}", b);
-        // println!("C: {:?}", c);
-        // println!("D: {:?}", d);
+        // half a pixel along each side, or to the middle
+        // of the side if it is less than a pixel long
+        let inward = |from: &Point, to: &Point| {
+            let (dx, dy) = (to.x - from.x, to.y - from.y);
+            let len = (dx * dx + dy * dy).sqrt();
+            if len == 0.0 {
+                return (0.0, 0.0);
+            }
+            let by = 0.5f32.min(len / 2.0) / len;
+            (dx * by, dy * by)
+        };
+        let (ux, uy) = inward(&a, &b);
+        let (vx, vy) = inward(&a, &d);
+        // the rounding errors of eg: a 90 degree rotation would
+        // otherwise make the edges of the rect partially cover pixels
+        let snap = |v: f32| {
+            let half = (v * 2.0).round() / 2.0;
+            if (v - half).abs() < 0.001 { half } else { v }
+        };
+        let corner = |p: &Point, dx: f32, dy: f32| Point { x: snap(p.x + dx) + x, y: snap(p.y + dy) + y };
+        let a = corner(&a, ux + vx, uy + vy);
+        let b = corner(&b, vx - ux, vy - uy);
+        let c = corner(&c, -ux - vx, -uy - vy);
+        let d = corner(&d, ux - vx, uy - vy);
         TiltedRect::from_points4(a, b, c, d)
     }
 
     #[inline(always)]
     pub fn shift_bounds_x(&mut self, by: i32) {
-        // TODO: do we need to move the a, b, c points?
-        // do we need to recalculate the vectors and dot products?
-        if by < 0 {
-            self.bounding_rect.x -= (0 - by) as u32;
-        } else {
-            self.bounding_rect.x += by as u32;
-        }
+        // the vectors and dot products are relative
+        // so they dont need to be recalculated
+        self.ax += by as f32;
+        self.bx += by as f32;
+        self.cx += by as f32;
+        // the shape might have moved partially off screen
+        // so the outer bounds have to be recalculated
+        self.bounding_rect = self.outer_bounds();
     }
 
     #[inline(always)]
     pub fn shift_bounds_y(&mut self, by: i32) {
-        // TODO: do we need to move the a, b, c points?
-        // do we need to recalculate the vectors and dot products?
-        if by < 0 {
-            self.bounding_rect.y -= (0 - by) as u32;
-        } else {
-            self.bounding_rect.y += by as u32;
+        self.ay += by as f32;
+        self.by += by as f32;
+        self.cy += by as f32;
+        self.bounding_rect = self.outer_bounds();
+    }
+
+    /// returns the 4 corners in order: A, B, C, D
+    /// where D is the corner opposite of B
+    #[inline(always)]
+    pub fn corners(&self) -> [Point; 4] {
+        [
+            Point { x: self.ax, y: self.ay },
+            Point { x: self.bx, y: self.by },
+            Point { x: self.cx, y: self.cy },
+            Point { x: self.ax + self.cx - self.bx, y: self.ay + self.cy - self.by },
+        ]
+    }
+
+    /// returns the range of x values on row y whose pixels
+    /// are inside of this tilted rect, as [x_start, x_end).
+    /// if no pixels on this row are inside, then x_start == x_end
+    pub fn spans(&self, y: u32) -> (u32, u32) {
+        polygon_spans(&self.corners(), y)
+    }
+
+    /// returns a copy of this tilted rect where every edge
+    /// is pushed outwards by `by`. the bounding_rect is unchanged
+    pub fn grown(&self, by: f32) -> TiltedRect {
+        let ab_len = self.ab_dot.sqrt();
+        let bc_len = self.bc_dot.sqrt();
+        let (ux, uy) = if ab_len > 0.0 {
+            (self.ab_vec.x / ab_len * by, self.ab_vec.y / ab_len * by)
+        } else { (0.0, 0.0) };
+        let (vx, vy) = if bc_len > 0.0 {
+            (self.bc_vec.x / bc_len * by, self.bc_vec.y / bc_len * by)
+        } else { (0.0, 0.0) };
+        let mut t = *self;
+        t.ax = self.ax - ux - vx;
+        t.ay = self.ay - uy - vy;
+        t.bx = self.bx + ux - vx;
+        t.by = self.by + uy - vy;
+        t.cx = self.cx + ux + vx;
+        t.cy = self.cy + uy + vy;
+        t.prepare();
+        t
+    }
+
+    /// separating axis test against an axis aligned rectangle.
+    /// returns true if any of the pixels of the rectangle
+    /// could be inside this tilted rect.
+    pub fn overlaps_rect(&self, r: &Rect) -> bool {
+        if r.w == 0 || r.h == 0 {
+            return false;
         }
+        let corners = self.corners();
+        let corner_refs = [&corners[0], &corners[1], &corners[2], &corners[3]];
+        // pixels are points, so the last pixel of the rect is at x + w - 1
+        let (rx0, ry0) = (r.x as f32, r.y as f32);
+        let (rx1, ry1) = ((r.x + r.w - 1) as f32, (r.y + r.h - 1) as f32);
+
+        // the axes of the rectangle:
+        if get_largest_x(&corner_refs) < rx0 || get_smallest_x(&corner_refs) > rx1 {
+            return false;
+        }
+        if get_largest_y(&corner_refs) < ry0 || get_smallest_y(&corner_refs) > ry1 {
+            return false;
+        }
+
+        // the axes of the tilted rect. we project the rect corners
+        // onto AB and BC, and compare to the range that
+        // the tilted rect occupies on that axis
+        let rect_corners = [(rx0, ry0), (rx1, ry0), (rx1, ry1), (rx0, ry1)];
+        let axes = [
+            (self.ab_vec, self.ax, self.ay, self.ab_dot),
+            (self.bc_vec, self.bx, self.by, self.bc_dot),
+        ];
+        for (axis, origin_x, origin_y, length) in axes.iter() {
+            let mut min = f32::MAX;
+            let mut max = f32::MIN;
+            for (x, y) in rect_corners.iter() {
+                let projected = dot(axis, &vector(*origin_x, *origin_y, *x, *y));
+                if projected < min { min = projected; }
+                if projected > max { max = projected; }
+            }
+            if max < 0.0 || min > *length {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// returns the polygon of the part of this tilted rect that
+    /// is inside the rectangle. the polygon is empty if they dont overlap
+    pub fn clip_to_rect(&self, r: &Rect) -> Vec<Point> {
+        clip_polygon_to_rect(&self.corners(), r)
     }
 
     pub fn prepare(&mut self) {
@@ -183,24 +1004,7 @@ impl TiltedRect {
     }
 
     pub fn from_points4(a: Point, b: Point, c: Point, d: Point) -> TiltedRect {
-        let point_array = [&a, &b, &c, &d];
-        let [x_min, x_max] = [
-            get_smallest_x(&point_array),
-            get_largest_x(&point_array),
-        ];
-        let [y_min, y_max] = [
-            get_smallest_y(&point_array),
-            get_largest_y(&point_array),
-        ];
-
-        let [
-            x_min, x_max,
-            y_min, y_max
-        ] = [
-            x_min as u32, x_max as u32,
-            y_min as u32, y_max as u32,
-        ];
-
+        let bounding_rect = outer_bounds_of(&[&a, &b, &c, &d]);
         let mut t = TiltedRect {
             ax: a.x, ay: a.y,
             bx: b.x, by: b.y,
@@ -209,12 +1013,18 @@ impl TiltedRect {
             bc_vec: Vector { x: 0.0, y: 0.0 },
             ab_dot: 0.0,
             bc_dot: 0.0,
-            bounding_rect: Rect { x: x_min, y: y_min, w: x_max - x_min + 1, h: y_max - y_min + 1 },
+            bounding_rect,
         };
         t.prepare();
         t
     }
 
+    /// the rectangular bounds of the on screen part of the 4 corners
+    pub fn outer_bounds(&self) -> Rect {
+        let [a, b, c, d] = self.corners();
+        outer_bounds_of(&[&a, &b, &c, &d])
+    }
+
     pub fn from_points(a: Point, b: Point, c: Point) -> TiltedRect {
         let [sorted_x, sorted_y] = sorted_values(&a, &b, &c);
 
@@ -243,12 +1053,18 @@ impl TiltedRect {
 }
 
 impl Intersects for TiltedRect {
-    /// too lazy right now to figure out a good intersection
-    /// algorithm for tilted rectangles... just going to
-    /// use the rectangular outer bounds
-    #[inline(always)]
+    /// returns the smallest rectangle that contains every part of this
+    /// tilted rect that is inside of b's rectangular bounds.
+    /// Note that b is only considered via its rectangular bounds.
     fn intersection<C: GetRectangularBounds>(&self, b: C) -> Option<Rect> {
-        self.bounding_rect.intersection(b.get_bounds())
+        let b = b.get_bounds();
+        // the outer bounds is cheap, and lets us skip most cases
+        let outer = self.bounding_rect.intersection(b)?;
+        if !self.overlaps_rect(&outer) {
+            return None;
+        }
+        let polygon = self.clip_to_rect(&outer);
+        tight_bounds_of(&polygon, &outer)
     }
 }
 
@@ -266,6 +1082,156 @@ impl GetRectangularBounds for TiltedRect {
     }
 }
 
+impl GetRectangularBounds for CircleBounds {
+    #[inline(always)]
+    fn get_bounds(&self) -> Rect {
+        self.bounding_rect
+    }
+}
+
+impl Intersects for CircleBounds {
+    /// returns the smallest rectangle that contains every pixel of
+    /// this circle that is inside of b's rectangular bounds.
+    fn intersection<C: GetRectangularBounds>(&self, b: C) -> Option<Rect> {
+        let outer = self.bounding_rect.intersection(b.get_bounds())?;
+        let mut tight: Option<Rect> = None;
+        for y in outer.y..(outer.y + outer.h) {
+            let (start, end) = self.spans(y);
+            let start = cmp::max(start, outer.x);
+            let end = cmp::min(end, outer.x + outer.w);
+            if start >= end {
+                continue;
+            }
+            let row = Rect { x: start, y, w: end - start, h: 1 };
+            tight = Some(match tight {
+                Some(t) => t.union(&row),
+                None => row,
+            });
+        }
+        tight
+    }
+}
+
+impl Contains for CircleBounds {
+    #[inline(always)]
+    fn contains(&self, x: f32, y: f32) -> bool {
+        let dx = x - self.cx;
+        let dy = y - self.cy;
+        dx * dx + dy * dy <= self.radius * self.radius
+    }
+
+    #[inline(always)]
+    fn contains_u32(&self, x: u32, y: u32) -> bool {
+        self.contains(x as f32, y as f32)
+    }
+}
+
+impl GetRectangularBounds for PolygonBounds {
+    #[inline(always)]
+    fn get_bounds(&self) -> Rect {
+        self.bounding_rect
+    }
+}
+
+impl Intersects for PolygonBounds {
+    /// returns the smallest rectangle that contains every part of this
+    /// polygon that is inside of b's rectangular bounds.
+    fn intersection<C: GetRectangularBounds>(&self, b: C) -> Option<Rect> {
+        let outer = self.bounding_rect.intersection(b.get_bounds())?;
+        let polygon = self.clip_to_rect(&outer);
+        tight_bounds_of(&polygon, &outer)
+    }
+}
+
+impl Contains for PolygonBounds {
+    /// the point is inside if it is on the same side of every edge
+    fn contains(&self, x: f32, y: f32) -> bool {
+        const EPSILON: f32 = 0.0001;
+        let len = self.points.len();
+        if len < 3 {
+            return false;
+        }
+        let mut has_positive = false;
+        let mut has_negative = false;
+        for i in 0..len {
+            let a = &self.points[i];
+            let b = &self.points[(i + 1) % len];
+            let cross = (b.x - a.x) * (y - a.y) - (b.y - a.y) * (x - a.x);
+            if cross > EPSILON { has_positive = true; }
+            if cross < -EPSILON { has_negative = true; }
+            if has_positive && has_negative {
+                return false;
+            }
+        }
+        true
+    }
+
+    #[inline(always)]
+    fn contains_u32(&self, x: u32, y: u32) -> bool {
+        self.contains(x as f32, y as f32)
+    }
+
+    fn coverage(&self, x: f32, y: f32) -> f32 {
+        polygon_coverage(&self.points, x, y)
+    }
+}
+
+impl GetRectangularBounds for Bounds {
+    #[inline(always)]
+    fn get_bounds(&self) -> Rect {
+        match self {
+            Bounds::Rect(r) => *r,
+            Bounds::Tilted(t) => t.get_bounds(),
+            Bounds::Circle(c) => c.get_bounds(),
+            Bounds::Polygon(p) => p.get_bounds(),
+        }
+    }
+}
+
+impl Intersects for Bounds {
+    #[inline(always)]
+    fn intersection<C: GetRectangularBounds>(&self, b: C) -> Option<Rect> {
+        match self {
+            Bounds::Rect(r) => r.intersection(b),
+            Bounds::Tilted(t) => t.intersection(b),
+            Bounds::Circle(c) => c.intersection(b),
+            Bounds::Polygon(p) => p.intersection(b),
+        }
+    }
+}
+
+impl Contains for Bounds {
+    #[inline(always)]
+    fn contains(&self, x: f32, y: f32) -> bool {
+        match self {
+            Bounds::Rect(r) => r.contains(x, y),
+            Bounds::Tilted(t) => t.contains(x, y),
+            Bounds::Circle(c) => c.contains(x, y),
+            Bounds::Polygon(p) => p.contains(x, y),
+        }
+    }
+
+    #[inline(always)]
+    fn contains_u32(&self, x: u32, y: u32) -> bool {
+        match self {
+            Bounds::Rect(r) => r.contains_u32(x, y),
+            Bounds::Tilted(t) => t.contains_u32(x, y),
+            Bounds::Circle(c) => c.contains_u32(x, y),
+            Bounds::Polygon(p) => p.contains_u32(x, y),
+        }
+    }
+
+    #[inline(always)]
+    fn coverage(&self, x: f32, y: f32) -> f32 {
+        match self {
+            Bounds::Rect(r) => r.coverage(x, y),
+            Bounds::Tilted(t) => t.coverage(x, y),
+            Bounds::Circle(c) => c.coverage(x, y),
+            Bounds::Polygon(p) => p.coverage(x, y),
+        }
+    }
+}
+
 impl Intersects for Rect {
     // stolen from
     // https://referencesource.microsoft.com/#System.Drawing/commonui/System/Drawing/Rectangle.cs,438
@@ -341,6 +1307,10 @@ impl Contains for TiltedRect {
     fn contains_u32(&self, x: u32, y: u32) -> bool {
         self.contains(x as f32, y as f32)
     }
+
+    fn coverage(&self, x: f32, y: f32) -> f32 {
+        polygon_coverage(&self.corners(), x, y)
+    }
 }
 
 #[cfg(test)]
@@ -356,9 +1326,57 @@ mod tests {
             Point { x: 6.0, y: 1.0 },
         );
         
+        // (1, 1) is inside of the outer bounds, but not
+        // inside of the tilted rect
         let r = Rect { x: 1, y: 1, w: 1, h: 1 };
-        assert_eq!(t.intersection(r), Some(r));
+        assert_eq!(t.intersection(r), None);
+        // a normal rect only knows about the outer bounds
         assert_eq!(r.intersection(t), Some(r));
+
+        // (3, 3) is on the tilted rect
+        let r = Rect { x: 3, y: 3, w: 1, h: 1 };
+        assert_eq!(t.intersection(r), Some(r));
+    }
+
+    #[test]
+    fn tilted_rect_intersection_is_tight() {
+        // a diamond with the top at (11, 8)
+        let t = TiltedRect::from_points4(
+            Point { x: 5.0, y: 14.0 },
+            Point { x: 11.0, y: 20.0 },
+            Point { x: 17.0, y: 14.0 },
+            Point { x: 11.0, y: 8.0 },
+        );
+        // the top left corner of the outer bounds is empty
+        let r = Rect { x: 5, y: 8, w: 3, h: 3 };
+        assert!(r.intersection(t.bounding_rect).is_some());
+        assert_eq!(t.intersection(r), None);
+
+        // the top left quarter of the diamond is
+        // only from x = 8 to 11 when y <= 11
+        let r = Rect { x: 0, y: 0, w: 12, h: 12 };
+        assert_eq!(t.intersection(r), Some(Rect { x: 8, y: 8, w: 4, h: 4 }));
+
+        // rect fully containing the diamond should give the outer bounds
+        let r = Rect { x: 0, y: 0, w: 100, h: 100 };
+        assert_eq!(t.intersection(r), Some(t.bounding_rect));
+    }
+
+    #[test]
+    fn shifting_tilted_rect_moves_the_shape() {
+        let mut t = TiltedRect::from_points4(
+            Point { x: 5.0, y: 14.0 },
+            Point { x: 11.0, y: 20.0 },
+            Point { x: 17.0, y: 14.0 },
+            Point { x: 11.0, y: 8.0 },
+        );
+        assert!(t.contains(5.0, 14.0));
+        t.shift_bounds_x(2);
+        t.shift_bounds_y(-1);
+        assert!(!t.contains(5.0, 14.0));
+        assert!(t.contains(7.0, 13.0));
+        assert_eq!(t.bounding_rect.x, 7);
+        assert_eq!(t.bounding_rect.y, 7);
     }
 
     #[test]
@@ -554,4 +1572,326 @@ mod tests {
             w: 8, h: 5,
         }));
     }
+
+    #[test]
+    fn tilted_rect_spans_match_contains() {
+        let t = TiltedRect::from_points4(
+            Point { x: 5.0, y: 14.0 },
+            Point { x: 11.0, y: 20.0 },
+            Point { x: 17.0, y: 14.0 },
+            Point { x: 11.0, y: 8.0 },
+        );
+        assert_eq!(t.spans(8), (11, 12));
+        assert_eq!(t.spans(14), (5, 18));
+        assert_eq!(t.spans(7), (0, 0));
+        assert_eq!(t.spans(21), (0, 0));
+
+        for y in 0..25 {
+            let (start, end) = t.spans(y);
+            for x in 0..25 {
+                let in_span = x >= start && x < end;
+                assert_eq!(in_span, t.contains_u32(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn tilted_rect_spans_of_axis_aligned_rect() {
+        let t = TiltedRect::from_points4(
+            Point { x: 2.0, y: 1.0 },
+            Point { x: 5.0, y: 1.0 },
+            Point { x: 5.0, y: 3.0 },
+            Point { x: 2.0, y: 3.0 },
+        );
+        assert_eq!(t.spans(0), (0, 0));
+        assert_eq!(t.spans(1), (2, 6));
+        assert_eq!(t.spans(2), (2, 6));
+        assert_eq!(t.spans(3), (2, 6));
+        assert_eq!(t.spans(4), (0, 0));
+
+        let grown = t.grown(1.0);
+        assert_eq!(grown.spans(0), (1, 7));
+        assert_eq!(grown.spans(4), (1, 7));
+    }
+
+    #[test]
+    fn offset_rect_visible_bounds_works() {
+        let r = OffsetRect { x: -2, y: 3, w: 5, h: 5 };
+        assert_eq!(r.clipped_by(), (2, 0));
+        assert_eq!(r.visible_bounds(), Rect { x: 0, y: 3, w: 3, h: 5 });
+
+        // completely off screen has no area
+        let r = OffsetRect { x: -10, y: -10, w: 5, h: 5 };
+        let visible = r.visible_bounds();
+        assert_eq!(visible.w, 0);
+        assert_eq!(visible.h, 0);
+
+        let r: OffsetRect = Rect { x: 1, y: 2, w: 3, h: 4 }.into();
+        assert_eq!(r.visible_bounds(), Rect { x: 1, y: 2, w: 3, h: 4 });
+    }
+
+    #[test]
+    fn shifting_tilted_rect_off_screen_cuts_bounds() {
+        let mut t = TiltedRect::from_points4(
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 3.0, y: 0.0 },
+            Point { x: 3.0, y: 3.0 },
+            Point { x: 0.0, y: 3.0 },
+        );
+        t.shift_bounds_x(-2);
+        assert_eq!(t.bounding_rect, Rect { x: 0, y: 0, w: 2, h: 4 });
+        t.shift_bounds_x(-5);
+        assert_eq!(t.bounding_rect, EMPTY_RECT);
+        t.shift_bounds_x(7);
+        assert_eq!(t.bounding_rect, Rect { x: 0, y: 0, w: 4, h: 4 });
+    }
+
+    #[test]
+    fn rect_union_works() {
+        let r1 = Rect { x: 1, y: 1, w: 2, h: 2 };
+        let r2 = Rect { x: 5, y: 0, w: 1, h: 1 };
+        assert_eq!(r1.union(&r2), Rect { x: 1, y: 0, w: 5, h: 3 });
+        assert_eq!(r1.union(&EMPTY_RECT), r1);
+        assert_eq!(EMPTY_RECT.union(&r1), r1);
+    }
+
+    #[test]
+    fn rect_translate_and_inflate_work() {
+        let r = Rect { x: 2, y: 2, w: 3, h: 3 };
+        assert_eq!(r.translate(1, -1), Rect { x: 3, y: 1, w: 3, h: 3 });
+        // cut off at the left edge
+        assert_eq!(r.translate(-3, 0), Rect { x: 0, y: 2, w: 2, h: 3 });
+
+        assert_eq!(r.inflate(1), Rect { x: 1, y: 1, w: 5, h: 5 });
+        assert_eq!(r.inflate(3), Rect { x: 0, y: 0, w: 8, h: 8 });
+    }
+
+    #[test]
+    fn rect_clamp_to_and_area_work() {
+        let screen = Rect { x: 0, y: 0, w: 10, h: 10 };
+        let r = Rect { x: 8, y: 5, w: 5, h: 2 };
+        assert_eq!(r.area(), 10);
+        let clamped = r.clamp_to(&screen);
+        assert_eq!(clamped, Rect { x: 8, y: 5, w: 2, h: 2 });
+        assert_eq!(clamped.area(), 4);
+
+        let r = Rect { x: 20, y: 5, w: 5, h: 2 };
+        let clamped = r.clamp_to(&screen);
+        assert!(clamped.is_empty());
+        assert_eq!(clamped.area(), 0);
+    }
+
+    #[test]
+    fn rect_split_covers_everything() {
+        let r = Rect { x: 1, y: 2, w: 10, h: 5 };
+        let parts = r.split(3, 2);
+        assert_eq!(parts.len(), 6);
+        assert_eq!(parts[0], Rect { x: 1, y: 2, w: 3, h: 2 });
+        assert_eq!(parts[2], Rect { x: 7, y: 2, w: 4, h: 2 });
+        assert_eq!(parts[5], Rect { x: 7, y: 4, w: 4, h: 3 });
+        let total: u64 = parts.iter().map(|p| p.area()).sum();
+        assert_eq!(total, r.area());
+        for (i, a) in parts.iter().enumerate() {
+            for b in parts.iter().skip(i + 1) {
+                assert_eq!(a.intersection(*b), None);
+            }
+        }
+    }
+
+    #[test]
+    fn circle_contains_excludes_corners() {
+        let c = CircleBounds::from_rect(Rect { x: 2, y: 2, w: 6, h: 6 });
+        assert_eq!(c.bounding_rect, Rect { x: 2, y: 2, w: 6, h: 6 });
+        // center and the middle of each edge
+        assert!(c.contains_u32(4, 4));
+        assert!(c.contains_u32(2, 4));
+        assert!(c.contains_u32(7, 5));
+        assert!(c.contains_u32(4, 2));
+        assert!(c.contains_u32(5, 7));
+        // corners are not part of the circle
+        assert!(!c.contains_u32(2, 2));
+        assert!(!c.contains_u32(7, 2));
+        assert!(!c.contains_u32(7, 7));
+        assert!(!c.contains_u32(2, 7));
+
+        for y in 0..10 {
+            let (start, end) = c.spans(y);
+            for x in 0..10 {
+                let in_span = x >= start && x < end;
+                assert_eq!(in_span, c.contains_u32(x, y), "({}, {})", x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn circle_intersection_ignores_corners() {
+        let c = CircleBounds::from_rect(Rect { x: 2, y: 2, w: 6, h: 6 });
+        // the top left corner pixel is not in the circle
+        assert_eq!(c.intersection(Rect { x: 0, y: 0, w: 3, h: 3 }), None);
+        let r = Rect { x: 0, y: 0, w: 5, h: 5 };
+        let i = c.intersection(r).unwrap();
+        assert_eq!(i, Rect { x: 2, y: 2, w: 3, h: 3 });
+        assert_eq!(c.intersection(Rect { x: 0, y: 0, w: 100, h: 100 }), Some(c.bounding_rect));
+    }
+
+    #[test]
+    fn polygon_contains_works() {
+        // a diamond, in counter clockwise order
+        let p = PolygonBounds::new(vec![
+            Point { x: 4.0, y: 0.0 },
+            Point { x: 0.0, y: 4.0 },
+            Point { x: 4.0, y: 8.0 },
+            Point { x: 8.0, y: 4.0 },
+        ]);
+        assert_eq!(p.bounding_rect, Rect { x: 0, y: 0, w: 9, h: 9 });
+        assert!(p.contains_u32(4, 4));
+        assert!(p.contains_u32(4, 0));
+        assert!(p.contains_u32(2, 2));
+        assert!(!p.contains_u32(1, 1));
+        assert!(!p.contains_u32(7, 7));
+
+        for y in 0..10 {
+            let (start, end) = p.spans(y);
+            for x in 0..10 {
+                let in_span = x >= start && x < end;
+                assert_eq!(in_span, p.contains_u32(x, y), "({}, {})", x, y);
+            }
+        }
+
+        // same diamond in the other order should be the same
+        let mut reversed = p.points.clone();
+        reversed.reverse();
+        let r = PolygonBounds::new(reversed);
+        for y in 0..10 {
+            for x in 0..10 {
+                assert_eq!(r.contains_u32(x, y), p.contains_u32(x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn polygon_intersection_works() {
+        let hex = PolygonBounds::regular(10.0, 10.0, 5.0, 6);
+        assert_eq!(hex.points.len(), 6);
+        assert!(hex.contains_u32(10, 10));
+        assert!(hex.contains_u32(14, 10));
+        // the corners of the bounding rect are not inside
+        assert!(!hex.contains_u32(5, 5));
+        assert_eq!(hex.intersection(Rect { x: 5, y: 5, w: 2, h: 2 }), None);
+        assert!(hex.intersection(Rect { x: 9, y: 9, w: 2, h: 2 }).is_some());
+
+        let mut hex = hex;
+        hex.shift_bounds_x(-20);
+        assert_eq!(hex.bounding_rect, EMPTY_RECT);
+        hex.shift_bounds_x(20);
+        hex.shift_bounds_y(2);
+        assert!(hex.contains_u32(10, 12));
+    }
+
+    #[test]
+    fn bounds_transformed_works() {
+        let position = OffsetRect { x: 10, y: 10, w: 4, h: 4 };
+        let m = Matrix::rotate_degrees(90.0);
+        let rect = Bounds::Rect(Rect { x: 10, y: 10, w: 4, h: 4 });
+        match rect.transformed(position, &m) {
+            Bounds::Tilted(t) => assert_eq!(t.bounding_rect, Rect { x: 7, y: 10, w: 4, h: 4 }),
+            _ => panic!("rotating a rect should give a tilted rect"),
+        }
+
+        // circles rotate about the top left of the position
+        let circle = Bounds::Circle(CircleBounds::new(12.0, 10.0, 1.0));
+        match circle.transformed(position, &m) {
+            Bounds::Circle(c) => {
+                assert!((c.cx - 10.0).abs() < 0.001);
+                assert!((c.cy - 12.0).abs() < 0.001);
+            }
+            _ => panic!("rotating a circle should give a circle"),
+        }
+
+        let s = Bounds::Rect(Rect { x: 1, y: 1, w: 2, h: 2 });
+        assert_eq!(s.spans(0), (0, 0));
+        assert_eq!(s.spans(2), (1, 3));
+        assert!(s.is_rect());
+        assert!(!circle.is_rect());
+    }
+
+    #[test]
+    fn tilted_rect_coverage_works() {
+        // a 4x4 square whose edges are on pixel boundaries
+        let t = TiltedRect::from_points4(
+            Point { x: 0.5, y: 0.5 },
+            Point { x: 4.5, y: 0.5 },
+            Point { x: 4.5, y: 4.5 },
+            Point { x: 0.5, y: 4.5 },
+        );
+        assert_eq!(t.coverage(2.0, 2.0), 1.0);
+        assert_eq!(t.coverage(1.0, 1.0), 1.0);
+        assert_eq!(t.coverage(0.0, 2.0), 0.0);
+        assert_eq!(t.coverage(6.0, 6.0), 0.0);
+
+        // a diamond, the pixel at its center is fully covered,
+        // and pixels that its edges cut through diagonally are half covered
+        let t = TiltedRect::from_points4(
+            Point { x: 4.0, y: 0.0 },
+            Point { x: 8.0, y: 4.0 },
+            Point { x: 4.0, y: 8.0 },
+            Point { x: 0.0, y: 4.0 },
+        );
+        assert_eq!(t.coverage(4.0, 4.0), 1.0);
+        assert!((t.coverage(2.0, 2.0) - 0.5).abs() < 0.001);
+        assert!((t.coverage(6.0, 6.0) - 0.5).abs() < 0.001);
+        assert!(t.coverage(1.0, 1.0) < 0.001);
+        let partial = t.coverage(4.0, 0.0);
+        assert!(partial > 0.0 && partial < 0.5);
+    }
+
+    #[test]
+    fn row_clipper_visible_spans_works() {
+        let regions = [
+            Rect { x: 6, y: 0, w: 2, h: 10 },
+            Rect { x: 2, y: 0, w: 2, h: 10 },
+            Rect { x: 3, y: 5, w: 4, h: 1 },
+            Rect { x: 50, y: 0, w: 2, h: 10 },
+        ];
+        let clipper = RowClipper::new(&regions, 0, 10, 0, 10);
+        let mut spans = vec![];
+        clipper.visible_spans(0, 0, 10, &mut spans);
+        assert_eq!(spans, vec![(0, 2), (4, 6), (8, 10)]);
+        // overlapping regions leave nothing in between
+        clipper.visible_spans(5, 0, 10, &mut spans);
+        assert_eq!(spans, vec![(0, 2), (8, 10)]);
+        clipper.visible_spans(5, 3, 7, &mut spans);
+        assert_eq!(spans, vec![]);
+        clipper.visible_spans(10, 1, 9, &mut spans);
+        assert_eq!(spans, vec![(1, 9)]);
+    }
+
+    #[test]
+    fn rect_subtract_works() {
+        let a = Rect { x: 0, y: 0, w: 4, h: 4 };
+        // moved right by 1
+        assert_eq!(a.subtract(&Rect { x: 1, y: 0, w: 4, h: 4 }), vec![Rect { x: 0, y: 0, w: 1, h: 4 }]);
+        // moved diagonally
+        assert_eq!(a.subtract(&Rect { x: 1, y: 1, w: 4, h: 4 }), vec![
+            Rect { x: 0, y: 0, w: 4, h: 1 },
+            Rect { x: 0, y: 1, w: 1, h: 3 },
+        ]);
+        // a hole in the middle
+        let pieces = a.subtract(&Rect { x: 1, y: 1, w: 2, h: 2 });
+        assert_eq!(pieces.len(), 4);
+        assert_eq!(pieces.iter().map(|r| r.area()).sum::<u64>(), 12);
+        assert!(a.subtract(&Rect { x: 0, y: 0, w: 5, h: 5 }).is_empty());
+        assert_eq!(a.subtract(&Rect { x: 10, y: 10, w: 1, h: 1 }), vec![a]);
+    }
+
+    #[test]
+    fn row_intersects_any_works() {
+        let regions = [Rect { x: 5, y: 5, w: 2, h: 2 }];
+        assert!(row_intersects_any(&regions, 5, 0, 10));
+        assert!(row_intersects_any(&regions, 6, 6, 7));
+        assert!(!row_intersects_any(&regions, 7, 0, 10));
+        assert!(!row_intersects_any(&regions, 5, 0, 5));
+        assert!(!row_intersects_any(&regions, 5, 7, 10));
+        assert!(!row_intersects_any(&[], 5, 0, 10));
+    }
 }
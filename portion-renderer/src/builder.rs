@@ -0,0 +1,143 @@
+use super::*;
+
+/// builds a PortionRenderer, so that new options dont change the
+/// signature of the constructor. eg:
+///
+/// ```
+/// # use portion_renderer::*;
+/// let p = PortionRenderer::<u8>::builder(640, 480)
+///     .grid(8, 8)
+///     .background_color(PIXEL_BLACK)
+///     .antialiasing(true)
+///     .build();
+/// ```
+pub struct PortionRendererBuilder<T> {
+    width: u32,
+    height: u32,
+    num_rows: u32,
+    num_cols: u32,
+    pixel_format: PixelFormatEnum,
+    clear: ClearSource<T>,
+    antialias: bool,
+    frame_stats: bool,
+    command_log: bool,
+    backend: Option<Box<dyn RenderBackend>>,
+}
+
+impl<T: Default + Clone> PortionRendererBuilder<T> {
+    /// the defaults are the same as PortionRenderer::new
+    pub fn new(width: u32, height: u32) -> PortionRendererBuilder<T> {
+        PortionRendererBuilder {
+            width,
+            height,
+            num_rows: 4,
+            num_cols: 4,
+            pixel_format: PixelFormatEnum::RGBA8888,
+            clear: ClearSource::SolidColor(PIXEL_BLANK),
+            antialias: false,
+            frame_stats: false,
+            command_log: false,
+            backend: None,
+        }
+    }
+
+    /// how many rows and columns of portions the screen is split into.
+    /// the width and height must be divisible by them
+    pub fn grid(mut self, num_rows: u32, num_cols: u32) -> Self {
+        self.num_rows = num_rows;
+        self.num_cols = num_cols;
+        self
+    }
+
+    pub fn pixel_format(mut self, pixel_format: PixelFormatEnum) -> Self {
+        self.pixel_format = pixel_format;
+        self
+    }
+
+    /// the color that the previous bounds of objects get cleared to
+    pub fn background_color(mut self, color: RgbaPixel) -> Self {
+        self.clear = ClearSource::SolidColor(color);
+        self
+    }
+
+    /// see set_clear_source. a buffer must be the same size as the pixel buffer
+    pub fn clear_source(mut self, clear: ClearSource<T>) -> Self {
+        self.clear = clear;
+        self
+    }
+
+    /// see set_antialiasing
+    pub fn antialiasing(mut self, enabled: bool) -> Self {
+        self.antialias = enabled;
+        self
+    }
+
+    /// see set_frame_stats
+    pub fn frame_stats(mut self, enabled: bool) -> Self {
+        self.frame_stats = enabled;
+        self
+    }
+
+    /// starts the command log right away, so that
+    /// the log can replay the whole life of the renderer
+    pub fn command_log(mut self, enabled: bool) -> Self {
+        self.command_log = enabled;
+        self
+    }
+
+    /// see set_backend. SoftwareBackend by default
+    pub fn backend(mut self, backend: impl RenderBackend + 'static) -> Self {
+        self.backend = Some(Box::new(backend));
+        self
+    }
+
+    /// panics if the grid doesnt divide the width and height,
+    /// or if the clear source is a buffer of the wrong size
+    pub fn build(self) -> PortionRenderer<T> {
+        let mut renderer = PortionRenderer::new_ex(
+            self.width, self.height, self.num_rows, self.num_cols, self.pixel_format,
+        );
+        // started first so that the options below are in the log
+        if self.command_log {
+            renderer.start_command_log();
+        }
+        renderer.set_clear_source(self.clear);
+        if self.antialias {
+            renderer.set_antialiasing(true);
+        }
+        renderer.set_frame_stats(self.frame_stats);
+        if let Some(backend) = self.backend {
+            renderer.backend = backend;
+        }
+        renderer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builder_applies_every_option() {
+        let mut p = PortionRenderer::<u8>::builder(8, 4)
+            .grid(2, 4)
+            .pixel_format(PixelFormatEnum::BGRA8888)
+            .background_color(PIXEL_RED)
+            .antialiasing(true)
+            .frame_stats(true)
+            .command_log(true)
+            .build();
+        assert_eq!(p.portioner.get_grid_dimensions(), (2, 4));
+        assert_eq!(p.pixel_format, PixelFormatEnum::BGRA8888);
+        assert!(p.antialias);
+        assert!(p.last_frame_stats().is_some());
+
+        let o = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        p.move_object_x_by(o, 1);
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(0, 0)]), PIXEL_RED);
+        let log = p.take_command_log().unwrap();
+        assert_eq!(log.replay().pixel_buffer, p.pixel_buffer);
+    }
+}
@@ -0,0 +1,309 @@
+use super::*;
+
+impl<T> PortionRenderer<T> {
+    /// the world coordinate of the top left of the screen
+    pub fn camera_position(&self) -> (i32, i32) {
+        (self.camera_x, self.camera_y)
+    }
+
+    /// how many screen pixels one world pixel takes up
+    pub fn camera_zoom(&self) -> f32 {
+        self.zoom
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// moves the camera to (x, y) in world coordinates, see move_camera
+    pub fn set_camera_position(&mut self, x: i32, y: i32) {
+        self.move_camera(x - self.camera_x, y - self.camera_y);
+    }
+
+    /// scrolls the view of the world by (dx, dy). the pixels that stay on
+    /// screen are copied to where they end up, and only the strips along
+    /// the edges that come into view are drawn from the objects.
+    /// the entire screen is marked as dirty.
+    /// if the clear source is a buffer, then it stays in place on the
+    /// screen, so the whole screen is drawn from the objects instead.
+    /// the same goes for a clip rect, which the copy would ignore.
+    /// when zoomed, the camera moves by whole world pixels, which
+    /// dont line up with screen pixels, so the whole screen is drawn as well
+    pub fn move_camera(&mut self, dx: i32, dy: i32) {
+        self.log_command(|| RenderCommand::MoveCamera { dx, dy });
+        if dx == 0 && dy == 0 {
+            return;
+        }
+        self.camera_x += dx;
+        self.camera_y += dy;
+        // the viewports are scrolled along with the rest of the screen
+        self.redraw_viewports();
+        // the light map stays on the screen while the pixels
+        // move, so the moved pixels would have the wrong light
+        if self.zoom != 1.0 || self.lighting.is_some() || self.has_fixed_objects() {
+            self.relayout_objects();
+            return;
+        }
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+
+        // everything on screen moves the opposite way of the camera
+        let (sx, sy) = (-dx, -dy);
+        let pending: HashSet<usize> = self.layers.iter()
+            .flat_map(|layer| layer.updates.iter().copied())
+            .collect();
+        let object_indices: Vec<usize> = self.objects.used_indices().collect();
+        for object_index in object_indices.iter().copied() {
+            self.move_object_on_screen_by(object_index, sx, sy);
+            let object = &mut self.objects[object_index];
+            object.previous_bounds = object.previous_bounds.translate(sx, sy).clamp_to(&screen);
+            match &mut object.previous_shape {
+                Bounds::Rect(r) => *r = object.previous_bounds,
+                shape => {
+                    shape.shift_bounds_x(sx);
+                    shape.shift_bounds_y(sy);
+                }
+            }
+            self.update_spatial_index(object_index);
+        }
+        for rect in self.removed_regions.iter_mut() {
+            *rect = rect.translate(sx, sy).clamp_to(&screen);
+        }
+
+        let exposed = if sx.unsigned_abs() >= self.width || sy.unsigned_abs() >= self.height
+            || matches!(self.clear, ClearSource::Buffer(_)) || self.clip.is_some()
+        {
+            vec![screen]
+        } else {
+            self.shift_pixel_buffer(sx, sy);
+            exposed_strips(screen, sx, sy)
+        };
+        self.portioner.take_region((0, 0), (self.width - 1, self.height - 1));
+        for rect in exposed.iter() {
+            self.composite_region(*rect);
+        }
+        self.objects_were_composited(object_indices, &pending, &exposed);
+    }
+
+    /// views the world at zoom times its size, eg: 2.0 draws every world
+    /// pixel as 2x2 screen pixels, and 0.5 fits 2x2 world pixels into one
+    /// screen pixel. objects are scaled by their transform, so their
+    /// textures are sampled with the sampling filter.
+    /// the camera position stays at the top left of the screen, and the
+    /// whole screen is drawn again and marked as dirty
+    pub fn set_camera_zoom(&mut self, zoom: f32) {
+        self.log_command(|| RenderCommand::SetCameraZoom(zoom));
+        if zoom.is_nan() || zoom <= 0.0 {
+            panic!("Camera zoom must be more than 0, but got {}", zoom);
+        }
+        if zoom == self.zoom {
+            return;
+        }
+        self.zoom = zoom;
+        self.relayout_objects();
+    }
+
+    /// puts every object where the camera now sees it, and draws
+    /// the whole screen from the objects
+    fn relayout_objects(&mut self) {
+        self.redraw_viewports();
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        let object_indices: Vec<usize> = self.objects.used_indices().collect();
+        for object_index in object_indices.iter().copied() {
+            let object = &self.objects[object_index];
+            let (x, y) = self.layer_to_screen(object.layer_index, object.world_x, object.world_y, self.camera_x, self.camera_y);
+            let (dx, dy) = (x - object.position.x, y - object.position.y);
+            self.objects[object_index].move_by(dx, dy);
+            self.apply_object_transform(object_index);
+            self.update_spatial_index(object_index);
+        }
+        // whatever was removed is drawn over along with everything else
+        self.removed_regions.clear();
+        self.portioner.take_region((0, 0), (self.width - 1, self.height - 1));
+        self.unlight(screen);
+        self.composite_region(screen);
+        self.light_frame();
+        // none of the old pixels are left, so even objects with a
+        // pending update only need to clear what was drawn just now
+        self.objects_were_composited(object_indices, &HashSet::new(), &[screen]);
+    }
+
+    /// the objects are now drawn wherever they are on screen, as far as
+    /// the exposed rects go. objects with a pending update still have their
+    /// old pixels, so they keep their previous bounds as well as what was drawn of them now
+    fn objects_were_composited(&mut self, object_indices: Vec<usize>, pending: &HashSet<usize>, exposed: &[Rect]) {
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        for object_index in object_indices {
+            let object = &mut self.objects[object_index];
+            if object.initial_render {
+                continue;
+            }
+            if !pending.contains(&object_index) {
+                object.previous_bounds = object.get_bounds().clamp_to(&screen);
+                object.previous_shape = object.shape.clone();
+                continue;
+            }
+            let bounds = object.get_bounds();
+            let drawn = exposed.iter()
+                .filter_map(|rect| rect.intersection(bounds))
+                .fold(object.previous_bounds, |acc, r| acc.union(&r));
+            if drawn != object.previous_bounds {
+                object.previous_bounds = drawn;
+                object.previous_shape = Bounds::Rect(drawn);
+            }
+        }
+    }
+
+    /// moves the pixels of the buffer by (sx, sy). the
+    /// pixels that move off of the screen are lost, and the
+    /// pixels that are uncovered are left as they were
+    fn shift_pixel_buffer(&mut self, sx: i32, sy: i32) {
+        let ipp = self.indices_per_pixel as usize;
+        let pitch = self.pitch;
+        let moved_w = (self.width - sx.unsigned_abs()) as usize;
+        let moved_h = self.height - sy.unsigned_abs();
+        let (src_x, dst_x) = if sx < 0 { ((-sx) as usize, 0) } else { (0, sx as usize) };
+        let (src_y, dst_y) = if sy < 0 { ((-sy) as u32, 0) } else { (0, sy as u32) };
+        let len = moved_w * ipp;
+        let mut copy_row = |row: u32| {
+            let src = (src_y + row) as usize * pitch + src_x * ipp;
+            let dst = (dst_y + row) as usize * pitch + dst_x * ipp;
+            self.pixel_buffer.copy_within(src..src + len, dst);
+        };
+        // rows are copied in the order that doesnt overwrite
+        // a row before it was copied
+        if sy > 0 {
+            (0..moved_h).rev().for_each(&mut copy_row);
+        } else {
+            (0..moved_h).for_each(&mut copy_row);
+        }
+    }
+}
+
+/// the parts of the screen that have nothing in them after
+/// the pixels were moved by (sx, sy)
+fn exposed_strips(screen: Rect, sx: i32, sy: i32) -> Vec<Rect> {
+    let mut strips = vec![];
+    let (ax, ay) = (sx.unsigned_abs(), sy.unsigned_abs());
+    if sx > 0 {
+        strips.push(Rect { x: 0, y: 0, w: ax, h: screen.h });
+    } else if sx < 0 {
+        strips.push(Rect { x: screen.w - ax, y: 0, w: ax, h: screen.h });
+    }
+    if sy > 0 {
+        strips.push(Rect { x: 0, y: 0, w: screen.w, h: ay });
+    } else if sy < 0 {
+        strips.push(Rect { x: 0, y: screen.h - ay, w: screen.w, h: ay });
+    }
+    strips
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(p: &mut PortionRenderer<u8>) -> (usize, usize) {
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 24, h: 24 }, PIXEL_BLUE);
+        let red = p.create_object_from_color(1, Rect { x: 2, y: 2, w: 5, h: 3 }, PIXEL_RED);
+        let green = p.create_object_from_color(1, Rect { x: 14, y: 9, w: 3, h: 6 }, PIXEL_GREEN);
+        p.set_object_rotation(green, 30.0);
+        (red, green)
+    }
+
+    #[test]
+    fn moving_the_camera_matches_drawing_from_scratch() {
+        let mut p = PortionRenderer::<u8>::new(12, 12);
+        let (red, green) = scene(&mut p);
+        p.draw_all_layers();
+        p.take_dirty_rects();
+        p.move_camera(4, -3);
+        assert_eq!(p.camera_position(), (4, -3));
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 12, h: 12 }]);
+
+        let mut expected = PortionRenderer::<u8>::new(12, 12);
+        expected.set_camera_position(4, -3);
+        scene(&mut expected);
+        expected.draw_all_layers();
+        assert_eq!(p.pixel_buffer, expected.pixel_buffer);
+
+        // objects that move after the camera dont leave anything behind
+        p.set_object_position(red, 6, 0);
+        p.move_camera(-2, 5);
+        p.move_object_y_by(green, 2);
+        p.draw_all_layers();
+        let mut fresh = PortionRenderer::<u8>::new(12, 12);
+        fresh.set_camera_position(2, 2);
+        let (fresh_red, fresh_green) = scene(&mut fresh);
+        fresh.set_object_position(fresh_red, 6, 0);
+        fresh.move_object_y_by(fresh_green, 2);
+        fresh.draw_all_layers();
+        assert_eq!(p.pixel_buffer, fresh.pixel_buffer);
+    }
+
+    #[test]
+    fn moving_the_camera_past_the_screen_redraws_everything() {
+        let mut p = PortionRenderer::<u8>::new(12, 12);
+        scene(&mut p);
+        p.draw_all_layers();
+        p.move_camera(0, 40);
+        let mut expected = PortionRenderer::<u8>::new(12, 12);
+        expected.draw_all_layers();
+        assert_eq!(p.pixel_buffer, expected.pixel_buffer);
+    }
+
+    #[test]
+    fn zooming_scales_the_scene_without_rebuilding_it() {
+        let mut p = PortionRenderer::<u8>::new(12, 12);
+        let (red, _) = scene(&mut p);
+        p.draw_all_layers();
+        let unzoomed = p.pixel_buffer.clone();
+        p.take_dirty_rects();
+
+        // the red rect at (2, 2) now covers 10x6 screen pixels at (4, 4)
+        p.set_camera_zoom(2.0);
+        p.draw_all_layers();
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 12, h: 12 }]);
+        let pixel = |p: &PortionRenderer<u8>, x, y| -> RgbaPixel { p[(x, y)].into() };
+        assert_eq!(pixel(&p, 4, 4), PIXEL_RED);
+        assert_eq!(pixel(&p, 11, 9), PIXEL_RED);
+        assert_eq!(pixel(&p, 3, 4), PIXEL_BLUE);
+        assert_eq!(pixel(&p, 4, 3), PIXEL_BLUE);
+        assert_eq!(pixel(&p, 4, 10), PIXEL_BLUE);
+
+        // zooming out, moving, and moving the camera match
+        // a scene that was created at that zoom
+        p.set_camera_zoom(0.5);
+        p.move_object_x_by(red, 6);
+        p.move_camera(2, 1);
+        p.draw_all_layers();
+        let mut expected = PortionRenderer::<u8>::new(12, 12);
+        expected.set_camera_zoom(0.5);
+        expected.set_camera_position(2, 1);
+        let (expected_red, _) = scene(&mut expected);
+        expected.move_object_x_by(expected_red, 6);
+        expected.draw_all_layers();
+        assert_eq!(p.pixel_buffer, expected.pixel_buffer);
+        assert_eq!(pixel(&p, 3, 1), PIXEL_RED);
+
+        p.move_object_x_by(red, -6);
+        p.set_camera_position(0, 0);
+        p.set_camera_zoom(1.0);
+        p.draw_all_layers();
+        assert_eq!(p.pixel_buffer, unzoomed);
+    }
+
+    #[test]
+    fn bilinear_zoom_blends_texture_pixels() {
+        let texture = vec![255, 0, 0, 255, 0, 0, 255, 255];
+        let mut p = PortionRenderer::<u8>::new(4, 4);
+        p.create_object_from_texture(0, Rect { x: 0, y: 0, w: 2, h: 1 }, texture, 2, 1);
+        p.set_camera_zoom(2.0);
+        p.draw_all_layers();
+        let pixel = |p: &PortionRenderer<u8>, x| -> RgbaPixel { p[(x, 0)].into() };
+        assert_eq!(pixel(&p, 1), PIXEL_RED);
+
+        p.set_sampling_filter(Filter::Bilinear);
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 0), PIXEL_RED);
+        assert_eq!(pixel(&p, 3), PIXEL_BLUE);
+        let blended = pixel(&p, 1);
+        assert!(blended.r > blended.b && blended.b > 0, "{:?}", blended);
+    }
+}
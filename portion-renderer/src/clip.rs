@@ -0,0 +1,242 @@
+use super::*;
+
+/// the rect that an object on the layer is clipped to, given
+/// the renderer's clip. None if neither of them is set
+#[inline(always)]
+pub(crate) fn combined_clip(clip: Option<Rect>, layer: &Layer) -> Option<Rect> {
+    match (clip, layer.clip) {
+        (None, None) => None,
+        (Some(clip), None) | (None, Some(clip)) => Some(clip),
+        (Some(clip), Some(layer_clip)) => Some(clip.clamp_to(&layer_clip)),
+    }
+}
+
+impl<T> PortionRenderer<T> {
+    /// the rect that all drawing and clearing is constrained
+    /// to, or None if the whole screen can be drawn to
+    pub fn clip_rect(&self) -> Option<Rect> {
+        self.clip
+    }
+
+    /// the clip rect of just the layer, without the renderer's clip rect
+    pub fn layer_clip_rect(&self, layer_index: u32) -> Option<Rect> {
+        self.layers.iter()
+            .find(|layer| layer.index == layer_index)
+            .and_then(|layer| layer.clip)
+    }
+
+    /// the rect that the objects of layers[layer_index]
+    /// are drawn inside of, if they are clipped at all
+    pub(crate) fn layer_clip(&self, layer_index: usize) -> Option<Rect> {
+        combined_clip(self.clip, &self.layers[layer_index])
+    }
+
+    /// the parts of the screen that the objects of layers[layer_index]
+    /// must not draw to, which are skipped the same way as the parts
+    /// that are covered by objects above them
+    pub(crate) fn outside_layer_clip(&self, layer_index: usize) -> Vec<Rect> {
+        match self.layer_clip(layer_index) {
+            Some(clip) => Rect { x: 0, y: 0, w: self.width, h: self.height }.subtract(&clip),
+            None => vec![],
+        }
+    }
+
+    /// the clip of the object, relative to the top left of its position
+    pub fn object_clip(&self, object_index: usize) -> Option<Rect> {
+        self.objects.get(object_index)?.clip
+    }
+
+    /// the parts of the screen that the object must not draw to
+    pub(crate) fn outside_object_clip(&self, object_index: usize) -> Vec<Rect> {
+        match self.objects[object_index].clip_on_screen() {
+            Some(clip) => Rect { x: 0, y: 0, w: self.width, h: self.height }.subtract(&clip),
+            None => vec![],
+        }
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// only draws the part of the object inside of clip, eg: a progress
+    /// bar that reveals more of itself as it fills up. clip is relative
+    /// to the top left of the object, so it moves along with the object,
+    /// but it isnt rotated or scaled along with it. the part of the object
+    /// outside of clip doesnt cover the objects below it, and isnt marked
+    /// as dirty. None to draw the whole object again
+    pub fn set_object_clip(&mut self, object_index: usize, clip: Option<Rect>) {
+        self.log_command(|| RenderCommand::SetObjectClip { object_index, clip });
+        let object = &mut self.objects[object_index];
+        if object.clip == clip {
+            return;
+        }
+        object.clip = clip;
+        self.mark_object_dirty(object_index, DirtyFlags::SHAPE);
+    }
+}
+
+impl Object {
+    /// the clip of the object on the screen, which has no area if it is off screen
+    pub(crate) fn clip_on_screen(&self) -> Option<Rect> {
+        self.clip.map(|clip| OffsetRect {
+            x: self.position.x + clip.x as i32,
+            y: self.position.y + clip.y as i32,
+            w: clip.w,
+            h: clip.h,
+        }.visible_bounds())
+    }
+
+    /// the parts of within that drawing the object writes to,
+    /// see Bounds::covered_rects and Bounds::footprint
+    pub fn covered_rects(&self, within: &Rect) -> Vec<Rect> {
+        let shape = self.shape.footprint();
+        match self.clip_on_screen() {
+            Some(clip) => match within.intersection(clip) {
+                Some(within) => shape.covered_rects(&within),
+                None => vec![],
+            },
+            None => shape.covered_rects(within),
+        }
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// constrains all drawing and clearing to the clip rect, eg: so that
+    /// the content of a scrollable panel does not leak outside of it.
+    /// the pixels outside of the clip rect are left as they are, even
+    /// when the objects that were drawn there move. when the clip rect
+    /// grows, or is set to None, the part of the screen that it now
+    /// includes is drawn from the objects right away
+    pub fn set_clip_rect(&mut self, clip: Option<Rect>) {
+        self.log_command(|| RenderCommand::SetClipRect(clip));
+        let previous = std::mem::replace(&mut self.clip, clip);
+        if previous == clip {
+            return;
+        }
+        if let Some(previous) = previous {
+            let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+            // composite_region only draws the part inside of the new clip
+            for rect in screen.subtract(&previous) {
+                self.composite_region(rect);
+            }
+            self.redraw_viewports();
+        }
+    }
+
+    /// constrains the drawing of the objects on the layer to the clip rect.
+    /// the objects of the layer are drawn again on the next
+    /// draw_all_layers, inside of the new clip rect
+    pub fn set_layer_clip_rect(&mut self, layer_index: u32, clip: Option<Rect>) {
+        self.log_command(|| RenderCommand::SetLayerClipRect { layer_index, clip });
+        let layer_index = self.get_or_make_layer(layer_index);
+        let layer = &mut self.layers[layer_index];
+        if layer.clip == clip {
+            return;
+        }
+        layer.clip = clip;
+        // whatever the objects drew outside of the new clip rect
+        // is drawn from the layers below them instead
+        let object_indices = layer.objects.clone();
+        for object_index in object_indices {
+            let object = &self.objects[object_index];
+            if !object.initial_render {
+                self.removed_regions.push(object.previous_bounds);
+            }
+            self.set_layer_update(object_index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(p: &mut PortionRenderer<u8>) -> (usize, usize) {
+        let blue = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 8, h: 8 }, PIXEL_BLUE);
+        let red = p.create_object_from_color(1, Rect { x: 1, y: 1, w: 4, h: 4 }, PIXEL_RED);
+        (blue, red)
+    }
+
+    fn pixel(p: &PortionRenderer<u8>, x: u32, y: u32) -> RgbaPixel {
+        p[(x, y)].into()
+    }
+
+    #[test]
+    fn nothing_is_drawn_outside_of_the_clip_rect() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let (_, red) = scene(&mut p);
+        p.set_clip_rect(Some(Rect { x: 2, y: 2, w: 4, h: 4 }));
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 2, 2), PIXEL_RED);
+        assert_eq!(pixel(&p, 5, 5), PIXEL_BLUE);
+        assert_eq!(pixel(&p, 1, 1), PIXEL_BLANK);
+        assert_eq!(pixel(&p, 6, 6), PIXEL_BLANK);
+
+        // the red pixels outside of the clip rect are left behind
+        // when the red rect moves, but not the ones inside of it
+        p.move_object_x_by(red, 3);
+        p.draw(&[9; 4], Rect { x: 0, y: 7, w: 1, h: 1 });
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 2, 2), PIXEL_BLUE);
+        assert_eq!(pixel(&p, 4, 4), PIXEL_RED);
+        assert_eq!(pixel(&p, 0, 7), PIXEL_BLANK);
+
+        // removing the clip draws everything that was left out
+        p.set_clip_rect(None);
+        let mut expected = PortionRenderer::<u8>::new(8, 8);
+        let (_, expected_red) = scene(&mut expected);
+        expected.move_object_x_by(expected_red, 3);
+        expected.draw_all_layers();
+        assert_eq!(p.pixel_buffer, expected.pixel_buffer);
+    }
+
+    #[test]
+    fn layer_clip_rect_only_clips_its_layer() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let (_, red) = scene(&mut p);
+        p.draw_all_layers();
+        p.set_layer_clip_rect(1, Some(Rect { x: 0, y: 0, w: 3, h: 8 }));
+        assert_eq!(p.layer_clip_rect(1), Some(Rect { x: 0, y: 0, w: 3, h: 8 }));
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 2, 2), PIXEL_RED);
+        assert_eq!(pixel(&p, 3, 2), PIXEL_BLUE);
+        assert_eq!(pixel(&p, 6, 6), PIXEL_BLUE);
+
+        // the blue rect below is what gets cleared back to
+        // where the red rect is clipped away
+        p.move_object_y_by(red, 2);
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 1, 1), PIXEL_BLUE);
+        assert_eq!(pixel(&p, 1, 6), PIXEL_RED);
+        assert_eq!(pixel(&p, 4, 6), PIXEL_BLUE);
+    }
+
+    #[test]
+    fn object_clip_only_draws_and_damages_part_of_the_object() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 8, h: 8 }, PIXEL_BLUE);
+        let bar = p.create_object_from_color(1, Rect { x: 0, y: 2, w: 8, h: 1 }, PIXEL_RED);
+        p.set_object_clip(bar, Some(Rect { x: 0, y: 0, w: 3, h: 1 }));
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 2, 2), PIXEL_RED);
+        assert_eq!(pixel(&p, 3, 2), PIXEL_BLUE);
+
+        // the bar fills up, and only the part it grew into is marked
+        p.take_dirty_rects();
+        p.set_object_clip(bar, Some(Rect { x: 0, y: 0, w: 5, h: 1 }));
+        assert_eq!(p.object_clip(bar), Some(Rect { x: 0, y: 0, w: 5, h: 1 }));
+        p.draw_all_layers();
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 2, w: 6, h: 2 }]);
+        assert_eq!(pixel(&p, 4, 2), PIXEL_RED);
+        assert_eq!(pixel(&p, 5, 2), PIXEL_BLUE);
+
+        // the clip moves along with the object
+        p.move_object_x_by(bar, 1);
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 0, 2), PIXEL_BLUE);
+        assert_eq!(pixel(&p, 5, 2), PIXEL_RED);
+        assert_eq!(pixel(&p, 6, 2), PIXEL_BLUE);
+
+        p.set_object_clip(bar, None);
+        p.draw_all_layers();
+        assert_eq!(pixel(&p, 7, 2), PIXEL_RED);
+    }
+}
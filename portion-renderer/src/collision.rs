@@ -0,0 +1,54 @@
+use super::*;
+
+impl PortionRenderer<u8> {
+    /// true if the objects have a non transparent pixel on the same
+    /// pixel of the screen. the shapes of the objects are checked first,
+    /// so most objects that dont collide are cheap to check, and then
+    /// every pixel where their shapes overlap is sampled the same way that
+    /// drawing samples it, including rotation. only the part of the
+    /// objects that is on screen can collide. false if either object doesnt exist
+    pub fn objects_collide(&self, a: usize, b: usize) -> bool {
+        let (object_a, object_b) = match (self.objects.get(a), self.objects.get(b)) {
+            (Some(object_a), Some(object_b)) => (object_a, object_b),
+            _ => return false,
+        };
+        let overlap = match object_a.intersection(object_b.get_bounds())
+            .and_then(|overlap| object_b.intersection(overlap))
+        {
+            Some(overlap) if !overlap.is_empty() => overlap,
+            _ => return false,
+        };
+        let ctx = draw_context!(self);
+        let (sampler_a, sampler_b) = (ctx.sampler(a), ctx.sampler(b));
+        let is_opaque = |pixel: Option<RgbaPixel>| matches!(pixel, Some(pixel) if pixel.a != 0);
+        (overlap.y..overlap.y + overlap.h).any(|y| {
+            (overlap.x..overlap.x + overlap.w).any(|x| {
+                is_opaque(sampler_a.sample(x, y)) && is_opaque(sampler_b.sample(x, y))
+            })
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_opaque_pixels_collide() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        // a 2x2 texture where only the bottom right pixel is opaque
+        let mut texture = vec![0; 16];
+        texture[12..16].copy_from_slice(&[255, 0, 0, 255]);
+        let corner = p.create_object_from_texture(0, Rect { x: 0, y: 0, w: 2, h: 2 }, texture, 2, 2);
+        let block = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 2 }, PIXEL_BLUE);
+        let far = p.create_object_from_color(0, Rect { x: 6, y: 6, w: 2, h: 2 }, PIXEL_BLUE);
+
+        // the bounds overlap, but the overlap is transparent in the texture
+        assert!(!p.objects_collide(corner, block));
+        p.move_object_x_by(block, 1);
+        assert!(p.objects_collide(corner, block));
+        assert!(p.objects_collide(block, corner));
+        assert!(!p.objects_collide(corner, far));
+        assert!(!p.objects_collide(corner, 100));
+    }
+}
@@ -0,0 +1,401 @@
+use std::ops::{Add, Mul, Sub};
+
+use super::*;
+
+/// a change to the color of every pixel of a texture, see adjust_texture
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ColorAdjustment {
+    /// multiplies the color channels, eg: 1.5 is 50% brighter
+    Brightness(f32),
+    /// scales how far each color channel is from the middle gray,
+    /// eg: 0.0 makes every pixel gray, and 2.0 doubles the contrast
+    Contrast(f32),
+    Grayscale,
+    Sepia,
+    Invert,
+    /// rotates the hue by this many degrees, see RgbaPixel::hue_shifted
+    HueRotate(f32),
+}
+
+impl ColorAdjustment {
+    /// the pixel after the adjustment. the alpha is left as it is
+    pub fn apply(&self, pixel: RgbaPixel) -> RgbaPixel {
+        let clamped = |c: f32| c.round().clamp(0.0, 255.0) as u8;
+        let (r, g, b) = (pixel.r as f32, pixel.g as f32, pixel.b as f32);
+        match *self {
+            ColorAdjustment::Brightness(by) => pixel * by,
+            ColorAdjustment::Contrast(by) => {
+                let channel = |c: f32| clamped((c - 128.0) * by + 128.0);
+                RgbaPixel { r: channel(r), g: channel(g), b: channel(b), a: pixel.a }
+            }
+            ColorAdjustment::Grayscale => {
+                let luma = clamped(0.299 * r + 0.587 * g + 0.114 * b);
+                RgbaPixel { r: luma, g: luma, b: luma, a: pixel.a }
+            }
+            ColorAdjustment::Sepia => RgbaPixel {
+                r: clamped(0.393 * r + 0.769 * g + 0.189 * b),
+                g: clamped(0.349 * r + 0.686 * g + 0.168 * b),
+                b: clamped(0.272 * r + 0.534 * g + 0.131 * b),
+                a: pixel.a,
+            },
+            ColorAdjustment::Invert => RgbaPixel {
+                r: 255 - pixel.r,
+                g: 255 - pixel.g,
+                b: 255 - pixel.b,
+                a: pixel.a,
+            },
+            ColorAdjustment::HueRotate(degrees) => pixel.hue_shifted(degrees),
+        }
+    }
+}
+
+/// applies the adjustment to every RGBA8888 pixel of pixels.
+/// transparent pixels are left as they are
+pub fn adjust_pixels(pixels: &mut [u8], adjustment: ColorAdjustment) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        if pixel[3] == 0 {
+            continue;
+        }
+        let adjusted = adjustment.apply(RgbaPixel::from(&*pixel));
+        pixel.copy_from_slice(&[adjusted.r, adjusted.g, adjusted.b, adjusted.a]);
+    }
+}
+
+impl RgbaPixel {
+    /// the pixel of a u32 whose bytes, from the most significant
+    /// to the least, are the bytes of a pixel in the format.
+    /// eg: 0xRRGGBBAA for RGBA8888, or 0xAARRGGBB for ARGB8888
+    pub fn from_u32(value: u32, format: PixelFormatEnum) -> RgbaPixel {
+        let bytes = value.to_be_bytes();
+        let order = format.channel_order();
+        RgbaPixel {
+            r: bytes[order[0]],
+            g: bytes[order[1]],
+            b: bytes[order[2]],
+            a: bytes[order[3]],
+        }
+    }
+
+    /// the pixel of 0xRRGGBBAA
+    pub fn from_u32_rgba(value: u32) -> RgbaPixel {
+        RgbaPixel::from_u32(value, PixelFormatEnum::RGBA8888)
+    }
+
+    /// the pixel of 0xAARRGGBB
+    pub fn from_u32_argb(value: u32) -> RgbaPixel {
+        RgbaPixel::from_u32(value, PixelFormatEnum::ARGB8888)
+    }
+
+    /// the opposite of from_u32
+    pub fn to_u32(&self, format: PixelFormatEnum) -> u32 {
+        let mut bytes = [0; 4];
+        format.write_rgba(&[self.r, self.g, self.b, self.a], &mut bytes);
+        u32::from_be_bytes(bytes)
+    }
+
+    /// parses "#rrggbbaa", or "#rrggbb" for an opaque color.
+    /// the # is optional. returns None if it is not a hex color
+    pub fn from_hex(hex: &str) -> Option<RgbaPixel> {
+        let hex = hex.strip_prefix('#').unwrap_or(hex);
+        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok();
+        let a = match hex.len() {
+            6 => 255,
+            8 => channel(3)?,
+            _ => return None,
+        };
+        Some(RgbaPixel { r: channel(0)?, g: channel(1)?, b: channel(2)?, a })
+    }
+
+    /// the pixel as "#rrggbbaa", see from_hex
+    pub fn to_hex(&self) -> String {
+        format!("#{:02x}{:02x}{:02x}{:02x}", self.r, self.g, self.b, self.a)
+    }
+
+    /// the color t of the way from this pixel to other, including
+    /// the alpha. t is clamped to [0, 1]
+    pub fn lerp(&self, other: RgbaPixel, t: f32) -> RgbaPixel {
+        let t = t.clamp(0.0, 1.0);
+        let channel = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+        RgbaPixel {
+            r: channel(self.r, other.r),
+            g: channel(self.g, other.g),
+            b: channel(self.b, other.b),
+            a: channel(self.a, other.a),
+        }
+    }
+
+    /// (hue, saturation, value), where the hue is in degrees
+    /// [0, 360) and the saturation and value are in [0, 1]
+    pub fn to_hsv(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.unit_rgb();
+        let max = r.max(g).max(b);
+        let chroma = max - r.min(g).min(b);
+        let saturation = if max == 0.0 { 0.0 } else { chroma / max };
+        (hue_of(r, g, b, max, chroma), saturation, max)
+    }
+
+    /// the opposite of to_hsv. the hue wraps around, and the
+    /// saturation and value are clamped to [0, 1]
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32, a: u8) -> RgbaPixel {
+        let value = value.clamp(0.0, 1.0);
+        let chroma = value * saturation.clamp(0.0, 1.0);
+        from_hue_chroma(hue, chroma, value - chroma, a)
+    }
+
+    /// (hue, saturation, lightness), where the hue is in degrees
+    /// [0, 360) and the saturation and lightness are in [0, 1]
+    pub fn to_hsl(&self) -> (f32, f32, f32) {
+        let (r, g, b) = self.unit_rgb();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let chroma = max - min;
+        let lightness = (max + min) / 2.0;
+        let saturation = if chroma == 0.0 { 0.0 } else { chroma / (1.0 - (2.0 * lightness - 1.0).abs()) };
+        (hue_of(r, g, b, max, chroma), saturation, lightness)
+    }
+
+    /// the opposite of to_hsl. the hue wraps around, and the
+    /// saturation and lightness are clamped to [0, 1]
+    pub fn from_hsl(hue: f32, saturation: f32, lightness: f32, a: u8) -> RgbaPixel {
+        let lightness = lightness.clamp(0.0, 1.0);
+        let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation.clamp(0.0, 1.0);
+        from_hue_chroma(hue, chroma, lightness - chroma / 2.0, a)
+    }
+
+    /// the same color, with its hue rotated by degrees
+    pub fn hue_shifted(&self, degrees: f32) -> RgbaPixel {
+        let (hue, saturation, value) = self.to_hsv();
+        RgbaPixel::from_hsv(hue + degrees, saturation, value, self.a)
+    }
+
+    /// r, g, and b as [0, 1]
+    fn unit_rgb(&self) -> (f32, f32, f32) {
+        (self.r as f32 / 255.0, self.g as f32 / 255.0, self.b as f32 / 255.0)
+    }
+}
+
+/// adds every channel, including the alpha, stopping at 255
+impl Add for RgbaPixel {
+    type Output = RgbaPixel;
+
+    fn add(self, other: RgbaPixel) -> RgbaPixel {
+        RgbaPixel {
+            r: self.r.saturating_add(other.r),
+            g: self.g.saturating_add(other.g),
+            b: self.b.saturating_add(other.b),
+            a: self.a.saturating_add(other.a),
+        }
+    }
+}
+
+/// subtracts every channel, including the alpha, stopping at 0
+impl Sub for RgbaPixel {
+    type Output = RgbaPixel;
+
+    fn sub(self, other: RgbaPixel) -> RgbaPixel {
+        RgbaPixel {
+            r: self.r.saturating_sub(other.r),
+            g: self.g.saturating_sub(other.g),
+            b: self.b.saturating_sub(other.b),
+            a: self.a.saturating_sub(other.a),
+        }
+    }
+}
+
+/// multiplies every channel as if they were in [0, 1], eg: to tint
+/// a pixel. multiplying by PIXEL_WHITE leaves the pixel as it is
+impl Mul for RgbaPixel {
+    type Output = RgbaPixel;
+
+    fn mul(self, other: RgbaPixel) -> RgbaPixel {
+        let channel = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8;
+        RgbaPixel {
+            r: channel(self.r, other.r),
+            g: channel(self.g, other.g),
+            b: channel(self.b, other.b),
+            a: channel(self.a, other.a),
+        }
+    }
+}
+
+/// scales the color channels, stopping at 0 and 255.
+/// the alpha is left as it is
+impl Mul<f32> for RgbaPixel {
+    type Output = RgbaPixel;
+
+    fn mul(self, by: f32) -> RgbaPixel {
+        let channel = |c: u8| (c as f32 * by).round().clamp(0.0, 255.0) as u8;
+        RgbaPixel { r: channel(self.r), g: channel(self.g), b: channel(self.b), a: self.a }
+    }
+}
+
+/// rotates the hue of every RGBA8888 pixel of pixels by degrees, eg: to
+/// recolor a texture before creating an object from it.
+/// transparent pixels are left as they are
+pub fn hue_shift_pixels(pixels: &mut [u8], degrees: f32) {
+    adjust_pixels(pixels, ColorAdjustment::HueRotate(degrees));
+}
+
+impl PortionRenderer<u8> {
+    /// changes the colors of the texture in place, eg: Brightness for a
+    /// hit flash, or a tint for day and night, without making a copy of
+    /// the texture. every object that draws the texture is drawn
+    /// again on the next draw_all_layers.
+    /// panics if there is no texture at texture_index
+    pub fn adjust_texture(&mut self, texture_index: usize, adjustment: ColorAdjustment) {
+        self.log_command(|| RenderCommand::AdjustTexture { texture_index, adjustment });
+        let texture = match self.textures.get_mut(texture_index) {
+            Some(texture) => Arc::make_mut(texture),
+            None => panic!("Cannot adjust texture {} because it does not exist", texture_index),
+        };
+        adjust_pixels(&mut texture.data, adjustment);
+        let users: Vec<usize> = self.objects.iter()
+            .filter(|(_, object)| object.texture_color.is_none() && object.texture_index == texture_index)
+            .map(|(object_index, _)| object_index)
+            .collect();
+        for object_index in users {
+            self.mark_object_dirty(object_index, DirtyFlags::TEXTURE);
+        }
+    }
+}
+
+/// the hue in degrees of the unit rgb color, whose
+/// largest channel is max, and max - min is chroma
+fn hue_of(r: f32, g: f32, b: f32, max: f32, chroma: f32) -> f32 {
+    if chroma == 0.0 {
+        return 0.0;
+    }
+    let sector = if max == r {
+        ((g - b) / chroma).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / chroma + 2.0
+    } else {
+        (r - g) / chroma + 4.0
+    };
+    sector * 60.0
+}
+
+/// the color of the hue with the chroma, with m added to every channel.
+/// both hsv and hsl only differ by how they get the chroma and m
+fn from_hue_chroma(hue: f32, chroma: f32, m: f32, a: u8) -> RgbaPixel {
+    let sector = hue.rem_euclid(360.0) / 60.0;
+    let x = chroma * (1.0 - (sector.rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match sector as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let channel = |c: f32| ((c + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    RgbaPixel { r: channel(r), g: channel(g), b: channel(b), a }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PIXEL: RgbaPixel = RgbaPixel { r: 0x11, g: 0x22, b: 0x33, a: 0x44 };
+
+    #[test]
+    fn u32_conversions_follow_the_pixel_format() {
+        assert_eq!(RgbaPixel::from_u32_rgba(0x11223344), PIXEL);
+        assert_eq!(RgbaPixel::from_u32_argb(0x44112233), PIXEL);
+        assert_eq!(RgbaPixel::from_u32(0x33221144, PixelFormatEnum::BGRA8888), PIXEL);
+        assert_eq!(PIXEL.to_u32(PixelFormatEnum::ABGR8888), 0x44332211);
+        let formats = [
+            PixelFormatEnum::ABGR8888, PixelFormatEnum::ARGB8888,
+            PixelFormatEnum::RGBA8888, PixelFormatEnum::BGRA8888,
+        ];
+        for format in formats.iter() {
+            assert_eq!(RgbaPixel::from_u32(PIXEL.to_u32(*format), *format), PIXEL);
+        }
+    }
+
+    #[test]
+    fn hex_conversions() {
+        assert_eq!(RgbaPixel::from_hex("#11223344"), Some(PIXEL));
+        assert_eq!(RgbaPixel::from_hex("ff0000"), Some(PIXEL_RED));
+        assert_eq!(PIXEL.to_hex(), "#11223344");
+        assert_eq!(RgbaPixel::from_hex("#1122334"), None);
+        assert_eq!(RgbaPixel::from_hex("#11223g"), None);
+        assert_eq!(RgbaPixel::from_hex("#ééé"), None);
+        assert_eq!(RgbaPixel::from_hex("+1+2+3"), None);
+    }
+
+    #[test]
+    fn lerp_blends_every_channel() {
+        let half = PIXEL_RED.lerp(PIXEL_BLANK, 0.5);
+        assert_eq!(half, RgbaPixel { r: 128, g: 0, b: 0, a: 128 });
+        assert_eq!(PIXEL_RED.lerp(PIXEL_BLUE, 0.0), PIXEL_RED);
+        assert_eq!(PIXEL_RED.lerp(PIXEL_BLUE, 2.0), PIXEL_BLUE);
+    }
+
+    #[test]
+    fn hsv_and_hsl_round_trip() {
+        assert_eq!(PIXEL_GREEN.to_hsv(), (120.0, 1.0, 1.0));
+        assert_eq!(PIXEL_BLUE.to_hsl(), (240.0, 1.0, 0.5));
+        assert_eq!(RgbaPixel::from_hsv(-120.0, 1.0, 1.0, 255), PIXEL_BLUE);
+        assert_eq!(RgbaPixel::from_hsl(0.0, 1.0, 0.5, 255), PIXEL_RED);
+        for pixel in [PIXEL, PIXEL_BLACK, RgbaPixel { r: 200, g: 150, b: 10, a: 9 }].iter() {
+            let (h, s, v) = pixel.to_hsv();
+            assert_eq!(RgbaPixel::from_hsv(h, s, v, pixel.a), *pixel);
+            let (h, s, l) = pixel.to_hsl();
+            assert_eq!(RgbaPixel::from_hsl(h, s, l, pixel.a), *pixel);
+        }
+    }
+
+    #[test]
+    fn arithmetic_saturates() {
+        let gray = RgbaPixel { r: 200, g: 100, b: 0, a: 255 };
+        assert_eq!(gray + PIXEL, RgbaPixel { r: 217, g: 134, b: 51, a: 255 });
+        assert_eq!(gray + gray, RgbaPixel { r: 255, g: 200, b: 0, a: 255 });
+        assert_eq!(PIXEL - gray, RgbaPixel { r: 0, g: 0, b: 0x33, a: 0 });
+        assert_eq!(gray * PIXEL_WHITE, gray);
+        assert_eq!(gray * PIXEL_RED, RgbaPixel { r: 200, g: 0, b: 0, a: 255 });
+        assert_eq!(gray * 1.5, RgbaPixel { r: 255, g: 150, b: 0, a: 255 });
+        assert_eq!(gray * -1.0, RgbaPixel { r: 0, g: 0, b: 0, a: 255 });
+    }
+
+    #[test]
+    fn adjustments_leave_the_alpha_alone() {
+        let pixel = RgbaPixel { r: 200, g: 100, b: 50, a: 9 };
+        let adjust = |adjustment: ColorAdjustment| adjustment.apply(pixel);
+        assert_eq!(adjust(ColorAdjustment::Brightness(0.5)), RgbaPixel { r: 100, g: 50, b: 25, a: 9 });
+        assert_eq!(adjust(ColorAdjustment::Contrast(0.0)), RgbaPixel { r: 128, g: 128, b: 128, a: 9 });
+        assert_eq!(adjust(ColorAdjustment::Contrast(2.0)), RgbaPixel { r: 255, g: 72, b: 0, a: 9 });
+        assert_eq!(adjust(ColorAdjustment::Grayscale), RgbaPixel { r: 124, g: 124, b: 124, a: 9 });
+        assert_eq!(adjust(ColorAdjustment::Sepia), RgbaPixel { r: 165, g: 147, b: 114, a: 9 });
+        assert_eq!(adjust(ColorAdjustment::Invert), RgbaPixel { r: 55, g: 155, b: 205, a: 9 });
+    }
+
+    #[test]
+    fn adjusting_a_texture_redraws_its_objects() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let texture_index = p.create_texture(Texture::new(vec![255, 0, 0, 255], 1, 1));
+        p.create_object_from_texture_index(0, Rect { x: 0, y: 0, w: 1, h: 1 }, texture_index);
+        p.create_object_from_texture_index(0, Rect { x: 4, y: 4, w: 1, h: 1 }, texture_index);
+        p.draw_all_layers();
+        p.take_dirty_rects();
+
+        p.adjust_texture(texture_index, ColorAdjustment::HueRotate(120.0));
+        p.draw_all_layers();
+        let pixel = |p: &PortionRenderer<u8>, x, y| -> RgbaPixel { p[(x, y)].into() };
+        assert_eq!(pixel(&p, 0, 0), PIXEL_GREEN);
+        assert_eq!(pixel(&p, 4, 4), PIXEL_GREEN);
+        assert_eq!(p.take_dirty_rects().len(), 2);
+    }
+
+    #[test]
+    fn hue_shifting_recolors_pixels() {
+        assert_eq!(PIXEL_RED.hue_shifted(120.0), PIXEL_GREEN);
+        let mut pixels = vec![255, 0, 0, 255, 255, 0, 0, 0];
+        hue_shift_pixels(&mut pixels, 240.0);
+        assert_eq!(pixels, vec![0, 0, 255, 255, 255, 0, 0, 0]);
+    }
+}
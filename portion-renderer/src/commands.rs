@@ -0,0 +1,264 @@
+use super::*;
+
+/// a call that changes the renderer, so that a sequence of them
+/// can be recorded and applied again to another renderer
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RenderCommand<T> {
+    CreateObject {
+        layer_index: u32,
+        bounds: Rect,
+        texture: Option<Texture<T>>,
+        color: Option<RgbaPixel>,
+    },
+    CreateTexture(Texture<T>),
+    CreateObjectFromTextureIndex { layer_index: u32, bounds: Rect, texture_index: usize },
+    ReleaseTexture(usize),
+    AdjustTexture { texture_index: usize, adjustment: ColorAdjustment },
+    RemoveObject(usize),
+    RedrawObject(usize),
+    MoveObjectXBy { object_index: usize, by: i32 },
+    MoveObjectYBy { object_index: usize, by: i32 },
+    SetObjectRotation { object_index: usize, degrees: f32 },
+    SetObjectShape { object_index: usize, shape: Bounds },
+    SetObjectSourceRect { object_index: usize, source: Option<Rect> },
+    SetObjectColor { object_index: usize, color: RgbaPixel },
+    SetObjectClip { object_index: usize, clip: Option<Rect> },
+    SetObjectMask { object_index: usize, mask: Option<usize> },
+    SetObjectBackground { object_index: usize, background: RgbaPixel },
+    CreateLayerGroup { follows_camera: bool },
+    RemoveLayerGroup(usize),
+    SetLayerGroup { layer_index: u32, group_index: Option<usize> },
+    SetLayerGroupOffset { group_index: usize, x: i32, y: i32 },
+    SetLayerGroupOpacity { group_index: usize, opacity: f32 },
+    SetClearSource(ClearSource<T>),
+    MoveCamera { dx: i32, dy: i32 },
+    SetCameraZoom(f32),
+    AddViewport { screen: Rect, camera_x: i32, camera_y: i32 },
+    RemoveViewport(usize),
+    SetViewportCamera { viewport_index: usize, x: i32, y: i32 },
+    SetSamplingFilter(Filter),
+    SetAntialiasing(bool),
+    SetGridSnapping(bool),
+    SetClipRect(Option<Rect>),
+    SetLayerClipRect { layer_index: u32, clip: Option<Rect> },
+    Draw { pixels: Vec<T>, bounds: Rect },
+    BlurRegion { rect: Rect, radius: u32 },
+    BoxBlurRegion { rect: Rect, radius: u32 },
+    EnableLighting { cell_size: u32, ambient: RgbaPixel },
+    DisableLighting,
+    SetAmbientLight(RgbaPixel),
+    AddLight(Light),
+    SetLight { light_index: usize, light: Light },
+    RemoveLight(usize),
+    /// any of the draw_all_layers variants, since they all draw the same thing
+    DrawAllLayers,
+    ForceDrawAllLayers,
+    DamageAllLayers,
+}
+
+/// every command applied to a renderer since the log was started,
+/// and what is needed to make a renderer like the one it started on
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CommandLog<T> {
+    pub width: u32,
+    pub height: u32,
+    pub num_rows: u32,
+    pub num_cols: u32,
+    pub pixel_format: PixelFormatEnum,
+    pub commands: Vec<RenderCommand<T>>,
+}
+
+impl CommandLog<u8> {
+    /// applies every command to a new renderer. if the log was started
+    /// right after the original renderer was created, the result
+    /// has the same pixels, objects, and dirty portions
+    pub fn replay(&self) -> PortionRenderer<u8> {
+        let mut renderer = PortionRenderer::new_ex(
+            self.width, self.height, self.num_rows, self.num_cols, self.pixel_format,
+        );
+        for command in self.commands.iter() {
+            renderer.apply_command(command.clone());
+        }
+        renderer
+    }
+}
+
+/// queues commands for a renderer from another thread, see
+/// PortionRenderer::commands. objects created through the queue
+/// get their index when the command is applied, in the order
+/// they were queued, the same as calling create_object directly.
+/// commands that are queued after the renderer was dropped are ignored
+#[derive(Clone)]
+pub struct RendererCommands<T> {
+    sender: Sender<RenderCommand<T>>,
+}
+
+impl<T> RendererCommands<T> {
+    pub(crate) fn new(sender: Sender<RenderCommand<T>>) -> RendererCommands<T> {
+        RendererCommands { sender }
+    }
+
+    pub fn send(&self, command: RenderCommand<T>) {
+        // the only error is that the renderer is gone, in which
+        // case there is nothing left to apply the command to
+        let _ = self.sender.send(command);
+    }
+
+    pub fn create_object_from_color(&self, layer_index: u32, bounds: Rect, color: RgbaPixel) {
+        self.send(RenderCommand::CreateObject { layer_index, bounds, texture: None, color: Some(color) });
+    }
+
+    pub fn create_object_from_texture(
+        &self, layer_index: u32, bounds: Rect,
+        texture_data: Vec<T>, texture_width: u32, texture_height: u32,
+    ) {
+        let texture = Texture::new(texture_data, texture_width, texture_height);
+        self.send(RenderCommand::CreateObject { layer_index, bounds, texture: Some(texture), color: None });
+    }
+
+    pub fn move_object_x_by(&self, object_index: usize, by: i32) {
+        self.send(RenderCommand::MoveObjectXBy { object_index, by });
+    }
+
+    pub fn move_object_y_by(&self, object_index: usize, by: i32) {
+        self.send(RenderCommand::MoveObjectYBy { object_index, by });
+    }
+
+    pub fn set_object_rotation(&self, object_index: usize, degrees: f32) {
+        self.send(RenderCommand::SetObjectRotation { object_index, degrees });
+    }
+
+    pub fn remove_object(&self, object_index: usize) {
+        self.send(RenderCommand::RemoveObject(object_index));
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// applies every command that was queued by a RendererCommands
+    /// handle so far. this is called by draw_all_layers, so it is
+    /// only needed to see the queued changes without drawing
+    pub fn apply_queued_commands(&mut self) {
+        let queued: Vec<RenderCommand<u8>> = match self.command_queue.as_ref() {
+            Some((_, receiver)) => receiver.try_iter().collect(),
+            None => return,
+        };
+        for command in queued {
+            self.apply_command(command);
+        }
+    }
+
+    /// calls the method that the command was recorded from
+    pub fn apply_command(&mut self, command: RenderCommand<u8>) {
+        match command {
+            RenderCommand::CreateObject { layer_index, bounds, texture, color } => {
+                self.create_object(layer_index, bounds, texture, color);
+            }
+            RenderCommand::CreateTexture(texture) => {
+                self.create_texture(texture);
+            }
+            RenderCommand::CreateObjectFromTextureIndex { layer_index, bounds, texture_index } => {
+                self.create_object_from_texture_index(layer_index, bounds, texture_index);
+            }
+            RenderCommand::ReleaseTexture(texture_index) => self.release_texture(texture_index),
+            RenderCommand::AdjustTexture { texture_index, adjustment } => self.adjust_texture(texture_index, adjustment),
+            RenderCommand::RemoveObject(object_index) => {
+                self.remove_object(object_index);
+            }
+            RenderCommand::RedrawObject(object_index) => {
+                self.redraw_object(object_index);
+            }
+            RenderCommand::MoveObjectXBy { object_index, by } => self.move_object_x_by(object_index, by),
+            RenderCommand::MoveObjectYBy { object_index, by } => self.move_object_y_by(object_index, by),
+            RenderCommand::SetObjectRotation { object_index, degrees } => self.set_object_rotation(object_index, degrees),
+            RenderCommand::SetObjectShape { object_index, shape } => self.set_object_shape(object_index, shape),
+            RenderCommand::SetObjectSourceRect { object_index, source } => self.set_object_source_rect(object_index, source),
+            RenderCommand::SetObjectColor { object_index, color } => self.set_object_color(object_index, color),
+            RenderCommand::SetObjectClip { object_index, clip } => self.set_object_clip(object_index, clip),
+            RenderCommand::SetObjectMask { object_index, mask } => self.set_object_mask(object_index, mask),
+            RenderCommand::SetObjectBackground { object_index, background } => self.set_object_background(object_index, background),
+            RenderCommand::CreateLayerGroup { follows_camera } => {
+                self.create_layer_group(follows_camera);
+            }
+            RenderCommand::RemoveLayerGroup(group_index) => self.remove_layer_group(group_index),
+            RenderCommand::SetLayerGroup { layer_index, group_index } => self.set_layer_group(layer_index, group_index),
+            RenderCommand::SetLayerGroupOffset { group_index, x, y } => self.set_layer_group_offset(group_index, x, y),
+            RenderCommand::SetLayerGroupOpacity { group_index, opacity } => self.set_layer_group_opacity(group_index, opacity),
+            RenderCommand::SetClearSource(clear) => self.set_clear_source(clear),
+            RenderCommand::MoveCamera { dx, dy } => self.move_camera(dx, dy),
+            RenderCommand::SetCameraZoom(zoom) => self.set_camera_zoom(zoom),
+            RenderCommand::AddViewport { screen, camera_x, camera_y } => {
+                self.add_viewport(screen, camera_x, camera_y);
+            }
+            RenderCommand::RemoveViewport(viewport_index) => self.remove_viewport(viewport_index),
+            RenderCommand::SetViewportCamera { viewport_index, x, y } => self.set_viewport_camera(viewport_index, x, y),
+            RenderCommand::SetSamplingFilter(filter) => self.set_sampling_filter(filter),
+            RenderCommand::SetAntialiasing(enabled) => self.set_antialiasing(enabled),
+            RenderCommand::SetGridSnapping(enabled) => self.set_grid_snapping(enabled),
+            RenderCommand::SetClipRect(clip) => self.set_clip_rect(clip),
+            RenderCommand::SetLayerClipRect { layer_index, clip } => self.set_layer_clip_rect(layer_index, clip),
+            RenderCommand::Draw { pixels, bounds } => self.draw(&pixels, bounds),
+            RenderCommand::BlurRegion { rect, radius } => self.blur_region(rect, radius),
+            RenderCommand::BoxBlurRegion { rect, radius } => self.box_blur_region(rect, radius),
+            RenderCommand::EnableLighting { cell_size, ambient } => self.enable_lighting(cell_size, ambient),
+            RenderCommand::DisableLighting => self.disable_lighting(),
+            RenderCommand::SetAmbientLight(ambient) => self.set_ambient_light(ambient),
+            RenderCommand::AddLight(light) => {
+                self.add_light(light);
+            }
+            RenderCommand::SetLight { light_index, light } => self.set_light(light_index, light),
+            RenderCommand::RemoveLight(light_index) => {
+                self.remove_light(light_index);
+            }
+            RenderCommand::DrawAllLayers => self.draw_all_layers(),
+            RenderCommand::ForceDrawAllLayers => self.force_draw_all_layers(),
+            RenderCommand::DamageAllLayers => self.damage_all_layers(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replay_matches_the_original() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.start_command_log();
+        let red = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 3, h: 3 }, PIXEL_RED);
+        p.create_object_from_texture(1, Rect { x: 4, y: 4, w: 2, h: 2 }, vec![9; 16], 2, 2);
+        p.draw_all_layers();
+        p.set_object_position(red, 2, 3);
+        p.set_object_rotation(red, -90.0);
+        p.draw(&[7; 4], Rect { x: 7, y: 0, w: 1, h: 1 });
+        p.draw_all_layers();
+
+        let log = p.take_command_log().unwrap();
+        assert_eq!(log.commands.len(), 8);
+        assert!(p.take_command_log().is_none());
+        let mut replayed = log.replay();
+        assert_eq!(replayed.pixel_buffer, p.pixel_buffer);
+        assert_eq!(replayed.take_dirty_rects(), p.take_dirty_rects());
+    }
+
+    #[test]
+    fn queued_commands_are_applied_when_drawing() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let mut direct = PortionRenderer::<u8>::new(8, 8);
+        let commands = p.commands();
+        std::thread::spawn(move || {
+            commands.create_object_from_color(0, Rect { x: 0, y: 0, w: 3, h: 3 }, PIXEL_RED);
+            commands.move_object_x_by(0, 2);
+            commands.set_object_rotation(0, 45.0);
+        }).join().unwrap();
+        assert!(p.get_object(0).is_none());
+        p.draw_all_layers();
+
+        let red = direct.create_object_from_color(0, Rect { x: 0, y: 0, w: 3, h: 3 }, PIXEL_RED);
+        direct.move_object_x_by(red, 2);
+        direct.set_object_rotation(red, 45.0);
+        direct.draw_all_layers();
+        assert_eq!(p.pixel_buffer, direct.pixel_buffer);
+    }
+}
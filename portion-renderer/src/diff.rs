@@ -0,0 +1,88 @@
+use super::*;
+
+impl<T: Clone + PartialEq> PortionRenderer<T> {
+    /// the pixels that changed since the last call, as runs of changed
+    /// pixels in a row, each with its values in the pixel buffer. unlike the
+    /// dirty rects, nothing that is the same as before is included, eg: for
+    /// a remote desktop or an e-paper display, where every byte sent counts.
+    /// a copy of the presented frame is kept to compare against, and the
+    /// first call returns every row of the screen. only the dirty portions
+    /// are compared, so this takes the renderer's dirty rects, and dont
+    /// mix it with other presenters
+    pub fn diff_from_previous(&mut self) -> Vec<(Rect, &[T])> {
+        let dirty = self.take_dirty_rects();
+        let ipp = self.indices_per_pixel as usize;
+        let mut runs = vec![];
+        match self.previous_frame.as_mut() {
+            Some(previous) => {
+                for rect in dirty.iter() {
+                    for y in rect.y..rect.y + rect.h {
+                        let start = red_index(rect.x, y, self.width, self.indices_per_pixel);
+                        let now = &self.pixel_buffer[start..start + rect.w as usize * ipp];
+                        let before = &mut previous[start..start + rect.w as usize * ipp];
+                        let mut x = 0;
+                        while x < rect.w as usize {
+                            let same = |x: usize| now[x * ipp..(x + 1) * ipp] == before[x * ipp..(x + 1) * ipp];
+                            if same(x) {
+                                x += 1;
+                                continue;
+                            }
+                            let run_start = x;
+                            while x < rect.w as usize && !same(x) {
+                                x += 1;
+                            }
+                            runs.push(Rect { x: rect.x + run_start as u32, y, w: (x - run_start) as u32, h: 1 });
+                        }
+                        before.clone_from_slice(now);
+                    }
+                }
+            }
+            None => {
+                self.previous_frame = Some(self.pixel_buffer.clone());
+                if self.width > 0 {
+                    runs.extend((0..self.height).map(|y| Rect { x: 0, y, w: self.width, h: 1 }));
+                }
+            }
+        }
+        let (width, indices_per_pixel, pixels) = (self.width, self.indices_per_pixel, &self.pixel_buffer);
+        runs.into_iter()
+            .map(|run| {
+                let start = red_index(run.x, run.y, width, indices_per_pixel);
+                (run, &pixels[start..start + run.w as usize * ipp])
+            })
+            .collect()
+    }
+
+    /// frees the copy of the frame that diff_from_previous compares
+    /// against, so its next call returns the whole screen again
+    pub fn forget_previous_frame(&mut self) {
+        self.previous_frame = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_changed_pixels_are_returned() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let red = p.create_object_from_color(0, Rect { x: 1, y: 1, w: 2, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        assert_eq!(p.diff_from_previous().len(), 8);
+
+        // the portions are 2x2, but only the pixels that moved changed
+        p.move_object_x_by(red, 1);
+        p.draw_all_layers();
+        let diff = p.diff_from_previous();
+        assert_eq!(diff.len(), 2);
+        assert_eq!(diff[0], (Rect { x: 1, y: 1, w: 1, h: 1 }, &[0, 0, 0, 0][..]));
+        assert_eq!(diff[1], (Rect { x: 3, y: 1, w: 1, h: 1 }, &[255, 0, 0, 255][..]));
+        assert!(p.diff_from_previous().is_empty());
+
+        // drawn over with the same pixels
+        p.set_object_updated(red);
+        p.draw_all_layers();
+        assert!(p.diff_from_previous().is_empty());
+    }
+}
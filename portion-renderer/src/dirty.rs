@@ -0,0 +1,114 @@
+use std::ops::{BitOr, BitOrAssign};
+
+use super::*;
+
+/// what changed about an object since it was last drawn. an object
+/// is only queued to be drawn when something actually changed, and
+/// only once, no matter how many of its properties changed
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DirtyFlags(u8);
+
+impl DirtyFlags {
+    pub const NONE: DirtyFlags = DirtyFlags(0);
+    /// where the object is on screen
+    pub const POSITION: DirtyFlags = DirtyFlags(1);
+    /// its rotation, or the camera zoom
+    pub const TRANSFORM: DirtyFlags = DirtyFlags(1 << 1);
+    /// the pixels of its texture, or the part of the texture it draws
+    pub const TEXTURE: DirtyFlags = DirtyFlags(1 << 2);
+    pub const COLOR: DirtyFlags = DirtyFlags(1 << 3);
+    pub const SHAPE: DirtyFlags = DirtyFlags(1 << 4);
+    /// eg: a newly created object, or set_object_updated
+    pub const ALL: DirtyFlags = DirtyFlags(0b11111);
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// true if every flag of other is set
+    pub fn contains(self, other: DirtyFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// true if any flag of other is set
+    pub fn intersects(self, other: DirtyFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for DirtyFlags {
+    type Output = DirtyFlags;
+
+    fn bitor(self, other: DirtyFlags) -> DirtyFlags {
+        DirtyFlags(self.0 | other.0)
+    }
+}
+
+impl BitOrAssign for DirtyFlags {
+    fn bitor_assign(&mut self, other: DirtyFlags) {
+        self.0 |= other.0;
+    }
+}
+
+impl<T> PortionRenderer<T> {
+    /// what changed about the object since it was last drawn,
+    /// or NONE if it wont be drawn by the next draw_all_layers
+    pub fn object_dirty_flags(&self, object_index: usize) -> DirtyFlags {
+        self.objects[object_index].dirty
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// queues the object to be drawn, unless it is queued
+    /// already, or nothing changed, ie: flags is NONE
+    pub fn mark_object_dirty(&mut self, object_index: usize, flags: DirtyFlags) {
+        if flags.is_empty() {
+            return;
+        }
+        if self.mask_moved(object_index) {
+            self.mark_masked_dirty(object_index);
+            return;
+        }
+        let object = &mut self.objects[object_index];
+        let was_dirty = !object.dirty.is_empty();
+        object.dirty |= flags;
+        if !was_dirty {
+            self.layers[object.layer_index].updates.push(object_index);
+        }
+        self.update_spatial_index(object_index);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_real_changes_queue_an_update() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let o = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+        assert_eq!(p.object_dirty_flags(o), DirtyFlags::ALL);
+        p.draw_all_layers();
+        assert!(p.object_dirty_flags(o).is_empty());
+
+        // setting what the object already is doesnt change anything
+        p.set_object_color(o, PIXEL_RED);
+        p.set_object_rotation(o, 0.0);
+        p.move_object_x_by(o, 0);
+        assert!(p.object_dirty_flags(o).is_empty());
+        assert!(p.layers[0].updates.is_empty());
+
+        p.set_object_color(o, PIXEL_BLUE);
+        p.move_object_x_by(o, 1);
+        p.move_object_y_by(o, 1);
+        let flags = p.object_dirty_flags(o);
+        assert!(flags.contains(DirtyFlags::COLOR | DirtyFlags::POSITION));
+        assert!(!flags.intersects(DirtyFlags::TRANSFORM | DirtyFlags::TEXTURE));
+        // queued once, no matter how many times it changed
+        assert_eq!(p.layers[0].updates, vec![o]);
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(2, 2)]), PIXEL_BLUE);
+        assert!(p.object_dirty_flags(o).is_empty());
+    }
+}
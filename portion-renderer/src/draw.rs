@@ -0,0 +1,988 @@
+use std::borrow::Cow;
+use std::ops::Range;
+use tightvec::TightVec;
+
+use super::*;
+// the fixed point sampling takes precedence over the float one from super
+#[cfg(feature = "fixed-point")]
+use super::fixed::{Fixed, interpolate_filtered, interpolate_nearest_pixel};
+
+/// everything that drawing an object reads from. This is split out
+/// of the renderer so that drawing only needs mutable access to the
+/// pixels it writes to, which lets separate bands of the
+/// pixel buffer be drawn at the same time.
+pub struct DrawContext<'a> {
+    pub textures: &'a TightVec<Arc<Texture<u8>>>,
+    pub objects: &'a TightVec<Object>,
+    pub clear: &'a ClearSource<u8>,
+    pub width: u32,
+    pub indices_per_pixel: u32,
+    pub antialias: bool,
+    /// how textures are sampled when the object is transformed
+    pub filter: Filter,
+    /// the renderer's clip rect, see clip.rs
+    pub clip: Option<Rect>,
+    /// only used for the clip rects and groups of the layers
+    pub layers: &'a [Layer],
+    /// only used for the opacity of the layers, see group.rs
+    pub groups: &'a TightVec<LayerGroup>,
+    /// only set when frame stats are enabled
+    pub counter: Option<&'a PixelCounter>,
+    /// only set when overdraw tracking is enabled
+    pub overdraw: Option<&'a OverdrawMap>,
+    /// what the pixels are written with, see backend.rs
+    pub backend: &'a dyn RenderBackend,
+    /// the (col_width, row_height) of the portions, when
+    /// objects are snapped to them, see snap.rs
+    pub snap: Option<(u32, u32)>,
+}
+
+/// the rows [y_start, y_end) of the pixel buffer.
+/// pixels[0] is the first pixel of row y_start
+pub struct Band<'a> {
+    pub pixels: &'a mut [u8],
+    pub y_start: u32,
+    pub y_end: u32,
+    /// the index of pixels[0] in the entire pixel buffer
+    pub offset: usize,
+}
+
+/// one object update, with the state of the object at the time
+/// that it was queued so that the updates can be drawn later
+/// in any number of bands, and still give the same result
+pub struct DrawWork {
+    pub object_index: usize,
+    pub previous_bounds: Rect,
+    pub previous_shape: Bounds,
+    /// the parts of the previous bounds that need to be cleared
+    pub clear_regions: Vec<Rect>,
+    pub is_first_time: bool,
+    pub skip_above: AboveRegions,
+    pub skip_below: BelowRegions,
+}
+
+/// the part of the screen that an object is drawn to
+#[derive(Copy, Clone)]
+pub struct DrawArea<'b> {
+    /// the regions of the objects above, which are skipped
+    pub skip_above: &'b AboveRegions,
+    pub shape: &'b Bounds,
+    /// the rows [min_y, max_y) and the columns [min_x, max_x)
+    pub min_y: u32,
+    pub max_y: u32,
+    pub min_x: u32,
+    pub max_x: u32,
+}
+
+/// how a rotated object is sampled. shift_x and shift_y are the top left
+/// of the object, and width and height are its size before it was rotated
+pub struct RotatedDraw {
+    pub transform: Matrix,
+    pub shift_x: f32,
+    pub shift_y: f32,
+    pub width: u32,
+    pub height: u32,
+    /// what the parts of the object outside of its texture are drawn as
+    pub background: RgbaPixel,
+}
+
+impl RotatedDraw {
+    pub fn new(transform: Matrix, position: OffsetRect, background: RgbaPixel) -> RotatedDraw {
+        RotatedDraw {
+            transform,
+            shift_x: position.x as f32,
+            shift_y: position.y as f32,
+            width: position.w,
+            height: position.h,
+            background,
+        }
+    }
+
+    fn mapping(&self, source: Rect) -> RotatedMapping {
+        RotatedMapping::new(&self.transform, self.shift_x, self.shift_y, self.width, self.height, source)
+    }
+}
+
+impl<'b> DrawArea<'b> {
+    /// the same area, but over the whole shape, which
+    /// is where a rotated object is drawn
+    fn whole_shape(&self) -> DrawArea<'b> {
+        let bounds = self.shape.get_bounds();
+        DrawArea {
+            min_y: bounds.y,
+            max_y: bounds.y + bounds.h,
+            min_x: bounds.x,
+            max_x: bounds.x + bounds.w,
+            ..*self
+        }
+    }
+}
+
+/// everything needed to read the pixels of one object, looked up
+/// once so that reading many pixels of the same object does not
+/// need to look up the object, its texture, or convert its matrix again
+pub struct ObjectSampler<'a> {
+    /// the footprint of the shape, which together with the bounds
+    /// gives the same pixels that drawing the object writes to
+    shape: Cow<'a, Bounds>,
+    pub(crate) bounds: Rect,
+    source: SampleSource<'a>,
+    /// only the pixels where the mask is opaque are part of the object, see mask.rs
+    mask: Option<Box<ObjectSampler<'a>>>,
+    /// the alpha of every pixel is scaled by this, see group.rs
+    opacity: u8,
+}
+
+enum SampleSource<'a> {
+    Color(RgbaPixel),
+    RotatedColor {
+        color: RgbaPixel,
+        mapping: RotatedMapping,
+        background: RgbaPixel,
+    },
+    RotatedTexture {
+        texture: &'a Texture<u8>,
+        mapping: RotatedMapping,
+        filter: Filter,
+        background: RgbaPixel,
+    },
+    Exact {
+        texture: &'a Texture<u8>,
+        position: OffsetRect,
+        source: Rect,
+        current_bounds: Rect,
+        indices_per_pixel: u32,
+    },
+}
+
+impl<'a> ObjectSampler<'a> {
+    /// see Bounds::spans
+    #[inline(always)]
+    pub fn spans(&self, y: u32) -> (u32, u32) {
+        let bounds = &self.bounds;
+        if y < bounds.y || y >= bounds.y + bounds.h {
+            return (0, 0);
+        }
+        let (start, end) = self.shape.spans(y);
+        let (start, end) = (start.max(bounds.x), end.min(bounds.x + bounds.w));
+        match &self.mask {
+            Some(mask) => {
+                let (mask_start, mask_end) = mask.spans(y);
+                (start.max(mask_start), end.min(mask_end))
+            }
+            None => (start, end),
+        }
+    }
+
+    /// the pixel of the object at the screen coordinate (x, y)
+    pub fn sample(&self, x: u32, y: u32) -> Option<RgbaPixel> {
+        let pixel = self.sample_source(x, y)?;
+        if self.opacity == 255 {
+            return Some(pixel);
+        }
+        let a = (pixel.a as u32 * self.opacity as u32 + 127) / 255;
+        Some(RgbaPixel { a: a as u8, ..pixel })
+    }
+
+    fn sample_source(&self, x: u32, y: u32) -> Option<RgbaPixel> {
+        // the corners of round/polygon objects are not part of the object
+        match &*self.shape {
+            Bounds::Circle(_) | Bounds::Polygon(_) if !self.shape.contains_u32(x, y) => return None,
+            // the same pixels that drawing the rotated object writes
+            Bounds::Tilted(t) => {
+                let (start, end) = t.spans(y);
+                if x < start || x >= end {
+                    return None;
+                }
+            }
+            _ => {}
+        }
+        if let Some(mask) = &self.mask {
+            let (start, end) = mask.spans(y);
+            if x < start || x >= end {
+                return None;
+            }
+            match mask.sample(x, y) {
+                Some(pixel) if pixel.a != 0 => {}
+                _ => return None,
+            }
+        }
+
+        match &self.source {
+            SampleSource::Color(color) => Some(*color),
+            SampleSource::RotatedColor { color, mapping, background } => {
+                Some(mapping.sample_color(*color, x, y, *background))
+            }
+            SampleSource::RotatedTexture { texture, mapping, filter, background } => {
+                Some(mapping.sample_texture(*filter, texture, x, y, *background))
+            }
+            SampleSource::Exact { texture, position, source, current_bounds, indices_per_pixel } => {
+                // it should be guaranteed that x and y exist within the objects current bounds
+                if x < current_bounds.x || y < current_bounds.y {
+                    panic!("Called get_pixel_from_object_at with ({}, {}) but objects bounds are {:?}", x, y, current_bounds);
+                }
+                // the position can be negative, so the local coordinates
+                // are relative to the full bounds, not just the visible bounds
+                let local_x = (x as i64 - position.x as i64) as u32;
+                let local_y = (y as i64 - position.y as i64) as u32;
+                let texture_x = texture_coord(local_x, position.w, source.x, source.w);
+                let texture_y = texture_coord(local_y, position.h, source.y, source.h);
+                if texture_x >= texture.width || texture_y >= texture.height {
+                    return None;
+                }
+                let red_index = red_index(texture_x, texture_y, texture.width, *indices_per_pixel);
+                texture.data.get(red_index..(red_index + 4)).map(|u8_slice| u8_slice.into())
+            }
+        }
+    }
+}
+
+impl<'a> Band<'a> {
+    #[inline(always)]
+    pub fn rows(&self, min_y: u32, max_y: u32) -> Range<u32> {
+        min_y.max(self.y_start)..max_y.min(self.y_end)
+    }
+}
+
+impl<'a> DrawContext<'a> {
+    /// the index into band.pixels of the pixel at (x, y).
+    /// y must be within the band's rows
+    #[inline(always)]
+    fn band_index(&self, band: &Band, x: u32, y: u32) -> usize {
+        red_index(x, y, self.width, self.indices_per_pixel) - band.offset
+    }
+
+    /// the part of an object past the right edge of the screen would
+    /// wrap around to the next row, so it gets cut off. the rows
+    /// past the bottom are already cut off by the band
+    #[inline(always)]
+    fn clip_x(&self, max_x: u32) -> u32 {
+        max_x.min(self.width)
+    }
+
+    /// clears the pixels of row y between [x_start, x_end) from the clear source
+    #[inline(always)]
+    fn clear_span(&self, band: &mut Band, y: u32, x_start: u32, x_end: u32) {
+        let span_len = (x_end - x_start) as usize * self.indices_per_pixel as usize;
+        let clear_index = red_index(x_start, y, self.width, self.indices_per_pixel);
+        let red_index = clear_index - band.offset;
+        let dest = &mut band.pixels[red_index..red_index + span_len];
+        match self.clear {
+            ClearSource::Buffer(buffer) => {
+                self.backend.copy_row(dest, &buffer[clear_index..clear_index + span_len]);
+            }
+            ClearSource::SolidColor(color) => self.backend.fill_span(dest, *color),
+            ClearSource::None => {}
+        }
+    }
+
+    #[inline(always)]
+    fn count_written(&self, n: usize) {
+        if let Some(counter) = self.counter {
+            counter.add_written(n);
+        }
+    }
+
+    /// counts a write to every pixel of row y between [x_start, x_end)
+    #[inline(always)]
+    fn count_overdraw(&self, y: u32, x_start: u32, x_end: u32) {
+        if let Some(overdraw) = self.overdraw {
+            overdraw.add_span(y, x_start, x_end);
+        }
+    }
+
+    #[inline(always)]
+    fn count_cleared(&self, n: usize) {
+        if let Some(counter) = self.counter {
+            counter.add_cleared(n);
+        }
+    }
+
+    pub fn get_pixel_from_object_at_rotated(
+        &self,
+        object_index: usize,
+        transform: &Matrix,
+        x: u32, y: u32,
+    ) -> Option<RgbaPixel> {
+        let obj = &self.objects[object_index];
+        let texture = &self.textures[obj.texture_index];
+        let position = obj.position;
+        let mapping = RotatedMapping::new(
+            transform, position.x as f32, position.y as f32,
+            position.w, position.h, obj.source_rect(),
+        );
+        Some(mapping.sample_texture(self.filter, texture, x, y, obj.background))
+    }
+
+    pub fn get_pixel_from_object_at(
+        &self,
+        object_index: usize,
+        x: u32, y: u32
+    ) -> Option<RgbaPixel> {
+        self.sampler(object_index).sample(x, y)
+    }
+
+    pub fn sampler(&self, object_index: usize) -> ObjectSampler<'a> {
+        let mut sampler = self.unmasked_sampler(object_index);
+        if let Some(mask_index) = self.objects[object_index].mask {
+            sampler.mask = Some(Box::new(self.unmasked_sampler(mask_index)));
+        }
+        sampler
+    }
+
+    fn unmasked_sampler(&self, object_index: usize) -> ObjectSampler<'a> {
+        let objects: &'a TightVec<Object> = self.objects;
+        let object = &objects[object_index];
+        let position = object.position;
+        let source = match (object.transform, object.texture_color) {
+            (Some(transform), Some(color)) => SampleSource::RotatedColor {
+                color,
+                mapping: RotatedMapping::new(
+                    &transform, position.x as f32, position.y as f32,
+                    position.w, position.h, object.source_rect(),
+                ),
+                background: object.background,
+            },
+            (Some(transform), None) => {
+                SampleSource::RotatedTexture {
+                    texture: &self.textures[object.texture_index],
+                    mapping: RotatedMapping::new(
+                        &transform, position.x as f32, position.y as f32,
+                        position.w, position.h, object.source_rect(),
+                    ),
+                    filter: self.filter,
+                    background: object.background,
+                }
+            }
+            (None, Some(color)) => SampleSource::Color(color),
+            (None, None) => SampleSource::Exact {
+                texture: &self.textures[object.texture_index],
+                position,
+                source: object.source_rect(),
+                current_bounds: object.current_bounds,
+                indices_per_pixel: self.indices_per_pixel,
+            },
+        };
+        let shape = object.shape.footprint();
+        let bounds = match combined_clip(self.clip, &self.layers[object.layer_index]) {
+            Some(clip) => object.get_bounds().clamp_to(&clip),
+            None => object.get_bounds(),
+        };
+        let opacity = layer_opacity(&self.layers[object.layer_index], self.groups);
+        ObjectSampler { shape, bounds, source, mask: None, opacity }
+    }
+
+    /// a sampler for each of the below regions, in the same order
+    fn below_samplers(&self, skip_below: &BelowRegions) -> Vec<(Rect, ObjectSampler<'a>)> {
+        skip_below.below_my_previous.iter()
+            .map(|below| (below.region, self.sampler(below.region_belongs_to)))
+            .collect()
+    }
+
+    /// pb_red_index is the index into band.pixels
+    pub fn clear_pixels_from_below_object(
+        &self, band: &mut Band,
+        pb_red_index: usize, x: u32, y: u32,
+        skip_below: &BelowRegions,
+    ) -> bool {
+        let below = self.below_samplers(skip_below);
+        clear_pixel_from_below_samplers(self.backend, band, pb_red_index, x, y, &below)
+    }
+
+    /// rotated objects are drawn over their whole shape
+    pub fn draw_pixel(
+        &self, band: &mut Band, pixel: RgbaPixel,
+        area: &DrawArea, rotated: Option<&RotatedDraw>,
+    ) {
+        if let Some(rotated) = rotated {
+            return self.draw_pixel_rotated(band, pixel, &area.whole_shape(), rotated);
+        }
+
+        let DrawArea { skip_above, shape, min_y, max_y, min_x, max_x } = *area;
+        let max_x = self.clip_x(max_x);
+        let clipper = RowClipper::new(&skip_above.above_my_current, min_x, max_x, min_y, max_y);
+        let mut spans = vec![];
+        let mut written = 0;
+        for i in band.rows(min_y, max_y) {
+            let (span_start, span_end) = shape.spans(i);
+            let span_start = span_start.max(min_x);
+            let span_end = span_end.min(max_x);
+            clipper.visible_spans(i, span_start, span_end, &mut spans);
+            for &(start, end) in spans.iter() {
+                written += (end - start) as usize;
+                self.count_overdraw(i, start, end);
+                // TODO: pixel format???
+                let red_index = self.band_index(band, start, i);
+                let span_len = (end - start) as usize * self.indices_per_pixel as usize;
+                self.backend.fill_span(&mut band.pixels[red_index..red_index + span_len], pixel);
+            }
+        }
+        self.count_written(written);
+    }
+
+    pub fn draw_pixel_rotated(
+        &self, band: &mut Band, pixel: RgbaPixel,
+        area: &DrawArea, rotated: &RotatedDraw,
+    ) {
+        let DrawArea { skip_above, shape, min_y, max_y, min_x, max_x } = *area;
+        let source = Rect { x: 0, y: 0, w: rotated.width, h: rotated.height };
+        let mapping = rotated.mapping(source);
+        let background = rotated.background;
+        let shape = shape.footprint();
+        let max_x = self.clip_x(max_x);
+        let clipper = RowClipper::new(&skip_above.above_my_current, min_x, max_x, min_y, max_y);
+        let mut spans = vec![];
+        let mut written = 0;
+        for i in band.rows(min_y, max_y) {
+            let (span_start, span_end) = shape.spans(i);
+            let span_start = span_start.max(min_x);
+            let span_end = span_end.min(max_x);
+            clipper.visible_spans(i, span_start, span_end, &mut spans);
+            for j in spans.iter().flat_map(|&(start, end)| start..end) {
+                let pix = mapping.sample_color(pixel, j, i, background);
+                if pix.a == 0 {
+                    continue;
+                }
+                let red_index = self.band_index(band, j, i);
+                if self.antialias {
+                    let coverage = shape.coverage(j as f32, i as f32);
+                    if coverage <= 0.0 {
+                        continue;
+                    }
+                    if coverage < 1.0 {
+                        let dest = &mut band.pixels[red_index..red_index + 4];
+                        self.backend.blend(dest, pix, coverage);
+                        written += 1;
+                        self.count_overdraw(i, j, j + 1);
+                        continue;
+                    }
+                }
+                // TODO: pixel format?
+                self.backend.set_pixel(&mut band.pixels[red_index..red_index + 4], pix);
+                written += 1;
+                self.count_overdraw(i, j, j + 1);
+            }
+        }
+        self.count_written(written);
+    }
+
+    /// source is the part of the texture that is stretched over the object
+    pub fn draw_exact_rotated(
+        &self, band: &mut Band, texture_index: usize,
+        area: &DrawArea, rotated: &RotatedDraw, source: Rect,
+    ) {
+        let DrawArea { skip_above, shape, min_y, max_y, min_x, max_x } = *area;
+        let texture = &self.textures[texture_index];
+        let mapping = rotated.mapping(source);
+        let background = rotated.background;
+        let shape = shape.footprint();
+        let max_x = self.clip_x(max_x);
+        let clipper = RowClipper::new(&skip_above.above_my_current, min_x, max_x, min_y, max_y);
+        let mut spans = vec![];
+        let mut written = 0;
+        for i in band.rows(min_y, max_y) {
+            let (span_start, span_end) = shape.spans(i);
+            let span_start = span_start.max(min_x);
+            let span_end = span_end.min(max_x);
+            clipper.visible_spans(i, span_start, span_end, &mut spans);
+            for j in spans.iter().flat_map(|&(start, end)| start..end) {
+                let pix = mapping.sample_texture(self.filter, texture, j, i, background);
+                if pix.a == 0 {
+                    continue;
+                }
+                let red_index = self.band_index(band, j, i);
+                if self.antialias {
+                    let coverage = shape.coverage(j as f32, i as f32);
+                    if coverage <= 0.0 {
+                        continue;
+                    }
+                    if coverage < 1.0 {
+                        let dest = &mut band.pixels[red_index..red_index + 4];
+                        self.backend.blend(dest, pix, coverage);
+                        written += 1;
+                        self.count_overdraw(i, j, j + 1);
+                        continue;
+                    }
+                }
+                // TODO: pixel format?
+                self.backend.set_pixel(&mut band.pixels[red_index..red_index + 4], pix);
+                written += 1;
+                self.count_overdraw(i, j, j + 1);
+            }
+        }
+        self.count_written(written);
+    }
+
+    /// source is the part of the texture that is stretched over
+    /// the object's position, see Object::source_rect.
+    /// rotated objects are drawn over their whole shape
+    pub fn draw_exact(
+        &self, band: &mut Band, texture_index: usize,
+        area: &DrawArea, rotated: Option<&RotatedDraw>,
+        position: OffsetRect, source: Rect,
+    ) {
+        if let Some(rotated) = rotated {
+            return self.draw_exact_rotated(band, texture_index, &area.whole_shape(), rotated, source);
+        }
+
+        let DrawArea { skip_above, shape, min_y, max_y, min_x, max_x } = *area;
+        let texture = &self.textures[texture_index];
+        let item_pixels = &texture.data;
+        let indices_per_pixel = self.indices_per_pixel as usize;
+        // if part of the object is off the top/left of the screen, we skip
+        // those rows and columns of the texture
+        let (clip_x, clip_y) = position.clipped_by();
+        let item_pitch = texture.width as usize * indices_per_pixel;
+        let is_scaled = source.w != position.w || source.h != position.h;
+        let max_x = self.clip_x(max_x);
+        let clipper = RowClipper::new(&skip_above.above_my_current, min_x, max_x, min_y, max_y);
+        let mut spans = vec![];
+        let mut written = 0;
+        for i in band.rows(min_y, max_y) {
+            let row = i - min_y;
+            let texture_y = texture_coord(clip_y + row, position.h, source.y, source.h);
+            // the parts of the object past the texture are transparent
+            if texture_y >= texture.height {
+                continue;
+            }
+            let (span_start, span_end) = shape.spans(i);
+            let span_start = span_start.max(min_x);
+            let span_end = span_end.min(max_x);
+            let row_start = texture_y as usize * item_pitch;
+            clipper.visible_spans(i, span_start, span_end, &mut spans);
+            for &(start, end) in spans.iter() {
+                let item_x = texture_coord(clip_x + start - min_x, position.w, source.x, source.w);
+                // if every pixel of this span is opaque,
+                // it can be copied at once
+                if !is_scaled && item_x + (end - start) <= texture.width {
+                    let item_pixel_index = row_start + item_x as usize * indices_per_pixel;
+                    let span_len = (end - start) as usize * indices_per_pixel;
+                    let src = &item_pixels[item_pixel_index..item_pixel_index + span_len];
+                    if src.chunks_exact(indices_per_pixel).all(|p| p[3] != 0) {
+                        let red_index = self.band_index(band, start, i);
+                        self.backend.copy_row(&mut band.pixels[red_index..red_index + span_len], src);
+                        written += (end - start) as usize;
+                        self.count_overdraw(i, start, end);
+                        continue;
+                    }
+                }
+                for j in start..end {
+                    let item_x = texture_coord(clip_x + j - min_x, position.w, source.x, source.w);
+                    if item_x >= texture.width {
+                        continue;
+                    }
+                    let item_pixel_index = row_start + item_x as usize * indices_per_pixel;
+                    // if the alpha value is 0, skip this pixel
+                    if item_pixels[item_pixel_index + 3] == 0 {
+                        continue;
+                    }
+
+                    let red_index = self.band_index(band, j, i);
+                    // TODO: pixel format???
+                    let pixel = (&item_pixels[item_pixel_index..item_pixel_index + 4]).into();
+                    self.backend.set_pixel(&mut band.pixels[red_index..red_index + 4], pixel);
+                    written += 1;
+                    self.count_overdraw(i, j, j + 1);
+                }
+            }
+        }
+        self.count_written(written);
+    }
+
+    /// restores the pixels of shape that are inside of the bounds,
+    /// from whatever is below them, or the clear source
+    pub fn clear_object_previous_bounds(
+        &self, band: &mut Band,
+        area: &DrawArea,
+        skip_below: &BelowRegions,
+    ) {
+        let DrawArea { skip_above, shape, min_y, max_y, min_x, max_x } = *area;
+        let should_try_clear_below = !skip_below.below_my_previous.is_empty();
+        let below_regions: Vec<Rect> = skip_below.below_my_previous.iter().map(|b| b.region).collect();
+        let below_samplers = self.below_samplers(skip_below);
+        let max_x = self.clip_x(max_x);
+        let clipper = RowClipper::new(&skip_above.above_my_previous, min_x, max_x, min_y, max_y);
+        let mut spans = vec![];
+        let mut cleared = 0;
+        for i in band.rows(min_y, max_y) {
+            let (span_start, span_end) = shape.spans(i);
+            let span_start = span_start.max(min_x);
+            let span_end = span_end.min(max_x);
+            clipper.visible_spans(i, span_start, span_end, &mut spans);
+            for &(start, end) in spans.iter() {
+                cleared += (end - start) as usize;
+                self.count_overdraw(i, start, end);
+                // if nothing is below this span, then all of
+                // it comes from the clear source
+                if !row_intersects_any(&below_regions, i, start, end) {
+                    self.clear_span(band, i, start, end);
+                    continue;
+                }
+                for j in start..end {
+                    let red_index = self.band_index(band, j, i);
+
+                    // try to clear this pixel from what was
+                    // underneath it first
+                    if should_try_clear_below && clear_pixel_from_below_samplers(
+                        self.backend, band, red_index, j, i, &below_samplers
+                    ) { continue; }
+
+                    self.clear_span(band, i, j, j + 1);
+                }
+            }
+        }
+        self.count_cleared(cleared);
+    }
+
+    /// redraws every pixel of rect in this band from the objects in
+    /// top_down, which are ordered from the top most object to the bottom.
+    /// each pixel is written once: by the first object that has a visible
+    /// pixel there, or by the clear source if no object does.
+    /// antialiasing is not applied
+    pub fn composite_rect(&self, band: &mut Band, rect: Rect, top_down: &[usize]) {
+        let samplers: Vec<ObjectSampler> = top_down.iter().map(|i| self.sampler(*i)).collect();
+        let mut filled = vec![false; rect.w as usize];
+        let mut written = 0;
+        let mut cleared = 0;
+        for y in band.rows(rect.y, rect.y + rect.h) {
+            filled.iter_mut().for_each(|f| *f = false);
+            let mut remaining = rect.w;
+            for sampler in samplers.iter() {
+                if remaining == 0 {
+                    break;
+                }
+                let (span_start, span_end) = sampler.spans(y);
+                let span_start = span_start.max(rect.x);
+                let span_end = span_end.min(rect.x + rect.w);
+                for x in span_start..span_end {
+                    let filled = &mut filled[(x - rect.x) as usize];
+                    if *filled {
+                        continue;
+                    }
+                    let pixel = match sampler.sample(x, y) {
+                        Some(pixel) if pixel.a != 0 => pixel,
+                        _ => continue,
+                    };
+                    let red_index = self.band_index(band, x, y);
+                    self.backend.set_pixel(&mut band.pixels[red_index..red_index + 4], pixel);
+                    *filled = true;
+                    remaining -= 1;
+                }
+            }
+            written += (rect.w - remaining) as usize;
+            self.count_overdraw(y, rect.x, rect.x + rect.w);
+            cleared += remaining as usize;
+
+            // whatever no object covers comes from the clear source
+            let mut x = 0;
+            while remaining > 0 && x < rect.w {
+                if filled[x as usize] {
+                    x += 1;
+                    continue;
+                }
+                let start = x;
+                while x < rect.w && !filled[x as usize] {
+                    x += 1;
+                }
+                self.clear_span(band, y, rect.x + start, rect.x + x);
+            }
+        }
+        self.count_written(written);
+        self.count_cleared(cleared);
+    }
+
+    /// the pixel of the clear source at (x, y), or blank if there is none
+    fn clear_pixel(&self, x: u32, y: u32) -> RgbaPixel {
+        match self.clear {
+            ClearSource::Buffer(buffer) => {
+                let index = red_index(x, y, self.width, self.indices_per_pixel);
+                (&buffer[index..index + 4]).into()
+            }
+            ClearSource::SolidColor(color) => *color,
+            ClearSource::None => PIXEL_BLANK,
+        }
+    }
+
+    /// the color of (x, y) with the pixels of every object in top_down,
+    /// which are ordered from the top most object to the bottom, blended
+    /// over each other and over the clear source by their alpha
+    pub fn composite_pixel(&self, x: u32, y: u32, top_down: &[usize]) -> RgbaPixel {
+        let samplers: Vec<ObjectSampler> = top_down.iter().map(|i| self.sampler(*i)).collect();
+        let below = self.clear_pixel(x, y);
+        // the color so far is premultiplied by its alpha, and whatever
+        // is below only shows through the part that isnt covered yet
+        let (mut r, mut g, mut b) = (0.0, 0.0, 0.0);
+        let mut uncovered = 1.0;
+        // the objects are only near (x, y), so they might not be on it
+        let visible = samplers.iter()
+            .filter(|sampler| {
+                let (start, end) = sampler.spans(y);
+                x >= start && x < end
+            })
+            .filter_map(|sampler| visible_sample(sampler, x, y));
+        for pixel in visible.chain(std::iter::once(below)) {
+            let weight = uncovered * pixel.a as f32 / 255.0;
+            r += pixel.r as f32 * weight;
+            g += pixel.g as f32 * weight;
+            b += pixel.b as f32 * weight;
+            uncovered -= weight;
+            if uncovered <= 0.0 {
+                break;
+            }
+        }
+        let alpha = 1.0 - uncovered;
+        if alpha <= 0.0 {
+            return PIXEL_BLANK;
+        }
+        RgbaPixel {
+            r: (r / alpha).round() as u8,
+            g: (g / alpha).round() as u8,
+            b: (b / alpha).round() as u8,
+            a: (alpha * 255.0).round() as u8,
+        }
+    }
+
+    /// clears the parts of the previous bounds of the object that need clearing, in this band
+    pub fn clear_work(&self, band: &mut Band, work: &DrawWork) {
+        let previous_shape = work.previous_shape.footprint();
+        for prev in work.clear_regions.iter() {
+            let area = DrawArea {
+                skip_above: &work.skip_above,
+                shape: &previous_shape,
+                min_y: prev.y, max_y: prev.y + prev.h,
+                min_x: prev.x, max_x: prev.x + prev.w,
+            };
+            self.clear_object_previous_bounds(band, &area, &work.skip_below);
+        }
+    }
+
+    /// the fast path for snapped objects: an opaque color rect that nothing
+    /// is drawn above is drawn by filling its first row, and then copying
+    /// that row to the rest of its rows. returns false if the object
+    /// cant be drawn this way
+    fn draw_snapped(&self, band: &mut Band, work: &DrawWork) -> bool {
+        let object = &self.objects[work.object_index];
+        let color = match object.texture_color {
+            Some(color) if color.a == 255 => color,
+            _ => return false,
+        };
+        if self.snap.is_none() || object.transform.is_some() || !object.shape.is_rect()
+            || !work.skip_above.above_my_current.is_empty() {
+            return false;
+        }
+        let now = object.current_bounds;
+        let max_x = self.clip_x(now.x + now.w);
+        let mut rows = band.rows(now.y, now.y + now.h);
+        let first = match rows.next() {
+            Some(first) if now.x < max_x => first,
+            _ => return true,
+        };
+        let span_len = (max_x - now.x) as usize * self.indices_per_pixel as usize;
+        let first_index = self.band_index(band, now.x, first);
+        self.backend.fill_span(&mut band.pixels[first_index..first_index + span_len], color);
+        self.count_overdraw(first, now.x, max_x);
+        for y in rows.clone() {
+            let index = self.band_index(band, now.x, y);
+            let (src, dest) = band.pixels.split_at_mut(index);
+            self.backend.copy_row(&mut dest[..span_len], &src[first_index..first_index + span_len]);
+            self.count_overdraw(y, now.x, max_x);
+        }
+        self.count_written((max_x - now.x) as usize * (rows.len() + 1));
+        true
+    }
+
+    /// draws a masked or faded object pixel by pixel from its sampler,
+    /// which leaves out every pixel where the mask is transparent
+    fn draw_sampled(&self, band: &mut Band, work: &DrawWork) {
+        let sampler = self.sampler(work.object_index);
+        let now = sampler.bounds;
+        let max_x = self.clip_x(now.x + now.w);
+        let clipper = RowClipper::new(&work.skip_above.above_my_current, now.x, max_x, now.y, now.y + now.h);
+        let mut spans = vec![];
+        let mut written = 0;
+        for y in band.rows(now.y, now.y + now.h) {
+            let (span_start, span_end) = sampler.spans(y);
+            if span_start >= span_end.min(max_x) {
+                continue;
+            }
+            clipper.visible_spans(y, span_start, span_end.min(max_x), &mut spans);
+            for &(start, end) in spans.iter() {
+                self.count_overdraw(y, start, end);
+                for x in start..end {
+                    let pixel = match sampler.sample(x, y) {
+                        Some(pixel) if pixel.a != 0 => pixel,
+                        _ => continue,
+                    };
+                    let red_index = self.band_index(band, x, y);
+                    self.backend.set_pixel(&mut band.pixels[red_index..red_index + 4], pixel);
+                    written += 1;
+                }
+            }
+        }
+        self.count_written(written);
+    }
+
+    /// draws the object's current bounds in this band
+    pub fn draw_work(&self, band: &mut Band, work: &DrawWork) {
+        let object = &self.objects[work.object_index];
+        let now = object.current_bounds;
+        if object.mask.is_some() || layer_opacity(&self.layers[object.layer_index], self.groups) != 255 {
+            return self.draw_sampled(band, work);
+        }
+        let area = DrawArea {
+            skip_above: &work.skip_above,
+            shape: &object.shape,
+            min_y: now.y, max_y: now.y + now.h,
+            min_x: now.x, max_x: now.x + now.w,
+        };
+        let rotated = object.transform.map(|transform| RotatedDraw::new(transform, object.position, object.background));
+        if let Some(color) = object.texture_color {
+            // can skip rendering if the alpha is 0, no point in iterating
+            if color.a == 0 || self.draw_snapped(band, work) {
+                return;
+            }
+            self.draw_pixel(band, color, &area, rotated.as_ref());
+        } else {
+            self.draw_exact(band, object.texture_index, &area, rotated.as_ref(),
+                object.position, object.source_rect(),
+            );
+        }
+    }
+}
+
+/// the coordinate in the texture of the coordinate local to an object
+/// that is object_len pixels long, when the part of the texture
+/// [source_start, source_start + source_len) is stretched over the object
+#[inline(always)]
+pub(crate) fn texture_coord(local: u32, object_len: u32, source_start: u32, source_len: u32) -> u32 {
+    if object_len == source_len {
+        return source_start + local;
+    }
+    source_start + (local as u64 * source_len as u64 / object_len as u64) as u32
+}
+
+/// the coordinates that rotated objects are sampled in, see SampleMatrix
+#[cfg(not(feature = "fixed-point"))]
+type Coord = f32;
+#[cfg(feature = "fixed-point")]
+type Coord = Fixed;
+
+/// maps the coordinates local to a rotated object,
+/// to the coordinates in its texture, see texture_coord
+#[derive(Debug, Copy, Clone)]
+struct SourceMapping {
+    x: Coord, y: Coord,
+    scale_x: Coord, scale_y: Coord,
+}
+
+impl SourceMapping {
+    #[cfg(not(feature = "fixed-point"))]
+    fn new(source: Rect, width: u32, height: u32) -> SourceMapping {
+        SourceMapping {
+            x: source.x as f32,
+            y: source.y as f32,
+            scale_x: if width == 0 { 1.0 } else { source.w as f32 / width as f32 },
+            scale_y: if height == 0 { 1.0 } else { source.h as f32 / height as f32 },
+        }
+    }
+
+    #[cfg(feature = "fixed-point")]
+    fn new(source: Rect, width: u32, height: u32) -> SourceMapping {
+        SourceMapping {
+            x: Fixed::from_int(source.x as i32),
+            y: Fixed::from_int(source.y as i32),
+            scale_x: Fixed::ratio(source.w, width),
+            scale_y: Fixed::ratio(source.h, height),
+        }
+    }
+
+    #[inline(always)]
+    fn map(&self, px: Coord, py: Coord) -> (Coord, Coord) {
+        (self.x + px * self.scale_x, self.y + py * self.scale_y)
+    }
+}
+
+/// maps the screen pixels of a rotated object to where they are on the
+/// object before it was rotated, and from there to its texture. with the
+/// fixed-point feature only new converts from floats, and every pixel
+/// is sampled with integer math
+struct RotatedMapping {
+    matrix: SampleMatrix,
+    /// the top left of the object's position
+    shift_x: Coord, shift_y: Coord,
+    /// the size of the object before it was rotated
+    width: u32, height: u32,
+    source: SourceMapping,
+}
+
+impl RotatedMapping {
+    /// source is the part of the texture that is stretched over the object
+    fn new(transform: &Matrix, shift_x: f32, shift_y: f32, width: u32, height: u32, source: Rect) -> RotatedMapping {
+        #[cfg(feature = "fixed-point")]
+        let (shift_x, shift_y) = (Fixed::from(shift_x), Fixed::from(shift_y));
+        RotatedMapping {
+            matrix: transform.into(),
+            shift_x, shift_y,
+            width, height,
+            source: SourceMapping::new(source, width, height),
+        }
+    }
+
+    #[cfg(not(feature = "fixed-point"))]
+    #[inline(always)]
+    fn local(&self, x: u32, y: u32) -> (Coord, Coord) {
+        self.matrix.compute_pt(x as f32 - self.shift_x, y as f32 - self.shift_y)
+    }
+
+    #[cfg(feature = "fixed-point")]
+    #[inline(always)]
+    fn local(&self, x: u32, y: u32) -> (Coord, Coord) {
+        self.matrix.compute_fixed(Fixed::from_int(x as i32) - self.shift_x, Fixed::from_int(y as i32) - self.shift_y)
+    }
+
+    /// the pixel of a rotated color object at the screen coordinate (x, y)
+    #[inline(always)]
+    fn sample_color(&self, color: RgbaPixel, x: u32, y: u32, background: RgbaPixel) -> RgbaPixel {
+        let (px, py) = self.local(x, y);
+        interpolate_nearest_pixel(color, self.width, self.height, px, py, background)
+    }
+
+    /// the pixel of a rotated texture object at the screen coordinate (x, y)
+    #[inline(always)]
+    fn sample_texture(&self, filter: Filter, texture: &Texture<u8>, x: u32, y: u32, background: RgbaPixel) -> RgbaPixel {
+        let (px, py) = self.local(x, y);
+        let (px, py) = self.source.map(px, py);
+        interpolate_filtered(filter, &texture.data, texture.width, texture.height, px, py, background)
+    }
+}
+
+/// pb_red_index is the index into band.pixels
+fn clear_pixel_from_below_samplers(
+    backend: &dyn RenderBackend,
+    band: &mut Band,
+    pb_red_index: usize, x: u32, y: u32,
+    below: &[(Rect, ObjectSampler)],
+) -> bool {
+    // below is ordered from the top most object down, so the first
+    // visible pixel is the one that should be shown. if every object
+    // is transparent here, then the pixel comes from the clear source
+    let visible = below.iter()
+        .filter(|(region, _)| region.contains_u32(x, y))
+        .find_map(|(_, sampler)| visible_sample(sampler, x, y));
+    match visible {
+        Some(pixel) => {
+            backend.set_pixel(&mut band.pixels[pb_red_index..pb_red_index + 4], pixel);
+            true
+        }
+        None => false,
+    }
+}
+
+/// the pixel of the sampler at (x, y), unless it is fully transparent
+#[inline(always)]
+fn visible_sample(sampler: &ObjectSampler, x: u32, y: u32) -> Option<RgbaPixel> {
+    match sampler.sample(x, y) {
+        Some(pixel) if pixel.a != 0 => Some(pixel),
+        _ => None,
+    }
+}
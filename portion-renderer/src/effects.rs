@@ -0,0 +1,105 @@
+use super::*;
+
+impl PortionRenderer<u8> {
+    /// blurs the pixels of rect, eg: for a frosted glass background
+    /// behind a dialog. three box blurs in a row, which looks
+    /// close to a gaussian blur with a standard deviation of about radius.
+    /// see box_blur_region
+    pub fn blur_region(&mut self, rect: Rect, radius: u32) {
+        self.log_command(|| RenderCommand::BlurRegion { rect, radius });
+        for _ in 0..3 {
+            self.box_blur(rect, radius);
+        }
+    }
+
+    /// sets every pixel of rect to the average of the pixels within
+    /// radius of it, horizontally and vertically. only the pixels inside
+    /// of rect are averaged, as if the pixels at its edges repeat past them.
+    /// the blur is done on the pixel buffer, so whatever objects are
+    /// drawn over rect afterwards are not blurred. only the part of
+    /// rect on screen and inside of the clip rect is blurred, and
+    /// it is marked as dirty
+    pub fn box_blur_region(&mut self, rect: Rect, radius: u32) {
+        self.log_command(|| RenderCommand::BoxBlurRegion { rect, radius });
+        self.box_blur(rect, radius);
+    }
+
+    fn box_blur(&mut self, rect: Rect, radius: u32) {
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        let rect = match rect.intersection(self.clip.unwrap_or(screen)).and_then(|r| r.intersection(screen)) {
+            Some(rect) => rect,
+            None => return,
+        };
+        self.portioner.take_region((rect.x, rect.y), (rect.x + rect.w - 1, rect.y + rect.h - 1));
+        if radius == 0 {
+            return;
+        }
+        let (width, ipp) = (self.width, self.indices_per_pixel);
+        let mut line = vec![];
+        let mut blurred = vec![];
+        for y in rect.y..rect.y + rect.h {
+            line.clear();
+            line.extend((rect.x..rect.x + rect.w).map(|x| red_index(x, y, width, ipp)));
+            self.blur_line(&line, radius, &mut blurred);
+        }
+        for x in rect.x..rect.x + rect.w {
+            line.clear();
+            line.extend((rect.y..rect.y + rect.h).map(|y| red_index(x, y, width, ipp)));
+            self.blur_line(&line, radius, &mut blurred);
+        }
+    }
+
+    /// box blurs the pixels at the red indices of line, which are
+    /// in the order that they are next to each other on screen
+    fn blur_line(&mut self, line: &[usize], radius: u32, blurred: &mut Vec<[u8; 4]>) {
+        let pixels = &mut self.pixel_buffer;
+        let last = line.len() as i64 - 1;
+        let at = |i: i64| {
+            let index = line[i.max(0).min(last) as usize];
+            [pixels[index], pixels[index + 1], pixels[index + 2], pixels[index + 3]]
+        };
+        let radius = radius as i64;
+        let count = 2 * radius as u32 + 1;
+        let mut sums = [0u32; 4];
+        for i in -radius..=radius {
+            sums.iter_mut().zip(at(i).iter()).for_each(|(sum, v)| *sum += *v as u32);
+        }
+        blurred.clear();
+        for i in 0..=last {
+            let mut average = [0u8; 4];
+            average.iter_mut().zip(sums.iter()).for_each(|(a, sum)| *a = ((sum + count / 2) / count) as u8);
+            blurred.push(average);
+            let (entering, leaving) = (at(i + radius + 1), at(i - radius));
+            for (c, sum) in sums.iter_mut().enumerate() {
+                *sum = *sum + entering[c] as u32 - leaving[c] as u32;
+            }
+        }
+        for (index, pixel) in line.iter().zip(blurred.iter()) {
+            pixels[*index..*index + 4].copy_from_slice(pixel);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_blur_averages_neighbors_inside_of_the_rect() {
+        let mut p = PortionRenderer::<u8>::new(8, 4);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 8, h: 4 }, PIXEL_BLACK);
+        p.create_object_from_color(0, Rect { x: 3, y: 0, w: 1, h: 4 }, PIXEL_WHITE);
+        p.draw_all_layers();
+        p.take_dirty_rects();
+
+        p.box_blur_region(Rect { x: 2, y: 0, w: 4, h: 4 }, 1);
+        let red = |p: &PortionRenderer<u8>, x| p[(x, 1)][0];
+        assert_eq!((0..8).map(|x| red(&p, x)).collect::<Vec<u8>>(), vec![0, 0, 85, 85, 85, 0, 0, 0]);
+        assert_eq!(p[(3, 1)][3], 255);
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 2, y: 0, w: 4, h: 4 }]);
+
+        // a blurred flat color stays the same
+        p.blur_region(Rect { x: 6, y: 0, w: 2, h: 4 }, 3);
+        assert_eq!(red(&p, 7), 0);
+    }
+}
@@ -0,0 +1,94 @@
+use core::convert::Infallible;
+
+use embedded_graphics_core::draw_target::DrawTarget;
+use embedded_graphics_core::geometry::{Dimensions, OriginDimensions, Size};
+use embedded_graphics_core::pixelcolor::{Rgb888, RgbColor};
+use embedded_graphics_core::primitives::Rectangle;
+use embedded_graphics_core::Pixel;
+
+use super::*;
+
+impl From<Rgb888> for RgbaPixel {
+    fn from(color: Rgb888) -> RgbaPixel {
+        RgbaPixel { r: color.r(), g: color.g(), b: color.b(), a: 255 }
+    }
+}
+
+impl OriginDimensions for PortionRenderer<u8> {
+    fn size(&self) -> Size {
+        Size::new(self.width, self.height)
+    }
+}
+
+/// lets embedded-graphics primitives, fonts, and images draw directly
+/// into the pixel buffer. every pixel drawn marks its portion as dirty,
+/// so take_dirty_rects only returns what was drawn since the last flush.
+/// like draw, these pixels are not objects, so they get overwritten
+/// if an object is drawn or cleared on top of them
+impl DrawTarget for PortionRenderer<u8> {
+    type Color = Rgb888;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let ipp = self.indices_per_pixel as usize;
+        for Pixel(point, color) in pixels {
+            if point.x < 0 || point.y < 0 {
+                continue;
+            }
+            let (x, y) = (point.x as u32, point.y as u32);
+            if x >= self.width || y >= self.height {
+                continue;
+            }
+            let red_index = red_index(x, y, self.width, self.indices_per_pixel);
+            fill_pixels(&mut self.pixel_buffer[red_index..red_index + ipp], color.into());
+            self.portioner.take_region((x, y), (x, y));
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        let bottom_right = match area.bottom_right() {
+            Some(bottom_right) => bottom_right,
+            None => return Ok(()),
+        };
+        let (x, y) = (area.top_left.x as u32, area.top_left.y as u32);
+        let (max_x, max_y) = (bottom_right.x as u32, bottom_right.y as u32);
+        let row_len = area.size.width as usize * self.indices_per_pixel as usize;
+        for row in y..=max_y {
+            let red_index = red_index(x, row, self.width, self.indices_per_pixel);
+            fill_pixels(&mut self.pixel_buffer[red_index..red_index + row_len], color.into());
+        }
+        self.portioner.take_region((x, y), (max_x, max_y));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use embedded_graphics_core::geometry::Point;
+
+    #[test]
+    fn drawing_marks_only_touched_portions() {
+        let mut p = PortionRenderer::<u8>::new_ex(40, 40, 4, 4, PixelFormatEnum::RGBA8888);
+        p.take_dirty_rects();
+        let pixels = [
+            Pixel(Point::new(1, 1), Rgb888::RED),
+            Pixel(Point::new(-1, 1), Rgb888::RED),
+            Pixel(Point::new(1, 400), Rgb888::RED),
+        ];
+        p.draw_iter(pixels.iter().copied()).unwrap();
+        assert_eq!(RgbaPixel::from(&p[(1, 1)]), PIXEL_RED);
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 10, h: 10 }]);
+
+        // goes off the bottom right, so only the visible part is filled
+        p.fill_solid(&Rectangle::new(Point::new(35, 35), Size::new(10, 10)), Rgb888::RED).unwrap();
+        assert_eq!(RgbaPixel::from(&p[(39, 39)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(34, 39)]), PIXEL_BLANK);
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 30, y: 30, w: 10, h: 10 }]);
+    }
+}
@@ -0,0 +1,199 @@
+use super::*;
+
+/// something that happened to objects since the last draw, see on_object_event
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ObjectEvent {
+    /// the shapes of the two objects started to overlap.
+    /// the smaller object index is first
+    OverlapStarted(usize, usize),
+    /// the two objects stopped overlapping, or one of them was removed
+    OverlapEnded(usize, usize),
+    /// every pixel of the object's bounds is covered by the
+    /// objects on the layers above it, so none of it gets drawn
+    Occluded(usize),
+    /// the object was occluded, and now part of it is visible again
+    Revealed(usize),
+}
+
+/// the callbacks, and what the objects were doing when
+/// the events were last checked, so that only changes fire
+#[derive(Default)]
+pub(crate) struct ObjectEvents {
+    callbacks: Vec<Box<dyn FnMut(ObjectEvent) + Send>>,
+    overlapping: HashSet<(usize, usize)>,
+    occluded: HashSet<usize>,
+    /// events from removed objects, which are fired with the next draw
+    pending: Vec<ObjectEvent>,
+    /// where removed objects were, since the objects
+    /// below them might not be occluded anymore
+    removed: Vec<Rect>,
+}
+
+fn pair(a: usize, b: usize) -> (usize, usize) {
+    (a.min(b), a.max(b))
+}
+
+impl<T> PortionRenderer<T> {
+    /// calls callback with the events of the objects that were updated,
+    /// and the objects near them, when the updates are taken by
+    /// draw_all_layers (or any of its variants), before they are drawn.
+    /// nothing is checked until the first callback is added
+    pub fn on_object_event(&mut self, callback: impl FnMut(ObjectEvent) + Send + 'static) {
+        self.events.callbacks.push(Box::new(callback));
+    }
+
+    pub(crate) fn remove_object_from_events(&mut self, object_index: usize, bounds: Rect) {
+        let events = &mut self.events;
+        if events.callbacks.is_empty() {
+            return;
+        }
+        events.removed.push(bounds);
+        let pending = &mut events.pending;
+        events.overlapping.retain(|&(a, b)| {
+            let ended = a == object_index || b == object_index;
+            if ended {
+                pending.push(ObjectEvent::OverlapEnded(a, b));
+            }
+            !ended
+        });
+        events.occluded.remove(&object_index);
+    }
+
+    pub(crate) fn remap_event_objects(&mut self, remap: &HashMap<usize, usize>) {
+        let new_index = |index: usize| *remap.get(&index).unwrap_or(&index);
+        let events = &mut self.events;
+        events.overlapping = events.overlapping.iter().map(|&(a, b)| pair(new_index(a), new_index(b))).collect();
+        events.occluded = events.occluded.iter().map(|&index| new_index(index)).collect();
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// checks the objects that were updated, and every object near
+    /// where they were or are now, since those are the only
+    /// objects whose overlaps and occlusion could have changed
+    pub(crate) fn fire_object_events(&mut self, updated: &[(usize, usize)]) {
+        if self.events.callbacks.is_empty() {
+            return;
+        }
+        let mut events = std::mem::take(&mut self.events.pending);
+        let mut regions = std::mem::take(&mut self.events.removed);
+        let mut affected = HashSet::new();
+        for (_, object_index) in updated.iter() {
+            let object = &self.objects[*object_index];
+            affected.insert(*object_index);
+            regions.push(object.previous_bounds);
+            regions.push(object.get_bounds());
+        }
+        let mut nearby = vec![];
+        for region in regions.iter() {
+            for layer in self.layers.iter() {
+                layer.spatial.query(region, &mut nearby);
+                affected.extend(nearby.iter().copied());
+            }
+        }
+        let mut affected: Vec<usize> = affected.into_iter().collect();
+        affected.sort_unstable();
+
+        let mut overlapping = HashSet::new();
+        for object_index in affected.iter().copied() {
+            let object = &self.objects[object_index];
+            for layer in self.layers.iter() {
+                layer.spatial.query(&object.get_bounds(), &mut nearby);
+                for other_index in nearby.iter().copied().filter(|other| *other != object_index) {
+                    let other = &self.objects[other_index];
+                    let overlap = object.intersection(other.get_bounds())
+                        .and_then(|overlap| other.intersection(overlap));
+                    if matches!(overlap, Some(overlap) if !overlap.is_empty()) {
+                        overlapping.insert(pair(object_index, other_index));
+                    }
+                }
+            }
+        }
+        let was_overlapping: Vec<(usize, usize)> = self.events.overlapping.iter()
+            .filter(|(a, b)| affected.binary_search(a).is_ok() || affected.binary_search(b).is_ok())
+            .copied().collect();
+        for (a, b) in was_overlapping {
+            if !overlapping.contains(&(a, b)) {
+                self.events.overlapping.remove(&(a, b));
+                events.push(ObjectEvent::OverlapEnded(a, b));
+            }
+        }
+        let mut started: Vec<(usize, usize)> = overlapping.into_iter()
+            .filter(|p| !self.events.overlapping.contains(p))
+            .collect();
+        started.sort_unstable();
+        for (a, b) in started {
+            self.events.overlapping.insert((a, b));
+            events.push(ObjectEvent::OverlapStarted(a, b));
+        }
+
+        for object_index in affected {
+            let occluded = self.is_occluded(object_index);
+            if occluded && self.events.occluded.insert(object_index) {
+                events.push(ObjectEvent::Occluded(object_index));
+            } else if !occluded && self.events.occluded.remove(&object_index) {
+                events.push(ObjectEvent::Revealed(object_index));
+            }
+        }
+
+        for event in events {
+            for callback in self.events.callbacks.iter_mut() {
+                callback(event);
+            }
+        }
+    }
+
+    /// true if the regions above the object cover all of its bounds.
+    /// an object that is off screen is not occluded
+    fn is_occluded(&self, object_index: usize) -> bool {
+        let object = &self.objects[object_index];
+        let bounds = object.get_bounds();
+        if bounds.is_empty() {
+            return false;
+        }
+        let above = self.get_regions_above_object(object_index, object.layer_index);
+        let mut visible = vec![bounds];
+        for region in above.above_my_current.iter() {
+            visible = visible.iter().flat_map(|r| r.subtract(region)).collect();
+            if visible.is_empty() {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc::channel;
+
+    #[test]
+    fn events_fire_when_overlaps_and_occlusion_change() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let (sender, receiver) = channel();
+        p.on_object_event(move |event| sender.send(event).unwrap());
+        let a = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+        let b = p.create_object_from_color(0, Rect { x: 4, y: 4, w: 2, h: 2 }, PIXEL_BLUE);
+        p.draw_all_layers();
+        assert_eq!(receiver.try_iter().count(), 0);
+
+        p.move_object_x_by(b, -3);
+        p.move_object_y_by(b, -3);
+        p.draw_all_layers();
+        assert_eq!(receiver.try_iter().collect::<Vec<_>>(), vec![ObjectEvent::OverlapStarted(a, b)]);
+
+        let cover = p.create_object_from_color(1, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        let events: Vec<ObjectEvent> = receiver.try_iter().collect();
+        assert!(events.contains(&ObjectEvent::OverlapStarted(a, cover)));
+        assert!(events.contains(&ObjectEvent::Occluded(a)));
+        assert!(!events.contains(&ObjectEvent::Occluded(b)));
+
+        p.remove_object(cover);
+        p.draw_all_layers();
+        let events: Vec<ObjectEvent> = receiver.try_iter().collect();
+        assert!(events.contains(&ObjectEvent::OverlapEnded(a, cover)));
+        assert!(events.contains(&ObjectEvent::Revealed(a)));
+    }
+}
@@ -0,0 +1,178 @@
+//! a C api, so that engines in other languages can embed the renderer.
+//! build it as a cdylib or staticlib from a crate that depends on
+//! this one with the ffi feature, or with
+//! `cargo rustc --features ffi --crate-type cdylib`.
+//!
+//! every function takes the pointer returned by `pr_renderer_new`.
+//! object indices that dont exist are ignored instead of panicking,
+//! because panics cant unwind into C
+
+use std::os::raw::c_float;
+use std::ptr;
+
+use super::*;
+
+pub type PrRenderer = PortionRenderer<u8>;
+
+/// returns null if width/height cant be split into num_rows/num_cols
+/// portions. free it with `pr_renderer_free`
+#[no_mangle]
+pub extern "C" fn pr_renderer_new(width: u32, height: u32, num_rows: u32, num_cols: u32) -> *mut PrRenderer {
+    if num_rows == 0 || num_cols == 0 || !dimensions_valid(width, height, num_rows, num_cols) {
+        return ptr::null_mut();
+    }
+    let renderer = PortionRenderer::new_ex(width, height, num_rows, num_cols, PixelFormatEnum::RGBA8888);
+    Box::into_raw(Box::new(renderer))
+}
+
+/// # Safety
+/// renderer must be null, or from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_renderer_free(renderer: *mut PrRenderer) {
+    if !renderer.is_null() {
+        drop(Box::from_raw(renderer));
+    }
+}
+
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_create_object_from_color(
+    renderer: *mut PrRenderer, layer_index: u32,
+    x: u32, y: u32, w: u32, h: u32,
+    r: u8, g: u8, b: u8, a: u8,
+) -> usize {
+    let renderer = &mut *renderer;
+    renderer.create_object_from_color(layer_index, Rect { x, y, w, h }, RgbaPixel { r, g, b, a })
+}
+
+/// copies texture_width * texture_height RGBA pixels from texture.
+/// the texture is stretched to fit the bounds
+///
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet,
+/// and texture must point to texture_width * texture_height * 4 bytes
+#[no_mangle]
+pub unsafe extern "C" fn pr_create_object_from_texture(
+    renderer: *mut PrRenderer, layer_index: u32,
+    x: u32, y: u32, w: u32, h: u32,
+    texture: *const u8, texture_width: u32, texture_height: u32,
+) -> usize {
+    let renderer = &mut *renderer;
+    let len = texture_width as usize * texture_height as usize * 4;
+    let data = std::slice::from_raw_parts(texture, len).to_vec();
+    renderer.create_object_from_texture(layer_index, Rect { x, y, w, h }, data, texture_width, texture_height)
+}
+
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_move_object_by(renderer: *mut PrRenderer, object_index: usize, dx: i32, dy: i32) {
+    let renderer = &mut *renderer;
+    if renderer.get_object(object_index).is_none() {
+        return;
+    }
+    renderer.move_object_x_by(object_index, dx);
+    renderer.move_object_y_by(object_index, dy);
+}
+
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_set_object_position(renderer: *mut PrRenderer, object_index: usize, x: i32, y: i32) {
+    let renderer = &mut *renderer;
+    if renderer.get_object(object_index).is_none() {
+        return;
+    }
+    renderer.set_object_position(object_index, x, y);
+}
+
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_set_object_rotation(renderer: *mut PrRenderer, object_index: usize, degrees: c_float) {
+    let renderer = &mut *renderer;
+    if renderer.get_object(object_index).is_none() {
+        return;
+    }
+    renderer.set_object_rotation(object_index, degrees);
+}
+
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_draw_all_layers(renderer: *mut PrRenderer) {
+    (*renderer).draw_all_layers();
+}
+
+/// writes up to capacity dirty rects to out, and returns how many were written.
+/// if there are more dirty rects than capacity, the last one written
+/// is the union of the rest, so nothing that changed is left out
+///
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet,
+/// and out must have room for capacity rects
+#[no_mangle]
+pub unsafe extern "C" fn pr_take_dirty_rects(renderer: *mut PrRenderer, out: *mut Rect, capacity: usize) -> usize {
+    let renderer = &mut *renderer;
+    let mut dirty = renderer.take_dirty_rects();
+    if capacity == 0 {
+        return 0;
+    }
+    if dirty.len() > capacity {
+        let rest = dirty.split_off(capacity - 1);
+        let union = rest.iter().fold(EMPTY_RECT, |acc, r| acc.union(r));
+        dirty.push(union);
+    }
+    ptr::copy_nonoverlapping(dirty.as_ptr(), out, dirty.len());
+    dirty.len()
+}
+
+/// the RGBA pixels, width * height * 4 bytes with no padding.
+/// the pointer is valid until the renderer is freed
+///
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_pixel_buffer(renderer: *const PrRenderer) -> *const u8 {
+    (*renderer).pixel_buffer.as_ptr()
+}
+
+/// # Safety
+/// renderer must be from `pr_renderer_new` and not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn pr_pixel_buffer_len(renderer: *const PrRenderer) -> usize {
+    (*renderer).pixel_buffer.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn c_api_draws_and_reports_dirty_rects() {
+        assert!(pr_renderer_new(10, 10, 4, 4).is_null());
+        let renderer = pr_renderer_new(8, 8, 4, 4);
+        unsafe {
+            let mut rects = [EMPTY_RECT; 2];
+            pr_take_dirty_rects(renderer, rects.as_mut_ptr(), 2);
+
+            let a = pr_create_object_from_color(renderer, 0, 0, 0, 1, 1, 255, 0, 0, 255);
+            pr_create_object_from_color(renderer, 0, 4, 0, 1, 1, 255, 0, 0, 255);
+            pr_create_object_from_color(renderer, 0, 6, 6, 1, 1, 255, 0, 0, 255);
+            pr_move_object_by(renderer, 99, 1, 1);
+            pr_draw_all_layers(renderer);
+            let written = pr_take_dirty_rects(renderer, rects.as_mut_ptr(), 2);
+            assert_eq!(written, 2);
+            assert_eq!(rects[0], Rect { x: 0, y: 0, w: 2, h: 2 });
+            assert_eq!(rects[1], Rect { x: 4, y: 0, w: 4, h: 8 });
+
+            pr_move_object_by(renderer, a, 2, 0);
+            pr_draw_all_layers(renderer);
+            let pixels = std::slice::from_raw_parts(pr_pixel_buffer(renderer), pr_pixel_buffer_len(renderer));
+            assert_eq!(&pixels[8..12], &[255, 0, 0, 255]);
+            assert_eq!(pixels[3], 0);
+            pr_renderer_free(renderer);
+        }
+    }
+}
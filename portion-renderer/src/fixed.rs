@@ -0,0 +1,278 @@
+use std::ops::{Add, Mul, Sub};
+
+use super::projection::ComputePoint;
+use super::transform::Filter;
+use super::{red_index, Matrix, RgbaPixel};
+
+/// number of fractional bits, ie: this is a 16.16 fixed point number
+pub const FRAC_BITS: u32 = 16;
+pub const FIXED_ONE: i32 = 1 << FRAC_BITS;
+
+/// A 16.16 fixed point number. This is meant for targets
+/// without an FPU (eg: cortex-m0) where every f32 operation
+/// is emulated in software and is therefore very slow.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct Fixed(pub i32);
+
+impl Fixed {
+    #[inline(always)]
+    pub fn from_int(x: i32) -> Fixed {
+        Fixed(x << FRAC_BITS)
+    }
+
+    #[inline(always)]
+    pub fn from_f32(x: f32) -> Fixed {
+        Fixed((x * FIXED_ONE as f32) as i32)
+    }
+
+    #[inline(always)]
+    pub fn to_f32(self) -> f32 {
+        self.0 as f32 / FIXED_ONE as f32
+    }
+
+    /// num / den, or 1 if den is 0, without going through floats
+    #[inline(always)]
+    pub fn ratio(num: u32, den: u32) -> Fixed {
+        if den == 0 {
+            return Fixed(FIXED_ONE);
+        }
+        Fixed((((num as i64) << FRAC_BITS) / den as i64) as i32)
+    }
+
+    /// the part after the point, from 0 to FIXED_ONE - 1
+    #[inline(always)]
+    pub fn fract(self) -> i32 {
+        self.0 & (FIXED_ONE - 1)
+    }
+
+    /// truncates towards negative infinity
+    #[inline(always)]
+    pub fn floor(self) -> i32 {
+        self.0 >> FRAC_BITS
+    }
+
+    /// rounds to the nearest integer, same as f32::round
+    /// for positive values
+    #[inline(always)]
+    pub fn round(self) -> i32 {
+        (self.0 + (FIXED_ONE >> 1)) >> FRAC_BITS
+    }
+}
+
+impl From<f32> for Fixed {
+    fn from(x: f32) -> Fixed {
+        Fixed::from_f32(x)
+    }
+}
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn add(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_add(rhs.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn sub(self, rhs: Fixed) -> Fixed {
+        Fixed(self.0.wrapping_sub(rhs.0))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    #[inline(always)]
+    fn mul(self, rhs: Fixed) -> Fixed {
+        Fixed(((self.0 as i64 * rhs.0 as i64) >> FRAC_BITS) as i32)
+    }
+}
+
+/// A general affine matrix stored in fixed point.
+/// It can be created from any Matrix variant, so the conversion
+/// from a Matrix only needs to happen once (which is the only place
+/// that floats are used), and then every point computed via
+/// compute_fixed is pure integer math.
+#[derive(Debug, Copy, Clone)]
+pub struct FixedMatrix {
+    a0: Fixed, a1: Fixed, tx: Fixed,
+    b0: Fixed, b1: Fixed, ty: Fixed,
+}
+
+impl FixedMatrix {
+    #[inline(always)]
+    pub fn compute_fixed(&self, x: Fixed, y: Fixed) -> (Fixed, Fixed) {
+        (
+            self.a0 * x + self.a1 * y + self.tx,
+            self.b0 * x + self.b1 * y + self.ty,
+        )
+    }
+
+    /// computes the point for an integer pixel position
+    /// and returns the position rounded to the nearest pixel
+    #[inline(always)]
+    pub fn compute_pt_i32(&self, x: i32, y: i32) -> (i32, i32) {
+        let (px, py) = self.compute_fixed(Fixed::from_int(x), Fixed::from_int(y));
+        (px.round(), py.round())
+    }
+}
+
+impl ComputePoint for FixedMatrix {
+    #[inline(always)]
+    fn compute_pt(&self, x: f32, y: f32) -> (f32, f32) {
+        let (px, py) = self.compute_fixed(Fixed::from_f32(x), Fixed::from_f32(y));
+        (px.to_f32(), py.to_f32())
+    }
+}
+
+/// the same as transform::interpolate_nearest_pixel, for a fixed point (x, y)
+#[inline(always)]
+pub fn interpolate_nearest_pixel(
+    pixel: RgbaPixel,
+    width: u32,
+    height: u32,
+    x: Fixed,
+    y: Fixed,
+    default: RgbaPixel,
+) -> RgbaPixel {
+    let (rx, ry) = (x.round(), y.round());
+    if rx < 0 || rx >= width as i32 || ry < 0 || ry >= height as i32 {
+        default
+    } else {
+        pixel
+    }
+}
+
+/// the same as transform::interpolate_filtered, for a fixed point (x, y).
+/// the bilinear weights are the fractional bits, so there are no floats
+#[inline(always)]
+pub fn interpolate_filtered(
+    filter: Filter,
+    texture: &[u8],
+    texture_width: u32,
+    texture_height: u32,
+    x: Fixed,
+    y: Fixed,
+    default: RgbaPixel,
+) -> RgbaPixel {
+    let (rx, ry) = (x.round(), y.round());
+    if rx < 0 || rx >= texture_width as i32 || ry < 0 || ry >= texture_height as i32 {
+        return default;
+    }
+    match filter {
+        Filter::Nearest => {
+            let red_index = red_index(rx as u32, ry as u32, texture_width, 4);
+            (&texture[red_index..red_index + 4]).into()
+        }
+        Filter::Bilinear | Filter::AreaAverage => {
+            (&sample_bilinear_clamped(texture, texture_width, texture_height, x, y)[..]).into()
+        }
+    }
+}
+
+/// see transform::sample_bilinear_clamped
+fn sample_bilinear_clamped(
+    texture: &[u8],
+    texture_width: u32,
+    texture_height: u32,
+    x: Fixed,
+    y: Fixed,
+) -> [u8; 4] {
+    let max_x = Fixed::from_int(texture_width as i32 - 1);
+    let max_y = Fixed::from_int(texture_height as i32 - 1);
+    let x = x.max(Fixed(0)).min(max_x);
+    let y = y.max(Fixed(0)).min(max_y);
+    let (left, top) = (x.floor() as u32, y.floor() as u32);
+    let right = (left + 1).min(texture_width - 1);
+    let bottom = (top + 1).min(texture_height - 1);
+    let right_weight = x.fract() as i64;
+    let bottom_weight = y.fract() as i64;
+    let one = FIXED_ONE as i64;
+
+    let tl = red_index(left, top, texture_width, 4);
+    let tr = red_index(right, top, texture_width, 4);
+    let bl = red_index(left, bottom, texture_width, 4);
+    let br = red_index(right, bottom, texture_width, 4);
+
+    let mut out = [0; 4];
+    for (i, out) in out.iter_mut().enumerate() {
+        let top = (one - right_weight) * texture[tl + i] as i64 + right_weight * texture[tr + i] as i64;
+        let bottom = (one - right_weight) * texture[bl + i] as i64 + right_weight * texture[br + i] as i64;
+        let value = (one - bottom_weight) * top + bottom_weight * bottom;
+        *out = ((value + (1 << (2 * FRAC_BITS - 1))) >> (2 * FRAC_BITS)) as u8;
+    }
+    out
+}
+
+impl From<&Matrix> for FixedMatrix {
+    fn from(orig: &Matrix) -> Self {
+        let m: [f32; 9] = orig.into();
+        FixedMatrix {
+            a0: Fixed::from_f32(m[0]), a1: Fixed::from_f32(m[1]), tx: Fixed::from_f32(m[2]),
+            b0: Fixed::from_f32(m[3]), b1: Fixed::from_f32(m[4]), ty: Fixed::from_f32(m[5]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_arithmetic_works() {
+        let a = Fixed::from_f32(1.5);
+        let b = Fixed::from_int(2);
+        assert_eq!((a * b).to_f32(), 3.0);
+        assert_eq!((a + b).to_f32(), 3.5);
+        assert_eq!((a - b).to_f32(), -0.5);
+        assert_eq!(a.floor(), 1);
+        assert_eq!(a.round(), 2);
+        assert_eq!(Fixed::from_f32(-0.5).floor(), -1);
+    }
+
+    #[test]
+    fn fixed_matrix_matches_float_matrix() {
+        let m = Matrix::TranslateXY(3.0, 4.0) * Matrix::rotate_degrees(90.0);
+        let fm: FixedMatrix = (&m).into();
+        // (2, 0) -> (0, 2) -> (3, 6)
+        assert_eq!(fm.compute_pt_i32(2, 0), (3, 6));
+
+        let (fx, fy) = fm.compute_pt(2.0, 1.0);
+        let (x, y) = m.mul_point(2.0, 1.0);
+        assert!((fx - x).abs() < 0.001);
+        assert!((fy - y).abs() < 0.001);
+    }
+
+    #[test]
+    fn rotated_objects_are_drawn_with_fixed_point() {
+        use crate::testing::PixelMap;
+        use crate::{PixelFormatEnum, PortionRenderer, Rect, PIXEL_RED, PIXEL_GREEN, PIXEL_BLUE};
+
+        let mut p = PortionRenderer::<u8>::new_ex(5, 4, 1, 1, PixelFormatEnum::RGBA8888);
+        let t = p.create_object_from_texture(0, Rect { x: 2, y: 1, w: 2, h: 1 }, [
+            PIXEL_RED, PIXEL_GREEN,
+        ].iter().flat_map(|p| [p.r, p.g, p.b, p.a]).collect(), 2, 1);
+        p.set_object_rotation(t, -90.0);
+        let b = p.create_object_from_color(0, Rect { x: 1, y: 3, w: 2, h: 1 }, PIXEL_BLUE);
+        // rotated around its top left, so it ends up to the left of it
+        p.set_object_rotation(b, 180.0);
+        p.draw_all_layers();
+        PixelMap::default().assert_matches(&p, &[
+            'x', 'x', 'g', 'x', 'x',
+            'x', 'x', 'r', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x',
+            'b', 'b', 'x', 'x', 'x',
+        ], 5);
+
+        // half way between two pixels is an even blend of them
+        let texture = [0, 0, 0, 0, 255, 255, 255, 255];
+        let pixel = interpolate_filtered(Filter::Bilinear, &texture, 2, 1, Fixed::from_f32(0.5), Fixed(0), PIXEL_RED);
+        assert_eq!(pixel, RgbaPixel { r: 128, g: 128, b: 128, a: 128 });
+        let pixel = interpolate_filtered(Filter::Bilinear, &texture, 2, 1, Fixed::from_int(2), Fixed(0), PIXEL_RED);
+        assert_eq!(pixel, PIXEL_RED);
+    }
+}
@@ -0,0 +1,551 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use wgpu::util::DeviceExt;
+
+use super::*;
+
+const SHADER: &str = r#"
+struct VertexOut {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+    @location(1) color: vec4<f32>,
+    @location(2) background: vec4<f32>,
+};
+
+@group(0) @binding(0) var pixels: texture_2d<f32>;
+@group(0) @binding(1) var pixels_sampler: sampler;
+
+@vertex
+fn vs_main(
+    @location(0) position: vec2<f32>, @location(1) uv: vec2<f32>,
+    @location(2) color: vec4<f32>, @location(3) background: vec4<f32>,
+) -> VertexOut {
+    var out: VertexOut;
+    out.position = vec4<f32>(position, 0.0, 1.0);
+    out.uv = uv;
+    out.color = color;
+    out.background = background;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOut) -> @location(0) vec4<f32> {
+    let sampled = textureSample(pixels, pixels_sampler, in.uv) * in.color;
+    // the object's background where it is sampled outside of its texture,
+    // eg: past the bottom of the texture when the source rect is too big
+    let inside = all(in.uv >= vec2<f32>(0.0)) && all(in.uv < vec2<f32>(1.0));
+    let pixel = select(in.background, sampled, inside);
+    // like the software renderer, transparent pixels are skipped,
+    // and every other pixel replaces what is below it
+    if (pixel.a == 0.0) {
+        discard;
+    }
+    return pixel;
+}
+"#;
+
+/// position, uv, color, and background
+const VERTEX_ATTRIBUTES: [wgpu::VertexAttribute; 4] = wgpu::vertex_attr_array![
+    0 => Float32x2, 1 => Float32x2, 2 => Float32x4, 3 => Float32x4,
+];
+const FLOATS_PER_VERTEX: usize = 12;
+
+/// composites the objects of a renderer on the GPU instead of into
+/// its pixel buffer. each texture is uploaded the first time that it
+/// is drawn, and every frame only the dirty rects of the renderer
+/// are composited again, into target. the renderer's pixel buffer
+/// is not drawn to at all, so effects like blur_region, and
+/// viewports, are not shown. circle and polygon objects are drawn as
+/// their bounding rect, and textures are always sampled with the nearest pixel
+pub struct GpuCompositor {
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+    target: wgpu::Texture,
+    target_view: wgpu::TextureView,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// one white pixel, which color objects and
+    /// the clear color are drawn with
+    white: GpuTexture,
+    /// the renderer's textures that were uploaded, by texture index
+    textures: HashMap<usize, GpuTexture>,
+    /// only uploaded once the clear source is a buffer
+    clear_buffer: Option<GpuTexture>,
+}
+
+struct GpuTexture {
+    width: u32,
+    height: u32,
+    bind_group: wgpu::BindGroup,
+}
+
+/// what every texture is uploaded with
+struct TextureUpload<'a> {
+    device: &'a wgpu::Device,
+    queue: &'a wgpu::Queue,
+    layout: &'a wgpu::BindGroupLayout,
+    sampler: &'a wgpu::Sampler,
+    format: wgpu::TextureFormat,
+}
+
+/// the 4 corners of a quad, in the order top left,
+/// top right, bottom left, bottom right
+type Corners = [(f32, f32); 4];
+
+/// which texture a quad is drawn with
+#[derive(Copy, Clone)]
+enum QuadSource {
+    White,
+    ClearBuffer,
+    Texture(usize),
+}
+
+impl GpuCompositor {
+    /// panics if the renderer's pixel format is not RGBA8888 or BGRA8888
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, renderer: &PortionRenderer<u8>) -> GpuCompositor {
+        let format = match renderer.pixel_format {
+            PixelFormatEnum::RGBA8888 => wgpu::TextureFormat::Rgba8Unorm,
+            PixelFormatEnum::BGRA8888 => wgpu::TextureFormat::Bgra8Unorm,
+            other => panic!("Cannot composite {:?} on the GPU because only RGBA8888 and BGRA8888 are supported", other),
+        };
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("portion-renderer texture"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("portion-renderer compositor"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("portion-renderer compositor"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("portion-renderer compositor"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: "vs_main",
+                compilation_options: Default::default(),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: (FLOATS_PER_VERTEX * 4) as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Vertex,
+                    attributes: &VERTEX_ATTRIBUTES,
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: "fs_main",
+                compilation_options: Default::default(),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("portion-renderer texture"),
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            ..Default::default()
+        });
+        let white = GpuTexture::upload(&TextureUpload {
+            device, queue, layout: &bind_group_layout, sampler: &sampler, format,
+        }, &[255; 4], 1, 1);
+        let target = create_target(device, format, renderer.width, renderer.height);
+        let target_view = target.create_view(&wgpu::TextureViewDescriptor::default());
+        GpuCompositor {
+            width: renderer.width,
+            height: renderer.height,
+            format,
+            target,
+            target_view,
+            pipeline,
+            bind_group_layout,
+            sampler,
+            white,
+            textures: HashMap::new(),
+            clear_buffer: None,
+        }
+    }
+
+    /// what the renderer is composited into. it is the size of the
+    /// renderer, in its pixel format, and can be copied or sampled from
+    pub fn target(&self) -> &wgpu::Texture {
+        &self.target
+    }
+
+    /// takes the renderer's updates with damage_all_layers, and composites
+    /// its dirty rects into target, which are returned, eg: to only
+    /// copy those to the screen. this takes the renderer's dirty rects,
+    /// so dont mix it with other presenters
+    pub fn render(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, renderer: &mut PortionRenderer<u8>) -> Vec<Rect> {
+        renderer.damage_all_layers();
+        let dirty = renderer.take_dirty_rects();
+        self.composite(device, queue, renderer, &dirty)
+    }
+
+    /// composites the whole renderer into target, eg: for the first
+    /// frame. the updates and dirty rects are left as they are
+    pub fn render_all(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, renderer: &PortionRenderer<u8>) {
+        let screen = Rect { x: 0, y: 0, w: renderer.width, h: renderer.height };
+        self.composite(device, queue, renderer, &[screen]);
+    }
+
+    /// uploads the texture again the next time it is drawn. call this after
+    /// changing the pixels of a texture, eg: with adjust_texture
+    pub fn reupload_texture(&mut self, texture_index: usize) {
+        self.textures.remove(&texture_index);
+    }
+
+    /// uploads every texture, and the clear buffer, again the next time they
+    /// are drawn. call this after compact, which moves the textures around,
+    /// or after setting a different clear buffer
+    pub fn reupload_all(&mut self) {
+        self.textures.clear();
+        self.clear_buffer = None;
+    }
+
+    /// returns the rects that were composited, which are
+    /// all of target if the renderer was resized
+    fn composite(
+        &mut self, device: &wgpu::Device, queue: &wgpu::Queue,
+        renderer: &PortionRenderer<u8>, rects: &[Rect],
+    ) -> Vec<Rect> {
+        let screen = Rect { x: 0, y: 0, w: renderer.width, h: renderer.height };
+        let mut rects = rects.to_vec();
+        if (renderer.width, renderer.height) != (self.width, self.height) {
+            self.target = create_target(device, self.format, renderer.width, renderer.height);
+            self.target_view = self.target.create_view(&wgpu::TextureViewDescriptor::default());
+            self.width = renderer.width;
+            self.height = renderer.height;
+            self.clear_buffer = None;
+            rects = vec![screen];
+        }
+        if let (ClearSource::Buffer(buffer), None) = (&renderer.clear, &self.clear_buffer) {
+            self.clear_buffer = Some(GpuTexture::upload(
+                &self.texture_upload(device, queue), buffer, renderer.width, renderer.height,
+            ));
+        }
+
+        // every quad is drawn inside of the rect it is for,
+        // from the bottom most object up
+        let mut vertices: Vec<f32> = vec![];
+        let mut draws: Vec<(Rect, QuadSource, Range<u32>)> = vec![];
+        for rect in visible_rects(renderer, &rects) {
+            let whole_rect = [(rect.x as f32, rect.y as f32), ((rect.x + rect.w) as f32, rect.y as f32),
+                (rect.x as f32, (rect.y + rect.h) as f32), ((rect.x + rect.w) as f32, (rect.y + rect.h) as f32)];
+            let start = (vertices.len() / FLOATS_PER_VERTEX) as u32;
+            match &renderer.clear {
+                ClearSource::SolidColor(color) => {
+                    push_quad(&mut vertices, screen, whole_rect, [(0.5, 0.5); 4], *color, PIXEL_BLANK);
+                    draws.push((rect, QuadSource::White, start..start + 6));
+                }
+                ClearSource::Buffer(_) => {
+                    let uvs = whole_rect.map(|(x, y)| (x / screen.w as f32, y / screen.h as f32));
+                    push_quad(&mut vertices, screen, whole_rect, uvs, PIXEL_WHITE, PIXEL_BLANK);
+                    draws.push((rect, QuadSource::ClearBuffer, start..start + 6));
+                }
+                ClearSource::None => {}
+            }
+            for object_index in renderer.objects_top_down_in(&rect).into_iter().rev() {
+                let object = &renderer.objects[object_index];
+                let scissor = match object_scissor(renderer, object, rect) {
+                    Some(scissor) => scissor,
+                    None => continue,
+                };
+                let (source, color, texture_size) = match object.texture_color {
+                    Some(color) if color.a == 0 => continue,
+                    Some(color) => (QuadSource::White, color, None),
+                    None => {
+                        let texture = &renderer.textures[object.texture_index];
+                        self.upload_texture(device, queue, texture, object.texture_index);
+                        (QuadSource::Texture(object.texture_index), PIXEL_WHITE, Some((texture.width, texture.height)))
+                    }
+                };
+                let (corners, uvs) = object_quad(object, texture_size);
+                // like the software renderer, only rotated objects are sampled outside of their texture
+                let background = if object.transform.is_some() { object.background } else { PIXEL_BLANK };
+                let start = (vertices.len() / FLOATS_PER_VERTEX) as u32;
+                push_quad(&mut vertices, screen, corners, uvs, color, background);
+                draws.push((scissor, source, start..start + 6));
+            }
+        }
+        if draws.is_empty() {
+            return rects;
+        }
+
+        let bytes: Vec<u8> = vertices.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("portion-renderer quads"),
+            contents: &bytes,
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("portion-renderer compositor"),
+        });
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("portion-renderer compositor"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.target_view,
+                    resolve_target: None,
+                    // only the dirty rects are drawn, so the rest is kept
+                    ops: wgpu::Operations { load: wgpu::LoadOp::Load, store: wgpu::StoreOp::Store },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            pass.set_pipeline(&self.pipeline);
+            pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            for (scissor, source, range) in draws {
+                let texture = match source {
+                    QuadSource::White => &self.white,
+                    // uploaded above whenever the clear source is a buffer
+                    QuadSource::ClearBuffer => self.clear_buffer.as_ref().unwrap(),
+                    QuadSource::Texture(texture_index) => &self.textures[&texture_index],
+                };
+                pass.set_scissor_rect(scissor.x, scissor.y, scissor.w, scissor.h);
+                pass.set_bind_group(0, &texture.bind_group, &[]);
+                pass.draw(range, 0..1);
+            }
+        }
+        queue.submit(Some(encoder.finish()));
+        rects
+    }
+
+    /// uploads the texture if it wasnt already. a texture index that was
+    /// reused for a texture of a different size is uploaded again
+    fn upload_texture(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, texture: &Texture<u8>, texture_index: usize) {
+        if let Some(uploaded) = self.textures.get(&texture_index) {
+            if (uploaded.width, uploaded.height) == (texture.width, texture.height) {
+                return;
+            }
+        }
+        let uploaded = GpuTexture::upload(
+            &self.texture_upload(device, queue), &texture.data, texture.width, texture.height,
+        );
+        self.textures.insert(texture_index, uploaded);
+    }
+
+    fn texture_upload<'a>(&'a self, device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> TextureUpload<'a> {
+        TextureUpload { device, queue, layout: &self.bind_group_layout, sampler: &self.sampler, format: self.format }
+    }
+}
+
+impl GpuTexture {
+    fn upload(upload: &TextureUpload, data: &[u8], width: u32, height: u32) -> GpuTexture {
+        let TextureUpload { device, queue, layout, sampler, format } = *upload;
+        // an empty texture is never sampled, but it still needs a texture to bind
+        let size = wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("portion-renderer texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        if width > 0 && height > 0 {
+            queue.write_texture(
+                wgpu::ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d::ZERO,
+                    aspect: wgpu::TextureAspect::All,
+                },
+                &data[..width as usize * height as usize * 4],
+                wgpu::ImageDataLayout { offset: 0, bytes_per_row: Some(width * 4), rows_per_image: Some(height) },
+                size,
+            );
+        }
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("portion-renderer texture"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry { binding: 0, resource: wgpu::BindingResource::TextureView(&view) },
+                wgpu::BindGroupEntry { binding: 1, resource: wgpu::BindingResource::Sampler(sampler) },
+            ],
+        });
+        GpuTexture { width, height, bind_group }
+    }
+}
+
+fn create_target(device: &wgpu::Device, format: wgpu::TextureFormat, width: u32, height: u32) -> wgpu::Texture {
+    device.create_texture(&wgpu::TextureDescriptor {
+        label: Some("portion-renderer target"),
+        size: wgpu::Extent3d { width: width.max(1), height: height.max(1), depth_or_array_layers: 1 },
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format,
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | wgpu::TextureUsages::TEXTURE_BINDING
+            | wgpu::TextureUsages::COPY_SRC,
+        view_formats: &[],
+    })
+}
+
+/// the parts of rects that are on screen and inside of the renderer's clip
+fn visible_rects(renderer: &PortionRenderer<u8>, rects: &[Rect]) -> Vec<Rect> {
+    let screen = Rect { x: 0, y: 0, w: renderer.width, h: renderer.height };
+    let visible = renderer.clip.and_then(|clip| clip.intersection(screen)).unwrap_or(screen);
+    rects.iter()
+        .filter_map(|r| r.intersection(visible))
+        .filter(|r| !r.is_empty())
+        .collect()
+}
+
+/// the part of rect that the object can be drawn to, given the clip of
+/// the renderer, of its layer, and of itself. None if it cant be drawn there
+fn object_scissor(renderer: &PortionRenderer<u8>, object: &Object, rect: Rect) -> Option<Rect> {
+    let scissor = match combined_clip(renderer.clip, &renderer.layers[object.layer_index]) {
+        Some(clip) => rect.intersection(clip)?,
+        None => rect,
+    };
+    match object.clip_on_screen() {
+        Some(clip) => scissor.intersection(clip),
+        None => Some(scissor),
+    }
+}
+
+/// the corners of the object on screen, and their texture coordinates.
+/// texture_size is None for color objects
+fn object_quad(object: &Object, texture_size: Option<(u32, u32)>) -> (Corners, Corners) {
+    let position = object.position;
+    let (w, h) = (position.w as f32, position.h as f32);
+    let local = [(0.0, 0.0), (w, 0.0), (0.0, h), (w, h)];
+    // the transform maps the center of each screen pixel to the center of
+    // a pixel of the object, so the edges are half a pixel away from those
+    let forward = object.transform.as_ref().and_then(|inverse| inverse.invert());
+    let corners = local.map(|(x, y)| match &forward {
+        Some(forward) => {
+            let (x, y) = forward.mul_point(x - 0.5, y - 0.5);
+            (x + position.x as f32 + 0.5, y + position.y as f32 + 0.5)
+        }
+        None => (x + position.x as f32, y + position.y as f32),
+    });
+    let uvs = match texture_size {
+        Some((texture_width, texture_height)) => {
+            let source = object.source_rect();
+            local.map(|(x, y)| {
+                let u = (source.x as f32 + x / w.max(1.0) * source.w as f32) / texture_width as f32;
+                let v = (source.y as f32 + y / h.max(1.0) * source.h as f32) / texture_height as f32;
+                (u, v)
+            })
+        }
+        None => [(0.5, 0.5); 4],
+    };
+    (corners, uvs)
+}
+
+/// two triangles, with the corners in screen pixels
+fn push_quad(
+    vertices: &mut Vec<f32>, screen: Rect, corners: Corners, uvs: Corners,
+    color: RgbaPixel, background: RgbaPixel,
+) {
+    let color = [color.r, color.g, color.b, color.a].map(|c| c as f32 / 255.0);
+    let background = [background.r, background.g, background.b, background.a].map(|c| c as f32 / 255.0);
+    for corner in [0, 1, 2, 2, 1, 3].iter() {
+        let (x, y) = corners[*corner];
+        let (u, v) = uvs[*corner];
+        vertices.extend_from_slice(&[
+            x / screen.w as f32 * 2.0 - 1.0,
+            1.0 - y / screen.h as f32 * 2.0,
+            u, v,
+        ]);
+        vertices.extend_from_slice(&color);
+        vertices.extend_from_slice(&background);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close_to(a: (f32, f32), b: (f32, f32)) -> bool {
+        (a.0 - b.0).abs() < 0.001 && (a.1 - b.1).abs() < 0.001
+    }
+
+    #[test]
+    fn object_quad_covers_the_object_and_its_source() {
+        let mut p = PortionRenderer::<u8>::new(12, 12);
+        let t = p.create_object_from_texture(0, Rect { x: 2, y: 3, w: 4, h: 2 }, vec![255; 4 * 2 * 4], 4, 2);
+        p.set_object_source_rect(t, Some(Rect { x: 1, y: 0, w: 2, h: 1 }));
+        let (corners, uvs) = object_quad(&p.objects[t], Some((4, 2)));
+        assert_eq!(corners, [(2.0, 3.0), (6.0, 3.0), (2.0, 5.0), (6.0, 5.0)]);
+        assert_eq!(uvs, [(0.25, 0.0), (0.75, 0.0), (0.25, 0.5), (0.75, 0.5)]);
+        let c = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, PIXEL_RED);
+        assert_eq!(object_quad(&p.objects[c], None).1, [(0.5, 0.5); 4]);
+
+        // the software renderer draws the pixels whose center is within half
+        // a pixel of the tilted rect, whose corners are pixel centers. the
+        // quad's coordinates are pixel edges, so those are half a pixel further
+        p.set_object_rotation(t, 30f32);
+        let shape = match &p.objects[t].shape {
+            Bounds::Tilted(tilted) => tilted.grown(0.5),
+            _ => panic!("rotated rect should be tilted"),
+        };
+        let (corners, _) = object_quad(&p.objects[t], Some((4, 2)));
+        for point in shape.corners().iter() {
+            let corner = (point.x + 0.5, point.y + 0.5);
+            assert!(corners.iter().any(|c| close_to(*c, corner)), "{:?} is not a corner of {:?}", corner, corners);
+        }
+    }
+
+    #[test]
+    fn objects_are_scissored_by_every_clip() {
+        let mut p = PortionRenderer::<u8>::new(12, 12);
+        let a = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 12, h: 12 }, PIXEL_RED);
+        let b = p.create_object_from_color(1, Rect { x: 4, y: 4, w: 4, h: 4 }, PIXEL_RED);
+        let dirty = Rect { x: 0, y: 0, w: 12, h: 6 };
+        assert_eq!(object_scissor(&p, &p.objects[a], dirty), Some(dirty));
+
+        p.set_clip_rect(Some(Rect { x: 2, y: 2, w: 20, h: 20 }));
+        p.set_layer_clip_rect(1, Some(Rect { x: 0, y: 0, w: 6, h: 12 }));
+        p.set_object_clip(b, Some(Rect { x: 1, y: 1, w: 3, h: 3 }));
+        assert_eq!(object_scissor(&p, &p.objects[a], dirty), Some(Rect { x: 2, y: 2, w: 10, h: 4 }));
+        assert_eq!(object_scissor(&p, &p.objects[b], dirty), Some(Rect { x: 5, y: 5, w: 1, h: 1 }));
+        assert_eq!(object_scissor(&p, &p.objects[b], Rect { x: 0, y: 6, w: 12, h: 6 }), Some(Rect { x: 5, y: 6, w: 1, h: 2 }));
+        assert_eq!(object_scissor(&p, &p.objects[b], Rect { x: 6, y: 0, w: 6, h: 12 }), None);
+
+        // only the parts of the dirty rects that are on screen and inside of the clip are composited
+        let rects = [dirty, Rect { x: 0, y: 0, w: 2, h: 2 }, Rect { x: 10, y: 10, w: 5, h: 5 }];
+        assert_eq!(visible_rects(&p, &rects), vec![
+            Rect { x: 2, y: 2, w: 10, h: 4 },
+            Rect { x: 10, y: 10, w: 2, h: 2 },
+        ]);
+    }
+}
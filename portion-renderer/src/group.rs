@@ -0,0 +1,203 @@
+use super::*;
+
+/// what several layers have in common, eg: a "world" group that moves
+/// with the camera, and a "UI" group that stays on the screen
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerGroup {
+    /// false to ignore the camera position and zoom, so that the
+    /// world coordinates of the objects are screen coordinates
+    pub follows_camera: bool,
+    /// in screen pixels, added to where the objects would be on screen
+    pub offset_x: i32,
+    pub offset_y: i32,
+    /// from 0 to 1, which scales the alpha of every pixel of the objects
+    pub opacity: f32,
+}
+
+impl<T> PortionRenderer<T> {
+    pub fn layer_group(&self, group_index: usize) -> Option<&LayerGroup> {
+        self.layer_groups.get(group_index)
+    }
+
+    fn group_of_layer(&self, layer_index: usize) -> Option<&LayerGroup> {
+        self.layers[layer_index].group.and_then(|g| self.layer_groups.get(g))
+    }
+
+    /// where the world coordinate (x, y) of an object on the layer ends
+    /// up on screen, if the camera was at (camera_x, camera_y)
+    pub(crate) fn layer_to_screen(&self, layer_index: usize, x: i32, y: i32, camera_x: i32, camera_y: i32) -> (i32, i32) {
+        match self.group_of_layer(layer_index) {
+            Some(group) if !group.follows_camera => (x + group.offset_x, y + group.offset_y),
+            Some(group) => {
+                let (x, y) = (zoomed(x - camera_x, self.zoom), zoomed(y - camera_y, self.zoom));
+                (x + group.offset_x, y + group.offset_y)
+            }
+            None => (zoomed(x - camera_x, self.zoom), zoomed(y - camera_y, self.zoom)),
+        }
+    }
+
+    /// the camera zoom, unless the object's group doesnt follow the camera
+    pub(crate) fn object_zoom(&self, object_index: usize) -> f32 {
+        match self.group_of_layer(self.objects[object_index].layer_index) {
+            Some(group) if !group.follows_camera => 1.0,
+            _ => self.zoom,
+        }
+    }
+
+    /// true if moving the camera leaves some objects where they are
+    pub(crate) fn has_fixed_objects(&self) -> bool {
+        self.layers.iter().enumerate().any(|(layer_index, layer)| {
+            !layer.objects.is_empty()
+                && matches!(self.group_of_layer(layer_index), Some(group) if !group.follows_camera)
+        })
+    }
+
+    /// 0 to 255, which is the alpha that a pixel
+    /// of 255 alpha is drawn at on the layer
+    pub(crate) fn layer_opacity(&self, layer_index: usize) -> u8 {
+        layer_opacity(&self.layers[layer_index], &self.layer_groups)
+    }
+
+    /// every object on the layers of the group, including masks
+    fn group_objects(&self, group_index: usize) -> Vec<usize> {
+        self.objects.iter()
+            .filter(|(_, object)| self.layers[object.layer_index].group == Some(group_index))
+            .map(|(object_index, _)| object_index)
+            .collect()
+    }
+}
+
+/// see PortionRenderer::layer_opacity
+pub(crate) fn layer_opacity(layer: &Layer, groups: &TightVec<LayerGroup>) -> u8 {
+    match layer.group.and_then(|g| groups.get(g)) {
+        Some(group) => (group.opacity * 255.0).round() as u8,
+        None => 255,
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// a group with no offset that is fully opaque. layers are
+    /// put in it with set_layer_group. layers that arent in
+    /// a group follow the camera
+    pub fn create_layer_group(&mut self, follows_camera: bool) -> usize {
+        self.log_command(|| RenderCommand::CreateLayerGroup { follows_camera });
+        self.layer_groups.insert(LayerGroup { follows_camera, offset_x: 0, offset_y: 0, opacity: 1.0 })
+    }
+
+    /// the layers of the group are no longer in a group, so they follow the camera
+    pub fn remove_layer_group(&mut self, group_index: usize) {
+        self.log_command(|| RenderCommand::RemoveLayerGroup(group_index));
+        let objects = self.group_objects(group_index);
+        if self.layer_groups.take(group_index).is_none() {
+            return;
+        }
+        for layer in self.layers.iter_mut().filter(|layer| layer.group == Some(group_index)) {
+            layer.group = None;
+        }
+        self.relayout_group_objects(objects);
+    }
+
+    /// puts the layer in the group, or in no group if None, and moves its
+    /// objects to where the group puts them. the layer is created if it
+    /// doesnt exist yet. panics if there is no group at group_index
+    pub fn set_layer_group(&mut self, layer_index: u32, group_index: Option<usize>) {
+        self.log_command(|| RenderCommand::SetLayerGroup { layer_index, group_index });
+        if let Some(group_index) = group_index {
+            if self.layer_groups.get(group_index).is_none() {
+                panic!("Cannot put layer {} in group {} because the group does not exist", layer_index, group_index);
+            }
+        }
+        let layer_index = self.get_or_make_layer(layer_index);
+        if self.layers[layer_index].group == group_index {
+            return;
+        }
+        self.layers[layer_index].group = group_index;
+        let objects: Vec<usize> = self.objects.iter()
+            .filter(|(_, object)| object.layer_index == layer_index)
+            .map(|(object_index, _)| object_index)
+            .collect();
+        self.relayout_group_objects(objects);
+    }
+
+    /// moves every object of the group by (x, y) screen pixels
+    /// from where the group would otherwise put them
+    pub fn set_layer_group_offset(&mut self, group_index: usize, x: i32, y: i32) {
+        self.log_command(|| RenderCommand::SetLayerGroupOffset { group_index, x, y });
+        let group = &mut self.layer_groups[group_index];
+        if (group.offset_x, group.offset_y) == (x, y) {
+            return;
+        }
+        group.offset_x = x;
+        group.offset_y = y;
+        let objects = self.group_objects(group_index);
+        self.relayout_group_objects(objects);
+    }
+
+    /// fades every object of the group, where 0 is invisible and 1 is as
+    /// they are. the pixels are not blended with what is below them, so
+    /// like any partly transparent pixel, only the alpha that is
+    /// written is lower. the objects stay above what is below them
+    /// unless the opacity is 0
+    pub fn set_layer_group_opacity(&mut self, group_index: usize, opacity: f32) {
+        self.log_command(|| RenderCommand::SetLayerGroupOpacity { group_index, opacity });
+        let opacity = opacity.clamp(0.0, 1.0);
+        let group = &mut self.layer_groups[group_index];
+        if group.opacity == opacity {
+            return;
+        }
+        group.opacity = opacity;
+        for object_index in self.group_objects(group_index) {
+            self.mark_object_dirty(object_index, DirtyFlags::COLOR);
+        }
+    }
+
+    /// moves the objects to where they are on screen now,
+    /// after their group, or something about it changed
+    fn relayout_group_objects(&mut self, objects: Vec<usize>) {
+        for object_index in objects {
+            self.move_object_to_world_position(object_index);
+            if self.apply_object_transform(object_index) {
+                self.mark_object_dirty(object_index, DirtyFlags::TRANSFORM);
+            }
+            // the opacity might have changed too
+            self.mark_object_dirty(object_index, DirtyFlags::COLOR);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ui_group_stays_on_screen_while_the_camera_moves() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let world = p.create_object_from_color(0, Rect { x: 3, y: 3, w: 1, h: 1 }, PIXEL_RED);
+        let ui = p.create_layer_group(false);
+        p.set_layer_group(10, Some(ui));
+        let button = p.create_object_from_color(10, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        p.move_camera(1, 1);
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(2, 2)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(1, 1)]), PIXEL_GREEN);
+        assert_eq!(p.get_object(button).unwrap().position.x, 0);
+
+        p.set_layer_group_offset(ui, 4, 4);
+        p.set_layer_group_opacity(ui, 0.5);
+        p.draw_all_layers();
+        assert_eq!(&p[(0, 0)], &[0, 0, 0, 0]);
+        assert_eq!(&p[(5, 4)], &[0, 255, 0, 128]);
+        assert_eq!(p.composited_pixel_at(4, 4), RgbaPixel { a: 128, ..PIXEL_GREEN });
+
+        // back to following the camera
+        p.remove_layer_group(ui);
+        p.draw_all_layers();
+        assert!(p.layer_group(ui).is_none());
+        assert_eq!(p.get_object(button).unwrap().position.x, -1);
+        assert_eq!(RgbaPixel::from(&p[(0, 0)]), PIXEL_GREEN);
+        assert_eq!(&p[(1, 1)], &[0, 0, 0, 0]);
+        assert_eq!(p.get_object(world).unwrap().position.x, 2);
+    }
+}
@@ -0,0 +1,110 @@
+use std::path::Path;
+
+use image::{DynamicImage, ImageResult, RgbaImage};
+
+use super::*;
+
+impl Texture<u8> {
+    /// converts the image to RGBA8888
+    pub fn from_image(image: &DynamicImage) -> Texture<u8> {
+        Texture::from_image_with_format(image, &PixelFormatEnum::RGBA8888)
+    }
+
+    /// converts the image to the given 4 byte pixel format
+    pub fn from_image_with_format(image: &DynamicImage, format: &PixelFormatEnum) -> Texture<u8> {
+        let rgba = image.to_rgba8();
+        let (width, height) = rgba.dimensions();
+        let mut data = rgba.into_raw();
+        if format.channel_order() != PixelFormatEnum::RGBA8888.channel_order() {
+            for pixel in data.chunks_exact_mut(4) {
+                let source = [pixel[0], pixel[1], pixel[2], pixel[3]];
+                format.write_rgba(&source, pixel);
+            }
+        }
+        Texture::new(data, width, height)
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// the image is stretched to fit the bounds
+    pub fn create_object_from_image(
+        &mut self, layer_index: u32, bounds: Rect, image: &DynamicImage,
+    ) -> usize {
+        let texture = Texture::from_image_with_format(image, &self.pixel_format);
+        self.create_object(layer_index, bounds, Some(texture), None)
+    }
+
+    /// copies the part of the pixel buffer in region to a RGBA image.
+    /// the parts of region that are off screen are left out
+    pub fn region_to_image(&self, region: Rect) -> RgbaImage {
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        let region = region.intersection(screen).unwrap_or(EMPTY_RECT);
+        let ipp = self.indices_per_pixel as usize;
+        let mut data = Vec::with_capacity(region.w as usize * region.h as usize * 4);
+        for y in region.y..region.y + region.h {
+            let start = red_index(region.x, y, self.width, self.indices_per_pixel);
+            let row = &self.pixel_buffer[start..start + region.w as usize * ipp];
+            for pixel in row.chunks_exact(ipp) {
+                data.extend_from_slice(&self.pixel_format.read_rgba(pixel));
+            }
+        }
+        RgbaImage::from_raw(region.w, region.h, data).expect("Region data should fit its image")
+    }
+
+    pub fn to_image(&self) -> RgbaImage {
+        self.region_to_image(Rect { x: 0, y: 0, w: self.width, h: self.height })
+    }
+
+    /// saves the whole pixel buffer as a PNG, eg: for screenshots
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> ImageResult<()> {
+        self.to_image().save_with_format(path, image::ImageFormat::Png)
+    }
+
+    pub fn save_region_png<P: AsRef<Path>>(&self, region: Rect, path: P) -> ImageResult<()> {
+        self.region_to_image(region).save_with_format(path, image::ImageFormat::Png)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_image_converts_to_the_pixel_format() {
+        let mut rgba = RgbaImage::new(2, 1);
+        rgba.put_pixel(0, 0, image::Rgba([1, 2, 3, 4]));
+        rgba.put_pixel(1, 0, image::Rgba([5, 6, 7, 8]));
+        let image = DynamicImage::ImageRgba8(rgba);
+
+        let texture = Texture::from_image(&image);
+        assert_eq!((texture.width, texture.height), (2, 1));
+        assert_eq!(texture.data, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        let texture = Texture::from_image_with_format(&image, &PixelFormatEnum::BGRA8888);
+        assert_eq!(texture.data, vec![3, 2, 1, 4, 7, 6, 5, 8]);
+
+        let mut p = PortionRenderer::<u8>::new(4, 4);
+        let object = p.create_object_from_image(0, Rect { x: 0, y: 0, w: 2, h: 1 }, &image);
+        p.draw_all_layers();
+        assert_eq!(&p[(0, 0)], &[1, 2, 3, 4]);
+        assert_eq!(&p[(1, 0)], &[5, 6, 7, 8]);
+        assert_eq!(p.objects[object].current_bounds, Rect { x: 0, y: 0, w: 2, h: 1 });
+    }
+
+    #[test]
+    fn save_region_png_round_trips() {
+        let mut p = PortionRenderer::<u8>::new(12, 12);
+        p.create_object_from_color(0, Rect { x: 2, y: 2, w: 3, h: 3 }, PIXEL_RED);
+        p.draw_all_layers();
+
+        let path = std::env::temp_dir().join(format!("portion-renderer-{}.png", std::process::id()));
+        p.save_region_png(Rect { x: 1, y: 1, w: 20, h: 2 }, &path).unwrap();
+        let saved = image::open(&path).unwrap().to_rgba8();
+        std::fs::remove_file(&path).unwrap();
+
+        // clipped to the right edge of the screen
+        assert_eq!(saved.dimensions(), (11, 2));
+        assert_eq!(saved.get_pixel(0, 0).0, [0, 0, 0, 0]);
+        assert_eq!(saved.get_pixel(1, 1).0, [255, 0, 0, 255]);
+        assert_eq!(saved, p.region_to_image(Rect { x: 1, y: 1, w: 11, h: 2 }));
+    }
+}
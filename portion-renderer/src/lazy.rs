@@ -0,0 +1,182 @@
+use super::*;
+
+/// the pixels of a texture, one row at a time, eg: decoded from a file
+/// or a video stream as they are needed, see create_texture_from_provider
+pub trait TextureProvider: Send {
+    fn width(&self) -> u32;
+    fn height(&self) -> u32;
+    /// writes row y into row, which is width pixels
+    /// long in the renderer's pixel format
+    fn fetch_row(&mut self, y: u32, row: &mut [u8]);
+}
+
+/// which rows of a texture from a provider are fetched
+pub(crate) struct LazyTexture {
+    provider: Box<dyn TextureProvider>,
+    /// when each row was last needed, or None if it isnt fetched
+    last_used: Vec<Option<u64>>,
+    loaded: usize,
+    cache_rows: usize,
+    /// counts the times that rows were loaded, for last_used
+    clock: u64,
+}
+
+impl LazyTexture {
+    /// fetches the rows in needed that arent fetched yet, and then
+    /// forgets the least recently needed rows until at most cache_rows
+    /// are left. the rows in needed are never forgotten. returns
+    /// true if any of the rows were fetched or forgotten
+    fn load(&mut self, texture: &mut Texture<u8>, needed: &[(u32, u32)]) -> bool {
+        self.clock += 1;
+        let row_len = texture.width as usize * 4;
+        let mut changed = false;
+        for &(start, end) in needed.iter() {
+            for y in start..end {
+                let row_start = y as usize * row_len;
+                let row = &mut texture.data[row_start..row_start + row_len];
+                if self.last_used[y as usize].is_none() {
+                    self.provider.fetch_row(y, row);
+                    self.loaded += 1;
+                    changed = true;
+                }
+                self.last_used[y as usize] = Some(self.clock);
+            }
+        }
+        while self.loaded > self.cache_rows {
+            let oldest = self.last_used.iter().enumerate()
+                .filter_map(|(y, used)| used.map(|used| (used, y)))
+                .filter(|(used, _)| *used != self.clock)
+                .min();
+            let y = match oldest {
+                Some((_, y)) => y,
+                None => break,
+            };
+            // made transparent, so that nothing stale is drawn
+            // if the row is sampled before it is fetched again
+            texture.data[y * row_len..(y + 1) * row_len].iter_mut().for_each(|v| *v = 0);
+            self.last_used[y] = None;
+            self.loaded -= 1;
+            changed = true;
+        }
+        changed
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// a texture that objects can share, like create_texture, but whose
+    /// rows are only fetched from the provider once an object needs them
+    /// to draw. the texture is allocated at its full size, but the pixels
+    /// start transparent. once more than cache_rows rows were fetched,
+    /// the rows that were needed the longest time ago are forgotten,
+    /// and fetched again if they are needed again. rotated objects, and
+    /// objects in viewports, need every row of their source rect
+    pub fn create_texture_from_provider(&mut self, provider: impl TextureProvider + 'static, cache_rows: usize) -> usize {
+        let (width, height) = (provider.width(), provider.height());
+        let len = checked_buffer_len(width, height, self.indices_per_pixel)
+            .expect("Texture dimensions overflow usize");
+        // not logged, since a replay has no provider to fetch the rows from
+        let texture_index = self.textures.insert(Arc::new(Texture::new(vec![0; len], width, height)));
+        self.texture_refs.insert(texture_index, 1);
+        self.lazy_textures.insert(texture_index, LazyTexture {
+            provider: Box::new(provider),
+            last_used: vec![None; height as usize],
+            loaded: 0,
+            cache_rows,
+            clock: 0,
+        });
+        texture_index
+    }
+
+    /// fetches the rows of the lazy textures that the objects
+    /// need to draw where they are now
+    pub(crate) fn load_lazy_rows(&mut self) {
+        if self.lazy_textures.is_empty() {
+            return;
+        }
+        let mut needed: HashMap<usize, Vec<(u32, u32)>> = HashMap::new();
+        for (_, object) in self.objects.iter() {
+            if object.texture_color.is_some() || !self.lazy_textures.contains_key(&object.texture_index) {
+                continue;
+            }
+            let texture_height = self.textures[object.texture_index].height;
+            let source = object.source_rect();
+            let source_end = (source.y + source.h).min(texture_height);
+            let rows = if object.transform.is_some() || self.viewports.len() > 0 {
+                (source.y, source_end)
+            } else {
+                let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+                let bounds = match object.current_bounds.intersection(screen) {
+                    Some(bounds) if !bounds.is_empty() => bounds,
+                    _ => continue,
+                };
+                // the same rows that draw_exact reads
+                let (_, clip_y) = object.position.clipped_by();
+                let (h, source_y, source_h) = (object.position.h, source.y, source.h);
+                let first = draw::texture_coord(clip_y, h, source_y, source_h);
+                let last = draw::texture_coord(clip_y + bounds.h - 1, h, source_y, source_h);
+                (first, (last + 1).min(source_end))
+            };
+            if rows.0 < rows.1 {
+                needed.entry(object.texture_index).or_default().push(rows);
+            }
+        }
+        for (texture_index, lazy) in self.lazy_textures.iter_mut() {
+            let rows = needed.get(texture_index).map(|rows| &rows[..]).unwrap_or(&[]);
+            if lazy.load(Arc::make_mut(&mut self.textures[*texture_index]), rows) {
+                self.opaque_textures.remove(texture_index);
+            }
+        }
+    }
+}
+
+impl<T> PortionRenderer<T> {
+    pub(crate) fn remap_lazy_textures(&mut self, remap: &HashMap<usize, usize>) {
+        let lazy_textures = std::mem::take(&mut self.lazy_textures);
+        self.lazy_textures = lazy_textures.into_iter()
+            .map(|(index, lazy)| (*remap.get(&index).unwrap_or(&index), lazy))
+            .collect();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// every row is the color (y, 0, 0, 255)
+    struct Rows {
+        fetched: Arc<Mutex<Vec<u32>>>,
+    }
+
+    impl TextureProvider for Rows {
+        fn width(&self) -> u32 { 2 }
+        fn height(&self) -> u32 { 8 }
+        fn fetch_row(&mut self, y: u32, row: &mut [u8]) {
+            self.fetched.lock().unwrap().push(y);
+            fill_pixels(row, RgbaPixel { r: y as u8, g: 0, b: 0, a: 255 });
+        }
+    }
+
+    #[test]
+    fn only_the_visible_rows_are_fetched() {
+        let fetched = Arc::new(Mutex::new(vec![]));
+        let mut p = PortionRenderer::<u8>::new(4, 4);
+        let texture = p.create_texture_from_provider(Rows { fetched: fetched.clone() }, 4);
+        let o = p.create_object_from_texture_index(0, Rect { x: 0, y: 0, w: 2, h: 8 }, texture);
+        p.set_object_position(o, 0, -4);
+        p.draw_all_layers();
+        assert_eq!(*fetched.lock().unwrap(), vec![4, 5, 6, 7]);
+        assert_eq!(p[(0, 0)][0], 4);
+        assert_eq!(p[(1, 3)][0], 7);
+
+        // rows 2 and 3 are fetched, and 6 and 7 are forgotten
+        fetched.lock().unwrap().clear();
+        p.set_object_position(o, 0, -2);
+        p.draw_all_layers();
+        assert_eq!(*fetched.lock().unwrap(), vec![2, 3]);
+        assert_eq!(p[(0, 0)][0], 2);
+        p.set_object_position(o, 0, -4);
+        p.draw_all_layers();
+        assert_eq!(*fetched.lock().unwrap(), vec![2, 3, 6, 7]);
+    }
+}
@@ -0,0 +1,208 @@
+use super::*;
+
+/// presents a renderer in the middle of a target of any size, eg: a
+/// window that the user resized. the renderer is scaled up as much as
+/// it fits while keeping its aspect ratio, and the rest of the target
+/// is filled with bars. only the dirty portions are copied each frame
+pub struct Letterbox {
+    target_width: u32,
+    target_height: u32,
+    bar_color: RgbaPixel,
+    integer_scale: bool,
+}
+
+impl Letterbox {
+    /// black bars, and any scale that fits
+    pub fn new(target_width: u32, target_height: u32) -> Letterbox {
+        Letterbox { target_width, target_height, bar_color: PIXEL_BLACK, integer_scale: false }
+    }
+
+    pub fn target_size(&self) -> (u32, u32) {
+        (self.target_width, self.target_height)
+    }
+
+    /// eg: when the window was resized. call present_all
+    /// afterwards, since every pixel of the target moves
+    pub fn set_target_size(&mut self, target_width: u32, target_height: u32) {
+        self.target_width = target_width;
+        self.target_height = target_height;
+    }
+
+    /// the color of the bars, applied by the next present_all
+    pub fn set_bar_color(&mut self, color: RgbaPixel) {
+        self.bar_color = color;
+    }
+
+    /// only scales by whole numbers, so that every pixel is the same size,
+    /// at the cost of wider bars. if the target is smaller than the
+    /// renderer, it is scaled down to fit anyway
+    pub fn set_integer_scale(&mut self, integer_scale: bool) {
+        self.integer_scale = integer_scale;
+    }
+
+    /// the part of the target that a renderer of width x height is drawn to
+    pub fn view(&self, width: u32, height: u32) -> Rect {
+        let (tw, th) = (self.target_width, self.target_height);
+        if width == 0 || height == 0 {
+            return Rect { x: tw / 2, y: th / 2, w: 0, h: 0 };
+        }
+        let scale = (tw / width).min(th / height);
+        let (w, h) = if self.integer_scale && scale > 0 {
+            (width * scale, height * scale)
+        } else if tw as u64 * height as u64 <= th as u64 * width as u64 {
+            // the width fills the target, so the bars are above and below
+            (tw, (height as u64 * tw as u64 / width as u64) as u32)
+        } else {
+            ((width as u64 * th as u64 / height as u64) as u32, th)
+        };
+        Rect { x: (tw - w) / 2, y: (th - h) / 2, w, h }
+    }
+
+    /// the rects of the target that the rects of a
+    /// renderer of width x height are drawn to
+    pub fn map_rects(&self, width: u32, height: u32, rects: &[Rect]) -> Vec<Rect> {
+        let view = self.view(width, height);
+        rects.iter()
+            .map(|r| {
+                let (x0, x1) = (scaled_edge(r.x, view.w, width), scaled_edge(r.x + r.w, view.w, width));
+                let (y0, y1) = (scaled_edge(r.y, view.h, height), scaled_edge(r.y + r.h, view.h, height));
+                Rect { x: view.x + x0, y: view.y + y0, w: x1 - x0, h: y1 - y0 }
+            })
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+
+    /// the pixel of a renderer of width x height that is drawn at
+    /// (x, y) of the target, eg: to find what the mouse is over.
+    /// None if (x, y) is on the bars
+    pub fn to_renderer(&self, width: u32, height: u32, x: u32, y: u32) -> Option<(u32, u32)> {
+        let view = self.view(width, height);
+        if !view.contains_u32(x, y) {
+            return None;
+        }
+        let x = (x - view.x) as u64 * width as u64 / view.w as u64;
+        let y = (y - view.y) as u64 * height as u64 / view.h as u64;
+        Some((x as u32, y as u32))
+    }
+
+    /// copies the dirty portions of the renderer into target, scaled to
+    /// fit. target has the renderer's pixel format and is target_size big.
+    /// returns the dirty rects of target, eg: to pass on as the damage
+    /// of a window. this takes the renderer's dirty rects, so dont
+    /// mix it with other presenters. panics if target is too small
+    pub fn present(&self, renderer: &mut PortionRenderer<u8>, target: &mut [u8]) -> Vec<Rect> {
+        let dirty = renderer.take_dirty_rects();
+        for rect in dirty.iter() {
+            self.copy_rect(renderer, *rect, target);
+        }
+        self.map_rects(renderer.width, renderer.height, &dirty)
+    }
+
+    /// fills the bars, and copies the whole renderer into target, eg: for
+    /// the first frame, or after a resize. the dirty rects are left as they are
+    pub fn present_all(&self, renderer: &PortionRenderer<u8>, target: &mut [u8]) {
+        self.check_target(renderer, target);
+        let ipp = renderer.indices_per_pixel as usize;
+        let mut bar_pixel = vec![0; ipp];
+        renderer.pixel_format.write_rgba(&[self.bar_color.r, self.bar_color.g, self.bar_color.b, self.bar_color.a], &mut bar_pixel);
+        let view = self.view(renderer.width, renderer.height);
+        let target_rect = Rect { x: 0, y: 0, w: self.target_width, h: self.target_height };
+        let target_pitch = self.target_width as usize * ipp;
+        for bar in target_rect.subtract(&view) {
+            for y in bar.y..bar.y + bar.h {
+                let start = y as usize * target_pitch + bar.x as usize * ipp;
+                for pixel in target[start..start + bar.w as usize * ipp].chunks_exact_mut(ipp) {
+                    pixel.copy_from_slice(&bar_pixel);
+                }
+            }
+        }
+        let screen = Rect { x: 0, y: 0, w: renderer.width, h: renderer.height };
+        self.copy_rect(renderer, screen, target);
+    }
+
+    fn check_target(&self, renderer: &PortionRenderer<u8>, target: &[u8]) {
+        let target_len = self.target_width as usize * self.target_height as usize * renderer.indices_per_pixel as usize;
+        if target.len() < target_len {
+            panic!("Letterbox target has length {} but needs {}", target.len(), target_len);
+        }
+    }
+
+    fn copy_rect(&self, renderer: &PortionRenderer<u8>, rect: Rect, target: &mut [u8]) {
+        self.check_target(renderer, target);
+        let dest = match self.map_rects(renderer.width, renderer.height, &[rect]).pop() {
+            Some(dest) => dest,
+            None => return,
+        };
+        let ipp = renderer.indices_per_pixel as usize;
+        let view = self.view(renderer.width, renderer.height);
+        let target_pitch = self.target_width as usize * ipp;
+        // the renderer column of every column of dest, which is the
+        // same for every row
+        let columns: Vec<usize> = (dest.x..dest.x + dest.w)
+            .map(|x| ((x - view.x) as u64 * renderer.width as u64 / view.w as u64) as usize)
+            .collect();
+        let mut scaled_row = Vec::with_capacity(dest.w as usize * ipp);
+        let mut scaled_y = None;
+        for y in dest.y..dest.y + dest.h {
+            let src_y = ((y - view.y) as u64 * renderer.height as u64 / view.h as u64) as u32;
+            // rows that come from the same renderer row are the same
+            if scaled_y != Some(src_y) {
+                let row = red_index(0, src_y, renderer.width, renderer.indices_per_pixel);
+                let src = &renderer.pixel_buffer[row..row + renderer.width as usize * ipp];
+                scaled_row.clear();
+                for x in columns.iter() {
+                    scaled_row.extend_from_slice(&src[x * ipp..(x + 1) * ipp]);
+                }
+                scaled_y = Some(src_y);
+            }
+            let dest_start = y as usize * target_pitch + dest.x as usize * ipp;
+            target[dest_start..dest_start + scaled_row.len()].copy_from_slice(&scaled_row);
+        }
+    }
+}
+
+/// where the edge at the pixel at, of a renderer that is len pixels long,
+/// ends up when it is scaled to scaled_len. rounding up gives every
+/// target pixel to exactly one renderer pixel, the one it samples
+fn scaled_edge(at: u32, scaled_len: u32, len: u32) -> u32 {
+    (at as u64 * scaled_len as u64).div_ceil(len as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renderer_is_centered_between_bars() {
+        let mut p = PortionRenderer::<u8>::new_ex(4, 2, 1, 1, PixelFormatEnum::RGBA8888);
+        p.create_object_from_color(0, Rect { x: 1, y: 0, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        let mut letterbox = Letterbox::new(10, 10);
+        // scaled by 2.5, with bars above and below
+        assert_eq!(letterbox.view(4, 2), Rect { x: 0, y: 2, w: 10, h: 5 });
+        assert_eq!(letterbox.map_rects(4, 2, &[Rect { x: 1, y: 0, w: 1, h: 1 }]), vec![Rect { x: 3, y: 2, w: 2, h: 3 }]);
+        assert_eq!(letterbox.to_renderer(4, 2, 4, 3), Some((1, 0)));
+        assert_eq!(letterbox.to_renderer(4, 2, 4, 1), None);
+
+        letterbox.set_integer_scale(true);
+        letterbox.set_bar_color(PIXEL_BLUE);
+        assert_eq!(letterbox.view(4, 2), Rect { x: 1, y: 3, w: 8, h: 4 });
+        let mut target = vec![0; 10 * 10 * 4];
+        letterbox.present_all(&p, &mut target);
+        let pixel = |target: &[u8], x: usize, y: usize| -> RgbaPixel { (&target[(y * 10 + x) * 4..]).into() };
+        assert_eq!(pixel(&target, 0, 0), PIXEL_BLUE);
+        assert_eq!(pixel(&target, 9, 5), PIXEL_BLUE);
+        assert_eq!(pixel(&target, 3, 3), PIXEL_RED);
+        assert_eq!(pixel(&target, 4, 4), PIXEL_RED);
+        assert_eq!(pixel(&target, 5, 3), PIXEL_BLANK);
+
+        // the damage is where the dirty portion is in the target
+        p.take_dirty_rects();
+        p.move_object_x_by(0, 2);
+        p.draw_all_layers();
+        let damage = letterbox.present(&mut p, &mut target);
+        assert_eq!(damage, vec![Rect { x: 1, y: 3, w: 8, h: 4 }]);
+        assert_eq!(pixel(&target, 3, 3), PIXEL_BLANK);
+        assert_eq!(pixel(&target, 7, 4), PIXEL_RED);
+    }
+}
@@ -1,20 +1,126 @@
+use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::ops::Index;
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Instant;
+// rotated objects are sampled with FixedMatrix::compute_fixed instead
+#[cfg(not(feature = "fixed-point"))]
 use projection::ComputePoint;
+use clip::combined_clip;
+use group::layer_opacity;
 
 pub mod portioner;
 pub mod projection;
 pub mod transform;
 pub mod bounds;
+#[cfg(feature = "fixed-point")]
+pub mod fixed;
+pub mod draw;
+pub mod spatial;
+pub mod stats;
+pub mod yuv;
+pub mod commands;
+pub mod builder;
+pub mod camera;
+pub mod viewport;
+pub mod clip;
+pub mod thumbnail;
+pub mod color;
+pub mod effects;
+pub mod upscale;
+pub mod letterbox;
+pub mod patterns;
+pub mod stream;
+pub mod backend;
+pub mod events;
+pub mod motion;
+pub mod timeline;
+pub mod atlas;
+pub mod lazy;
+pub mod memory;
+pub mod snap;
+pub mod dirty;
+pub mod transition;
+pub mod diff;
+pub mod lighting;
+pub mod group;
+pub mod snapshot;
+pub mod rows;
+pub mod testing;
+#[cfg(feature = "sdl2")]
+pub mod present_sdl2;
+#[cfg(feature = "minifb")]
+pub mod present_minifb;
+#[cfg(feature = "windowed")]
+pub mod present_softbuffer;
+#[cfg(feature = "embedded-graphics")]
+pub mod embedded;
+#[cfg(all(feature = "fbdev", target_os = "linux"))]
+pub mod present_fbdev;
+#[cfg(feature = "image")]
+pub mod images;
+#[cfg(feature = "recorder")]
+pub mod recorder;
+#[cfg(feature = "wasm")]
+pub mod present_wasm;
+#[cfg(feature = "wayland")]
+pub mod present_wayland;
+#[cfg(feature = "terminal")]
+pub mod terminal;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "gpu")]
+pub mod gpu;
 pub use projection::Matrix;
 pub use projection::RotateMatrix;
+pub use projection::MatrixBuilder;
 pub use transform::*;
 pub use portioner::*;
 pub use bounds::*;
 pub use tightvec::TightVec;
+pub use draw::{DrawContext, DrawArea, RotatedDraw, Band, DrawWork, ObjectSampler};
+pub use spatial::SpatialIndex;
+pub use stats::{FrameStats, ObjectStats, OverdrawMap, PixelCounter};
+pub use yuv::Yuv420Planes;
+pub use commands::{CommandLog, RenderCommand, RendererCommands};
+pub use builder::PortionRendererBuilder;
+pub use viewport::Viewport;
+pub use color::ColorAdjustment;
+pub use upscale::{CrtEffect, Upscaler};
+pub use letterbox::Letterbox;
+pub use stream::{Compression, StreamDecoder, StreamEncoder};
+pub use backend::{RenderBackend, SoftwareBackend};
+pub use events::ObjectEvent;
+pub use motion::Motion;
+pub use timeline::{Easing, Keyframe, Property, Timeline};
+pub use atlas::{grid_regions, TextureAtlas};
+pub use lazy::TextureProvider;
+pub use memory::MemoryReport;
+pub use dirty::DirtyFlags;
+pub use transition::{Transition, TransitionEffect, WipeDirection};
+pub use lighting::Light;
+pub use group::LayerGroup;
+pub use snapshot::RendererSnapshot;
+use events::ObjectEvents;
+use lazy::LazyTexture;
+use lighting::Lighting;
+use mask::Mask;
 
 #[cfg(feature = "profile")]
 use profiler::Profiler;
 
+/// the ComputePoint used when sampling rotated or zoomed objects.
+/// with the fixed-point feature enabled, the point computation
+/// is done in 16.16 integer math which is much faster on targets without an FPU
+#[cfg(not(feature = "fixed-point"))]
+type SampleMatrix = projection::RotateScaleTranslateMatrix;
+#[cfg(feature = "fixed-point")]
+type SampleMatrix = fixed::FixedMatrix;
+
+/// both ends of the channel that RendererCommands handles send to
+type CommandChannel<T> = (Sender<RenderCommand<T>>, Receiver<RenderCommand<T>>);
+
 #[cfg(feature = "profile")]
 macro_rules! profile_start {
     ($s:expr, $x:expr) => {
@@ -36,6 +142,53 @@ macro_rules! profile_stop {
     ($s:expr, $x:expr) => {};
 }
 
+/// enters a tracing span until the returned guard is dropped,
+/// eg: let _span = span!(TRACE, "draw_object", object_index);
+#[cfg(feature = "tracing")]
+macro_rules! span {
+    ($level:ident, $($args:tt)*) => {
+        tracing::span!(tracing::Level::$level, $($args)*).entered()
+    };
+}
+#[cfg(not(feature = "tracing"))]
+macro_rules! span {
+    ($level:ident, $($args:tt)*) => { () };
+}
+
+
+/// borrows everything that drawing reads from the renderer,
+/// without borrowing the pixel buffer
+macro_rules! draw_context {
+    ($s:expr) => {
+        DrawContext {
+            textures: &$s.textures,
+            objects: &$s.objects,
+            clear: &$s.clear,
+            width: $s.width,
+            indices_per_pixel: $s.indices_per_pixel,
+            antialias: $s.antialias,
+            filter: $s.filter,
+            clip: $s.clip,
+            layers: &$s.layers,
+            groups: &$s.layer_groups,
+            counter: if $s.frame_stats.is_some() { Some(&$s.pixel_counter) } else { None },
+            overdraw: $s.overdraw.as_ref(),
+            backend: &*$s.backend,
+            snap: if $s.snap_to_grid { Some(($s.portioner.col_width, $s.portioner.row_height)) } else { None },
+        }
+    };
+}
+
+/// the entire pixel buffer as a single band
+macro_rules! whole_band {
+    ($s:expr) => {
+        Band { pixels: &mut $s.pixel_buffer, y_start: 0, y_end: $s.height, offset: 0 }
+    };
+}
+
+// after the macros, since it draws with draw_context
+pub mod collision;
+pub mod mask;
 
 #[macro_export]
 macro_rules! get_red_index {
@@ -51,8 +204,32 @@ macro_rules! get_pixel_start {
     }
 }
 
+/// same as get_red_index!, but the math is done in usize, so it
+/// cannot silently overflow like u32 can for large buffers
+#[inline(always)]
+pub fn red_index(x: u32, y: u32, width: u32, indices_per_pixel: u32) -> usize {
+    (y as usize * width as usize + x as usize) * indices_per_pixel as usize
+}
+
+/// like red_index, but returns None instead of overflowing
+#[inline(always)]
+pub fn checked_red_index(x: u32, y: u32, width: u32, indices_per_pixel: u32) -> Option<usize> {
+    (y as usize).checked_mul(width as usize)?
+        .checked_add(x as usize)?
+        .checked_mul(indices_per_pixel as usize)
+}
+
+/// the number of elements in a buffer of width * height pixels,
+/// or None if that does not fit in a usize
+#[inline(always)]
+pub fn checked_buffer_len(width: u32, height: u32, indices_per_pixel: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize)?
+        .checked_mul(indices_per_pixel as usize)
+}
+
 pub const PIXEL_BLANK: RgbaPixel = RgbaPixel { r: 0, g: 0, b: 0, a: 0 };
 pub const PIXEL_BLACK: RgbaPixel = RgbaPixel { r: 0, g: 0, b: 0, a: 255 };
+pub const PIXEL_WHITE: RgbaPixel = RgbaPixel { r: 255, g: 255, b: 255, a: 255 };
 pub const PIXEL_RED: RgbaPixel = RgbaPixel { r: 255, g: 0, b: 0, a: 255 };
 pub const PIXEL_GREEN: RgbaPixel = RgbaPixel { r: 0, g: 255, b: 0, a: 255 };
 pub const PIXEL_BLUE: RgbaPixel = RgbaPixel { r: 0, g: 0, b: 255, a: 255 };
@@ -66,13 +243,16 @@ pub const RGBA32_IPP: u32 = 1;
 
 static EMPTY_OBJECT: Object = Object {
     previous_bounds: EMPTY_RECT, current_bounds: EMPTY_RECT,
-    layer_index: 0, texture_index: 0, initial_render: false,
-    texture_color: None, transform: None,
+    position: EMPTY_OFFSET_RECT, world_x: 0, world_y: 0, rotation: 0.0, layer_index: 0, texture_index: 0, initial_render: false,
+    texture_color: None, transform: None, shape: Bounds::Rect(EMPTY_RECT), source: None,
+    previous_shape: Bounds::Rect(EMPTY_RECT), dirty: DirtyFlags::NONE, clip: None, mask: None,
+    background: PIXEL_BLANK,
 };
 
 pub struct PortionRenderer<T> {
     pixel_buffer: Vec<T>,
-    clear_buffer: Vec<T>,
+    /// what the previous bounds of objects get cleared with
+    clear: ClearSource<T>,
     portioner: Portioner,
 
     width: u32,
@@ -80,8 +260,62 @@ pub struct PortionRenderer<T> {
     pitch: usize,
     pixel_format: PixelFormatEnum,
     indices_per_pixel: u32,
-
-    textures: TightVec<Texture<T>>,
+    /// blend the edges of rotated objects by how much
+    /// of each edge pixel the object covers
+    antialias: bool,
+    /// how textures are sampled when their object is rotated or zoomed
+    filter: Filter,
+    /// the stats of the last frame, only Some when enabled
+    frame_stats: Option<FrameStats>,
+    pixel_counter: PixelCounter,
+    /// how many times each pixel was written to this frame, only Some when enabled
+    overdraw: Option<OverdrawMap>,
+    /// what drawing writes the pixels with, see backend.rs
+    backend: Box<dyn RenderBackend>,
+    /// every mutating call since the log was started, only Some when enabled
+    command_log: Option<CommandLog<T>>,
+    /// commands sent from RendererCommands handles, only
+    /// Some after the first handle was made
+    command_queue: Option<CommandChannel<T>>,
+
+    textures: TightVec<Arc<Texture<T>>>,
+    /// how many objects (and texture handles) use each texture.
+    /// a texture is removed when this reaches 0
+    texture_refs: HashMap<usize, usize>,
+    /// the on screen bounds of objects that were removed since the last
+    /// draw, which get redrawn from whatever is below them
+    removed_regions: Vec<Rect>,
+    /// the world coordinate of the top left of the screen. objects are
+    /// created in world coordinates, but their position is stored
+    /// relative to the screen, see camera.rs
+    camera_x: i32,
+    camera_y: i32,
+    /// how many screen pixels one world pixel takes up
+    zoom: f32,
+    /// extra views of the world drawn over parts of the screen, see viewport.rs
+    viewports: TightVec<Viewport>,
+    /// only the pixels inside of this rect are drawn to, see clip.rs
+    clip: Option<Rect>,
+    /// the overlap and occlusion callbacks, see events.rs
+    events: ObjectEvents,
+    /// the objects that move on their own, by object index, see motion.rs
+    motions: HashMap<usize, Motion>,
+    /// the textures whose rows are fetched when needed, by texture index, see lazy.rs
+    lazy_textures: HashMap<usize, LazyTexture>,
+    /// whether every pixel of a texture is opaque, by texture index, which
+    /// is found the first time that it is needed. it is forgotten when the
+    /// renderer changes the alpha of the texture, or removes it
+    opaque_textures: HashMap<usize, bool>,
+    /// objects are placed on the portion grid, see snap.rs
+    snap_to_grid: bool,
+    /// the light map multiplied over the pixels, only Some when enabled, see lighting.rs
+    lighting: Option<Lighting>,
+    /// the objects that gate where other objects are drawn, by object index, see mask.rs
+    masks: HashMap<usize, Mask>,
+    /// the frame that diff_from_previous last returned the changes of, see diff.rs
+    previous_frame: Option<Vec<T>>,
+    /// the offsets and opacities that several layers share, see group.rs
+    layer_groups: TightVec<LayerGroup>,
     layers: Vec<Layer>,
     objects: TightVec<Object>,
 
@@ -89,8 +323,23 @@ pub struct PortionRenderer<T> {
     profiler: Profiler,
 }
 
+/// where the pixels that are uncovered when an object moves come from
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ClearSource<T> {
+    /// every pixel is cleared to this color.
+    /// this does not need a second pixel buffer
+    SolidColor(RgbaPixel),
+    /// a background image that is the same size as the pixel buffer
+    Buffer(Vec<T>),
+    /// the pixels are left as they are
+    None,
+}
+
 // TODO: actually use these.
 // right now implementation just assumes RGBA8888....
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PixelFormatEnum {
     ABGR8888,
     ARGB8888,
@@ -99,6 +348,8 @@ pub enum PixelFormatEnum {
     RGBA32,
 }
 
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Layer {
     /// a human friendly index
     /// a Layer is stored in a vec where its actual index
@@ -112,30 +363,87 @@ pub struct Layer {
     /// a vector of objects indices on this layer that need to be updated next render cycle
     /// you can get the objects via Renderer.objects[Layer.objects[...]]
     pub updates: Vec<usize>,
+    /// the objects on this layer by where they are on screen,
+    /// so that only the objects near another object need to be checked
+    /// when finding what is above/below it
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub spatial: SpatialIndex,
+    /// the objects on this layer are only drawn inside of this rect, see clip.rs
+    pub clip: Option<Rect>,
+    /// the group that the layer is in, see group.rs
+    pub group: Option<usize>,
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Texture<T> {
     pub data: Vec<T>,
     pub width: u32,
     pub height: u32,
 }
 
-#[derive(Copy, Clone)]
-pub struct Transform {
-    pub matrix: Matrix,
-    pub bounds: TiltedRect,
+impl<T> Texture<T> {
+    pub fn new(data: Vec<T>, width: u32, height: u32) -> Texture<T> {
+        Texture { data, width, height }
+    }
+}
+
+impl Texture<u8> {
+    /// true if the alpha of every pixel of rect is not 0.
+    /// false if rect is not inside of the texture
+    pub fn is_opaque_in(&self, rect: Rect) -> bool {
+        if rect.x + rect.w > self.width || rect.y + rect.h > self.height {
+            return false;
+        }
+        let pitch = self.width as usize * 4;
+        (rect.y..rect.y + rect.h).all(|y| {
+            let row_start = y as usize * pitch + rect.x as usize * 4;
+            self.data[row_start..row_start + rect.w as usize * 4]
+                .chunks_exact(4).all(|p| p[3] != 0)
+        })
+    }
 }
 
 #[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Object {
     pub texture_color: Option<RgbaPixel>,
     pub texture_index: usize,
-    pub transform: Option<Transform>,
+    /// the inverse of the matrix that was applied to the object,
+    /// used to find which texture pixel to sample for a screen pixel
+    pub transform: Option<Matrix>,
+    /// the on screen shape of the object, after the transform.
+    /// only the pixels inside of this shape get drawn,
+    /// and only this shape occludes objects below it
+    pub shape: Bounds,
+    /// the part of the texture that is stretched over the object,
+    /// see source_rect
+    pub source: Option<Rect>,
+    /// the shape when the object was last drawn, so that only
+    /// the pixels it actually drew over get cleared
+    pub previous_shape: Bounds,
     pub layer_index: usize,
+    /// the full bounds of the object, which can be partially
+    /// or fully off the left/top of the screen
+    pub position: OffsetRect,
+    /// the top left of the object in world coordinates, see camera.rs
+    pub world_x: i32,
+    pub world_y: i32,
+    /// in degrees
+    pub rotation: f32,
+    /// the part of the position that is on screen
     pub current_bounds: Rect,
     pub previous_bounds: Rect,
     pub initial_render: bool,
+    /// what changed since the object was last drawn, see dirty.rs
+    pub dirty: DirtyFlags,
+    /// the part of the object that is drawn, relative to
+    /// the top left of its position, see set_object_clip
+    pub clip: Option<Rect>,
+    /// the object is only drawn where this object is opaque, see set_object_mask
+    pub mask: Option<usize>,
+    /// what a rotated object is sampled as outside of its texture, see set_object_background
+    pub background: RgbaPixel,
 }
 
 #[derive(Debug, Default)]
@@ -157,6 +465,7 @@ pub struct BelowRegions {
 }
 
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct RgbaPixel {
     pub r: u8,
     pub g: u8,
@@ -184,13 +493,70 @@ pub fn pixel_vec_to_texture(pixel_vec: Vec<RgbaPixel>) -> Vec<u8> {
 impl GetRectangularBounds for Object {
     #[inline(always)]
     fn get_bounds(&self) -> Rect {
-        match self.transform {
-            Some(transform) => transform.bounds.get_bounds(),
-            None => self.current_bounds,
+        match self.clip_on_screen() {
+            Some(clip) => self.shape.get_bounds().clamp_to(&clip),
+            None => self.shape.get_bounds(),
+        }
+    }
+}
+
+impl Intersects for Object {
+    /// this uses the pixels that drawing the object writes to,
+    /// see Bounds::footprint, instead of its outer rectangular bounds
+    #[inline(always)]
+    fn intersection<C: GetRectangularBounds>(&self, b: C) -> Option<Rect> {
+        let overlap = self.shape.footprint().intersection(b)?;
+        match self.clip_on_screen() {
+            Some(clip) => overlap.intersection(clip),
+            None => Some(overlap),
         }
     }
 }
 
+/// blends the pixel into dest, where weight 1.0 fully replaces dest
+#[inline(always)]
+pub fn blend_pixel_into(dest: &mut [u8], pixel: RgbaPixel, weight: f32) {
+    let inverse = 1.0 - weight;
+    dest[0] = (pixel.r as f32 * weight + dest[0] as f32 * inverse) as u8;
+    dest[1] = (pixel.g as f32 * weight + dest[1] as f32 * inverse) as u8;
+    dest[2] = (pixel.b as f32 * weight + dest[2] as f32 * inverse) as u8;
+    dest[3] = (pixel.a as f32 * weight + dest[3] as f32 * inverse) as u8;
+}
+
+/// packs a RGBA8888 pixel into a 0RGB u32, which is what
+/// most window buffers (minifb, softbuffer) expect
+#[inline(always)]
+pub fn rgba_to_0rgb(pixel: &[u8]) -> u32 {
+    (pixel[0] as u32) << 16 | (pixel[1] as u32) << 8 | pixel[2] as u32
+}
+
+/// sets every 4 byte pixel of dest to pixel. instead of writing
+/// each pixel, the filled part is doubled with memcpy until
+/// dest is full, which is much faster for long rows
+#[inline]
+pub fn fill_pixels(dest: &mut [u8], pixel: RgbaPixel) {
+    if dest.len() < 4 {
+        return;
+    }
+    dest[0..4].copy_from_slice(&[pixel.r, pixel.g, pixel.b, pixel.a]);
+    let len = dest.len() - dest.len() % 4;
+    let mut filled = 4;
+    while filled < len {
+        let n = cmp::min(filled, len - filled);
+        dest.copy_within(0..n, filled);
+        filled += n;
+    }
+}
+
+/// the length of v world pixels on screen at the camera zoom
+#[inline(always)]
+fn zoomed(v: i32, zoom: f32) -> i32 {
+    if zoom == 1.0 {
+        return v;
+    }
+    (v as f32 * zoom).round() as i32
+}
+
 impl SetPixel<u8> for &mut [u8] {
     #[inline(always)]
     fn set_pixel(&mut self, pixel: &[u8]) {
@@ -212,6 +578,40 @@ impl PixelFormatEnum {
             PixelFormatEnum::RGBA32 => RGBA32_IPP,
         }
     }
+
+    /// the index of the (r, g, b, a) bytes within a pixel of this format.
+    /// RGBA32 is treated as RGBA8888 when it is stored as bytes
+    #[inline(always)]
+    pub fn channel_order(&self) -> [usize; 4] {
+        match self {
+            PixelFormatEnum::ABGR8888 => [3, 2, 1, 0],
+            PixelFormatEnum::ARGB8888 => [1, 2, 3, 0],
+            PixelFormatEnum::RGBA8888 | PixelFormatEnum::RGBA32 => [0, 1, 2, 3],
+            PixelFormatEnum::BGRA8888 => [2, 1, 0, 3],
+        }
+    }
+
+    /// writes a RGBA pixel into dest in this format
+    #[inline(always)]
+    pub fn write_rgba(&self, rgba: &[u8], dest: &mut [u8]) {
+        let order = self.channel_order();
+        for (channel, index) in order.iter().enumerate() {
+            dest[*index] = rgba[channel];
+        }
+    }
+
+    /// reads a pixel of this format as RGBA
+    #[inline(always)]
+    pub fn read_rgba(&self, src: &[u8]) -> [u8; 4] {
+        let order = self.channel_order();
+        [src[order[0]], src[order[1]], src[order[2]], src[order[3]]]
+    }
+
+    /// reads a pixel of this format packed as 0RGB, see rgba_to_0rgb
+    #[inline(always)]
+    pub fn read_0rgb(&self, src: &[u8]) -> u32 {
+        rgba_to_0rgb(&self.read_rgba(src))
+    }
 }
 
 impl<'a> Default for Object {
@@ -220,10 +620,33 @@ impl<'a> Default for Object {
     }
 }
 
+impl Object {
+    /// the part of the texture that is stretched over the full bounds of
+    /// the object. if it wasnt set, the texture is drawn at its own size
+    /// from its top left corner, and cut off at the bounds of the object
+    pub fn source_rect(&self) -> Rect {
+        self.source.unwrap_or(Rect { x: 0, y: 0, w: self.position.w, h: self.position.h })
+    }
+
+    fn move_by(&mut self, dx: i32, dy: i32) {
+        self.position.x += dx;
+        self.position.y += dy;
+        self.current_bounds = self.position.visible_bounds();
+        match &mut self.shape {
+            Bounds::Rect(r) => *r = self.current_bounds,
+            shape => {
+                shape.shift_bounds_x(dx);
+                shape.shift_bounds_y(dy);
+            }
+        }
+    }
+}
+
 impl Layer {
     /// returns the layer's actual index of the Vec its in,
     /// whereas the layer_index: u32 is a human friendly index
-    /// like 0, 1000, 1001, etc.
+    /// like 0, 1000, 1001, etc. a new layer is inserted in order,
+    /// so the layers after it move up by one
     pub fn get_or_make_layer(layers: &mut Vec<Layer>, layer_index: u32) -> usize {
         let mut insert_at_index = 0;
         let mut update_at_index = None;
@@ -244,10 +667,13 @@ impl Layer {
         if let Some(i) = update_at_index {
             i
         } else {
-            layers.push(Layer {
+            layers.insert(insert_at_index, Layer {
                 index: layer_index,
                 objects: vec![],
                 updates: vec![],
+                spatial: SpatialIndex::new(),
+                clip: None,
+                group: None,
             });
             insert_at_index
         }
@@ -264,7 +690,7 @@ impl<T> Index<(u32, u32)> for PortionRenderer<T> {
     type Output = [T];
 
     fn index(&self, index: (u32, u32)) -> &Self::Output {
-        let red_index = get_red_index!(index.0, index.1, self.width, self.indices_per_pixel) as usize;
+        let red_index = red_index(index.0, index.1, self.width, self.indices_per_pixel);
         self.pixel_buffer.get(red_index..(red_index+4)).expect("Pixel out of bounds")
     }
 }
@@ -299,7 +725,7 @@ impl<T> AsMut<Vec<T>> for PortionRenderer<T> {
 impl<T: Default + Clone> PortionRenderer<T> {
     /// provides sensible default of 4x4 portion grid,
     /// and RGBA8888 pixel format. if you dont like these defaults,
-    /// use new_ex instead and manually set your starting parameters
+    /// use builder instead and manually set your starting parameters
     pub fn new(
         width: u32,
         height: u32,
@@ -307,6 +733,12 @@ impl<T: Default + Clone> PortionRenderer<T> {
         PortionRenderer::new_ex(width, height, 4, 4, PixelFormatEnum::RGBA8888)
     }
 
+    /// a builder that starts with the same defaults as new
+    pub fn builder(width: u32, height: u32) -> PortionRendererBuilder<T> {
+        PortionRendererBuilder::new(width, height)
+    }
+
+    /// prefer builder, which can set every option
     pub fn new_ex(
         width: u32,
         height: u32,
@@ -315,20 +747,44 @@ impl<T: Default + Clone> PortionRenderer<T> {
         pixel_format: PixelFormatEnum,
     ) -> PortionRenderer<T> {
         let indices_per_pixel = pixel_format.indices_per_pixel();
-        let num_pixels = width * height;
-        let data_len: usize = (num_pixels * indices_per_pixel) as usize;
+        let data_len = checked_buffer_len(width, height, indices_per_pixel)
+            .expect("Pixel buffer dimensions overflow usize");
         let pixel_buffer = vec![T::default(); data_len];
-        let pitch = (width * indices_per_pixel) as usize;
+        let pitch = width as usize * indices_per_pixel as usize;
         PortionRenderer {
-            clear_buffer: pixel_buffer.clone(),
+            clear: ClearSource::SolidColor(PIXEL_BLANK),
             pixel_buffer,
             width,
             pitch,
             height,
             indices_per_pixel,
             pixel_format,
-            layers: vec![Layer { index: 0, objects: vec![], updates: vec![], }],
+            antialias: false,
+            filter: Filter::Nearest,
+            frame_stats: None,
+            pixel_counter: PixelCounter::default(),
+            overdraw: None,
+            backend: Box::new(SoftwareBackend),
+            command_log: None,
+            command_queue: None,
+            layers: vec![Layer { index: 0, objects: vec![], updates: vec![], spatial: SpatialIndex::new(), clip: None, group: None }],
             textures: TightVec::new(),
+            texture_refs: HashMap::new(),
+            removed_regions: vec![],
+            camera_x: 0,
+            camera_y: 0,
+            zoom: 1.0,
+            viewports: TightVec::new(),
+            clip: None,
+            events: ObjectEvents::default(),
+            motions: HashMap::new(),
+            lazy_textures: HashMap::new(),
+            opaque_textures: HashMap::new(),
+            snap_to_grid: false,
+            lighting: None,
+            masks: HashMap::new(),
+            previous_frame: None,
+            layer_groups: TightVec::new(),
             objects: TightVec::new(),
             portioner: Portioner::new(width, height, num_rows, num_cols),
 
@@ -341,26 +797,280 @@ impl<T: Default + Clone> PortionRenderer<T> {
     /// useful when you want to render an intial scene, and
     /// then use that as the background
     pub fn set_clear_buffer(&mut self) {
-        self.clear_buffer = self.pixel_buffer.clone();
+        self.set_clear_source(ClearSource::Buffer(self.pixel_buffer.clone()));
+    }
+
+    /// panics if the source is a buffer that is not the
+    /// same size as the pixel buffer
+    pub fn set_clear_source(&mut self, clear: ClearSource<T>) {
+        if let ClearSource::Buffer(buffer) = &clear {
+            if buffer.len() != self.pixel_buffer.len() {
+                panic!("Clear buffer has length {} but the pixel buffer has length {}", buffer.len(), self.pixel_buffer.len());
+            }
+        }
+        self.log_command(|| RenderCommand::SetClearSource(clear.clone()));
+        self.clear = clear;
+    }
+
+    /// when enabled, the edge pixels of rotated objects are blended
+    /// with what is already in the pixel buffer, weighted by
+    /// how much of the pixel the object covers. off by default
+    /// so that rotated objects are drawn with exact texture values
+    pub fn set_antialiasing(&mut self, enabled: bool) {
+        self.log_command(|| RenderCommand::SetAntialiasing(enabled));
+        self.antialias = enabled;
+    }
+
+    /// how the textures of rotated or zoomed objects are sampled.
+    /// Nearest by default, which keeps hard edges
+    pub fn set_sampling_filter(&mut self, filter: Filter) {
+        self.log_command(|| RenderCommand::SetSamplingFilter(filter));
+        if self.filter == filter {
+            return;
+        }
+        self.filter = filter;
+        let sampled: Vec<usize> = self.objects.used_indices()
+            .filter(|i| self.objects[*i].transform.is_some() && self.objects[*i].texture_color.is_none())
+            .collect();
+        for object_index in sampled {
+            self.mark_object_dirty(object_index, DirtyFlags::TEXTURE);
+        }
+    }
+
+    /// when enabled, every frame records how long it took to draw,
+    /// and how long each object took, see last_frame_stats.
+    /// off by default because timing every object isnt free
+    pub fn set_frame_stats(&mut self, enabled: bool) {
+        self.frame_stats = if enabled { Some(FrameStats::default()) } else { None };
+    }
+
+    /// when enabled, every frame counts how many times each pixel was
+    /// written to, see last_overdraw. for finding objects and layers
+    /// that draw over each other more than they need to
+    pub fn set_overdraw_tracking(&mut self, enabled: bool) {
+        self.overdraw = if enabled { Some(OverdrawMap::new(self.width, self.height)) } else { None };
+    }
+
+    /// the writes of the most recent draw_all_layers call,
+    /// or None if overdraw tracking is not enabled
+    pub fn last_overdraw(&self) -> Option<&OverdrawMap> {
+        self.overdraw.as_ref()
+    }
+
+    /// the stats of the most recent draw_all_layers call,
+    /// or None if frame stats are not enabled
+    pub fn last_frame_stats(&self) -> Option<&FrameStats> {
+        self.frame_stats.as_ref()
+    }
+
+    /// starts recording every call that changes the renderer, eg: to
+    /// reproduce a rendering bug with CommandLog::replay. start it right
+    /// after creating the renderer so that the replay starts from the same state.
+    /// restarts the log if it was already started
+    pub fn start_command_log(&mut self) {
+        let (num_rows, num_cols) = self.portioner.get_grid_dimensions();
+        self.command_log = Some(CommandLog {
+            width: self.width,
+            height: self.height,
+            num_rows: num_rows as u32,
+            num_cols: num_cols as u32,
+            pixel_format: self.pixel_format,
+            commands: vec![],
+        });
+    }
+
+    /// stops recording, and returns what was recorded
+    pub fn take_command_log(&mut self) -> Option<CommandLog<T>> {
+        self.command_log.take()
+    }
+
+    /// a handle that other threads can use to queue commands.
+    /// the queued commands are applied at the start of
+    /// the next draw_all_layers (or any of its variants)
+    pub fn commands(&mut self) -> RendererCommands<T> {
+        let (sender, _) = self.command_queue.get_or_insert_with(channel);
+        RendererCommands::new(sender.clone())
     }
 }
 
-impl<T> PortionRenderer<T> {
+impl<T: Clone> PortionRenderer<T> {
+    /// the rectangles of the pixel buffer that were drawn to since
+    /// the last call, in pixels. this resets the portioner, so the
+    /// same rectangles are only returned once
+    pub fn take_dirty_rects(&mut self) -> Vec<Rect> {
+        let col_width = self.portioner.col_width;
+        let row_height = self.portioner.row_height;
+        self.portioner.flush_portions().into_iter().map(|r| Rect {
+            x: r.x * col_width,
+            y: r.y * row_height,
+            w: r.w * col_width,
+            h: r.h * row_height,
+        }).collect()
+    }
+
+    /// the same as take_dirty_rects, but as (x, y, width, height)
+    /// in the i32s that eg: wl_surface.damage_buffer takes
+    pub fn take_damage_rects(&mut self) -> Vec<(i32, i32, i32, i32)> {
+        self.take_dirty_rects().iter()
+            .map(|r| (r.x as i32, r.y as i32, r.w as i32, r.h as i32))
+            .collect()
+    }
+
+    /// copies the dirty portions of the pixel buffer into dst, which
+    /// is in the same pixel format, with dst_pitch values per row, and
+    /// returns them like take_dirty_rects. the rest of dst is left as it
+    /// was, so it should hold the previous frame, eg: a buffer that
+    /// stays mapped. panics if dst is too small for the renderer
+    pub fn blit_dirty_to(&mut self, dst: &mut [T], dst_pitch: usize) -> Vec<Rect> {
+        let row_len = self.width as usize * self.indices_per_pixel as usize;
+        let needed = if self.height == 0 { 0 } else { (self.height as usize - 1) * dst_pitch + row_len };
+        if dst_pitch < row_len || dst.len() < needed {
+            panic!("Cannot blit to a buffer of {} values with a pitch of {} because the renderer needs {} values with a pitch of at least {}", dst.len(), dst_pitch, needed, row_len);
+        }
+        let ipp = self.indices_per_pixel as usize;
+        let dirty = self.take_dirty_rects();
+        for rect in dirty.iter() {
+            let w = rect.w.min(self.width.saturating_sub(rect.x)) as usize * ipp;
+            for y in rect.y..(rect.y + rect.h).min(self.height) {
+                let start = red_index(rect.x, y, self.width, self.indices_per_pixel);
+                let dst_start = y as usize * dst_pitch + rect.x as usize * ipp;
+                dst[dst_start..dst_start + w].clone_from_slice(&self.pixel_buffer[start..start + w]);
+            }
+        }
+        dirty
+    }
+
+    /// runs f with the fine grained tracking of dirty portions turned
+    /// off, and then marks the whole screen as dirty once. for bulk
+    /// operations that touch most of the screen anyways, eg: drawing the
+    /// initial scene or a screen transition, where marking each object
+    /// as it is drawn is wasted work
+    pub fn with_portions_disabled<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let was_disabled = std::mem::replace(&mut self.portioner.disabled, true);
+        let out = f(self);
+        self.portioner.disabled = was_disabled;
+        if self.width > 0 && self.height > 0 {
+            self.portioner.take_region((0, 0), (self.width - 1, self.height - 1));
+        }
+        out
+    }
+
+    /// make_command is only called if the log is started,
+    /// so that nothing gets cloned otherwise
+    #[inline(always)]
+    fn log_command(&mut self, make_command: impl FnOnce() -> RenderCommand<T>) {
+        if let Some(log) = self.command_log.as_mut() {
+            log.commands.push(make_command());
+        }
+    }
+
     /// returns the layer's actual index of the Vec its in,
     /// whereas the layer_index: u32 is a human friendly index
     /// like 0, 1000, 1001, etc.
     pub fn get_or_make_layer(&mut self, layer_index: u32) -> usize {
-        Layer::get_or_make_layer(&mut self.layers, layer_index)
+        let layer_count = self.layers.len();
+        let layer = Layer::get_or_make_layer(&mut self.layers, layer_index);
+        if self.layers.len() != layer_count {
+            // the objects on the layers above the new one moved with them
+            for (_, object) in self.objects.iter_mut() {
+                if object.layer_index >= layer {
+                    object.layer_index += 1;
+                }
+            }
+        }
+        layer
+    }
+
+    /// returns None if there is no object at object_index
+    pub fn get_object(&self, object_index: usize) -> Option<&Object> {
+        self.objects.get(object_index)
+    }
+
+    /// the format of the pixel buffer, which textures are expected to be in
+    pub fn pixel_format(&self) -> PixelFormatEnum {
+        self.pixel_format
+    }
+
+    /// returns None if there is no texture at texture_index.
+    /// objects made from a color dont have a texture
+    pub fn get_texture(&self, texture_index: usize) -> Option<&Texture<T>> {
+        self.textures.get(texture_index).map(Arc::as_ref)
+    }
+
+    /// frees the memory of removed objects and textures by moving the
+    /// rest into their slots, and updates the layers and objects to the new
+    /// indices. returns (old_index, new_index) for every object that moved,
+    /// so that the caller can update the object indices it holds
+    pub fn compact(&mut self) -> Vec<(usize, usize)> {
+        let mut texture_moves = HashMap::new();
+        self.textures.compact(|old, new| {
+            texture_moves.insert(old, new);
+        });
+        let texture_refs = std::mem::take(&mut self.texture_refs);
+        self.texture_refs = texture_refs.into_iter()
+            .map(|(index, refs)| (*texture_moves.get(&index).unwrap_or(&index), refs))
+            .collect();
+        self.remap_lazy_textures(&texture_moves);
+        let opaque_textures = std::mem::take(&mut self.opaque_textures);
+        self.opaque_textures = opaque_textures.into_iter()
+            .map(|(index, opaque)| (*texture_moves.get(&index).unwrap_or(&index), opaque))
+            .collect();
+        let mut object_moves = vec![];
+        self.objects.compact(|old, new| object_moves.push((old, new)));
+        for (_, object) in self.objects.iter_mut() {
+            if object.texture_color.is_none() {
+                if let Some(new) = texture_moves.get(&object.texture_index) {
+                    object.texture_index = *new;
+                }
+            }
+        }
+        if object_moves.is_empty() {
+            return object_moves;
+        }
+        let remap: HashMap<usize, usize> = object_moves.iter().copied().collect();
+        let new_index = |index: &mut usize| {
+            if let Some(new) = remap.get(index) {
+                *index = *new;
+            }
+        };
+        for layer in self.layers.iter_mut() {
+            layer.objects.iter_mut().for_each(new_index);
+            layer.updates.iter_mut().for_each(new_index);
+            for (old, _) in object_moves.iter() {
+                layer.spatial.remove(*old);
+            }
+        }
+        for (_, new) in object_moves.iter() {
+            self.update_spatial_index(*new);
+        }
+        self.remap_viewport_objects(&remap);
+        self.remap_event_objects(&remap);
+        self.remap_motions(&remap);
+        self.remap_light_objects(&remap);
+        self.remap_masks(&remap);
+        object_moves
     }
 
     pub fn set_object_updated(&mut self, object_index: usize) {
-        let layer_index = self.objects[object_index].layer_index;
-        self.set_object_updated_on_layer(object_index, layer_index)
+        self.set_layer_update(object_index);
     }
 
-    fn set_object_updated_on_layer(&mut self, object_index: usize, layer_index: usize) {
+    /// only called once per object, when it is created
+    fn add_object_to_layer(&mut self, object_index: usize, layer_index: usize) {
         self.layers[layer_index].objects.push(object_index);
         self.layers[layer_index].updates.push(object_index);
+        self.update_spatial_index(object_index);
+    }
+
+    /// call this whenever the bounds of the object might have changed
+    fn update_spatial_index(&mut self, object_index: usize) {
+        // masks arent on their layer, see mask.rs
+        if self.mask_moved(object_index) {
+            return;
+        }
+        let object = &self.objects[object_index];
+        let bounds = object.get_bounds();
+        self.layers[object.layer_index].spatial.insert(object_index, bounds);
     }
 
     pub fn create_object(
@@ -368,21 +1078,152 @@ impl<T> PortionRenderer<T> {
         texture: Option<Texture<T>>,
         color: Option<RgbaPixel>,
     ) -> usize {
+        self.log_command(|| RenderCommand::CreateObject { layer_index, bounds, texture: texture.clone(), color });
         let texture_index = if let Some(txt) = texture {
-            self.textures.insert(txt)
+            let texture_index = self.textures.insert(Arc::new(txt));
+            self.texture_refs.insert(texture_index, 1);
+            texture_index
         } else { 0 };
+        self.insert_object(layer_index, bounds, texture_index, color)
+    }
+
+    /// adds a texture that several objects can share, see
+    /// create_object_from_texture_index. the returned index counts as a
+    /// reference to the texture, so call release_texture when you
+    /// no longer need to make objects from it
+    pub fn create_texture(&mut self, texture: Texture<T>) -> usize {
+        self.log_command(|| RenderCommand::CreateTexture(texture.clone()));
+        let texture_index = self.textures.insert(Arc::new(texture));
+        self.texture_refs.insert(texture_index, 1);
+        texture_index
+    }
+
+    /// the same as create_texture, but the texture data isnt copied, eg:
+    /// when it comes from an asset cache, or another renderer's
+    /// shared_texture. changing the texture, eg: with adjust_texture,
+    /// copies it first, so the other holders keep the original
+    pub fn create_shared_texture(&mut self, texture: Arc<Texture<T>>) -> usize {
+        self.log_command(|| RenderCommand::CreateTexture(texture.as_ref().clone()));
+        let texture_index = self.textures.insert(texture);
+        self.texture_refs.insert(texture_index, 1);
+        texture_index
+    }
+
+    /// the texture without copying it, to be given to create_shared_texture
+    /// of another renderer. returns None if there is no texture at texture_index
+    pub fn shared_texture(&self, texture_index: usize) -> Option<Arc<Texture<T>>> {
+        self.textures.get(texture_index).cloned()
+    }
+
+    /// the same as create_object_from_texture, but the texture data
+    /// isnt copied, see create_shared_texture
+    pub fn create_object_from_shared_texture(
+        &mut self, layer_index: u32, bounds: Rect, texture: Arc<Texture<T>>,
+    ) -> usize {
+        self.log_command(|| RenderCommand::CreateObject { layer_index, bounds, texture: Some(texture.as_ref().clone()), color: None });
+        let texture_index = self.textures.insert(texture);
+        self.texture_refs.insert(texture_index, 1);
+        self.insert_object(layer_index, bounds, texture_index, None)
+    }
+
+    /// creates an object that draws a texture from create_texture, without
+    /// copying it. the texture is drawn the same as in create_object_from_texture.
+    /// panics if there is no texture at texture_index
+    pub fn create_object_from_texture_index(
+        &mut self, layer_index: u32, bounds: Rect, texture_index: usize,
+    ) -> usize {
+        self.log_command(|| RenderCommand::CreateObjectFromTextureIndex { layer_index, bounds, texture_index });
+        match self.texture_refs.get_mut(&texture_index) {
+            Some(refs) => *refs += 1,
+            None => panic!("Cannot create an object from texture {} because it does not exist", texture_index),
+        }
+        self.insert_object(layer_index, bounds, texture_index, None)
+    }
+
+    /// gives up the reference from create_texture. the texture is
+    /// removed once every object that uses it is removed too
+    pub fn release_texture(&mut self, texture_index: usize) {
+        self.log_command(|| RenderCommand::ReleaseTexture(texture_index));
+        self.release_texture_ref(texture_index);
+    }
+
+    fn release_texture_ref(&mut self, texture_index: usize) {
+        let refs = match self.texture_refs.get_mut(&texture_index) {
+            Some(refs) => refs,
+            None => return,
+        };
+        *refs -= 1;
+        if *refs == 0 {
+            self.texture_refs.remove(&texture_index);
+            self.textures.remove(texture_index);
+            self.lazy_textures.remove(&texture_index);
+            self.opaque_textures.remove(&texture_index);
+        }
+    }
+
+    /// removes the object, and on the next draw its pixels get
+    /// restored from whatever is below it. returns false if
+    /// there was no object at object_index
+    pub fn remove_object(&mut self, object_index: usize) -> bool {
+        self.log_command(|| RenderCommand::RemoveObject(object_index));
+        let object = match self.objects.take(object_index) {
+            Some(object) => object,
+            None => return false,
+        };
+        let layer = &mut self.layers[object.layer_index];
+        layer.objects.retain(|o| *o != object_index);
+        layer.updates.retain(|o| *o != object_index);
+        layer.spatial.remove(object_index);
+        if !object.initial_render {
+            self.removed_regions.push(object.previous_bounds);
+        }
+        if object.texture_color.is_none() {
+            self.release_texture_ref(object.texture_index);
+        }
+        self.remove_object_from_viewports(object_index);
+        self.remove_object_from_events(object_index, object.get_bounds());
+        self.motions.remove(&object_index);
+        self.remove_object_lights(object_index);
+        self.remove_object_from_masks(object_index);
+        true
+    }
+
+    fn insert_object(
+        &mut self, layer_index: u32, bounds: Rect,
+        texture_index: usize, color: Option<RgbaPixel>,
+    ) -> usize {
         let layer_index = self.get_or_make_layer(layer_index);
+        let (world_x, world_y) = (bounds.x as i32, bounds.y as i32);
+        let (x, y) = self.layer_to_screen(layer_index, world_x, world_y, self.camera_x, self.camera_y);
+        let (x, y) = self.snap_position(x, y);
+        let (w, h) = self.snap_size(bounds.w, bounds.h);
+        let position = OffsetRect { x, y, w, h };
+        let visible = position.visible_bounds();
         let new_object = Object {
             texture_color: color,
             transform: None,
+            shape: Bounds::Rect(visible),
+            source: None,
+            previous_shape: Bounds::Rect(visible),
             layer_index,
             texture_index,
-            current_bounds: bounds,
-            previous_bounds: bounds,
+            position,
+            world_x,
+            world_y,
+            rotation: 0.0,
+            current_bounds: visible,
+            previous_bounds: visible,
             initial_render: true,
+            dirty: DirtyFlags::ALL,
+            clip: None,
+            mask: None,
+            background: PIXEL_BLANK,
         };
         let new_object_index = self.objects.insert(new_object);
-        self.set_object_updated_on_layer(new_object_index, layer_index);
+        if self.object_zoom(new_object_index) != 1.0 {
+            self.apply_object_transform(new_object_index);
+        }
+        self.add_object_to_layer(new_object_index, layer_index);
         new_object_index
     }
 
@@ -393,15 +1234,13 @@ impl<T> PortionRenderer<T> {
         self.create_object(layer_index, bounds, None, Some(color))
     }
 
+    /// the texture is drawn at its own size, so if it is bigger than the
+    /// bounds it gets cut off. see set_object_source_rect to stretch it instead
     pub fn create_object_from_texture(
         &mut self, layer_index: u32, bounds: Rect,
         texture: Vec<T>, texture_width: u32, texture_height: u32,
     ) -> usize {
-        let texture = Texture {
-            data: texture,
-            width: texture_width,
-            height: texture_height,
-        };
+        let texture = Texture::new(texture, texture_width, texture_height);
         self.create_object(layer_index, bounds, Some(texture), None)
     }
 
@@ -421,7 +1260,7 @@ impl<T> PortionRenderer<T> {
 
     pub fn object_needs_drawing(&mut self, object_index: usize) -> bool {
         let object = &self.objects[object_index];
-        object.previous_bounds != object.current_bounds
+        !object.dirty.is_empty() || object.previous_bounds != object.get_bounds()
     }
 
     /// layer_index is usize of the index of the layer as in PortionRenderer.layers[layer_index]
@@ -429,6 +1268,7 @@ impl<T> PortionRenderer<T> {
     /// so these regions should then be ignored when drawing this object, both for clearing
     /// its previous pixels, or updating its new pixels
     pub fn get_regions_above_object(&self, object_index: usize, layer_index: usize) -> AboveRegions {
+        let _span = span!(TRACE, "get_regions_above_object", object_index, layer_index);
         // layer_index is the index of the layer that this
         // object is on, so we check the layers above it:
         let start_layer_check_at = layer_index + 1;
@@ -436,18 +1276,24 @@ impl<T> PortionRenderer<T> {
         let object_current_bounds = &self.objects[object_index].get_bounds();
         let object_previous_bounds = &self.objects[object_index].previous_bounds;
         let mut above_bounds = AboveRegions::default();
+        let mut nearby = vec![];
         for i in start_layer_check_at..layers {
             let layer = &self.layers[i];
-            for layer_object_index in layer.objects.iter() {
-                let layer_object = &self.objects[*layer_object_index];
-                if let Some(intersection) = layer_object.get_bounds().intersection(*object_current_bounds) {
-                    above_bounds.above_my_current.push(intersection);
-                }
-                if let Some(intersection) = layer_object.get_bounds().intersection(*object_previous_bounds) {
-                    above_bounds.above_my_previous.push(intersection);
-                }
+            layer.spatial.query(object_current_bounds, &mut nearby);
+            for layer_object_index in nearby.iter() {
+                above_bounds.above_my_current.extend(self.object_covered_rects(*layer_object_index, object_current_bounds));
+            }
+            layer.spatial.query(object_previous_bounds, &mut nearby);
+            for layer_object_index in nearby.iter() {
+                above_bounds.above_my_previous.extend(self.object_covered_rects(*layer_object_index, object_previous_bounds));
             }
         }
+        let outside_clip = self.outside_layer_clip(layer_index);
+        above_bounds.above_my_current.extend(outside_clip.iter().copied());
+        above_bounds.above_my_previous.extend(outside_clip);
+        // the object doesnt draw outside of its own clip, but
+        // what it drew there before is still cleared
+        above_bounds.above_my_current.extend(self.outside_object_clip(object_index));
         above_bounds
     }
 
@@ -455,6 +1301,7 @@ impl<T> PortionRenderer<T> {
     /// and find the regions underneath us that were previously covered up, but are now
     /// open, so they should be drawn again
     pub fn get_regions_below_object(&self, object_index: usize, layer_index: usize) -> BelowRegions {
+        let _span = span!(TRACE, "get_regions_below_object", object_index, layer_index);
         // no need to check anything if we are at the bottom layer
         if layer_index == 0 {
             return BelowRegions::default();
@@ -462,11 +1309,20 @@ impl<T> PortionRenderer<T> {
         let object_previous_bounds = &self.objects[object_index].previous_bounds;
         let mut below_bounds = BelowRegions::default();
         let start_layer_check_at = layer_index;
+        let mut nearby = vec![];
         for i in (0..start_layer_check_at).rev() {
             let layer = &self.layers[i];
-            for layer_object_index in layer.objects.iter() {
+            let clip = self.layer_clip(i);
+            layer.spatial.query(object_previous_bounds, &mut nearby);
+            // objects that were created later are on top within a layer
+            for layer_object_index in nearby.iter().rev() {
                 let layer_object = &self.objects[*layer_object_index];
-                if let Some(intersection) = layer_object.get_bounds().intersection(*object_previous_bounds) {
+                let intersection = layer_object.intersection(*object_previous_bounds)
+                    .and_then(|r| match clip {
+                        Some(clip) => r.intersection(clip),
+                        None => Some(r),
+                    });
+                if let Some(intersection) = intersection {
                     below_bounds.below_my_previous.push(BelowRegion {
                         region: intersection,
                         region_belongs_to: *layer_object_index,
@@ -478,72 +1334,250 @@ impl<T> PortionRenderer<T> {
         below_bounds
     }
 
+    /// sets the shape of the object in screen coordinates, eg: a
+    /// CircleBounds for a round sprite so that the corners of its
+    /// bounds are neither drawn nor considered to cover what is below.
+    /// if the object is rotated, the rotation is applied to the new shape.
+    pub fn set_object_shape(&mut self, object_index: usize, shape: Bounds) {
+        self.log_command(|| RenderCommand::SetObjectShape { object_index, shape: shape.clone() });
+        let object = &mut self.objects[object_index];
+        object.shape = match object.transform.and_then(|inverse| inverse.invert()) {
+            Some(matrix) => shape.transformed(object.position, &matrix),
+            None => shape,
+        };
+        self.mark_object_dirty(object_index, DirtyFlags::SHAPE);
+    }
+
+    /// stretches the part of the object's texture inside of source over
+    /// the object's bounds, eg: to scale a texture, or to draw one frame
+    /// of a sprite sheet. None to draw the texture at its own size again
+    pub fn set_object_source_rect(&mut self, object_index: usize, source: Option<Rect>) {
+        self.log_command(|| RenderCommand::SetObjectSourceRect { object_index, source });
+        let object = &mut self.objects[object_index];
+        if object.source == source {
+            return;
+        }
+        object.source = source;
+        self.mark_object_dirty(object_index, DirtyFlags::TEXTURE);
+    }
+
+    /// the color of an object that was created from a color. a color with
+    /// an alpha of 0 hides the object. panics if the object has a texture
+    pub fn set_object_color(&mut self, object_index: usize, color: RgbaPixel) {
+        self.log_command(|| RenderCommand::SetObjectColor { object_index, color });
+        let object = &mut self.objects[object_index];
+        if object.texture_color.is_none() {
+            panic!("Cannot set the color of object {} because it has a texture", object_index);
+        }
+        if object.texture_color == Some(color) {
+            return;
+        }
+        object.texture_color = Some(color);
+        self.mark_object_dirty(object_index, DirtyFlags::COLOR);
+    }
+
+    /// the color that a rotated object is sampled as where it falls
+    /// outside of its texture or color, eg: along its edges, or past the
+    /// source rect. PIXEL_BLANK by default, so those pixels arent drawn
+    pub fn set_object_background(&mut self, object_index: usize, background: RgbaPixel) {
+        self.log_command(|| RenderCommand::SetObjectBackground { object_index, background });
+        let object = &mut self.objects[object_index];
+        if object.background == background {
+            return;
+        }
+        object.background = background;
+        self.mark_object_dirty(object_index, DirtyFlags::COLOR);
+    }
+
     pub fn set_object_rotation(&mut self, object_index: usize, degrees: f32) {
-        if degrees == 0f32 {
-            if self.objects[object_index].transform.is_some() {
-                self.objects[object_index].transform = None;
-                self.set_layer_update(object_index);
+        self.log_command(|| RenderCommand::SetObjectRotation { object_index, degrees });
+        self.objects[object_index].rotation = degrees;
+        if self.apply_object_transform(object_index) {
+            self.mark_object_dirty(object_index, DirtyFlags::TRANSFORM);
+        }
+    }
+
+    /// where the world coordinate (x, y) ends up on screen
+    fn world_to_screen(&self, x: i32, y: i32) -> (i32, i32) {
+        (zoomed(x - self.camera_x, self.zoom), zoomed(y - self.camera_y, self.zoom))
+    }
+
+    /// replaces the object's transform with its rotation and the
+    /// camera zoom. returns false if the transform didnt change
+    fn apply_object_transform(&mut self, object_index: usize) -> bool {
+        let zoom = self.object_zoom(object_index);
+        let object = &self.objects[object_index];
+        let position = object.position;
+        // undo the previous rotation first so that rotations dont accumulate.
+        // a tilted rect can only come from rotating the object's rect
+        let unrotated = match object.transform {
+            Some(inverse) => object.shape.transformed(position, &inverse),
+            None => object.shape.clone(),
+        };
+        let unrotated = match unrotated {
+            Bounds::Tilted(_) => Bounds::Rect(object.current_bounds),
+            shape => shape,
+        };
+
+        if object.rotation == 0f32 && zoom == 1.0 {
+            if object.transform.is_none() {
+                return false;
             }
-            return;
+            let object = &mut self.objects[object_index];
+            object.transform = None;
+            object.shape = unrotated;
+            return true;
         }
 
-        let current_bounds = self.objects[object_index].current_bounds;
-        let transform_matrix = Matrix::rotate_degrees(degrees);
+        // scaled about the top left corner of the top left pixel, rather
+        // than its center, so that objects next to each other in the
+        // world stay next to each other on screen
+        let transform_matrix = MatrixBuilder::new()
+            .then(MatrixBuilder::new().scale(zoom, zoom).about(-0.5, -0.5).build())
+            .rotate_deg(object.rotation)
+            .build();
         let inverse_transform = transform_matrix.invert().unwrap();
-        let tilted_rect = TiltedRect::from_bounds_and_matrix(current_bounds, transform_matrix);
-        let t = Transform {
-            matrix: inverse_transform,
-            bounds: tilted_rect,
-        };
-        self.objects[object_index].transform = Some(t);
-        self.set_layer_update(object_index);
+        let object = &mut self.objects[object_index];
+        object.shape = unrotated.transformed(position, &transform_matrix);
+        object.transform = Some(inverse_transform);
+        true
+    }
+
+    /// moves the object on screen by (dx, dy). a rotated object's shape
+    /// is made again at its new position instead of being shifted, so it
+    /// is the same shape that the object would have if it was made there
+    fn move_object_on_screen_by(&mut self, object_index: usize, dx: i32, dy: i32) {
+        self.objects[object_index].move_by(dx, dy);
+        if let Bounds::Tilted(_) = self.objects[object_index].shape {
+            self.apply_object_transform(object_index);
+        }
     }
 
+    /// queues the object to be drawn, as if everything about it changed
     pub fn set_layer_update(&mut self, object_index: usize) {
-        let layer_index = self.objects[object_index].layer_index;
-        self.layers[layer_index].updates.push(object_index);
+        self.mark_object_dirty(object_index, DirtyFlags::ALL);
     }
 
     pub fn move_object_x_by(&mut self, object_index: usize, by: i32) {
-        if by < 0 {
-            let current_x = self.objects[object_index].current_bounds.x;
-            let by = (0 - by) as u32;
-            if current_x >= by {
-                self.objects[object_index].current_bounds.x -= by;
-                self.set_layer_update(object_index);
-            }
-        } else {
-            self.objects[object_index].current_bounds.x += by as u32;
-            self.set_layer_update(object_index);
-        }
-        if let Some(transform) = &mut self.objects[object_index].transform {
-            transform.bounds.shift_bounds_x(by);
-        }
+        self.log_command(|| RenderCommand::MoveObjectXBy { object_index, by });
+        let object = &mut self.objects[object_index];
+        object.world_x += by;
+        self.move_object_to_world_position(object_index);
     }
 
     pub fn move_object_y_by(&mut self, object_index: usize, by: i32) {
-        if by < 0 {
-            let current_y = self.objects[object_index].current_bounds.y;
-            let by = (0 - by) as u32;
-            if current_y >= by {
-                self.objects[object_index].current_bounds.y -= by;
-                self.set_layer_update(object_index);
-            }
-        } else {
-            self.objects[object_index].current_bounds.y += by as u32;
-            self.set_layer_update(object_index);
-        }
-        if let Some(transform) = &mut self.objects[object_index].transform {
-            transform.bounds.shift_bounds_y(by);
+        self.log_command(|| RenderCommand::MoveObjectYBy { object_index, by });
+        let object = &mut self.objects[object_index];
+        object.world_y += by;
+        self.move_object_to_world_position(object_index);
+    }
+
+    /// moves the object such that its top left corner is at (x, y)
+    /// in world coordinates, see move_camera. the position can be negative,
+    /// in which case only the part of the object that is on screen gets drawn.
+    pub fn set_object_position(&mut self, object_index: usize, x: i32, y: i32) {
+        let object = &self.objects[object_index];
+        let (dx, dy) = (x - object.world_x, y - object.world_y);
+        self.move_object_x_by(object_index, dx);
+        self.move_object_y_by(object_index, dy);
+    }
+
+    /// moves the object on screen to where its world position is
+    fn move_object_to_world_position(&mut self, object_index: usize) {
+        let object = &self.objects[object_index];
+        let (x, y) = self.layer_to_screen(object.layer_index, object.world_x, object.world_y, self.camera_x, self.camera_y);
+        let (x, y) = self.snap_position(x, y);
+        let (dx, dy) = (x - object.position.x, y - object.position.y);
+        if (dx, dy) == (0, 0) {
+            return;
         }
+        self.move_object_on_screen_by(object_index, dx, dy);
+        self.mark_object_dirty(object_index, DirtyFlags::POSITION);
     }
 }
 
-
 /// This is the implementation for any pixel format in 8888 format
 /// TODO: implement these methods for 32 format
 impl PortionRenderer<u8> {
+    /// copies pixels (which are bounds.w * bounds.h pixels, in the renderer's
+    /// pixel format) into the pixel buffer at bounds. the parts of bounds
+    /// that are off the right/bottom of the screen, or outside of
+    /// the clip rect, are skipped.
+    /// panics if pixels is too short for bounds
     pub fn draw(&mut self, pixels: &[u8], bounds: Rect) {
+        let indices_per_pixel = self.indices_per_pixel as usize;
+        let src_pitch = bounds.w as usize * indices_per_pixel;
+        let src_len = src_pitch * bounds.h as usize;
+        if pixels.len() < src_len {
+            panic!("Called draw with {} pixel values but bounds {:?} needs {}", pixels.len(), bounds, src_len);
+        }
+        self.log_command(|| RenderCommand::Draw { pixels: pixels[..src_len].to_vec(), bounds });
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        let visible = match bounds.intersection(self.clip.unwrap_or(screen)).and_then(|r| r.intersection(screen)) {
+            Some(visible) => visible,
+            None => return,
+        };
+        let row_len = visible.w as usize * indices_per_pixel;
+        let src_x = (visible.x - bounds.x) as usize * indices_per_pixel;
+        for row in 0..visible.h {
+            let red_index = red_index(visible.x, visible.y + row, self.width, self.indices_per_pixel);
+            let src_index = (visible.y - bounds.y + row) as usize * src_pitch + src_x;
+            self.pixel_buffer[red_index..red_index + row_len]
+                .copy_from_slice(&pixels[src_index..src_index + row_len]);
+        }
+        self.portioner.take_region((visible.x, visible.y), (visible.x + visible.w - 1, visible.y + visible.h - 1));
+    }
+
+    /// copies the src_rect part of a larger image, eg: a decoded video
+    /// frame, into the pixel buffer with its top left at dst, without
+    /// copying it out of the image first. pixels has src_pitch values per
+    /// row, in the renderer's pixel format. the parts that are off
+    /// screen, or outside of the clip rect, are skipped like in draw.
+    /// panics if src_rect is not inside of pixels
+    pub fn draw_region(&mut self, pixels: &[u8], src_pitch: usize, src_rect: Rect, dst: (i32, i32)) {
+        let indices_per_pixel = self.indices_per_pixel as usize;
+        if src_rect.w == 0 || src_rect.h == 0 {
+            return;
+        }
+        let src_end = (src_rect.x + src_rect.w) as usize * indices_per_pixel;
+        let src_len = (src_rect.y + src_rect.h - 1) as usize * src_pitch + src_end;
+        if src_end > src_pitch || pixels.len() < src_len {
+            panic!("Cannot draw the region {:?} because {} pixel values with a pitch of {} dont contain it", src_rect, pixels.len(), src_pitch);
+        }
+        let screen = self.clip.unwrap_or(Rect { x: 0, y: 0, w: self.width, h: self.height });
+        let min_x = (dst.0 as i64).max(screen.x as i64);
+        let min_y = (dst.1 as i64).max(screen.y as i64);
+        let max_x = (dst.0 as i64 + src_rect.w as i64).min((screen.x + screen.w).min(self.width) as i64);
+        let max_y = (dst.1 as i64 + src_rect.h as i64).min((screen.y + screen.h).min(self.height) as i64);
+        if min_x >= max_x || min_y >= max_y {
+            return;
+        }
+        let visible = Rect { x: min_x as u32, y: min_y as u32, w: (max_x - min_x) as u32, h: (max_y - min_y) as u32 };
+        let row_len = visible.w as usize * indices_per_pixel;
+        let src_x = src_rect.x as usize + (min_x - dst.0 as i64) as usize;
+        let src_y = src_rect.y as usize + (min_y - dst.1 as i64) as usize;
+        let src_row = |row: u32| {
+            let start = (src_y + row as usize) * src_pitch + src_x * indices_per_pixel;
+            &pixels[start..start + row_len]
+        };
+        // logged as a draw of only the visible part, which replays the same
+        self.log_command(|| RenderCommand::Draw {
+            pixels: (0..visible.h).flat_map(|row| src_row(row).iter().copied()).collect(),
+            bounds: visible,
+        });
+        for row in 0..visible.h {
+            let red_index = red_index(visible.x, visible.y + row, self.width, self.indices_per_pixel);
+            self.pixel_buffer[red_index..red_index + row_len].copy_from_slice(src_row(row));
+        }
+        self.portioner.take_region((visible.x, visible.y), (visible.x + visible.w - 1, visible.y + visible.h - 1));
+    }
+
+    /// the same as draw, but without any bounds checks.
+    ///
+    /// # Safety
+    /// bounds must be fully on screen, and pixels must
+    /// contain at least bounds.w * bounds.h pixels
+    pub unsafe fn draw_unchecked(&mut self, pixels: &[u8], bounds: Rect) {
         let x = bounds.x as usize;
         let y = bounds.y as usize;
         let w = bounds.w as usize;
@@ -555,11 +1589,9 @@ impl PortionRenderer<u8> {
             for j in x..(x + w) {
                 let red_index = get_red_index!(j, i, self_width, indices_per_pixel);
                 let next_index = red_index + indices_per_pixel;
-                unsafe {
-                    let mut dest_pixel = self.pixel_buffer.get_unchecked_mut(red_index..next_index);
-                    let src_pixel = pixels.get_unchecked(pixels_index..pixels_index + indices_per_pixel);
-                    dest_pixel.set_pixel(src_pixel);
-                }
+                let mut dest_pixel = self.pixel_buffer.get_unchecked_mut(red_index..next_index);
+                let src_pixel = pixels.get_unchecked(pixels_index..pixels_index + indices_per_pixel);
+                dest_pixel.set_pixel(src_pixel);
 
                 pixels_index += 4;
             }
@@ -569,25 +1601,10 @@ impl PortionRenderer<u8> {
     pub fn get_pixel_from_object_at_rotated(
         &self,
         object_index: usize,
-        transform: &Transform,
+        transform: &Matrix,
         x: u32, y: u32,
     ) -> Option<RgbaPixel> {
-        let transform_matrix: RotateMatrix = (&transform.matrix).into();
-        let (shift_x, shift_y, texture_width, texture_height, texture_data) = {
-            let obj = &self.objects[object_index];
-            let texture_index = obj.texture_index;
-            let texture = &self.textures[texture_index];
-            let cb = &obj.current_bounds;
-            (cb.x as f32, cb.y as f32, texture.width, texture.height, &texture.data)
-        };
-        let x_shift = x as f32 - shift_x;
-        let y_shift = y as f32 - shift_y;
-        let (px, py) = transform_matrix.compute_pt(x_shift, y_shift);
-        let pix = interpolate_nearest(
-            &texture_data, texture_width, texture_height,
-            px, py, PIXEL_BLANK
-        );
-        Some(pix)
+        draw_context!(self).get_pixel_from_object_at_rotated(object_index, transform, x, y)
     }
 
     pub fn get_pixel_from_object_at(
@@ -595,77 +1612,84 @@ impl PortionRenderer<u8> {
         object_index: usize,
         x: u32, y: u32
     ) -> Option<RgbaPixel> {
-        if let Some(transform) = &self.objects[object_index].transform {
-            return self.get_pixel_from_object_at_rotated(object_index, transform, x, y);
-        }
-
-        if let Some(color) = self.objects[object_index].texture_color {
-            return Some(color);
-        }
-
-        let texture_index = self.objects[object_index].texture_index;
-        let texture = &self.textures[texture_index];
-
-        let current_bounds = self.objects[object_index].current_bounds;
-        // it should be guaranteed that x and y exist within the objects current bounds
-        if x < current_bounds.x || y < current_bounds.y {
-            panic!("Called get_pixel_from_object_at with ({}, {}) but objects bounds are {:?}", x, y, current_bounds);
-        }
-
-        // TODO: what if the object has a matrix transormation?
-        // need to handle that here to get the pixel value after transform
-        // currently this assumes the objects bounds are the same as the texture bounds!
-        let local_x = x - current_bounds.x;
-        let local_y = y - current_bounds.y;
-        let red_index = get_red_index!(local_x, local_y, current_bounds.w, self.indices_per_pixel) as usize;
-        let pixel: RgbaPixel = match texture.data.get(red_index..(red_index+4)) {
-            Some(u8_slice) => u8_slice.into(),
-            None => return None,
-        };
-        Some(pixel)
+        draw_context!(self).get_pixel_from_object_at(object_index, x, y)
     }
 
     pub fn clear_pixels_from_below_object(&mut self, pb_red_index: usize, x: u32, y: u32, skip_below: &BelowRegions) -> bool {
-        for below in skip_below.below_my_previous.iter() {
-            if below.region.contains_u32(x, y) {
-                let pixel = self.get_pixel_from_object_at(
-                    below.region_belongs_to, x, y
-                );
-                if let Some(pixel) = pixel {
-                    if pixel.a == 0 {
-                        return false;
-                    }
-
-                    self.pixel_buffer[pb_red_index] = pixel.r;
-                    self.pixel_buffer[pb_red_index + 1] = pixel.g;
-                    self.pixel_buffer[pb_red_index + 2] = pixel.b;
-                    self.pixel_buffer[pb_red_index + 3] = pixel.a;
-                    return true;
-                } else {
-                    return false;
-                }
-            }
-        }
-        false
+        let ctx = draw_context!(self);
+        ctx.clear_pixels_from_below_object(&mut whole_band!(self), pb_red_index, x, y, skip_below)
     }
 
-    pub fn draw_all_layers(&mut self) {
-        // TODO: can we avoid drawing bottom layers
-        // if a top layer fully covers it up?
+    /// drains the updates of every layer, and returns them
+    /// as (layer_index, object_index) in the order they should be drawn
+    /// the updated objects in the order they get drawn: bottom layer
+    /// first, and within a layer in the order the objects were added
+    /// to it, no matter what order they were updated in. so the same
+    /// updates always draw the same pixels, even when the clears and
+    /// draws of the objects overlap
+    fn take_layer_updates(&mut self) -> Vec<(usize, usize)> {
+        self.update_mask_coverage();
         let mut draw_object_indices = vec![];
         for (layer_index, layer) in self.layers.iter_mut().enumerate() {
+            if layer.updates.is_empty() {
+                continue;
+            }
+            // an object that was updated several times since the last draw
+            // only needs to be drawn once. its previous_bounds are still
+            // from the last time it was drawn, so one clear is enough.
             // make sure to drain so we remove these updates
             // and prevent them from showing up next draw
-            for object_index in layer.updates.drain(..) {
+            let updated: HashSet<usize> = layer.updates.drain(..).collect();
+            for object_index in layer.objects.iter().copied().filter(|o| updated.contains(o)) {
+                self.objects[object_index].dirty = DirtyFlags::NONE;
                 draw_object_indices.push((layer_index, object_index));
             }
         }
+        self.fire_object_events(&draw_object_indices);
+        draw_object_indices
+    }
 
-        for (layer_index, object_index) in draw_object_indices {
+    /// returns when the frame started if frame stats are enabled
+    fn start_frame_stats(&mut self) -> Option<Instant> {
+        if let Some(overdraw) = self.overdraw.as_ref() {
+            overdraw.reset();
+        }
+        let stats = self.frame_stats.as_mut()?;
+        *stats = FrameStats::default();
+        self.pixel_counter.take();
+        Some(Instant::now())
+    }
+
+    fn finish_frame_stats(&mut self, started: Option<Instant>) {
+        if let (Some(started), Some(stats)) = (started, self.frame_stats.as_mut()) {
+            stats.draw_time = started.elapsed();
+            // the per object stats already took their pixels from the counter
+            let (written, cleared) = self.pixel_counter.take();
+            stats.pixels_written += written;
+            stats.pixels_cleared += cleared;
+        }
+    }
+
+    pub fn draw_all_layers(&mut self) {
+        let _span = span!(DEBUG, "draw_all_layers");
+        self.apply_queued_commands();
+        self.load_lazy_rows();
+        self.log_command(|| RenderCommand::DrawAllLayers);
+        let started = self.start_frame_stats();
+        self.unlight_updates(false);
+        let viewport_damage = self.take_viewport_damage(false);
+        self.redraw_removed_regions();
+        // TODO: can we avoid drawing bottom layers
+        // if a top layer fully covers it up?
+        let draw_object_indices = self.take_layer_updates();
+        for (layer_index, object_index) in draw_object_indices {
             let above_regions = self.get_regions_above_object(object_index, layer_index);
             let below_regions = self.get_regions_below_object(object_index, layer_index);
             self.draw_object(object_index, above_regions, below_regions);
         }
+        self.draw_viewports(viewport_damage);
+        self.light_frame();
+        self.finish_frame_stats(started);
 
         #[cfg(feature = "profile")]
         {
@@ -674,196 +1698,358 @@ impl PortionRenderer<u8> {
         }
     }
 
-    /// like draw_all_layers, but iterates over layer.objects instead of
-    /// layer.updates, so it will always draw every object on every layer
-    /// mostly used for testing/benchmarking
-    pub fn force_draw_all_layers(&mut self) {
-        let mut draw_object_indices = vec![];
-        for (layer_index, layer) in self.layers.iter_mut().enumerate() {
-            for object_index in layer.objects.iter() {
-                draw_object_indices.push((layer_index, *object_index));
-            }
+    /// same result as draw_all_layers, but the pixel buffer is split into
+    /// horizontal bands, one per portion row, and the bands are drawn
+    /// concurrently. every update is drawn in the same order in each band,
+    /// and the bands dont share any pixels, so no locking is needed.
+    #[cfg(feature = "multithreaded")]
+    pub fn draw_all_layers_parallel(&mut self) {
+        use rayon::prelude::*;
+        let _span = span!(DEBUG, "draw_all_layers_parallel");
+        self.apply_queued_commands();
+        self.load_lazy_rows();
+        self.log_command(|| RenderCommand::DrawAllLayers);
+
+        let started = self.start_frame_stats();
+        self.unlight_updates(false);
+        let viewport_damage = self.take_viewport_damage(false);
+        self.redraw_removed_regions();
+        let draw_object_indices = self.take_layer_updates();
+        let mut work = Vec::with_capacity(draw_object_indices.len());
+        for (layer_index, object_index) in draw_object_indices {
+            let above_regions = self.get_regions_above_object(object_index, layer_index);
+            let below_regions = self.get_regions_below_object(object_index, layer_index);
+            work.push(self.prepare_draw_work(object_index, above_regions, below_regions));
         }
+        if work.is_empty() {
+            self.draw_viewports(viewport_damage);
+            self.light_frame();
+            self.finish_frame_stats(started);
+            return;
+        }
+
+        let band_height = self.portioner.row_height;
+        let band_len = band_height as usize * self.pitch;
+        let ctx = draw_context!(self);
+        self.pixel_buffer.par_chunks_mut(band_len).enumerate().for_each(|(n, pixels)| {
+            let y_start = n as u32 * band_height;
+            let _span = span!(TRACE, "draw_band", y_start);
+            let mut band = Band {
+                pixels,
+                y_start,
+                y_end: y_start + band_height,
+                offset: n * band_len,
+            };
+            for w in work.iter() {
+                ctx.clear_work(&mut band, w);
+                ctx.draw_work(&mut band, w);
+            }
+        });
+        self.draw_viewports(viewport_damage);
+        self.light_frame();
+        self.finish_frame_stats(started);
+    }
 
+    /// same result as draw_all_layers, but updates that dont share any rows
+    /// are drawn at the same time. the updates are split into waves, where
+    /// an update goes in the wave after the last earlier update that it
+    /// shares rows with, so updates that overlap are still drawn in order.
+    /// each update in a wave then gets its own band of rows. unlike
+    /// draw_all_layers_parallel, an update is only drawn by one thread
+    #[cfg(feature = "multithreaded")]
+    pub fn draw_all_layers_scheduled(&mut self) {
+        use rayon::prelude::*;
+        let _span = span!(DEBUG, "draw_all_layers_scheduled");
+        self.apply_queued_commands();
+        self.load_lazy_rows();
+        self.log_command(|| RenderCommand::DrawAllLayers);
+
+        let started = self.start_frame_stats();
+        self.unlight_updates(false);
+        let viewport_damage = self.take_viewport_damage(false);
+        self.redraw_removed_regions();
+        let draw_object_indices = self.take_layer_updates();
+        let mut work = Vec::with_capacity(draw_object_indices.len());
         for (layer_index, object_index) in draw_object_indices {
             let above_regions = self.get_regions_above_object(object_index, layer_index);
             let below_regions = self.get_regions_below_object(object_index, layer_index);
-            self.draw_object(object_index, above_regions, below_regions);
+            work.push(self.prepare_draw_work(object_index, above_regions, below_regions));
         }
-    }
 
-    pub fn draw_pixel(
-        &mut self, pixel: RgbaPixel,
-        skip_above: AboveRegions,
-        transform: Option<Transform>,
-        min_y: u32, max_y: u32,
-        min_x: u32, max_x: u32,
-        width: u32,
-        height: u32,
-    ) {
-        if let Some(transform) = transform {
-            let transform_bounds = transform.bounds.get_bounds();
-            let tmin_x = transform_bounds.x;
-            let tmax_x = tmin_x + transform_bounds.w;
-            let tmin_y = transform_bounds.y;
-            let tmax_y = tmin_y + transform_bounds.h;
-            return self.draw_pixel_rotated(pixel,
-                &skip_above, transform.matrix,
-                tmin_y, tmax_y,
-                tmin_x, tmax_x,
-                min_x as f32,
-                min_y as f32,
-                width, height
-            );
+        // the rows [start, end) that each update draws to
+        let height = self.height;
+        let damaged_rows: Vec<(u32, u32)> = work.iter().map(|w| {
+            let now = self.objects[w.object_index].get_bounds();
+            let damage = w.clear_regions.iter().fold(now, |acc, r| acc.union(r));
+            (cmp::min(damage.y, height), cmp::min(damage.y + damage.h, height))
+        }).collect();
+        let mut waves: Vec<Vec<usize>> = vec![];
+        let mut wave_of = Vec::with_capacity(work.len());
+        for (i, &(start, end)) in damaged_rows.iter().enumerate() {
+            let wave = (0..i)
+                .filter(|j| damaged_rows[*j].0 < end && start < damaged_rows[*j].1)
+                .map(|j| wave_of[j] + 1)
+                .max().unwrap_or(0);
+            wave_of.push(wave);
+            if wave == waves.len() {
+                waves.push(vec![]);
+            }
+            waves[wave].push(i);
         }
 
-        self.portioner.take_region((min_x, min_y), (max_x, max_y));
-        for i in min_y..max_y {
-            for j in min_x..max_x {
-                if should_skip_point(&skip_above.above_my_current, j, i) {
+        let pitch = self.pitch;
+        let ctx = draw_context!(self);
+        for mut wave in waves {
+            wave.sort_by_key(|i| damaged_rows[*i].0);
+            let mut bands = Vec::with_capacity(wave.len());
+            let mut rest: &mut [u8] = &mut self.pixel_buffer;
+            let mut rest_y = 0;
+            for i in wave {
+                let (start, end) = damaged_rows[i];
+                if start == end {
                     continue;
                 }
-
-                let red_index = get_red_index!(j, i, self.width, self.indices_per_pixel);
-                let red_index = red_index as usize;
-                // TODO: pixel format???
-                self.pixel_buffer[red_index] = pixel.r;
-                self.pixel_buffer[red_index + 1] = pixel.g;
-                self.pixel_buffer[red_index + 2] = pixel.b;
-                self.pixel_buffer[red_index + 3] = pixel.a;
+                let (_, after) = rest.split_at_mut((start - rest_y) as usize * pitch);
+                let (pixels, after) = after.split_at_mut((end - start) as usize * pitch);
+                rest = after;
+                rest_y = end;
+                bands.push((Band { pixels, y_start: start, y_end: end, offset: start as usize * pitch }, &work[i]));
             }
+            bands.into_par_iter().for_each(|(mut band, w)| {
+                ctx.clear_work(&mut band, w);
+                ctx.draw_work(&mut band, w);
+            });
         }
+        self.draw_viewports(viewport_damage);
+        self.light_frame();
+        self.finish_frame_stats(started);
     }
 
-    pub fn draw_pixel_rotated(
-        &mut self, pixel: RgbaPixel,
-        skip_above: &AboveRegions,
-        transform: Matrix,
-        min_y: u32, max_y: u32,
-        min_x: u32, max_x: u32,
-        shift_x: f32, shift_y: f32,
-        width: u32, height: u32,
-    ) {
-        let transform: RotateMatrix = (&transform).into();
-        self.portioner.take_region((min_x, min_y), (max_x, max_y));
-        for i in min_y..max_y {
-            for j in min_x..max_x {
-                if should_skip_point(&skip_above.above_my_current, j, i) {
-                    continue;
+    /// an alternative to draw_all_layers for scenes with a lot of overlap.
+    /// instead of clearing and drawing each updated object, this finds
+    /// every portion that any update touched, and then redraws each of
+    /// those portions once, from the top layer down, so every pixel is
+    /// only written once. frame stats do not have per object stats
+    /// in this mode, because objects are not drawn one at a time
+    pub fn draw_all_layers_by_portion(&mut self) {
+        let _span = span!(DEBUG, "draw_all_layers_by_portion");
+        self.apply_queued_commands();
+        self.load_lazy_rows();
+        self.log_command(|| RenderCommand::DrawAllLayers);
+        let started = self.start_frame_stats();
+        self.unlight_updates(false);
+        let viewport_damage = self.take_viewport_damage(false);
+        self.redraw_removed_regions();
+        let draw_object_indices = self.take_layer_updates();
+        let (num_rows, num_cols) = self.portioner.get_grid_dimensions();
+        let row_height = self.portioner.row_height;
+        let col_width = self.portioner.col_width;
+        let mut damaged = vec![false; num_rows * num_cols];
+        let mut mark_damaged = |r: Rect| {
+            if r.is_empty() {
+                return;
+            }
+            let col_start = (r.x / col_width) as usize;
+            let row_start = (r.y / row_height) as usize;
+            let col_end = cmp::min(((r.x + r.w - 1) / col_width) as usize + 1, num_cols);
+            let row_end = cmp::min(((r.y + r.h - 1) / row_height) as usize + 1, num_rows);
+            for row in row_start..row_end {
+                for col in col_start..col_end {
+                    damaged[row * num_cols + col] = true;
                 }
+            }
+        };
+        for (_, object_index) in draw_object_indices {
+            let object = &mut self.objects[object_index];
+            if !object.initial_render {
+                mark_damaged(object.previous_bounds);
+            }
+            let now = object.get_bounds();
+            mark_damaged(now);
+            object.initial_render = false;
+            object.previous_bounds = now;
+            object.previous_shape = object.shape.clone();
+        }
 
-                let j_shift = j as f32 - shift_x;
-                let i_shift = i as f32 - shift_y;
-                let (px, py) = transform.compute_pt(j_shift, i_shift);
-                let pix = interpolate_nearest_pixel(
-                    pixel, width, height,
-                    px, py, PIXEL_BLANK
-                );
-                if pix.a == 0 {
+        // neighboring damaged portions on the same row are redrawn together
+        let mut damaged_rects = vec![];
+        for row in 0..num_rows {
+            let mut col = 0;
+            while col < num_cols {
+                if !damaged[row * num_cols + col] {
+                    col += 1;
                     continue;
                 }
-                // println!("({}, {}), [{}, {}] => GOT PIXEL: {:?}", j, i, px, py, pix);
-                let red_index = get_red_index!(j, i, self.width, self.indices_per_pixel);
-                let red_index = red_index as usize;
-                // TODO: pixel format?
-                self.pixel_buffer[red_index] = pix.r;
-                self.pixel_buffer[red_index + 1] = pix.g;
-                self.pixel_buffer[red_index + 2] = pix.b;
-                self.pixel_buffer[red_index + 3] = pix.a;
+                let start = col;
+                while col < num_cols && damaged[row * num_cols + col] {
+                    col += 1;
+                }
+                damaged_rects.push(Rect {
+                    x: start as u32 * col_width,
+                    y: row as u32 * row_height,
+                    w: (col - start) as u32 * col_width,
+                    h: row_height,
+                });
             }
         }
+
+        for rect in damaged_rects {
+            self.composite_region(rect);
+        }
+        self.draw_viewports(viewport_damage);
+        self.light_frame();
+        self.finish_frame_stats(started);
     }
 
-    pub fn draw_exact_rotated(
-        &mut self, texture_index: usize,
-        skip_above: &AboveRegions,
-        transform: Matrix,
-        min_y: u32, max_y: u32,
-        min_x: u32, max_x: u32,
-        shift_x: f32, shift_y: f32,
-    ) {
-        let transform: RotateMatrix = (&transform).into();
-        let texture = &self.textures[texture_index];
-        let texture_data = &texture.data;
-        let texture_width = texture.width;
-        let texture_height = texture.height;
-        self.portioner.take_region((min_x, min_y), (max_x, max_y));
-        for i in min_y..max_y {
-            for j in min_x..max_x {
-                if should_skip_point(&skip_above.above_my_current, j, i) {
-                    continue;
-                }
+    /// takes the updates like draw_all_layers, and marks what it would
+    /// have drawn as dirty, but without writing to the pixel buffer.
+    /// for when the scene is drawn somewhere else, eg: by a GpuCompositor,
+    /// but only the dirty rects should be presented. viewports are not drawn
+    pub fn damage_all_layers(&mut self) {
+        let _span = span!(DEBUG, "damage_all_layers");
+        self.apply_queued_commands();
+        self.log_command(|| RenderCommand::DamageAllLayers);
+        let mut damaged = std::mem::take(&mut self.removed_regions);
+        for (_, object_index) in self.take_layer_updates() {
+            let object = &mut self.objects[object_index];
+            if !object.initial_render {
+                damaged.push(object.previous_bounds);
+            }
+            let now = object.get_bounds();
+            damaged.push(now);
+            object.initial_render = false;
+            object.previous_bounds = now;
+            object.previous_shape = object.shape.clone();
+        }
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        let visible = self.clip.and_then(|clip| clip.intersection(screen)).unwrap_or(screen);
+        for rect in damaged.iter().filter_map(|r| r.intersection(visible)) {
+            if !rect.is_empty() {
+                self.portioner.take_region((rect.x, rect.y), (rect.x + rect.w - 1, rect.y + rect.h - 1));
+            }
+        }
+    }
 
-                let j_shift = j as f32 - shift_x;
-                let i_shift = i as f32 - shift_y;
-                let (px, py) = transform.compute_pt(j_shift, i_shift);
-                let pix = interpolate_nearest(
-                    texture_data, texture_width, texture_height,
-                    px, py, PIXEL_BLANK
-                );
-                if pix.a == 0 {
-                    continue;
-                }
-                // println!("({}, {}), [{}, {}] => GOT PIXEL: {:?}", j, i, px, py, pix);
-                let red_index = get_red_index!(j, i, self.width, self.indices_per_pixel);
-                let red_index = red_index as usize;
-                // TODO: pixel format?
-                self.pixel_buffer[red_index] = pix.r;
-                self.pixel_buffer[red_index + 1] = pix.g;
-                self.pixel_buffer[red_index + 2] = pix.b;
-                self.pixel_buffer[red_index + 3] = pix.a;
+    /// restores the pixels of objects that were removed since the last draw
+    fn redraw_removed_regions(&mut self) {
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        for rect in std::mem::take(&mut self.removed_regions) {
+            if let Some(rect) = rect.intersection(screen) {
+                self.composite_region(rect);
             }
         }
     }
 
-    pub fn draw_exact(
-        &mut self, texture_index: usize,
-        skip_above: AboveRegions,
-        transform: Option<Transform>,
-        min_y: u32, max_y: u32,
-        min_x: u32, max_x: u32,
-    ) {
-        if let Some(transform) = transform {
-            let transform_bounds = transform.bounds.get_bounds();
-            let tmin_x = transform_bounds.x;
-            let tmax_x = tmin_x + transform_bounds.w;
-            let tmin_y = transform_bounds.y;
-            let tmax_y = tmin_y + transform_bounds.h;
-            return self.draw_exact_rotated(texture_index,
-                &skip_above, transform.matrix,
-                tmin_y, tmax_y,
-                tmin_x, tmax_x,
-                min_x as f32,
-                min_y as f32,
-            );
+    /// redraws every pixel of rect from all of the objects in it, and
+    /// marks it as dirty. rect must be on the screen. only the part
+    /// of rect that is inside of the clip rect is drawn
+    fn composite_region(&mut self, rect: Rect) {
+        let rect = match self.clip {
+            Some(clip) => match rect.intersection(clip) {
+                Some(rect) => rect,
+                None => return,
+            },
+            None => rect,
+        };
+        let _span = span!(TRACE, "composite_rect", rect.x, rect.y, rect.w, rect.h);
+        self.portioner.take_region((rect.x, rect.y), (rect.x + rect.w - 1, rect.y + rect.h - 1));
+        let top_down = self.objects_top_down_in(&rect);
+        let ctx = draw_context!(self);
+        ctx.composite_rect(&mut whole_band!(self), rect, &top_down);
+    }
+
+    /// the color at (x, y) with every object there blended over each
+    /// other by their alpha, from what the objects and the clear source
+    /// are now, not from the pixel buffer. so it is the same before
+    /// and after a draw, eg: for a color picker, or a test. the pixel
+    /// buffer doesnt blend partly transparent pixels, so where they are
+    /// this is not what was drawn. panics if (x, y) is not on the screen
+    pub fn composited_pixel_at(&self, x: u32, y: u32) -> RgbaPixel {
+        if x >= self.width || y >= self.height {
+            panic!("Cannot composite the pixel at ({}, {}) because the screen is {}x{}", x, y, self.width, self.height);
         }
+        let top_down = self.objects_top_down_in(&Rect { x, y, w: 1, h: 1 });
+        draw_context!(self).composite_pixel(x, y, &top_down)
+    }
 
-        self.portioner.take_region((min_x, min_y), (max_x, max_y));
-        let item_pixels = &self.textures[texture_index].data;
-        let indices_per_pixel = self.indices_per_pixel as usize;
-        let mut item_pixel_index = 0;
-        for i in min_y..max_y {
-            for j in min_x..max_x {
-                // if the alpha value is 0, skip this pixel
-                if item_pixels[item_pixel_index + 3] == 0 {
-                    item_pixel_index += indices_per_pixel;
-                    continue;
-                }
-                if should_skip_point(&skip_above.above_my_current, j, i) {
-                    item_pixel_index += indices_per_pixel;
-                    continue;
-                }
+    /// every object that might be inside of bounds, ordered from the
+    /// top most object to the bottom most. within a layer, objects that
+    /// were created later are considered to be on top
+    fn objects_top_down_in(&self, bounds: &Rect) -> Vec<usize> {
+        let mut top_down = vec![];
+        let mut nearby = vec![];
+        for layer in self.layers.iter().rev() {
+            layer.spatial.query(bounds, &mut nearby);
+            top_down.extend(nearby.iter().rev());
+        }
+        top_down
+    }
 
-                let red_index = get_red_index!(j, i, self.width, self.indices_per_pixel);
-                let red_index = red_index as usize;
-                // TODO: pixel format???
-                self.pixel_buffer[red_index] = item_pixels[item_pixel_index];
-                self.pixel_buffer[red_index + 1] = item_pixels[item_pixel_index + 1];
-                self.pixel_buffer[red_index + 2] = item_pixels[item_pixel_index + 2];
-                self.pixel_buffer[red_index + 3] = item_pixels[item_pixel_index + 3];
-                item_pixel_index += indices_per_pixel;
+    /// like draw_all_layers, but iterates over layer.objects instead of
+    /// layer.updates, so it will always draw every object on every layer
+    /// mostly used for testing/benchmarking
+    pub fn force_draw_all_layers(&mut self) {
+        let _span = span!(DEBUG, "force_draw_all_layers");
+        self.apply_queued_commands();
+        self.load_lazy_rows();
+        self.log_command(|| RenderCommand::ForceDrawAllLayers);
+        let started = self.start_frame_stats();
+        self.unlight_updates(true);
+        let viewport_damage = self.take_viewport_damage(true);
+        self.redraw_removed_regions();
+        let mut draw_object_indices = vec![];
+        for (layer_index, layer) in self.layers.iter_mut().enumerate() {
+            for object_index in layer.objects.iter() {
+                draw_object_indices.push((layer_index, *object_index));
             }
         }
+
+        for (layer_index, object_index) in draw_object_indices {
+            let above_regions = self.get_regions_above_object(object_index, layer_index);
+            let below_regions = self.get_regions_below_object(object_index, layer_index);
+            self.draw_object(object_index, above_regions, below_regions);
+        }
+        self.draw_viewports(viewport_damage);
+        self.light_frame();
+        self.finish_frame_stats(started);
+    }
+
+    /// rotated objects are drawn over their whole shape
+    pub fn draw_pixel(&mut self, pixel: RgbaPixel, area: &DrawArea, rotated: Option<&RotatedDraw>) {
+        let region = match rotated {
+            Some(_) => area.shape.get_bounds(),
+            None => Rect { x: area.min_x, y: area.min_y, w: area.max_x - area.min_x, h: area.max_y - area.min_y },
+        };
+        self.portioner.take_region((region.x, region.y), (region.x + region.w, region.y + region.h));
+        let ctx = draw_context!(self);
+        ctx.draw_pixel(&mut whole_band!(self), pixel, area, rotated);
+    }
+
+    pub fn draw_pixel_rotated(&mut self, pixel: RgbaPixel, area: &DrawArea, rotated: &RotatedDraw) {
+        self.portioner.take_region((area.min_x, area.min_y), (area.max_x, area.max_y));
+        let ctx = draw_context!(self);
+        ctx.draw_pixel_rotated(&mut whole_band!(self), pixel, area, rotated);
+    }
+
+    pub fn draw_exact_rotated(&mut self, texture_index: usize, area: &DrawArea, rotated: &RotatedDraw, source: Rect) {
+        self.portioner.take_region((area.min_x, area.min_y), (area.max_x, area.max_y));
+        let ctx = draw_context!(self);
+        ctx.draw_exact_rotated(&mut whole_band!(self), texture_index, area, rotated, source);
+    }
+
+    /// rotated objects are drawn over their whole shape
+    pub fn draw_exact(
+        &mut self, texture_index: usize,
+        area: &DrawArea, rotated: Option<&RotatedDraw>,
+        position: OffsetRect, source: Rect,
+    ) {
+        let region = match rotated {
+            Some(_) => area.shape.get_bounds(),
+            None => Rect { x: area.min_x, y: area.min_y, w: area.max_x - area.min_x, h: area.max_y - area.min_y },
+        };
+        self.portioner.take_region((region.x, region.y), (region.x + region.w, region.y + region.h));
+        let ctx = draw_context!(self);
+        ctx.draw_exact(&mut whole_band!(self), texture_index, area, rotated, position, source);
     }
 
     pub fn clear_object_previous_bounds(
@@ -873,96 +2059,168 @@ impl PortionRenderer<u8> {
         min_y: u32, max_y: u32,
         min_x: u32, max_x: u32,
     ) {
-        let should_try_clear_below = !skip_below.below_my_previous.is_empty();
+        let _span = span!(TRACE, "clear_object_previous_bounds", min_x, min_y, max_x, max_y);
         self.portioner.take_region((min_x, min_y), (max_x, max_y));
-        for i in min_y..max_y {
-            for j in min_x..max_x {
-                if should_skip_point(&skip_above.above_my_previous, j, i) {
-                    continue;
-                }
-                let red_index = get_red_index!(j, i, self.width, self.indices_per_pixel);
-                let red_index = red_index as usize;
-
-                // try to clear this pixel from what was
-                // underneath it first
-                if should_try_clear_below && self.clear_pixels_from_below_object(
-                    red_index, j, i, &skip_below
-                ) { continue; }
-
-                self.pixel_buffer[red_index] = self.clear_buffer[red_index];
-                self.pixel_buffer[red_index + 1] = self.clear_buffer[red_index + 1];
-                self.pixel_buffer[red_index + 2] = self.clear_buffer[red_index + 2];
-                self.pixel_buffer[red_index + 3] = self.clear_buffer[red_index + 3];
-            }
-        }
+        let ctx = draw_context!(self);
+        let shape = Bounds::Rect(Rect { x: min_x, y: min_y, w: max_x - min_x, h: max_y - min_y });
+        let area = DrawArea { skip_above, shape: &shape, min_y, max_y, min_x, max_x };
+        ctx.clear_object_previous_bounds(&mut whole_band!(self), &area, skip_below);
     }
 
-    pub fn draw_object(&mut self, object_index: usize, skip_above: AboveRegions, skip_below: BelowRegions) {
-        let (
-            previous_bounds, is_first_time, texture_index, object_color,
-        ) = {
-            let object = &self.objects[object_index];
-            (object.previous_bounds, object.initial_render, object.texture_index, object.texture_color)
+    /// marks the portions that the object update will draw to, and
+    /// advances the object's state as if it was already drawn. The returned
+    /// work has everything needed to draw the update afterwards.
+    fn prepare_draw_work(&mut self, object_index: usize, skip_above: AboveRegions, skip_below: BelowRegions) -> DrawWork {
+        let covers_its_bounds = self.object_covers_its_bounds(object_index);
+        let object = &mut self.objects[object_index];
+        let previous_bounds = object.previous_bounds;
+        let previous_shape = object.previous_shape.clone();
+        let is_first_time = object.initial_render;
+        // if the object will draw over every pixel of its new bounds,
+        // then the part of its previous bounds that it is still
+        // on top of doesnt need to be cleared first
+        let clear_regions = if is_first_time {
+            vec![]
+        } else if covers_its_bounds {
+            previous_bounds.subtract(&object.get_bounds())
+        } else {
+            vec![previous_bounds]
         };
-        let prev_x = previous_bounds.x;
-        let prev_y = previous_bounds.y;
-        let prev_w = previous_bounds.w;
-        let prev_h = previous_bounds.h;
+        // snapped objects cover whole portions, so only
+        // the portions that they are on are marked
+        let snapped = self.snap_to_grid;
         if !is_first_time {
-            profile_start!(self.profiler, "clear_object_previous_bounds");
-            self.clear_object_previous_bounds(
-                &skip_above,
-                &skip_below,
-                prev_y, prev_y + prev_h,
-                prev_x, prev_x + prev_w,
-            );
-            profile_stop!(self.profiler, "clear_object_previous_bounds");
-        } else {
-            self.objects[object_index].initial_render = false;
+            let prev = previous_bounds;
+            if snapped {
+                self.portioner.take_rect(prev);
+            } else {
+                self.portioner.take_region((prev.x, prev.y), (prev.x + prev.w, prev.y + prev.h));
+            }
         }
+        let is_invisible = match object.texture_color {
+            Some(color) => color.a == 0,
+            None => false,
+        };
+        if !is_invisible {
+            let now = object.get_bounds();
+            if snapped {
+                self.portioner.take_rect(now);
+            } else {
+                self.portioner.take_region((now.x, now.y), (now.x + now.w, now.y + now.h));
+            }
+        }
+        object.initial_render = false;
+        object.previous_bounds = object.get_bounds();
+        object.previous_shape = object.shape.clone();
+        DrawWork {
+            object_index, previous_bounds, previous_shape, clear_regions,
+            is_first_time, skip_above, skip_below,
+        }
+    }
 
-        let [
-            now_x, now_y,
-            now_w, now_h,
-        ] = {
-            let object = &self.objects[object_index];
-            let now = object.current_bounds;
-            [now.x, now.y, now.w, now.h]
+    /// true if drawing the object writes every pixel of its bounds,
+    /// ie: it is an unrotated rectangle with no transparent pixels
+    fn object_covers_its_bounds(&mut self, object_index: usize) -> bool {
+        let object = &self.objects[object_index];
+        if object.transform.is_some() || !object.shape.is_rect() || object.mask.is_some()
+            || self.layer_opacity(object.layer_index) != 255 {
+            return false;
+        }
+        let texture = match object.texture_color {
+            Some(color) => return color.a != 0,
+            None => &self.textures[object.texture_index],
         };
+        // every pixel of the object is sampled from the source rect,
+        // so it covers its bounds if every pixel of that is opaque.
+        // most objects show their whole texture, which
+        // is only checked once until its alpha changes
+        let source = object.source_rect();
+        if (source.w, source.h) != (texture.width, texture.height) {
+            return texture.is_opaque_in(source);
+        }
+        *self.opaque_textures.entry(object.texture_index)
+            .or_insert_with(|| texture.is_opaque_in(source))
+    }
 
-        if let Some(color) = object_color {
-            // can skip rendering if the alpha is 0, no point in iterating
-            if color.a == 0 {
-                let mut object = &mut self.objects[object_index];
-                object.previous_bounds = object.get_bounds();
-                return;
+    /// draws just this object now, and takes it out of the updates of
+    /// the next draw_all_layers, eg: for applications that decide
+    /// themselves when each object gets drawn. unlike draw_object, the
+    /// regions above and below the object are found for you, so the
+    /// objects around it are left intact. returns false if there
+    /// was no object at object_index
+    pub fn redraw_object(&mut self, object_index: usize) -> bool {
+        self.log_command(|| RenderCommand::RedrawObject(object_index));
+        let layer_index = match self.objects.get(object_index) {
+            Some(object) => object.layer_index,
+            None => return false,
+        };
+        self.load_lazy_rows();
+        self.layers[layer_index].updates.retain(|o| *o != object_index);
+        self.objects[object_index].dirty = DirtyFlags::NONE;
+        if self.lighting.is_some() {
+            let object = &self.objects[object_index];
+            let (previous, now) = (object.previous_bounds, object.get_bounds());
+            if !object.initial_render {
+                self.unlight(previous);
             }
-            profile_start!(self.profiler, "draw_pixel");
-            self.draw_pixel(color, skip_above,
-                self.objects[object_index].transform,
-                now_y, now_y + now_h,
-                now_x, now_x + now_w,
-                now_w, now_h,
-            );
-            profile_stop!(self.profiler, "draw_pixel");
-        } else {
-            profile_start!(self.profiler, "draw_exact");
-            self.draw_exact(
-                texture_index, skip_above,
-                self.objects[object_index].transform,
-                now_y, now_y + now_h,
-                now_x, now_x + now_w
-            );
-            profile_stop!(self.profiler, "draw_exact");
+            self.unlight(now);
         }
+        let above_regions = self.get_regions_above_object(object_index, layer_index);
+        let below_regions = self.get_regions_below_object(object_index, layer_index);
+        self.draw_object(object_index, above_regions, below_regions);
+        self.light_frame();
+        true
+    }
 
-        let mut object = &mut self.objects[object_index];
-        object.previous_bounds = object.get_bounds();
+    pub fn draw_object(&mut self, object_index: usize, skip_above: AboveRegions, skip_below: BelowRegions) {
+        let _span = span!(TRACE, "draw_object", object_index, layer_index = self.objects[object_index].layer_index);
+        let started = self.frame_stats.as_ref().map(|_| Instant::now());
+        let work = self.prepare_draw_work(object_index, skip_above, skip_below);
+        let ctx = draw_context!(self);
+        let mut band = whole_band!(self);
+        profile_start!(self.profiler, "clear_object_previous_bounds");
+        {
+            let _span = span!(TRACE, "clear_object_previous_bounds", object_index);
+            ctx.clear_work(&mut band, &work);
+        }
+        profile_stop!(self.profiler, "clear_object_previous_bounds");
+        profile_start!(self.profiler, "draw_object");
+        ctx.draw_work(&mut band, &work);
+        profile_stop!(self.profiler, "draw_object");
+
+        if let (Some(started), Some(stats)) = (started, self.frame_stats.as_mut()) {
+            let (pixels_written, pixels_cleared) = self.pixel_counter.take();
+            stats.pixels_written += pixels_written;
+            stats.pixels_cleared += pixels_cleared;
+            stats.objects.push(ObjectStats {
+                object_index,
+                time: started.elapsed(),
+                pixels_written,
+                pixels_cleared,
+            });
+        }
     }
 
     pub fn draw_grid_outline(&mut self) {
         draw_grid_outline(&self.portioner, &mut self.pixel_buffer, self.indices_per_pixel);
     }
+
+    /// blends the heatmap of the last frame's overdraw over the pixel
+    /// buffer, see OverdrawMap::heatmap. like draw_grid_outline, this
+    /// is only for debugging, and is drawn over on the next frame.
+    /// does nothing if overdraw tracking is not enabled
+    pub fn draw_overdraw_heatmap(&mut self, max_count: u32) {
+        let heatmap = match self.overdraw.as_ref() {
+            Some(overdraw) => overdraw.heatmap(max_count),
+            None => return,
+        };
+        for (dest, pixel) in self.pixel_buffer.chunks_exact_mut(4).zip(heatmap.chunks_exact(4)) {
+            if pixel[3] != 0 {
+                blend_pixel_into(dest, pixel.into(), 0.5);
+            }
+        }
+        self.portioner.take_region((0, 0), (self.width - 1, self.height - 1));
+    }
 }
 
 pub fn draw_grid_outline(
@@ -979,8 +2237,7 @@ pub fn draw_grid_outline(
         for j in 0..width {
             // (j, i) is the pixel index
             // but the pixel buffer has 4 values per pixel: RGBA
-            let red_index = get_red_index!(j, i, width, indices_per_pixel);
-            let index = red_index as usize;
+            let index = red_index(j, i, width, indices_per_pixel);
             pixel_buffer[index] = 100;
             pixel_buffer[index + 1] = 100;
             pixel_buffer[index + 2] = 100;
@@ -994,8 +2251,7 @@ pub fn draw_grid_outline(
     let mut i = 0;
     while i < width {
         for j in 0..height {
-            let red_index = get_red_index!(i, j, width, indices_per_pixel);
-            let index = red_index as usize;
+            let index = red_index(i, j, width, indices_per_pixel);
             pixel_buffer[index] = 100;
             pixel_buffer[index + 1] = 100;
             pixel_buffer[index + 2] = 100;
@@ -1016,71 +2272,12 @@ mod tests {
     const PIX4: RgbaPixel = RgbaPixel { r: 4, g: 4, b: 4, a: 4 };
 
     fn assert_pixels_in_map(p: &mut PortionRenderer<u8>, map: &[char], width: u32) {
-        const IDC_PIXEL: RgbaPixel = RgbaPixel { r: 22, g: 103, b: 2, a: 54 };
-        let mut x = 0;
-        let mut expected_string = String::from("[");
-        for pixel_color in map {
-            expected_string.push(*pixel_color);
-            expected_string.push_str(", ");
-            x += 1;
-            if x >= width {
-                expected_string.push_str("\n ");
-                x = 0;
-            }
-        }
-
-        let mut x = 0;
-        let mut y = 0;
-        let mut actual_string = String::from("[");
-        let mut should_panic = false;
-        for pixel_color in map {
-            let pixel_slice: RgbaPixel = p[(x, y)].into();
-            let mut should_newline = false;
-
-            x += 1;
-            if x >= width {
-                x = 0;
-                y += 1;
-                should_newline = true;
-            }
-
-            let pixel_compare = match pixel_color {
-                'g' => PIXEL_GREEN,
-                'r' => PIXEL_RED,
-                'b' => PIXEL_BLUE,
-                'x' => PIXEL_BLANK,
-                '1' => PIX1,
-                '2' => PIX2,
-                '3' => PIX3,
-                '4' => PIX4,
-                '?' => IDC_PIXEL,
-                c => panic!("Found undefined char in map: {}", c),
-            };
-            let c = match pixel_slice {
-                PIXEL_BLANK => 'x',
-                PIXEL_GREEN => 'g',
-                PIXEL_RED => 'r',
-                PIXEL_BLUE => 'b',
-                PIX1 => '1',
-                PIX2 => '2',
-                PIX3 => '3',
-                PIX4 => '4',
-                _ => '?',
-            };
-            let c = if pixel_compare == IDC_PIXEL { '?' } else { c };
-            actual_string.push(c);
-            actual_string.push_str(", ");
-            if should_newline {
-                actual_string.push_str("\n ");
-            }
-            if pixel_compare != IDC_PIXEL && pixel_compare != pixel_slice {
-                // panic!("\n\nExpected {:?}\nFound {:?}\n at index ({}, {})\n\n", pixel_compare, pixel_slice, debug_x, debug_y);
-                should_panic = true;
-            }
-        }
-        if should_panic {
-            panic!("\n\nExpected\n{}\nActual\n{}\n", expected_string, actual_string);
-        }
+        testing::PixelMap::default()
+            .with('1', PIX1)
+            .with('2', PIX2)
+            .with('3', PIX3)
+            .with('4', PIX4)
+            .assert_matches(p, map, width);
     }
 
     fn texture_from(pixels: &[RgbaPixel]) -> Vec<u8> {
@@ -1165,6 +2362,237 @@ mod tests {
         assert_pixels_in_map(&mut p, &assert_map, 4);
     }
 
+    #[test]
+    fn clear_sources_work() {
+        let mut p = get_test_renderer();
+        p.set_clear_source(ClearSource::SolidColor(PIXEL_BLUE));
+        let red = p.create_object_from_color(
+            0, Rect { x: 0, y: 0, w: 1, h: 1 },
+            PIXEL_RED
+        );
+        p.draw_all_layers();
+        p.move_object_x_by(red, 1);
+        p.draw_all_layers();
+        assert_pixels_in_map(&mut p, &['b', 'r', 'x', 'x'], 4);
+
+        // the old pixels are left behind
+        p.set_clear_source(ClearSource::None);
+        p.move_object_x_by(red, 1);
+        p.draw_all_layers();
+        assert_pixels_in_map(&mut p, &['b', 'r', 'r', 'x'], 4);
+
+        p.set_clear_buffer();
+        p.move_object_x_by(red, -2);
+        p.draw_all_layers();
+        assert_pixels_in_map(&mut p, &['r', 'r', 'r', 'x'], 4);
+    }
+
+    #[test]
+    fn rotated_color_objects_are_restored_from_below() {
+        let mut p = get_test_renderer();
+        let green = p.create_object_from_color(
+            0, Rect { x: 0, y: 3, w: 4, h: 4 },
+            PIXEL_GREEN
+        );
+        // rotates around the top left corner, up onto rows 0-3
+        p.set_object_rotation(green, -90f32);
+        let red = p.create_object_from_color(
+            1, Rect { x: 1, y: 1, w: 1, h: 1 },
+            PIXEL_RED
+        );
+        p.draw_all_layers();
+        assert_eq!(p.get_pixel_from_object_at(green, 1, 1), Some(PIXEL_GREEN));
+        p.move_object_x_by(red, 6);
+        p.draw_all_layers();
+        assert_pixels_in_map(&mut p, &[
+            'g', 'g', 'g', 'g',
+            'g', 'g', 'g', 'g',
+        ], 4);
+    }
+
+    #[test]
+    fn frame_stats_count_pixels_per_object() {
+        let mut p = get_test_renderer();
+        assert!(p.last_frame_stats().is_none());
+        p.set_frame_stats(true);
+        let red = p.create_object_from_color(
+            0, Rect { x: 0, y: 0, w: 2, h: 2 },
+            PIXEL_RED
+        );
+        p.draw_all_layers();
+        let stats = p.last_frame_stats().unwrap();
+        assert_eq!(stats.pixels_written, 4);
+        assert_eq!(stats.pixels_cleared, 0);
+        assert_eq!(stats.objects.len(), 1);
+        assert_eq!(stats.slowest_object().unwrap().object_index, red);
+
+        p.move_object_x_by(red, 1);
+        p.draw_all_layers();
+        let stats = p.last_frame_stats().unwrap();
+        assert_eq!(stats.pixels_written, 4);
+        // only the column that red moved off of gets cleared
+        assert_eq!(stats.pixels_cleared, 2);
+        assert_eq!(stats.objects[0].pixels_cleared, 2);
+
+        // nothing changed, so nothing is drawn
+        p.draw_all_layers();
+        assert!(p.last_frame_stats().unwrap().objects.is_empty());
+    }
+
+    #[test]
+    fn overdraw_counts_writes_per_pixel() {
+        let mut p = get_test_renderer();
+        p.set_overdraw_tracking(true);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 3, h: 1 }, PIXEL_RED);
+        p.create_object_from_color(0, Rect { x: 1, y: 0, w: 3, h: 1 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        let overdraw = p.last_overdraw().unwrap();
+        let counts: Vec<u32> = (0..5).map(|x| overdraw.count(x, 0)).collect();
+        assert_eq!(counts, vec![1, 2, 2, 1, 0]);
+        assert_eq!(overdraw.max(), 2);
+
+        p.draw_overdraw_heatmap(2);
+        let pixel: RgbaPixel = p[(1, 0)].into();
+        assert!(pixel.r > 0 && pixel.g > 0, "{:?}", pixel);
+        p.draw_all_layers();
+        assert_eq!(p.last_overdraw().unwrap().max(), 0);
+    }
+
+    #[test]
+    fn damage_marks_what_drawing_would_without_drawing() {
+        let mut p = PortionRenderer::<u8>::new_ex(8, 8, 1, 1, PixelFormatEnum::RGBA8888);
+        let o = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+        p.damage_all_layers();
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 8, h: 8 }]);
+        assert_eq!(RgbaPixel::from(&p[(0, 0)]), PIXEL_BLANK);
+        p.damage_all_layers();
+        assert!(p.take_dirty_rects().is_empty());
+
+        // the object was marked as drawn, so moving it clears where it was
+        p.move_object_x_by(o, 4);
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(4, 0)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(0, 0)]), PIXEL_BLANK);
+    }
+
+    #[test]
+    fn draw_clips_to_the_screen() {
+        let mut p = get_test_renderer();
+        let pixels = texture_from(&[PIX1, PIX2, PIX3, PIX4]);
+        // only the top left pixel is on screen
+        p.draw(&pixels, Rect { x: 9, y: 9, w: 2, h: 2 });
+        let pixel: RgbaPixel = p[(9, 9)].into();
+        assert_eq!(pixel, PIX1);
+        p.draw(&pixels, Rect { x: 20, y: 9, w: 2, h: 2 });
+
+        p.draw(&pixels, Rect { x: 0, y: 0, w: 2, h: 2 });
+        assert_pixels_in_map(&mut p, &[
+            '1', '2', 'x',
+            '3', '4', 'x',
+        ], 3);
+        let mut unchecked = get_test_renderer();
+        unsafe { unchecked.draw_unchecked(&pixels, Rect { x: 0, y: 0, w: 2, h: 2 }) };
+        assert_pixels_in_map(&mut unchecked, &[
+            '1', '2', 'x',
+            '3', '4', 'x',
+        ], 3);
+    }
+
+    #[test]
+    fn draw_marks_what_it_drew_as_dirty() {
+        let mut p = PortionRenderer::<u8>::new_ex(40, 40, 4, 4, PixelFormatEnum::RGBA8888);
+        let pixels = texture_from(&[PIXEL_RED; 6 * 3]);
+        p.draw(&pixels, Rect { x: 8, y: 12, w: 6, h: 3 });
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 10, w: 20, h: 10 }]);
+        // only the part that is on screen is dirty
+        p.draw(&pixels, Rect { x: 36, y: 38, w: 6, h: 3 });
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 30, y: 30, w: 10, h: 10 }]);
+    }
+
+    #[test]
+    #[should_panic]
+    fn draw_panics_if_pixels_are_too_short() {
+        let mut p = get_test_renderer();
+        p.draw(&texture_from(&[PIX1]), Rect { x: 0, y: 0, w: 2, h: 2 });
+    }
+
+    #[test]
+    fn multiple_updates_are_drawn_once() {
+        let mut p = get_test_renderer();
+        p.set_frame_stats(true);
+        let red = p.create_object_from_color(
+            0, Rect { x: 0, y: 0, w: 1, h: 1 },
+            PIXEL_RED
+        );
+        p.draw_all_layers();
+        p.move_object_x_by(red, 1);
+        p.move_object_x_by(red, 1);
+        p.set_object_updated(red);
+        assert_eq!(p.layers[0].objects, vec![red]);
+        p.draw_all_layers();
+        assert_eq!(p.last_frame_stats().unwrap().objects.len(), 1);
+        assert_pixels_in_map(&mut p, &['x', 'x', 'r', 'x'], 4);
+    }
+
+    #[test]
+    fn portion_drawing_matches_object_drawing() {
+        let make_scene = || {
+            let mut p = PortionRenderer::<u8>::new_ex(
+                20, 20, 4, 4, PixelFormatEnum::RGBA8888
+            );
+            let green = p.create_object_from_color(
+                0, Rect { x: 2, y: 2, w: 8, h: 8 },
+                PIXEL_GREEN
+            );
+            let red = p.create_object_from_color(
+                1, Rect { x: 4, y: 4, w: 6, h: 6 },
+                PIXEL_RED
+            );
+            p.set_object_shape(red, Bounds::Circle(CircleBounds::from_rect(Rect { x: 4, y: 4, w: 6, h: 6 })));
+            let textured = p.create_object_from_texture_exact(
+                2, Rect { x: 8, y: 0, w: 2, h: 2 },
+                texture_from(&[PIX1, PIXEL_BLANK, PIX3, PIX4]),
+            );
+            (p, [green, red, textured])
+        };
+        let (mut a, objects) = make_scene();
+        let (mut b, _) = make_scene();
+        a.draw_all_layers();
+        b.draw_all_layers_by_portion();
+        assert!(a.pixel_buffer == b.pixel_buffer);
+
+        for (dx, dy) in [(3, 1), (-2, 4), (5, 5)].iter() {
+            for p in [&mut a, &mut b].iter_mut() {
+                p.move_object_x_by(objects[1], *dx);
+                p.move_object_y_by(objects[2], *dy);
+                p.move_object_x_by(objects[0], -*dx / 2);
+            }
+            a.draw_all_layers();
+            b.draw_all_layers_by_portion();
+            assert!(a.pixel_buffer == b.pixel_buffer);
+        }
+    }
+
+    #[test]
+    fn fill_pixels_fills_every_pixel() {
+        for pixels in [0, 1, 2, 3, 7, 64, 100].iter() {
+            let mut dest = vec![0; pixels * 4];
+            fill_pixels(&mut dest, PIX3);
+            assert!(dest.chunks_exact(4).all(|p| RgbaPixel::from(p) == PIX3));
+        }
+    }
+
+    #[test]
+    fn dirty_rects_are_in_pixels() {
+        let mut p = PortionRenderer::<u8>::new_ex(
+            40, 40, 4, 4, PixelFormatEnum::RGBA8888
+        );
+        p.create_object_from_color(0, Rect { x: 12, y: 2, w: 5, h: 5 }, PIXEL_RED);
+        p.draw_all_layers();
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 10, y: 0, w: 10, h: 10 }]);
+        assert!(p.take_dirty_rects().is_empty());
+    }
+
     #[test]
     fn getting_pixel_from_object_at_position_works() {
         let mut p = get_test_renderer();
@@ -1422,6 +2850,95 @@ mod tests {
         )
     }
 
+    #[test]
+    fn regions_above_follow_moved_objects() {
+        let mut p = PortionRenderer::<u8>::new_ex(
+            400, 400, 4, 4, PixelFormatEnum::RGBA8888
+        );
+        let green = p.create_object_from_color(
+            0, Rect { x: 0, y: 0, w: 10, h: 10 },
+            PIXEL_GREEN
+        );
+        let red = p.create_object_from_color(
+            1, Rect { x: 300, y: 300, w: 10, h: 10 },
+            PIXEL_RED
+        );
+        p.draw_all_layers();
+        let above_bounds = p.get_regions_above_object(green, 0);
+        assert_eq!(above_bounds.above_my_current.len(), 0);
+
+        // red is now in a different cell of the index, but
+        // it should still be found above green
+        p.set_object_position(red, 5, 5);
+        let above_bounds = p.get_regions_above_object(green, 0);
+        assert_eq!(above_bounds.above_my_current, vec![Rect { x: 5, y: 5, w: 5, h: 5 }]);
+        p.draw_all_layers();
+
+        p.set_object_position(red, 300, 5);
+        let below_bounds = p.get_regions_below_object(red, 1);
+        assert_eq!(below_bounds.below_my_previous.len(), 1);
+        assert_eq!(below_bounds.below_my_previous[0].region_belongs_to, green);
+        let above_bounds = p.get_regions_above_object(green, 0);
+        assert_eq!(above_bounds.above_my_current.len(), 0);
+    }
+
+    #[test]
+    fn rotated_objects_only_cover_their_tilted_shape() {
+        let mut p = get_test_renderer();
+        let green = p.create_object_from_color(
+            0, Rect { x: 1, y: 0, w: 1, h: 1 },
+            PIXEL_GREEN
+        );
+        let red = p.create_object_from_color(
+            1, Rect { x: 4, y: 0, w: 4, h: 4 },
+            PIXEL_RED
+        );
+        p.set_object_rotation(red, 45f32);
+        // the outer bounds of red overlap green, but
+        // the actual diamond shape does not
+        assert!(p.objects[red].get_bounds().intersection(p.objects[green].current_bounds).is_some());
+        let above_bounds = p.get_regions_above_object(green, 0);
+        assert_eq!(above_bounds.above_my_current.len(), 0);
+        assert_eq!(above_bounds.above_my_previous.len(), 0);
+    }
+
+    #[test]
+    fn circle_objects_dont_cover_their_corners() {
+        let mut p = get_test_renderer();
+        let _green = p.create_object_from_color(
+            0, Rect { x: 0, y: 0, w: 6, h: 6 },
+            PIXEL_GREEN
+        );
+        let red = p.create_object_from_color(
+            1, Rect { x: 0, y: 0, w: 6, h: 6 },
+            PIXEL_RED
+        );
+        p.set_object_shape(red, Bounds::Circle(CircleBounds::from_rect(Rect { x: 0, y: 0, w: 6, h: 6 })));
+        p.draw_all_layers();
+
+        let assert_map = [
+            'g', 'r', 'r', 'r', 'r', 'g',
+            'r', 'r', 'r', 'r', 'r', 'r',
+            'r', 'r', 'r', 'r', 'r', 'r',
+            'r', 'r', 'r', 'r', 'r', 'r',
+            'r', 'r', 'r', 'r', 'r', 'r',
+            'g', 'r', 'r', 'r', 'r', 'g',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 6);
+
+        p.move_object_x_by(red, 1);
+        p.draw_all_layers();
+        let assert_map = [
+            'g', 'g', 'r', 'r', 'r', 'r', 'x',
+            'g', 'r', 'r', 'r', 'r', 'r', 'r',
+            'g', 'r', 'r', 'r', 'r', 'r', 'r',
+            'g', 'r', 'r', 'r', 'r', 'r', 'r',
+            'g', 'r', 'r', 'r', 'r', 'r', 'r',
+            'g', 'g', 'r', 'r', 'r', 'r', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 7);
+    }
+
     #[test]
     fn simple_underlap_move_simulatenous_works() {
         let mut p = get_test_renderer();
@@ -1443,28 +2960,182 @@ mod tests {
         ];
         assert_pixels_in_map(&mut p, &assert_map, 4);
 
-        p.move_object_x_by(green, 1);
+        p.move_object_x_by(green, 1);
+        p.draw_all_layers();
+
+        let assert_map = [
+            'x', 'g', 'r', 'r',
+            'x', 'g', 'r', 'r',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 4);
+
+        println!("this one!:");
+
+        // now if green and red move out of the way at the same time,
+        // then the overlapping portion should go back to black
+        p.move_object_x_by(red, 2);
+        p.move_object_y_by(green, 2);
+        p.draw_all_layers();
+
+        let assert_map = [
+            'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 4);
+    }
+
+    #[test]
+    fn draw_region_copies_part_of_a_larger_image() {
+        let mut p = PortionRenderer::<u8>::new(4, 4);
+        // a 6x3 image, with 2 values of padding per row,
+        // where every pixel is (x, y, 0, 255)
+        let pitch = 6 * 4 + 2;
+        let mut image = vec![0; pitch * 3];
+        for y in 0..3 {
+            for x in 0..6 {
+                let i = y * pitch + x * 4;
+                image[i..i + 4].copy_from_slice(&[x as u8, y as u8, 0, 255]);
+            }
+        }
+        p.draw_region(&image, pitch, Rect { x: 2, y: 1, w: 3, h: 2 }, (-1, 3));
+        // the left column and the bottom row are off screen
+        assert_eq!(&p[(0, 3)], &[3, 1, 0, 255]);
+        assert_eq!(&p[(1, 3)], &[4, 1, 0, 255]);
+        assert_eq!(&p[(2, 3)], &[0, 0, 0, 0]);
+        assert_eq!(&p[(0, 2)], &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn draw_region_marks_what_it_drew_as_dirty() {
+        let mut p = PortionRenderer::<u8>::new_ex(40, 40, 4, 4, PixelFormatEnum::RGBA8888);
+        let pitch = 8 * 4;
+        let image = texture_from(&[PIXEL_RED; 8 * 8]);
+        p.draw_region(&image, pitch, Rect { x: 2, y: 2, w: 4, h: 4 }, (12, 23));
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 10, y: 20, w: 10, h: 10 }]);
+        // only the part that is on screen is dirty
+        p.draw_region(&image, pitch, Rect { x: 0, y: 0, w: 8, h: 8 }, (-4, -4));
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 10, h: 10 }]);
+        p.draw_region(&image, pitch, Rect { x: 0, y: 0, w: 8, h: 8 }, (40, 0));
+        assert!(p.take_dirty_rects().is_empty());
+    }
+
+    #[test]
+    fn redraw_object_keeps_the_objects_around_it() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 4, h: 4 }, PIXEL_BLUE);
+        let o = p.create_object_from_color(1, Rect { x: 2, y: 2, w: 2, h: 2 }, PIXEL_RED);
+        p.create_object_from_color(2, Rect { x: 3, y: 3, w: 2, h: 2 }, PIXEL_GREEN);
+        p.draw_all_layers();
+
+        p.move_object_x_by(o, -2);
+        assert!(p.redraw_object(o));
+        assert!(p.layers[1].updates.is_empty());
+        // what o uncovered is drawn from the object below it
+        assert_eq!(RgbaPixel::from(&p[(3, 2)]), PIXEL_BLUE);
+        assert_eq!(RgbaPixel::from(&p[(0, 2)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(3, 3)]), PIXEL_GREEN);
+        assert!(!p.redraw_object(100));
+    }
+
+    #[test]
+    fn composited_pixel_blends_the_objects_over_each_other() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 4, h: 4 }, PIXEL_BLUE);
+        p.create_object_from_color(1, Rect { x: 2, y: 2, w: 2, h: 2 }, RgbaPixel { r: 255, g: 0, b: 0, a: 128 });
+        // nothing is drawn yet, but the objects are there
+        assert_eq!(p.composited_pixel_at(3, 3), RgbaPixel { r: 128, g: 0, b: 127, a: 255 });
+        assert_eq!(p.composited_pixel_at(1, 1), PIXEL_BLUE);
+        assert_eq!(p.composited_pixel_at(6, 6), PIXEL_BLANK);
+        p.set_clear_source(ClearSource::SolidColor(PIXEL_GREEN));
+        assert_eq!(p.composited_pixel_at(6, 6), PIXEL_GREEN);
+    }
+
+    #[test]
+    fn with_portions_disabled_marks_everything_once() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.take_dirty_rects();
+        let o = p.with_portions_disabled(|p| {
+            let o = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+            p.draw_all_layers();
+            assert!(p.portioner.dirty_portions().next().is_none());
+            o
+        });
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 8, h: 8 }]);
+        assert_eq!(RgbaPixel::from(&p[(1, 1)]), PIXEL_RED);
+        // tracking is back on after the closure
+        p.move_object_x_by(o, 4);
         p.draw_all_layers();
+        assert_ne!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 8, h: 8 }]);
+    }
 
-        let assert_map = [
-            'x', 'g', 'r', 'r',
-            'x', 'g', 'r', 'r',
-        ];
-        assert_pixels_in_map(&mut p, &assert_map, 4);
+    #[test]
+    fn blit_dirty_to_only_copies_the_dirty_portions() {
+        let mut p = PortionRenderer::<u8>::new_ex(4, 4, 2, 2, PixelFormatEnum::RGBA8888);
+        p.take_dirty_rects();
+        p.create_object_from_color(0, Rect { x: 3, y: 2, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        // 4 values of padding at the end of each row
+        let pitch = 4 * 4 + 4;
+        let mut dst = vec![9; pitch * 4];
+        let dirty = p.blit_dirty_to(&mut dst, pitch);
+        assert_eq!(dirty, vec![Rect { x: 2, y: 2, w: 2, h: 2 }]);
+        let red_start = 2 * pitch + 3 * 4;
+        assert_eq!(RgbaPixel::from(&dst[red_start..red_start + 4]), PIXEL_RED);
+        assert_eq!(&dst[2 * pitch + 8..2 * pitch + 12], &[0; 4]);
+        // outside of the dirty portion, and the padding, are left alone
+        assert_eq!(&dst[2 * pitch..2 * pitch + 8], &[9; 8]);
+        assert_eq!(&dst[2 * pitch + 16..3 * pitch], &[9; 4]);
+        assert!(p.blit_dirty_to(&mut dst, pitch).is_empty());
+    }
 
-        println!("this one!:");
+    #[test]
+    fn simultaneous_updates_draw_in_layer_order() {
+        let setup = || {
+            let mut p = get_test_renderer();
+            let a = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 3, h: 3 }, PIXEL_GREEN);
+            let b = p.create_object_from_color(0, Rect { x: 2, y: 0, w: 3, h: 3 }, PIXEL_BLUE);
+            let c = p.create_object_from_color(1, Rect { x: 1, y: 1, w: 3, h: 3 }, PIXEL_RED);
+            p.draw_all_layers();
+            (p, a, b, c)
+        };
+        let (mut forward, a, b, c) = setup();
+        forward.move_object_x_by(a, 1);
+        forward.move_object_x_by(b, -2);
+        forward.move_object_y_by(c, 2);
+        let (mut backward, ..) = setup();
+        backward.move_object_y_by(c, 2);
+        backward.move_object_x_by(b, -2);
+        backward.move_object_x_by(a, 1);
+        backward.move_object_x_by(b, 0);
+
+        assert_eq!(backward.take_layer_updates(), vec![(0, a), (0, b), (1, c)]);
+        backward.set_object_updated(c);
+        backward.set_object_updated(b);
+        backward.set_object_updated(a);
+        forward.draw_all_layers();
+        backward.draw_all_layers();
+        assert!(forward.pixel_buffer == backward.pixel_buffer);
+    }
 
-        // now if green and red move out of the way at the same time,
-        // then the overlapping portion should go back to black
-        p.move_object_x_by(red, 2);
-        p.move_object_y_by(green, 2);
+    #[test]
+    fn layers_made_out_of_order_are_drawn_in_order() {
+        let mut p = get_test_renderer();
+        let red = p.create_object_from_color(20, Rect { x: 0, y: 0, w: 3, h: 3 }, PIXEL_RED);
+        let blue = p.create_object_from_color(10, Rect { x: 1, y: 0, w: 3, h: 3 }, PIXEL_BLUE);
+        let green = p.create_object_from_color(5, Rect { x: 2, y: 0, w: 3, h: 3 }, PIXEL_GREEN);
+        let indices: Vec<u32> = p.layers.iter().map(|layer| layer.index).collect();
+        assert_eq!(indices, vec![0, 5, 10, 20]);
+        // the objects are still on the layers they were made on
+        for (object, index) in [(red, 20), (blue, 10), (green, 5)] {
+            let layer = &p.layers[p.objects[object].layer_index];
+            assert_eq!(layer.index, index);
+            assert_eq!(layer.objects, vec![object]);
+        }
         p.draw_all_layers();
-
-        let assert_map = [
-            'x', 'x', 'x', 'x',
-            'x', 'x', 'x', 'x',
-        ];
-        assert_pixels_in_map(&mut p, &assert_map, 4);
+        assert_pixels_in_map(&mut p, &[
+            'r', 'r', 'r', 'b', 'g', 'x',
+        ], 6);
+        assert_eq!(p.get_or_make_layer(10), 2);
     }
 
     #[test]
@@ -1533,12 +3204,45 @@ mod tests {
         let assert_map = [
             'x', 'x', 'x', 'x', 'x',
             'x', 'x', '1', '2', 'x',
-            'x', 'x', '3', '4', 'x',
+            'x', 'x', '4', 'b', 'x',
             'x', 'x', 'x', 'x', 'x',
         ];
         assert_pixels_in_map(&mut p, &assert_map, 5);
     }
 
+    #[test]
+    fn source_rect_stretches_the_texture_over_the_bounds() {
+        let mut p = get_test_renderer();
+        let t = p.create_object_from_texture(
+            0, Rect { x: 0, y: 0, w: 4, h: 2 },
+            texture_from(&[
+                PIX1, PIX2, PIX3,
+                PIX4, PIXEL_BLUE, PIXEL_RED,
+            ]),
+            3, 2,
+        );
+        p.set_object_source_rect(t, Some(Rect { x: 1, y: 0, w: 2, h: 2 }));
+        let g = p.create_object_from_color(1, Rect { x: 0, y: 0, w: 4, h: 2 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        p.move_object_y_by(g, 2);
+        p.draw_all_layers();
+        // the cleared pixels are restored from the same part of the texture
+        let assert_map = [
+            '2', '2', '3', '3', 'x',
+            'b', 'b', 'r', 'r', 'x',
+            'g', 'g', 'g', 'g', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+
+        p.set_object_source_rect(t, None);
+        p.draw_all_layers();
+        let assert_map = [
+            '1', '2', '3', 'x', 'x',
+            '4', 'b', 'r', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+    }
+
     #[test]
     fn can_draw_arbitrary_rotations1() {
         let mut p = get_test_renderer();
@@ -1723,4 +3427,498 @@ mod tests {
         p.move_object_x_by(red, 200);
         p.draw_all_layers();
     }
+
+    #[test]
+    fn rotated_edges_are_kept_over_a_moving_object_below() {
+        // red is drawn half a pixel past its tilted rect, so blue has to
+        // skip those pixels too, and restore them when it moves away
+        let scene = |p: &mut PortionRenderer<u8>, blue_x: u32| {
+            let red = p.create_object_from_color(1, Rect { x: 15, y: 11, w: 2, h: 9 }, PIXEL_RED);
+            p.set_object_rotation(red, 45f32);
+            p.create_object_from_color(0, Rect { x: blue_x, y: 14, w: 3, h: 1 }, PIXEL_BLUE)
+        };
+        let mut p = PortionRenderer::new_ex(40, 40, 4, 4, PixelFormatEnum::RGBA8888);
+        let blue = scene(&mut p, 10);
+        p.draw_all_layers();
+        p.move_object_x_by(blue, 3);
+        p.draw_all_layers();
+        let mut fresh = PortionRenderer::new_ex(40, 40, 4, 4, PixelFormatEnum::RGBA8888);
+        scene(&mut fresh, 13);
+        fresh.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(14, 14)]), PIXEL_RED);
+        assert!(p.pixel_buffer == fresh.pixel_buffer);
+    }
+
+    #[test]
+    fn moved_rotated_objects_are_drawn_like_new_ones() {
+        // moving a tilted shape by whole pixels can round differently
+        // than making it at the new position, so it is made again
+        let scene = |p: &mut PortionRenderer<u8>, size: u32, x: u32, y: u32| {
+            let red = p.create_object_from_color(0, Rect { x, y, w: size, h: size + 1 }, PIXEL_RED);
+            p.set_object_rotation(red, -60f32);
+            red
+        };
+        for size in 1..12 {
+            let mut p = PortionRenderer::new_ex(40, 40, 4, 4, PixelFormatEnum::RGBA8888);
+            let red = scene(&mut p, size, 11, 9);
+            p.draw_all_layers();
+            for (dx, dy, x, y) in [(3, 0, 14, 9), (0, 5, 14, 14), (-7, -2, 7, 12)] {
+                p.move_object_x_by(red, dx);
+                p.move_object_y_by(red, dy);
+                p.draw_all_layers();
+                let mut fresh = PortionRenderer::new_ex(40, 40, 4, 4, PixelFormatEnum::RGBA8888);
+                scene(&mut fresh, size, x, y);
+                fresh.draw_all_layers();
+                assert!(p.pixel_buffer == fresh.pixel_buffer, "size {} at ({}, {})", size, x, y);
+            }
+        }
+    }
+
+    #[test]
+    fn rotated_objects_are_sampled_as_their_background_outside_the_texture() {
+        let mut p = get_test_renderer();
+        let t = p.create_object_from_texture(
+            0, Rect { x: 4, y: 4, w: 2, h: 2 },
+            texture_from(&[PIX1, PIX2]), 2, 1,
+        );
+        // the bottom row of the source is past the bottom of the texture
+        p.set_object_source_rect(t, Some(Rect { x: 0, y: 0, w: 2, h: 2 }));
+        p.set_object_rotation(t, -90f32);
+        p.draw_all_layers();
+        let count = |p: &PortionRenderer<u8>, pixel: RgbaPixel| (0..p.height).flat_map(|y| (0..p.width).map(move |x| (x, y)))
+            .filter(|xy| RgbaPixel::from(&p[*xy]) == pixel)
+            .count();
+        assert_eq!((count(&p, PIX1), count(&p, PIX2), count(&p, PIXEL_GREEN)), (1, 1, 0));
+
+        p.set_object_background(t, PIXEL_GREEN);
+        p.draw_all_layers();
+        assert_eq!((count(&p, PIX1), count(&p, PIX2), count(&p, PIXEL_GREEN)), (1, 1, 2));
+        let (x, y) = (0..p.height).flat_map(|y| (0..p.width).map(move |x| (x, y)))
+            .find(|xy| RgbaPixel::from(&p[*xy]) == PIXEL_GREEN)
+            .unwrap();
+        assert_eq!(p.composited_pixel_at(x, y), PIXEL_GREEN);
+    }
+
+    #[test]
+    fn antialiasing_blends_rotated_edges() {
+        let mut p = get_test_renderer();
+        p.set_antialiasing(true);
+        let red = p.create_object_from_color(
+            0, Rect { x: 2, y: 2, w: 4, h: 4 },
+            PIXEL_RED
+        );
+        p.set_object_rotation(red, 45f32);
+        p.draw_all_layers();
+        // the drawn edge is half a pixel outside of the tilted rect
+        // because the corners of the tilted rect are pixel centers
+        let shape = match &p.objects[red].shape {
+            Bounds::Tilted(t) => t.grown(0.5),
+            _ => panic!("rotated rect should be tilted"),
+        };
+        let mut partial_pixels = 0;
+        for y in 0..10 {
+            for x in 0..10 {
+                let pixel: RgbaPixel = p[(x, y)].into();
+                let coverage = shape.coverage(x as f32, y as f32);
+                if pixel.a != 0 && pixel.a != 255 {
+                    partial_pixels += 1;
+                    assert!(coverage > 0.0 && coverage < 1.0);
+                }
+            }
+        }
+        assert!(partial_pixels > 0);
+
+        // a 90 degree rotation lines up with the pixel grid
+        // so there is nothing to blend
+        let mut p = get_test_renderer();
+        p.set_antialiasing(true);
+        let red = p.create_object_from_color(
+            0, Rect { x: 2, y: 1, w: 2, h: 2 },
+            PIXEL_RED
+        );
+        p.set_object_rotation(red, -90f32);
+        p.draw_all_layers();
+        let assert_map = [
+            'x', 'x', 'r', 'r', 'x',
+            'x', 'x', 'r', 'r', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn scene_types_can_be_serialized() {
+        let mut p = get_test_renderer();
+        let red = p.create_object_from_texture_exact(
+            0, Rect { x: 1, y: 2, w: 2, h: 1 },
+            texture_from(&[PIX1, PIX2]),
+        );
+        p.set_object_rotation(red, 45f32);
+        let object = &p.objects[red];
+        let json = serde_json::to_string(object).unwrap();
+        let loaded: Object = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.position, object.position);
+        assert_eq!(loaded.get_bounds(), object.get_bounds());
+        assert!(loaded.transform.is_some());
+
+        // the textures are shared, so the Arc is serialized through the texture it holds
+        let texture = p.textures[object.texture_index].as_ref();
+        let json = serde_json::to_string(texture).unwrap();
+        let loaded: Texture<u8> = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.data, texture.data);
+
+        let json = serde_json::to_string(&p.layers[0]).unwrap();
+        let loaded: Layer = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.objects, vec![red]);
+    }
+
+    #[test]
+    fn index_helpers_dont_overflow() {
+        assert_eq!(red_index(2, 3, 10, 4), get_red_index!(2, 3, 10, 4) as usize);
+        assert_eq!(checked_red_index(2, 3, 10, 4), Some(128));
+        assert_eq!(checked_buffer_len(10, 10, 4), Some(400));
+        // 70000 * 70000 * 4 does not fit in a u32, so it doesnt
+        // even compile as a usize on 32 bit targets
+        #[cfg(target_pointer_width = "64")]
+        {
+            assert_eq!(red_index(0, 70000, 70000, 4), 70000 * 70000 * 4);
+            assert_eq!(checked_buffer_len(70000, 70000, 4), Some(70000 * 70000 * 4));
+        }
+        #[cfg(not(target_pointer_width = "64"))]
+        {
+            assert_eq!(checked_buffer_len(70000, 70000, 4), None);
+            assert_eq!(checked_red_index(0, 70000, 70000, 4), None);
+        }
+    }
+
+    #[cfg(feature = "multithreaded")]
+    #[test]
+    fn parallel_draw_matches_sequential_draw() {
+        fn make_scene() -> PortionRenderer<u8> {
+            let mut p = PortionRenderer::<u8>::new_ex(
+                40, 40, 4, 4, PixelFormatEnum::RGBA8888
+            );
+            p.create_object_from_color(0, Rect { x: 0, y: 0, w: 30, h: 30 }, PIXEL_GREEN);
+            let red = p.create_object_from_color(1, Rect { x: 5, y: 5, w: 12, h: 20 }, PIXEL_RED);
+            let t = p.create_object_from_texture_exact(
+                2, Rect { x: 8, y: 3, w: 2, h: 2 },
+                texture_from(&[PIX1, PIX2, PIX3, PIX4]),
+            );
+            p.set_object_rotation(red, 30f32);
+            p.move_object_y_by(t, 9);
+            p
+        }
+
+        let mut sequential = make_scene();
+        let mut parallel = make_scene();
+        sequential.draw_all_layers();
+        parallel.draw_all_layers_parallel();
+        assert!(sequential.pixel_buffer == parallel.pixel_buffer);
+
+        for p in [&mut sequential, &mut parallel].iter_mut() {
+            p.move_object_x_by(1, 11);
+            p.move_object_y_by(2, -4);
+            p.set_object_rotation(1, -60f32);
+        }
+        sequential.draw_all_layers();
+        parallel.draw_all_layers_parallel();
+        assert!(sequential.pixel_buffer == parallel.pixel_buffer);
+    }
+
+    #[cfg(feature = "multithreaded")]
+    #[test]
+    fn scheduled_draw_matches_sequential_draw() {
+        fn make_scene() -> PortionRenderer<u8> {
+            let mut p = PortionRenderer::<u8>::new_ex(
+                40, 40, 4, 4, PixelFormatEnum::RGBA8888
+            );
+            p.create_object_from_color(0, Rect { x: 0, y: 0, w: 30, h: 10 }, PIXEL_GREEN);
+            p.create_object_from_color(0, Rect { x: 0, y: 20, w: 10, h: 10 }, PIXEL_BLUE);
+            let red = p.create_object_from_color(1, Rect { x: 5, y: 5, w: 12, h: 20 }, PIXEL_RED);
+            p.create_object_from_texture_exact(
+                2, Rect { x: 30, y: 35, w: 2, h: 2 },
+                texture_from(&[PIX1, PIX2, PIX3, PIX4]),
+            );
+            p.set_object_rotation(red, 30f32);
+            p
+        }
+
+        let mut sequential = make_scene();
+        let mut scheduled = make_scene();
+        sequential.draw_all_layers();
+        scheduled.draw_all_layers_scheduled();
+        assert!(sequential.pixel_buffer == scheduled.pixel_buffer);
+
+        for (dx, dy) in [(3, 2), (-6, 10), (1, -12)].iter() {
+            for p in [&mut sequential, &mut scheduled].iter_mut() {
+                p.move_object_x_by(0, *dx);
+                p.move_object_y_by(1, *dy);
+                p.move_object_y_by(3, -*dy);
+                p.set_object_rotation(2, *dx as f32 * 10.0);
+            }
+            sequential.draw_all_layers();
+            scheduled.draw_all_layers_scheduled();
+            assert!(sequential.pixel_buffer == scheduled.pixel_buffer);
+        }
+    }
+
+    #[test]
+    fn objects_can_move_off_the_top_left() {
+        let mut p = get_test_renderer();
+        let t = p.create_object_from_texture_exact(
+            0, Rect { x: 0, y: 0, w: 2, h: 2 },
+            texture_from(&[PIX1, PIX2, PIX3, PIX4]),
+        );
+        p.draw_all_layers();
+        p.move_object_x_by(t, -1);
+        p.draw_all_layers();
+        let assert_map = [
+            '2', 'x', 'x',
+            '4', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 3);
+
+        p.move_object_y_by(t, -1);
+        p.draw_all_layers();
+        let assert_map = [
+            '4', 'x', 'x',
+            'x', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 3);
+
+        // completely off screen, and then back again
+        p.set_object_position(t, -5, -5);
+        p.draw_all_layers();
+        let assert_map = [
+            'x', 'x', 'x',
+            'x', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 3);
+
+        p.set_object_position(t, 1, 0);
+        p.draw_all_layers();
+        let assert_map = [
+            'x', '1', '2',
+            'x', '3', '4',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 3);
+    }
+
+    #[test]
+    fn rotated_objects_can_move_off_the_left() {
+        let mut p = get_test_renderer();
+        let t = p.create_object_from_texture(
+            0, Rect { x: 2, y: 1, w: 2, h: 2 },
+            texture_from(&[
+                PIX1, PIX2,
+                PIX3, PIX4,
+            ]),
+            2, 2,
+        );
+        p.set_object_rotation(t, -90f32);
+        p.draw_all_layers();
+        let assert_map = [
+            'x', 'x', '2', '4', 'x',
+            'x', 'x', '1', '3', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+
+        p.move_object_x_by(t, -3);
+        p.draw_all_layers();
+        let assert_map = [
+            '4', 'x', 'x', 'x', 'x',
+            '3', 'x', 'x', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+    }
+
+    #[test]
+    fn get_object_and_texture_dont_panic() {
+        let mut p = get_test_renderer();
+        let red = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, PIXEL_RED);
+        let t = p.create_object_from_texture(0, Rect { x: 1, y: 0, w: 1, h: 1 }, texture_from(&[PIX1]), 1, 1);
+        assert_eq!(p.get_object(red).unwrap().texture_color, Some(PIXEL_RED));
+        assert!(p.get_object(t + 1).is_none());
+        let texture_index = p.get_object(t).unwrap().texture_index;
+        assert_eq!(p.get_texture(texture_index).unwrap().width, 1);
+        assert!(p.get_texture(texture_index + 1).is_none());
+    }
+
+    #[test]
+    fn compact_remaps_objects_and_textures() {
+        let mut p = get_test_renderer();
+        let a = p.create_object_from_texture(0, Rect { x: 0, y: 0, w: 1, h: 1 }, texture_from(&[PIX1]), 1, 1);
+        let b = p.create_object_from_texture(0, Rect { x: 1, y: 0, w: 1, h: 1 }, texture_from(&[PIX2]), 1, 1);
+        p.draw_all_layers();
+        assert!(p.remove_object(a));
+
+        assert_eq!(p.compact(), vec![(b, 0)]);
+        assert_eq!(p.objects.len(), 1);
+        assert_eq!(p.layers[0].objects, vec![0]);
+        assert_eq!(p.objects[0].texture_index, 0);
+        assert_eq!(p.texture_refs.get(&0), Some(&1));
+        p.move_object_x_by(0, 1);
+        p.draw_all_layers();
+        let assert_map = [
+            'x', 'x', '2', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+    }
+
+    #[test]
+    fn clearing_restores_through_transparent_pixels_of_lower_layers() {
+        let mut p = get_test_renderer();
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 5, h: 1 }, PIXEL_RED);
+        p.create_object_from_color(1, Rect { x: 4, y: 0, w: 1, h: 1 }, PIX3);
+        p.create_object_from_texture(1, Rect { x: 0, y: 0, w: 5, h: 1 },
+            texture_from(&[PIX1, PIXEL_BLANK, PIX2, PIXEL_BLANK, PIXEL_BLANK]), 5, 1);
+        let g = p.create_object_from_color(2, Rect { x: 0, y: 0, w: 5, h: 1 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        let assert_map = [
+            'g', 'g', 'g', 'g', 'g',
+            'x', 'x', 'x', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+
+        p.move_object_y_by(g, 1);
+        p.draw_all_layers();
+        let assert_map = [
+            '1', 'r', '2', 'r', '3',
+            'g', 'g', 'g', 'g', 'g',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+    }
+
+    #[test]
+    fn moving_a_rotated_object_only_clears_what_it_drew() {
+        let mut p = PortionRenderer::<u8>::new(12, 12);
+        // in the corner of the rotated object's bounds, but outside of its shape
+        p.create_object_from_color(0, Rect { x: 5, y: 2, w: 1, h: 1 }, PIXEL_BLUE);
+        let r = p.create_object_from_color(0, Rect { x: 2, y: 2, w: 6, h: 6 }, PIXEL_RED);
+        p.set_object_rotation(r, 45.0);
+        p.draw_all_layers();
+        assert!(p.objects[r].get_bounds().contains_u32(5, 2));
+        p.move_object_y_by(r, 3);
+        p.draw_all_layers();
+        let assert_map = [
+            '?', '?', '?', '?', '?', '?', '?', '?', '?', '?', '?', '?',
+            '?', '?', '?', '?', '?', '?', '?', '?', '?', '?', '?', '?',
+            'x', 'x', 'x', 'x', 'x', 'b', 'x', 'x', 'x', 'x', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 12);
+    }
+
+    #[test]
+    fn renderers_share_texture_data_until_it_changes() {
+        let mut a = get_test_renderer();
+        let mut b = get_test_renderer();
+        let data = Arc::new(Texture::new(texture_from(&[PIX1, PIX2]), 2, 1));
+        let texture = a.create_shared_texture(data.clone());
+        a.create_object_from_texture_index(0, Rect { x: 0, y: 0, w: 2, h: 1 }, texture);
+        let t = b.create_object_from_shared_texture(0, Rect { x: 1, y: 0, w: 2, h: 1 }, a.shared_texture(texture).unwrap());
+        let b_texture = b.get_object(t).unwrap().texture_index;
+        assert!(std::ptr::eq(a.get_texture(texture).unwrap(), b.get_texture(b_texture).unwrap()));
+        assert!(std::ptr::eq(data.as_ref(), b.get_texture(b_texture).unwrap()));
+
+        a.adjust_texture(texture, ColorAdjustment::Invert);
+        assert!(!std::ptr::eq(a.get_texture(texture).unwrap(), b.get_texture(b_texture).unwrap()));
+        b.draw_all_layers();
+        assert_pixels_in_map(&mut b, &['x', '1', '2'], 3);
+    }
+
+    #[test]
+    fn objects_cover_their_bounds_if_their_source_is_opaque() {
+        let mut p = get_test_renderer();
+        let mut data = texture_from(&[PIX1, PIX2, PIX3, PIX4]);
+        data[15] = 0;
+        let texture = p.create_texture(Texture { data, width: 2, height: 2 });
+        let whole = p.create_object_from_texture_index(0, Rect { x: 0, y: 0, w: 2, h: 2 }, texture);
+        let top = p.create_object_from_texture_index(0, Rect { x: 4, y: 0, w: 2, h: 1 }, texture);
+        p.set_object_source_rect(top, Some(Rect { x: 0, y: 0, w: 2, h: 1 }));
+        let bottom = p.create_object_from_texture_index(0, Rect { x: 4, y: 4, w: 2, h: 1 }, texture);
+        p.set_object_source_rect(bottom, Some(Rect { x: 0, y: 1, w: 2, h: 1 }));
+        assert!(!p.object_covers_its_bounds(whole));
+        assert!(p.object_covers_its_bounds(top));
+        assert!(!p.object_covers_its_bounds(bottom));
+        assert_eq!(p.opaque_textures.get(&texture), Some(&false));
+    }
+
+    #[test]
+    fn shared_texture_is_freed_after_its_last_object() {
+        let mut p = get_test_renderer();
+        let texture = p.create_texture(Texture::new(texture_from(&[PIX1, PIX2, PIX3, PIX4]), 2, 2));
+        let a = p.create_object_from_texture_index(0, Rect { x: 0, y: 0, w: 2, h: 2 }, texture);
+        let b = p.create_object_from_texture_index(0, Rect { x: 3, y: 0, w: 2, h: 2 }, texture);
+        let r = p.create_object_from_color(1, Rect { x: 1, y: 1, w: 1, h: 1 }, PIXEL_RED);
+        p.release_texture(texture);
+        assert_eq!(p.textures.len(), 1);
+        p.draw_all_layers();
+        let assert_map = [
+            '1', '2', 'x', '1', '2',
+            '3', 'r', 'x', '3', '4',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+
+        assert!(p.remove_object(a));
+        assert!(!p.remove_object(a));
+        assert!(p.get_texture(texture).is_some());
+        p.draw_all_layers();
+        let assert_map = [
+            'x', 'x', 'x', '1', '2',
+            'x', 'r', 'x', '3', '4',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+
+        assert!(p.remove_object(b));
+        assert!(p.get_texture(texture).is_none());
+        assert!(p.remove_object(r));
+        p.draw_all_layers();
+        let assert_map = [
+            'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 5);
+    }
+
+    #[test]
+    fn objects_past_the_right_and_bottom_are_clipped() {
+        let mut p = get_test_renderer();
+        let t = p.create_object_from_texture(0, Rect { x: 8, y: 7, w: 4, h: 4 }, texture_from(&[PIX1; 16]), 4, 4);
+        let r = p.create_object_from_color(0, Rect { x: 8, y: 0, w: 4, h: 2 }, PIXEL_RED);
+        p.draw_all_layers();
+        let assert_map = [
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'r', 'r',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'r', 'r',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', '1', '1',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', '1', '1',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', '1', '1',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 10);
+
+        p.move_object_x_by(t, -3);
+        p.move_object_x_by(r, -1);
+        p.set_object_rotation(r, 90.0);
+        p.draw_all_layers();
+        p.move_object_x_by(r, -7);
+        p.set_object_rotation(r, 0.0);
+        p.draw_all_layers();
+        let assert_map = [
+            'r', 'r', 'r', 'r', 'x', 'x', 'x', 'x', 'x', 'x',
+            'r', 'r', 'r', 'r', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x', 'x',
+            'x', 'x', 'x', 'x', 'x', '1', '1', '1', '1', 'x',
+            'x', 'x', 'x', 'x', 'x', '1', '1', '1', '1', 'x',
+            'x', 'x', 'x', 'x', 'x', '1', '1', '1', '1', 'x',
+        ];
+        assert_pixels_in_map(&mut p, &assert_map, 10);
+    }
 }
@@ -0,0 +1,343 @@
+use super::*;
+
+/// a light that brightens the pixels within radius of it, see add_light
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Light {
+    /// the center of the light in world coordinates, or if object is
+    /// Some, how far the center is from the center of the object
+    pub x: i32,
+    pub y: i32,
+    /// in world pixels. the light fades out from the center to here
+    pub radius: u32,
+    /// what is added to the ambient light at the center. the alpha is ignored
+    pub color: RgbaPixel,
+    /// the object that the light moves along with, eg: a torch
+    pub object: Option<usize>,
+}
+
+/// the light map, and the pixels as they are without it
+#[derive(Clone)]
+pub(crate) struct Lighting {
+    /// how many pixels wide and tall each cell of the map is
+    cell_size: u32,
+    ambient: RgbaPixel,
+    lights: TightVec<Light>,
+    /// the (r, g, b) that the pixels of each cell are multiplied
+    /// by, row by row, as of the last time the pixels were lit
+    map: Vec<[u8; 3]>,
+    map_cols: u32,
+    /// a copy of the pixel buffer from before the light was applied
+    unlit: Vec<u8>,
+    /// the portions that have their unlit pixels in the pixel
+    /// buffer, and have to be lit again, by row * cols + col
+    unlit_portions: Vec<bool>,
+}
+
+impl<T> PortionRenderer<T> {
+    pub fn lighting_enabled(&self) -> bool {
+        self.lighting.is_some()
+    }
+
+    /// None if there is no such light, or lighting isnt enabled
+    pub fn get_light(&self, light_index: usize) -> Option<&Light> {
+        self.lighting.as_ref()?.lights.get(light_index)
+    }
+
+    /// the lights of a removed object are removed with it
+    pub(crate) fn remove_object_lights(&mut self, object_index: usize) {
+        if let Some(lighting) = self.lighting.as_mut() {
+            let attached: Vec<usize> = lighting.lights.iter()
+                .filter(|(_, light)| light.object == Some(object_index))
+                .map(|(light_index, _)| light_index)
+                .collect();
+            for light_index in attached {
+                lighting.lights.take(light_index);
+            }
+        }
+    }
+
+    pub(crate) fn remap_light_objects(&mut self, remap: &HashMap<usize, usize>) {
+        if let Some(lighting) = self.lighting.as_mut() {
+            for (_, light) in lighting.lights.iter_mut() {
+                if let Some(object_index) = light.object.as_mut() {
+                    *object_index = *remap.get(object_index).unwrap_or(object_index);
+                }
+            }
+        }
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// multiplies a light map over the drawn pixels, eg: for day and night,
+    /// or a dark cave lit by torches. the map has one cell per cell_size x
+    /// cell_size pixels, which is the ambient light, plus the light of every
+    /// light that reaches the center of the cell. a white ambient light
+    /// changes nothing, and black makes everything dark except for the lights.
+    /// the light is applied to the portions that were drawn at the end of
+    /// every draw_all_layers, and to the portions whose cells changed.
+    /// drawing straight to the pixel buffer, eg: with draw or blur_region,
+    /// isnt lit, and is drawn over once the portion is lit again.
+    /// enabling lighting again keeps the lights, but makes a new map.
+    /// panics if cell_size is 0
+    pub fn enable_lighting(&mut self, cell_size: u32, ambient: RgbaPixel) {
+        self.log_command(|| RenderCommand::EnableLighting { cell_size, ambient });
+        if cell_size == 0 {
+            panic!("Cannot enable lighting because the cell size is 0");
+        }
+        let lights = match self.lighting.take() {
+            Some(lighting) => {
+                self.pixel_buffer = lighting.unlit;
+                lighting.lights
+            }
+            None => TightVec::new(),
+        };
+        let (rows, cols) = self.portioner.get_grid_dimensions();
+        let map_cols = self.width.div_ceil(cell_size);
+        let map_rows = self.height.div_ceil(cell_size);
+        // nothing is lit yet, which is the same as a white map
+        self.lighting = Some(Lighting {
+            cell_size,
+            ambient,
+            lights,
+            map: vec![[255; 3]; (map_cols * map_rows) as usize],
+            map_cols,
+            unlit: self.pixel_buffer.clone(),
+            unlit_portions: vec![false; rows * cols],
+        });
+        self.light_frame();
+    }
+
+    /// puts back the pixels as they are without the light,
+    /// and removes every light
+    pub fn disable_lighting(&mut self) {
+        self.log_command(|| RenderCommand::DisableLighting);
+        if let Some(lighting) = self.lighting.take() {
+            self.pixel_buffer = lighting.unlit;
+            self.portioner.take_region((0, 0), (self.width - 1, self.height - 1));
+        }
+    }
+
+    /// the light of the cells that no light reaches. takes
+    /// effect at the next draw, like the changes to lights
+    pub fn set_ambient_light(&mut self, ambient: RgbaPixel) {
+        self.log_command(|| RenderCommand::SetAmbientLight(ambient));
+        if let Some(lighting) = self.lighting.as_mut() {
+            lighting.ambient = ambient;
+        }
+    }
+
+    /// returns the light index. panics if lighting isnt enabled
+    pub fn add_light(&mut self, light: Light) -> usize {
+        self.log_command(|| RenderCommand::AddLight(light));
+        match self.lighting.as_mut() {
+            Some(lighting) => lighting.lights.insert(light),
+            None => panic!("Cannot add a light because lighting is not enabled"),
+        }
+    }
+
+    /// replaces the light, eg: to move it or to make it flicker.
+    /// does nothing if there is no such light
+    pub fn set_light(&mut self, light_index: usize, light: Light) {
+        self.log_command(|| RenderCommand::SetLight { light_index, light });
+        if let Some(existing) = self.lighting.as_mut().and_then(|l| l.lights.get_mut(light_index)) {
+            *existing = light;
+        }
+    }
+
+    /// returns false if there is no such light
+    pub fn remove_light(&mut self, light_index: usize) -> bool {
+        self.log_command(|| RenderCommand::RemoveLight(light_index));
+        self.lighting.as_mut().and_then(|l| l.lights.take(light_index)).is_some()
+    }
+
+    /// puts the unlit pixels back into the portions that rect touches,
+    /// so that they are drawn to without the light, and are lit
+    /// again by the next light_frame
+    pub(crate) fn unlight(&mut self, rect: Rect) {
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        let lighting = match self.lighting.as_mut() {
+            Some(lighting) => lighting,
+            None => return,
+        };
+        let rect = match rect.intersection(screen) {
+            Some(rect) if !rect.is_empty() => rect,
+            _ => return,
+        };
+        let (_, num_cols) = self.portioner.get_grid_dimensions();
+        let (col_width, row_height) = (self.portioner.col_width, self.portioner.row_height);
+        for row in (rect.y / row_height) as usize..=((rect.y + rect.h - 1) / row_height) as usize {
+            for col in (rect.x / col_width) as usize..=((rect.x + rect.w - 1) / col_width) as usize {
+                if lighting.unlit_portions[row * num_cols + col] {
+                    continue;
+                }
+                lighting.unlit_portions[row * num_cols + col] = true;
+                let portion = self.portioner.portion_rect(row, col);
+                copy_rect(&lighting.unlit, &mut self.pixel_buffer, portion, self.width, self.indices_per_pixel);
+            }
+        }
+    }
+
+    /// unlights what the updates, the removed objects,
+    /// and the viewports are about to draw to
+    pub(crate) fn unlight_updates(&mut self, full: bool) {
+        if self.lighting.is_none() {
+            return;
+        }
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        let mut damage = self.removed_regions.clone();
+        for layer in self.layers.iter() {
+            for object_index in layer.updates.iter().copied() {
+                let object = &self.objects[object_index];
+                if !object.initial_render {
+                    damage.push(object.previous_bounds);
+                }
+                damage.push(object.get_bounds());
+            }
+        }
+        damage.extend(self.viewports.iter().map(|(_, v)| v.screen));
+        if full {
+            damage.push(screen);
+        }
+        for rect in damage {
+            self.unlight(rect);
+        }
+    }
+
+    /// makes the light map from the lights as they are now, and lights
+    /// every portion that was unlit, or whose cells changed.
+    /// the portions that are lit get marked as dirty
+    pub(crate) fn light_frame(&mut self) {
+        let map = match self.lighting.as_ref() {
+            Some(lighting) => self.light_map(lighting),
+            None => return,
+        };
+        let lighting = self.lighting.as_ref().unwrap();
+        let (cell_size, map_cols) = (lighting.cell_size, lighting.map_cols);
+        let changed: Vec<Rect> = map.iter().zip(lighting.map.iter()).enumerate()
+            .filter(|(_, (new, old))| new != old)
+            .map(|(i, _)| {
+                let (col, row) = (i as u32 % map_cols, i as u32 / map_cols);
+                Rect { x: col * cell_size, y: row * cell_size, w: cell_size, h: cell_size }
+            })
+            .collect();
+        for rect in changed {
+            self.unlight(rect);
+        }
+
+        let (_, num_cols) = self.portioner.get_grid_dimensions();
+        let (width, ipp) = (self.width, self.indices_per_pixel);
+        let [r, g, b, _] = self.pixel_format.channel_order();
+        let lighting = self.lighting.as_mut().unwrap();
+        lighting.map = map;
+        for i in 0..lighting.unlit_portions.len() {
+            if !lighting.unlit_portions[i] {
+                continue;
+            }
+            lighting.unlit_portions[i] = false;
+            let portion = self.portioner.portion_rect(i / num_cols, i % num_cols);
+            copy_rect(&self.pixel_buffer, &mut lighting.unlit, portion, width, ipp);
+            for y in portion.y..portion.y + portion.h {
+                let map_row = (y / cell_size * map_cols) as usize;
+                let start = red_index(portion.x, y, width, ipp);
+                let row = &mut self.pixel_buffer[start..start + (portion.w * ipp) as usize];
+                for (x, pixel) in (portion.x..).zip(row.chunks_exact_mut(ipp as usize)) {
+                    let light = lighting.map[map_row + (x / cell_size) as usize];
+                    for (channel, light) in [r, g, b].iter().zip(light.iter()) {
+                        pixel[*channel] = ((pixel[*channel] as u32 * *light as u32 + 127) / 255) as u8;
+                    }
+                }
+            }
+            self.portioner.take_rect(portion);
+        }
+    }
+
+    /// the light of each cell, from the ambient light
+    /// and the lights where they are now
+    fn light_map(&self, lighting: &Lighting) -> Vec<[u8; 3]> {
+        let ambient = [lighting.ambient.r, lighting.ambient.g, lighting.ambient.b];
+        let mut sums = vec![ambient.map(|v| v as f32); lighting.map.len()];
+        let cell_size = lighting.cell_size;
+        for (_, light) in lighting.lights.iter() {
+            let (x, y) = match light.object.and_then(|o| self.objects.get(o)) {
+                Some(object) => {
+                    let bounds = object.get_bounds();
+                    let (dx, dy) = (zoomed(light.x, self.zoom), zoomed(light.y, self.zoom));
+                    (bounds.x as i32 + bounds.w as i32 / 2 + dx, bounds.y as i32 + bounds.h as i32 / 2 + dy)
+                }
+                None => self.world_to_screen(light.x, light.y),
+            };
+            let radius = light.radius as f32 * self.zoom;
+            if radius <= 0.0 {
+                continue;
+            }
+            let color = [light.color.r, light.color.g, light.color.b];
+            for (i, sum) in sums.iter_mut().enumerate() {
+                let (col, row) = (i as u32 % lighting.map_cols, i as u32 / lighting.map_cols);
+                let cx = (col * cell_size) as f32 + cell_size as f32 / 2.0;
+                let cy = (row * cell_size) as f32 + cell_size as f32 / 2.0;
+                let distance = ((cx - x as f32).powi(2) + (cy - y as f32).powi(2)).sqrt();
+                let strength = 1.0 - distance / radius;
+                if strength > 0.0 {
+                    sum.iter_mut().zip(color.iter()).for_each(|(s, c)| *s += *c as f32 * strength);
+                }
+            }
+        }
+        sums.into_iter().map(|sum| sum.map(|v| v.round().min(255.0) as u8)).collect()
+    }
+}
+
+/// copies the pixels of rect from src to dst, which are both
+/// a buffer of the whole screen. the part of rect off screen is ignored
+fn copy_rect(src: &[u8], dst: &mut [u8], rect: Rect, width: u32, ipp: u32) {
+    let height = (src.len() / (width * ipp) as usize) as u32;
+    let rect = match rect.intersection(Rect { x: 0, y: 0, w: width, h: height }) {
+        Some(rect) => rect,
+        None => return,
+    };
+    for y in rect.y..rect.y + rect.h {
+        let start = red_index(rect.x, y, width, ipp);
+        let end = start + (rect.w * ipp) as usize;
+        dst[start..end].copy_from_slice(&src[start..end]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lights_brighten_the_dark() {
+        let mut p = PortionRenderer::<u8>::new(16, 16);
+        let white = RgbaPixel { r: 255, g: 255, b: 255, a: 255 };
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 16, h: 16 }, white);
+        p.draw_all_layers();
+        let dark = RgbaPixel { r: 64, g: 64, b: 64, a: 255 };
+        p.enable_lighting(4, dark);
+        assert_eq!(&p[(0, 0)], &[64, 64, 64, 255]);
+
+        let torch = p.create_object_from_color(1, Rect { x: 0, y: 0, w: 4, h: 4 }, white);
+        p.add_light(Light { x: 0, y: 0, radius: 8, color: white, object: Some(torch) });
+        p.draw_all_layers();
+        // the torch itself is lit by its own light
+        assert_eq!(&p[(1, 1)], &[255, 255, 255, 255]);
+        assert_eq!(&p[(15, 15)], &[64, 64, 64, 255]);
+
+        // the light moves along with the torch, and the
+        // pixels are only lit once no matter how often they are drawn
+        p.take_dirty_rects();
+        p.set_object_position(torch, 12, 12);
+        p.draw_all_layers();
+        assert_eq!(&p[(1, 1)], &[64, 64, 64, 255]);
+        assert_eq!(&p[(13, 13)], &[255, 255, 255, 255]);
+        p.draw_all_layers();
+        assert_eq!(&p[(13, 13)], &[255, 255, 255, 255]);
+        assert_eq!(&p[(15, 0)], &[64, 64, 64, 255]);
+
+        p.remove_object(torch);
+        p.draw_all_layers();
+        assert_eq!(&p[(13, 13)], &[64, 64, 64, 255]);
+        p.disable_lighting();
+        assert_eq!(&p[(13, 13)], &[255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,223 @@
+use super::*;
+
+/// an object that is used as a mask, see set_object_mask
+#[derive(Debug, Default, Clone)]
+pub(crate) struct Mask {
+    /// the objects that are only drawn where the mask is opaque
+    masked: Vec<usize>,
+    /// the parts of the screen where the mask is opaque, one rect
+    /// per run of opaque pixels. only this is covered by the masked
+    /// objects, so that what is below them shows through the rest
+    opaque: Vec<Rect>,
+    /// the mask moved or its pixels changed since opaque was found
+    changed: bool,
+}
+
+impl<T> PortionRenderer<T> {
+    /// the object that gates where the object is drawn, see set_object_mask
+    pub fn object_mask(&self, object_index: usize) -> Option<usize> {
+        self.objects.get(object_index)?.mask
+    }
+
+    /// true if the object is used as a mask, and so isnt drawn itself
+    pub fn is_mask(&self, object_index: usize) -> bool {
+        self.masks.contains_key(&object_index)
+    }
+
+    /// the parts of within that the object covers, which
+    /// for a masked object is only where its mask is opaque
+    pub(crate) fn object_covered_rects(&self, object_index: usize, within: &Rect) -> Vec<Rect> {
+        let object = &self.objects[object_index];
+        // a fully faded object is not drawn, so it doesnt cover anything
+        if self.layer_opacity(object.layer_index) == 0 {
+            return vec![];
+        }
+        let covered = object.covered_rects(within);
+        let mask = match object.mask.and_then(|mask_index| self.masks.get(&mask_index)) {
+            Some(mask) => mask,
+            None => return covered,
+        };
+        covered.iter()
+            .flat_map(|r| mask.opaque.iter().filter_map(move |o| r.intersection(*o)))
+            .filter(|r| !r.is_empty())
+            .collect()
+    }
+
+    /// the mask's bounds changed, so where it is opaque has to be found again
+    pub(crate) fn mask_moved(&mut self, mask_index: usize) -> bool {
+        match self.masks.get_mut(&mask_index) {
+            Some(mask) => {
+                mask.changed = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub(crate) fn remap_masks(&mut self, remap: &HashMap<usize, usize>) {
+        let new_index = |index: usize| *remap.get(&index).unwrap_or(&index);
+        let masks = std::mem::take(&mut self.masks);
+        self.masks = masks.into_iter()
+            .map(|(mask_index, mut mask)| {
+                mask.masked.iter_mut().for_each(|o| *o = new_index(*o));
+                (new_index(mask_index), mask)
+            })
+            .collect();
+        for (_, object) in self.objects.iter_mut() {
+            if let Some(mask_index) = object.mask.as_mut() {
+                *mask_index = new_index(*mask_index);
+            }
+        }
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// only draws the object where the mask object is, and where the
+    /// pixels of the mask arent transparent, eg: a round mask over the
+    /// objects of a minimap. the mask is an object like any other, so it
+    /// can be moved, rotated, or given a shape, which redraws the objects
+    /// that it masks. once an object is used as a mask it is no longer
+    /// drawn itself, until it is removed. the object must be on the same
+    /// layer as the mask, or a lower one. None to draw the whole object again
+    pub fn set_object_mask(&mut self, object_index: usize, mask: Option<usize>) {
+        self.log_command(|| RenderCommand::SetObjectMask { object_index, mask });
+        let previous = self.objects[object_index].mask;
+        if previous == mask {
+            return;
+        }
+        if let Some(mask_index) = mask {
+            if mask_index == object_index || self.is_mask(object_index) {
+                panic!("Cannot mask object {} because it is a mask", object_index);
+            }
+            if self.objects[mask_index].mask.is_some() {
+                panic!("Cannot use object {} as a mask because it is masked itself", mask_index);
+            }
+            if self.objects[object_index].layer_index > self.objects[mask_index].layer_index {
+                panic!("Cannot mask object {} with object {} because the mask is on a lower layer", object_index, mask_index);
+            }
+            self.make_mask(mask_index);
+            self.masks.get_mut(&mask_index).unwrap().masked.push(object_index);
+        }
+        if let Some(mask) = previous.and_then(|previous| self.masks.get_mut(&previous)) {
+            mask.masked.retain(|o| *o != object_index);
+        }
+        self.objects[object_index].mask = mask;
+        self.mark_object_dirty(object_index, DirtyFlags::SHAPE);
+    }
+
+    /// takes the object out of its layer, so that it isnt drawn, and doesnt
+    /// cover anything. whatever it drew is drawn over from what is below it
+    fn make_mask(&mut self, object_index: usize) {
+        if self.is_mask(object_index) {
+            return;
+        }
+        self.masks.insert(object_index, Mask { changed: true, ..Default::default() });
+        let object = &mut self.objects[object_index];
+        let layer = &mut self.layers[object.layer_index];
+        layer.objects.retain(|o| *o != object_index);
+        layer.updates.retain(|o| *o != object_index);
+        layer.spatial.remove(object_index);
+        if !object.initial_render {
+            self.removed_regions.push(object.previous_bounds);
+        }
+        object.initial_render = true;
+        object.dirty = DirtyFlags::NONE;
+    }
+
+    /// the objects that the mask gates are drawn again instead of the mask
+    pub(crate) fn mark_masked_dirty(&mut self, mask_index: usize) {
+        let masked = self.masks[&mask_index].masked.clone();
+        for object_index in masked {
+            self.mark_object_dirty(object_index, DirtyFlags::SHAPE);
+        }
+    }
+
+    /// forgets the removed object as a mask, in which case the objects
+    /// it masked are drawn whole again, and as a masked object
+    pub(crate) fn remove_object_from_masks(&mut self, object_index: usize) {
+        if let Some(mask) = self.masks.remove(&object_index) {
+            for masked_index in mask.masked {
+                self.objects[masked_index].mask = None;
+                self.mark_object_dirty(masked_index, DirtyFlags::SHAPE);
+            }
+            return;
+        }
+        for mask in self.masks.values_mut() {
+            mask.masked.retain(|o| *o != object_index);
+        }
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// finds where the masks that changed are opaque, before
+    /// anything that they mask is drawn
+    pub(crate) fn update_mask_coverage(&mut self) {
+        let ctx = draw_context!(self);
+        for (mask_index, mask) in self.masks.iter_mut() {
+            if !mask.changed {
+                continue;
+            }
+            mask.changed = false;
+            mask.opaque.clear();
+            let sampler = ctx.sampler(*mask_index);
+            let bounds = sampler.bounds;
+            for y in bounds.y..bounds.y + bounds.h {
+                let (start, end) = sampler.spans(y);
+                let mut x = start;
+                while x < end {
+                    let opaque = |x: u32| matches!(sampler.sample(x, y), Some(pixel) if pixel.a != 0);
+                    if !opaque(x) {
+                        x += 1;
+                        continue;
+                    }
+                    let run_start = x;
+                    while x < end && opaque(x) {
+                        x += 1;
+                    }
+                    mask.opaque.push(Rect { x: run_start, y, w: x - run_start, h: 1 });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_are_only_drawn_where_their_mask_is_opaque() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 8, h: 8 }, PIXEL_BLUE);
+        let red = p.create_object_from_color(1, Rect { x: 2, y: 2, w: 4, h: 4 }, PIXEL_RED);
+        // the left half of the mask is opaque
+        let mut data = vec![];
+        for _ in 0..4 {
+            data.extend([255; 8]);
+            data.extend([0; 8]);
+        }
+        let mask = p.create_object_from_texture(1, Rect { x: 2, y: 2, w: 4, h: 4 }, data, 4, 4);
+        p.set_object_mask(red, Some(mask));
+        assert!(p.is_mask(mask));
+        assert_eq!(p.object_mask(red), Some(mask));
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(2, 2)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(3, 5)]), PIXEL_RED);
+        // the background shows through where the mask is transparent
+        assert_eq!(RgbaPixel::from(&p[(4, 2)]), PIXEL_BLUE);
+        assert_eq!(RgbaPixel::from(&p[(5, 5)]), PIXEL_BLUE);
+
+        // moving the mask redraws what it masks
+        p.move_object_x_by(mask, 1);
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(2, 2)]), PIXEL_BLUE);
+        assert_eq!(RgbaPixel::from(&p[(4, 2)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(5, 2)]), PIXEL_BLUE);
+
+        p.remove_object(mask);
+        p.draw_all_layers();
+        assert_eq!(p.object_mask(red), None);
+        assert_eq!(RgbaPixel::from(&p[(2, 2)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(5, 5)]), PIXEL_RED);
+    }
+}
@@ -0,0 +1,112 @@
+use std::mem::size_of;
+
+use super::*;
+
+/// roughly how many bytes the renderer has allocated, see memory_usage
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct MemoryReport {
+    /// and the copy of it kept by diff_from_previous
+    pub pixel_buffer: usize,
+    /// the background image of ClearSource::Buffer, or 0
+    pub clear_buffer: usize,
+    /// the pixels of every texture, and the slots of removed textures
+    pub textures: usize,
+    /// the objects, and what the layers, spatial indices, and
+    /// motions keep per object, including the slots of removed objects
+    pub objects: usize,
+    pub portioner: usize,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> usize {
+        self.pixel_buffer + self.clear_buffer + self.textures + self.objects + self.portioner
+    }
+}
+
+impl<T> PortionRenderer<T> {
+    /// counts the capacity of the buffers, not just what is used,
+    /// so memory that shrink_memory would free is included
+    pub fn memory_usage(&self) -> MemoryReport {
+        let clear_buffer = match &self.clear {
+            ClearSource::Buffer(buffer) => buffer.capacity() * size_of::<T>(),
+            _ => 0,
+        };
+        let textures = self.textures.capacity() * size_of::<Option<Arc<Texture<T>>>>()
+            + self.textures.iter().map(|(_, t)| t.data.capacity() * size_of::<T>()).sum::<usize>()
+            + self.texture_refs.capacity() * size_of::<(usize, usize)>();
+        let layers: usize = self.layers.iter().map(|layer| {
+            (layer.objects.capacity() + layer.updates.capacity()) * size_of::<usize>()
+                + layer.spatial.heap_size()
+        }).sum();
+        let objects = self.objects.capacity() * size_of::<Option<Object>>()
+            + self.layers.capacity() * size_of::<Layer>() + layers
+            + self.motions.capacity() * size_of::<(usize, Motion)>();
+        let (rows, cols) = self.portioner.grid.size();
+        MemoryReport {
+            pixel_buffer: (self.pixel_buffer.capacity()
+                + self.previous_frame.as_ref().map(|f| f.capacity()).unwrap_or(0)) * size_of::<T>(),
+            clear_buffer,
+            textures,
+            objects,
+            portioner: rows * cols * size_of::<GridPortion>(),
+        }
+    }
+
+    /// frees the capacity that isnt used, and the slots of removed objects
+    /// and textures that are after the last used one, eg: after a scene
+    /// with many objects was removed. no index changes, so unlike compact,
+    /// the removed slots between objects are kept
+    pub fn shrink_memory(&mut self) {
+        self.pixel_buffer.shrink_to_fit();
+        if let ClearSource::Buffer(buffer) = &mut self.clear {
+            buffer.shrink_to_fit();
+        }
+        self.textures.shrink_to_fit();
+        // a texture that a snapshot shares is left alone, rather than copied
+        self.textures.iter_mut()
+            .filter_map(|(_, t)| Arc::get_mut(t))
+            .for_each(|t| t.data.shrink_to_fit());
+        self.texture_refs.shrink_to_fit();
+        self.objects.shrink_to_fit();
+        for layer in self.layers.iter_mut() {
+            layer.objects.shrink_to_fit();
+            layer.updates.shrink_to_fit();
+            layer.spatial.shrink_to_fit();
+        }
+        self.layers.shrink_to_fit();
+        self.removed_regions.shrink_to_fit();
+        self.motions.shrink_to_fit();
+        self.lazy_textures.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shrink_memory_frees_removed_objects() {
+        let mut p = PortionRenderer::<u8>::new(16, 16);
+        assert_eq!(p.memory_usage().pixel_buffer, 16 * 16 * 4);
+        let texture = p.create_texture(Texture::new(vec![255; 8 * 8 * 4], 8, 8));
+        let objects: Vec<usize> = (0..100)
+            .map(|i| p.create_object_from_texture_index(0, Rect { x: i % 8, y: 0, w: 8, h: 8 }, texture))
+            .collect();
+        p.draw_all_layers();
+        let before = p.memory_usage();
+        assert!(before.textures >= 8 * 8 * 4);
+        for object_index in objects.into_iter().skip(1) {
+            p.remove_object(object_index);
+        }
+        p.shrink_memory();
+        let after = p.memory_usage();
+        assert!(after.objects < before.objects);
+        assert_eq!(after.pixel_buffer, before.pixel_buffer);
+        // the texture is still used by the first object
+        assert!(after.textures >= 8 * 8 * 4);
+        assert!(after.total() < before.total());
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(0, 0)]), PIXEL_RED);
+    }
+}
@@ -0,0 +1,121 @@
+use super::*;
+
+/// how an object moves on its own with every step, in world pixels per second
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct Motion {
+    pub velocity_x: f32,
+    pub velocity_y: f32,
+    pub acceleration_x: f32,
+    pub acceleration_y: f32,
+    /// the part of a pixel that the object moved, but that was
+    /// not applied yet because objects are at whole pixels
+    remainder_x: f32,
+    remainder_y: f32,
+}
+
+impl Motion {
+    fn is_still(&self) -> bool {
+        self.velocity_x == 0.0 && self.velocity_y == 0.0
+            && self.acceleration_x == 0.0 && self.acceleration_y == 0.0
+    }
+}
+
+impl<T> PortionRenderer<T> {
+    /// the motion of the object, or None if it doesnt move on its own
+    pub fn object_motion(&self, object_index: usize) -> Option<&Motion> {
+        self.motions.get(&object_index)
+    }
+
+    /// in world pixels per second, see step
+    pub fn set_object_velocity(&mut self, object_index: usize, x: f32, y: f32) {
+        self.set_motion(object_index, |motion| {
+            motion.velocity_x = x;
+            motion.velocity_y = y;
+        });
+    }
+
+    /// in world pixels per second per second, see step
+    pub fn set_object_acceleration(&mut self, object_index: usize, x: f32, y: f32) {
+        self.set_motion(object_index, |motion| {
+            motion.acceleration_x = x;
+            motion.acceleration_y = y;
+        });
+    }
+
+    /// an object with no velocity and no acceleration
+    /// is forgotten, so that step skips it
+    fn set_motion(&mut self, object_index: usize, change: impl FnOnce(&mut Motion)) {
+        if self.objects.get(object_index).is_none() {
+            panic!("Cannot set the motion of object {} because it doesnt exist", object_index);
+        }
+        let motion = self.motions.entry(object_index).or_default();
+        change(motion);
+        if motion.is_still() {
+            self.motions.remove(&object_index);
+        }
+    }
+
+    pub(crate) fn remap_motions(&mut self, remap: &HashMap<usize, usize>) {
+        let motions = std::mem::take(&mut self.motions);
+        self.motions = motions.into_iter()
+            .map(|(index, motion)| (*remap.get(&index).unwrap_or(&index), motion))
+            .collect();
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// moves every object that has a velocity or acceleration by
+    /// dt seconds worth of it. objects are at whole pixels, so the part
+    /// of a pixel that an object moved is kept until it adds up to a
+    /// whole pixel. the objects are moved like move_object_x_by,
+    /// so they are drawn with the next draw_all_layers
+    pub fn step(&mut self, dt: f32) {
+        let mut moving: Vec<usize> = self.motions.keys().copied().collect();
+        moving.sort_unstable();
+        for object_index in moving {
+            let motion = self.motions.get_mut(&object_index).unwrap();
+            motion.velocity_x += motion.acceleration_x * dt;
+            motion.velocity_y += motion.acceleration_y * dt;
+            let x = motion.remainder_x + motion.velocity_x * dt;
+            let y = motion.remainder_y + motion.velocity_y * dt;
+            let (by_x, by_y) = (x.trunc(), y.trunc());
+            motion.remainder_x = x - by_x;
+            motion.remainder_y = y - by_y;
+            if by_x != 0.0 {
+                self.move_object_x_by(object_index, by_x as i32);
+            }
+            if by_y != 0.0 {
+                self.move_object_y_by(object_index, by_y as i32);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn step_moves_by_whole_pixels_and_keeps_the_rest() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let o = p.create_object_from_color(0, Rect { x: 0, y: 4, w: 1, h: 1 }, PIXEL_RED);
+        p.set_object_velocity(o, 3.0, -2.0);
+        p.step(0.5);
+        // moved 1.5 and -1, so the half pixel is kept for later
+        assert_eq!((p.objects[o].world_x, p.objects[o].world_y), (1, 3));
+        p.step(0.5);
+        assert_eq!((p.objects[o].world_x, p.objects[o].world_y), (3, 2));
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(3, 2)]), PIXEL_RED);
+
+        p.set_object_velocity(o, 0.0, 0.0);
+        p.set_object_acceleration(o, 4.0, 0.0);
+        p.step(0.5);
+        assert_eq!(p.object_motion(o).unwrap().velocity_x, 2.0);
+        assert_eq!(p.objects[o].world_x, 4);
+
+        p.set_object_acceleration(o, 0.0, 0.0);
+        p.set_object_velocity(o, 0.0, 0.0);
+        assert!(p.object_motion(o).is_none());
+    }
+}
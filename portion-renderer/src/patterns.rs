@@ -0,0 +1,107 @@
+use super::*;
+
+impl Texture<u8> {
+    /// a texture where the pixel at (x, y) is pixel(x, y), in the format
+    pub fn from_fn<F: FnMut(u32, u32) -> RgbaPixel>(
+        width: u32, height: u32, format: &PixelFormatEnum, mut pixel: F,
+    ) -> Texture<u8> {
+        let mut data = vec![0; width as usize * height as usize * 4];
+        let rows = data.chunks_exact_mut(width.max(1) as usize * 4);
+        for (y, row) in rows.enumerate() {
+            for (x, dest) in row.chunks_exact_mut(4).enumerate() {
+                let p = pixel(x as u32, y as u32);
+                format.write_rgba(&[p.r, p.g, p.b, p.a], dest);
+            }
+        }
+        Texture::new(data, width, height)
+    }
+
+    /// squares of cell_size pixels that alternate between a and b,
+    /// starting with a in the top left
+    pub fn checkerboard(
+        width: u32, height: u32, cell_size: u32,
+        a: RgbaPixel, b: RgbaPixel, format: &PixelFormatEnum,
+    ) -> Texture<u8> {
+        let cell_size = cell_size.max(1);
+        Texture::from_fn(width, height, format, |x, y| {
+            if (x / cell_size + y / cell_size).is_multiple_of(2) { a } else { b }
+        })
+    }
+
+    /// stripes of stripe_width pixels that alternate between a and b,
+    /// starting with a. vertical stripes go from top to bottom
+    pub fn stripes(
+        width: u32, height: u32, stripe_width: u32, vertical: bool,
+        a: RgbaPixel, b: RgbaPixel, format: &PixelFormatEnum,
+    ) -> Texture<u8> {
+        let stripe_width = stripe_width.max(1);
+        Texture::from_fn(width, height, format, |x, y| {
+            let along = if vertical { x } else { y };
+            if (along / stripe_width).is_multiple_of(2) { a } else { b }
+        })
+    }
+
+    /// blends from the color from at the left (or top, if vertical)
+    /// to the color to at the right (or bottom)
+    pub fn gradient(
+        width: u32, height: u32, vertical: bool,
+        from: RgbaPixel, to: RgbaPixel, format: &PixelFormatEnum,
+    ) -> Texture<u8> {
+        let len = if vertical { height } else { width };
+        let steps = len.saturating_sub(1).max(1) as f32;
+        Texture::from_fn(width, height, format, |x, y| {
+            let along = if vertical { y } else { x };
+            from.lerp(to, along as f32 / steps)
+        })
+    }
+
+    /// opaque gray pixels of random brightness. the same
+    /// seed always makes the same texture
+    pub fn noise(width: u32, height: u32, seed: u64, format: &PixelFormatEnum) -> Texture<u8> {
+        // xorshift64, which gets stuck at 0
+        let mut state = seed.max(1);
+        Texture::from_fn(width, height, format, |_, _| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            let v = (state >> 56) as u8;
+            RgbaPixel { r: v, g: v, b: v, a: 255 }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixels_of(texture: &Texture<u8>) -> Vec<RgbaPixel> {
+        texture.data.chunks_exact(4).map(RgbaPixel::from).collect()
+    }
+
+    #[test]
+    fn patterns_alternate_colors() {
+        let rgba = PixelFormatEnum::RGBA8888;
+        let checkers = Texture::checkerboard(4, 2, 2, PIXEL_RED, PIXEL_BLUE, &rgba);
+        let (r, b) = (PIXEL_RED, PIXEL_BLUE);
+        assert_eq!(pixels_of(&checkers), vec![r, r, b, b, r, r, b, b]);
+        let stripes = Texture::stripes(2, 3, 1, false, PIXEL_RED, PIXEL_BLUE, &rgba);
+        assert_eq!(pixels_of(&stripes), vec![r, r, b, b, r, r]);
+        let vertical = Texture::stripes(3, 1, 1, true, PIXEL_RED, PIXEL_BLUE, &rgba);
+        assert_eq!(pixels_of(&vertical), vec![r, b, r]);
+    }
+
+    #[test]
+    fn gradient_goes_from_one_color_to_the_other() {
+        let gradient = Texture::gradient(3, 1, false, PIXEL_BLACK, PIXEL_WHITE, &PixelFormatEnum::ARGB8888);
+        assert_eq!(gradient.data, vec![255, 0, 0, 0, 255, 128, 128, 128, 255, 255, 255, 255]);
+    }
+
+    #[test]
+    fn noise_depends_on_the_seed() {
+        let rgba = PixelFormatEnum::RGBA8888;
+        let noise = Texture::noise(8, 8, 7, &rgba);
+        assert_eq!(noise.data, Texture::noise(8, 8, 7, &rgba).data);
+        assert_ne!(noise.data, Texture::noise(8, 8, 8, &rgba).data);
+        assert!(pixels_of(&noise).iter().all(|p| p.a == 255 && p.r == p.g && p.g == p.b));
+    }
+}
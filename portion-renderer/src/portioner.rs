@@ -36,6 +36,9 @@ pub struct Portioner {
     pub grid: Grid<GridPortion>,
     pub row_height: u32,
     pub col_width: u32,
+    /// while true, nothing gets marked as dirty, see
+    /// PortionRenderer::with_portions_disabled
+    pub disabled: bool,
 }
 
 
@@ -64,6 +67,9 @@ impl Portioner {
 
     #[inline(always)]
     pub fn take_pixel(&mut self, x: u32, y: u32) {
+        if self.disabled {
+            return;
+        }
         let row_index = y / self.row_height;
         let col_index = x / self.col_width;
         // TODO: can we use unsafe method for faster?
@@ -78,6 +84,9 @@ impl Portioner {
 
     #[inline(always)]
     pub fn take_region(&mut self, top_left: (u32, u32), bottom_right: (u32, u32)) {
+        if self.disabled {
+            return;
+        }
         let (tx, ty) = top_left;
         let (bx, by) = bottom_right;
         let start_row_index = (ty / self.row_height) as usize;
@@ -93,6 +102,42 @@ impl Portioner {
         }
     }
 
+    /// marks every portion that the rect touches as dirty, eg: after
+    /// drawing into the pixel buffer directly. the parts of the rect
+    /// that are off the grid are ignored
+    pub fn take_rect(&mut self, rect: Rect) {
+        if rect.w == 0 || rect.h == 0 || rect.x >= self.pix_w || rect.y >= self.pix_h {
+            return;
+        }
+        let right = (rect.x as u64 + rect.w as u64 - 1).min(self.pix_w as u64 - 1) as u32;
+        let bottom = (rect.y as u64 + rect.h as u64 - 1).min(self.pix_h as u64 - 1) as u32;
+        self.take_region((rect.x, rect.y), (right, bottom));
+    }
+
+    /// false for portions that are off the grid
+    pub fn is_portion_dirty(&self, row: usize, col: usize) -> bool {
+        self.grid.get(row, col).map(|p| p.active).unwrap_or(false)
+    }
+
+    /// the pixels that the portion covers
+    pub fn portion_rect(&self, row: usize, col: usize) -> Rect {
+        Rect {
+            x: col as u32 * self.col_width,
+            y: row as u32 * self.row_height,
+            w: self.col_width,
+            h: self.row_height,
+        }
+    }
+
+    /// the (row, col) of every dirty portion, row by row. unlike
+    /// flush_portions, the portions stay dirty
+    pub fn dirty_portions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let num_cols = self.grid.cols();
+        self.grid.iter().enumerate()
+            .filter(|(_, p)| p.active)
+            .map(move |(i, _)| (i / num_cols, i % num_cols))
+    }
+
     /// returns (num_rows, num_cols)
     pub fn get_grid_dimensions(&self) -> (usize, usize) {
         let num_rows = self.grid.rows();
@@ -358,6 +403,35 @@ mod tests {
         assert_eq!(portion_vec.len(), 5);
     }
 
+    #[test]
+    fn take_rect_marks_the_portions_it_touches() {
+        let mut p = Portioner::new(
+            8, 8, 4, 4
+        );
+        p.take_rect(Rect { x: 1, y: 3, w: 2, h: 1 });
+        p.take_rect(Rect { x: 7, y: 7, w: 100, h: 100 });
+        p.take_rect(Rect { x: 20, y: 0, w: 1, h: 1 });
+        assert!(p.is_portion_dirty(1, 0));
+        assert!(p.is_portion_dirty(1, 1));
+        assert!(!p.is_portion_dirty(0, 0));
+        assert!(!p.is_portion_dirty(10, 10));
+        assert_eq!(p.dirty_portions().collect::<Vec<_>>(), vec![(1, 0), (1, 1), (3, 3)]);
+        assert_eq!(p.portion_rect(3, 3), Rect { x: 6, y: 6, w: 2, h: 2 });
+        // the portions are still dirty until they are flushed
+        assert_eq!(p.flush_portions().len(), 2);
+    }
+
+    #[test]
+    fn disabled_portioner_marks_nothing() {
+        let mut p = Portioner::new(
+            10, 10, 10, 10
+        );
+        p.disabled = true;
+        p.take_pixel(0, 0);
+        p.take_rect(Rect { x: 0, y: 0, w: 10, h: 10 });
+        assert!(p.flush_portions().is_empty());
+    }
+
     #[test]
     fn flush_portions_resets_the_grid() {
         // simple square, should be 1 rect
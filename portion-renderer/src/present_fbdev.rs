@@ -0,0 +1,280 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+
+use super::*;
+
+const FBIOGET_VSCREENINFO: libc::c_ulong = 0x4600;
+const FBIOGET_FSCREENINFO: libc::c_ulong = 0x4602;
+
+/// struct fb_bitfield from linux/fb.h
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct FbBitfield {
+    pub offset: u32,
+    pub length: u32,
+    pub msb_right: u32,
+}
+
+/// struct fb_var_screeninfo from linux/fb.h
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct FbVarScreeninfo {
+    xres: u32,
+    yres: u32,
+    xres_virtual: u32,
+    yres_virtual: u32,
+    xoffset: u32,
+    yoffset: u32,
+    bits_per_pixel: u32,
+    grayscale: u32,
+    red: FbBitfield,
+    green: FbBitfield,
+    blue: FbBitfield,
+    transp: FbBitfield,
+    nonstd: u32,
+    activate: u32,
+    height: u32,
+    width: u32,
+    accel_flags: u32,
+    pixclock: u32,
+    left_margin: u32,
+    right_margin: u32,
+    upper_margin: u32,
+    lower_margin: u32,
+    hsync_len: u32,
+    vsync_len: u32,
+    sync: u32,
+    vmode: u32,
+    rotate: u32,
+    colorspace: u32,
+    reserved: [u32; 4],
+}
+
+/// struct fb_fix_screeninfo from linux/fb.h
+#[repr(C)]
+#[derive(Debug, Default, Copy, Clone)]
+struct FbFixScreeninfo {
+    id: [u8; 16],
+    smem_start: libc::c_ulong,
+    smem_len: u32,
+    type_: u32,
+    type_aux: u32,
+    visual: u32,
+    xpanstep: u16,
+    ypanstep: u16,
+    ywrapstep: u16,
+    line_length: u32,
+    mmio_start: libc::c_ulong,
+    mmio_len: u32,
+    accel: u32,
+    capabilities: u16,
+    reserved: [u16; 2],
+}
+
+/// where each color channel goes in a framebuffer pixel
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FbPixelLayout {
+    pub bytes_per_pixel: usize,
+    pub red: FbBitfield,
+    pub green: FbBitfield,
+    pub blue: FbBitfield,
+}
+
+impl FbPixelLayout {
+    /// packs a RGBA8888 pixel into the framebuffer's format,
+    /// dropping the low bits of channels that are less than 8 bits
+    #[inline(always)]
+    pub fn pack(&self, pixel: &[u8]) -> u32 {
+        #[inline(always)]
+        fn channel(value: u8, field: &FbBitfield) -> u32 {
+            if field.length == 0 {
+                return 0;
+            }
+            ((value as u32) >> (8 - field.length.min(8))) << field.offset
+        }
+        channel(pixel[0], &self.red) | channel(pixel[1], &self.green) | channel(pixel[2], &self.blue)
+    }
+}
+
+/// a memory mapped linux framebuffer device, eg: /dev/fb0.
+/// the renderer is drawn in the top left of the screen,
+/// and anything that doesnt fit is cut off
+pub struct Framebuffer {
+    // kept so that the device stays open while it is mapped
+    _file: File,
+    map: *mut u8,
+    map_len: usize,
+    /// the byte index of the first visible pixel
+    origin: usize,
+    line_length: usize,
+    pub width: u32,
+    pub height: u32,
+    pub layout: FbPixelLayout,
+}
+
+impl Framebuffer {
+    pub fn open_default() -> io::Result<Framebuffer> {
+        Framebuffer::open("/dev/fb0")
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Framebuffer> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        let fd = file.as_raw_fd();
+        let mut var = FbVarScreeninfo::default();
+        let mut fix = FbFixScreeninfo::default();
+        unsafe {
+            if libc::ioctl(fd, FBIOGET_VSCREENINFO as _, &mut var) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            if libc::ioctl(fd, FBIOGET_FSCREENINFO as _, &mut fix) == -1 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        let bytes_per_pixel = var.bits_per_pixel as usize / 8;
+        if bytes_per_pixel != 2 && bytes_per_pixel != 3 && bytes_per_pixel != 4 {
+            return Err(io::Error::other(
+                format!("Unsupported framebuffer depth of {} bits per pixel", var.bits_per_pixel),
+            ));
+        }
+        let map_len = fix.smem_len as usize;
+        let map = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(), map_len,
+                libc::PROT_READ | libc::PROT_WRITE, libc::MAP_SHARED,
+                fd, 0,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let line_length = fix.line_length as usize;
+        Ok(Framebuffer {
+            _file: file,
+            map: map as *mut u8,
+            map_len,
+            origin: var.yoffset as usize * line_length + var.xoffset as usize * bytes_per_pixel,
+            line_length,
+            width: var.xres,
+            height: var.yres,
+            layout: FbPixelLayout {
+                bytes_per_pixel,
+                red: var.red,
+                green: var.green,
+                blue: var.blue,
+            },
+        })
+    }
+
+    fn as_mut_slice(&mut self) -> &mut [u8] {
+        unsafe { std::slice::from_raw_parts_mut(self.map, self.map_len) }
+    }
+
+    fn write_rect(&mut self, renderer: &PortionRenderer<u8>, rect: Rect) {
+        let (layout, origin, line_length) = (self.layout, self.origin, self.line_length);
+        if let Some(map) = self.as_mut_slice().get_mut(origin..) {
+            write_rect(renderer, map, line_length, layout, rect);
+        }
+    }
+
+    fn visible(&self, renderer: &PortionRenderer<u8>) -> Rect {
+        Rect {
+            x: 0, y: 0,
+            w: cmp::min(self.width, renderer.width),
+            h: cmp::min(self.height, renderer.height),
+        }
+    }
+
+    /// writes every pixel of the renderer, eg: for the first frame
+    pub fn write_all(&mut self, renderer: &PortionRenderer<u8>) {
+        let visible = self.visible(renderer);
+        self.write_rect(renderer, visible);
+    }
+
+    /// writes the dirty portions of the renderer to the framebuffer.
+    /// this takes the renderer's dirty rects, so dont mix it with other presenters
+    pub fn present(&mut self, renderer: &mut PortionRenderer<u8>) {
+        let visible = self.visible(renderer);
+        for rect in renderer.take_dirty_rects() {
+            if let Some(rect) = rect.intersection(visible) {
+                self.write_rect(renderer, rect);
+            }
+        }
+    }
+}
+
+/// converts the rect of the renderer from its pixel format into the
+/// layout of a framebuffer that starts at the start of map. the rows
+/// that are past the end of map are skipped
+fn write_rect(renderer: &PortionRenderer<u8>, map: &mut [u8], line_length: usize, layout: FbPixelLayout, rect: Rect) {
+    let ipp = renderer.indices_per_pixel as usize;
+    let bpp = layout.bytes_per_pixel;
+    let format = renderer.pixel_format;
+    for y in rect.y..rect.y + rect.h {
+        let start = red_index(rect.x, y, renderer.width, renderer.indices_per_pixel);
+        let src = &renderer.pixel_buffer[start..start + rect.w as usize * ipp];
+        let dest_start = y as usize * line_length + rect.x as usize * bpp;
+        let dest = match map.get_mut(dest_start..dest_start + rect.w as usize * bpp) {
+            Some(dest) => dest,
+            None => return,
+        };
+        for (d, s) in dest.chunks_exact_mut(bpp).zip(src.chunks_exact(ipp)) {
+            d.copy_from_slice(&layout.pack(&format.read_rgba(s)).to_le_bytes()[..bpp]);
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.map as *mut libc::c_void, self.map_len);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_handles_16_and_32_bit_layouts() {
+        let rgb565 = FbPixelLayout {
+            bytes_per_pixel: 2,
+            red: FbBitfield { offset: 11, length: 5, msb_right: 0 },
+            green: FbBitfield { offset: 5, length: 6, msb_right: 0 },
+            blue: FbBitfield { offset: 0, length: 5, msb_right: 0 },
+        };
+        assert_eq!(rgb565.pack(&[255, 0, 0, 255]), 0xf800);
+        assert_eq!(rgb565.pack(&[0, 255, 0, 255]), 0x07e0);
+        assert_eq!(rgb565.pack(&[0, 0, 255, 255]), 0x001f);
+
+        let xrgb8888 = FbPixelLayout {
+            bytes_per_pixel: 4,
+            red: FbBitfield { offset: 16, length: 8, msb_right: 0 },
+            green: FbBitfield { offset: 8, length: 8, msb_right: 0 },
+            blue: FbBitfield { offset: 0, length: 8, msb_right: 0 },
+        };
+        assert_eq!(xrgb8888.pack(&[1, 2, 3, 255]), 0x010203);
+    }
+
+    #[test]
+    fn write_rect_converts_from_the_pixel_format() {
+        let mut p = PortionRenderer::<u8>::new_ex(2, 1, 1, 1, PixelFormatEnum::BGRA8888);
+        // the texture is already in the renderer's format, so it is copied as is
+        p.create_object_from_texture(0, Rect { x: 0, y: 0, w: 2, h: 1 }, vec![
+            3, 2, 1, 255,
+            255, 0, 0, 255,
+        ], 2, 1);
+        p.draw_all_layers();
+        let xrgb8888 = FbPixelLayout {
+            bytes_per_pixel: 4,
+            red: FbBitfield { offset: 16, length: 8, msb_right: 0 },
+            green: FbBitfield { offset: 8, length: 8, msb_right: 0 },
+            blue: FbBitfield { offset: 0, length: 8, msb_right: 0 },
+        };
+        let mut map = vec![9; 8];
+        write_rect(&p, &mut map, 8, xrgb8888, Rect { x: 0, y: 0, w: 2, h: 1 });
+        assert_eq!(&map, &[3, 2, 1, 0, 255, 0, 0, 0]);
+    }
+}
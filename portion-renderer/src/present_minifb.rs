@@ -0,0 +1,99 @@
+use minifb::Window;
+
+use super::*;
+
+/// keeps a copy of the renderer's pixels in the format minifb expects.
+/// only the dirty portions are converted from the renderer's pixel
+/// format each frame. eg:
+///
+/// ```no_run
+/// # use portion_renderer::{PortionRenderer, present_minifb::MinifbAdapter};
+/// # let mut p = PortionRenderer::<u8>::new(640, 480);
+/// # let mut window = minifb::Window::new("", 640, 480, Default::default()).unwrap();
+/// let mut adapter = MinifbAdapter::new(&p);
+/// while window.is_open() {
+///     p.draw_all_layers();
+///     adapter.update_window(&mut p, &mut window).unwrap();
+/// }
+/// ```
+pub struct MinifbAdapter {
+    buffer: Vec<u32>,
+    width: usize,
+    height: usize,
+    format: PixelFormatEnum,
+}
+
+impl MinifbAdapter {
+    pub fn new(renderer: &PortionRenderer<u8>) -> MinifbAdapter {
+        let format = renderer.pixel_format;
+        let buffer = renderer.pixel_buffer.chunks_exact(renderer.indices_per_pixel as usize)
+            .map(|pixel| format.read_0rgb(pixel)).collect();
+        MinifbAdapter {
+            buffer,
+            width: renderer.width as usize,
+            height: renderer.height as usize,
+            format,
+        }
+    }
+
+    /// converts the portions of the renderer that changed since the last
+    /// sync, and returns the whole converted buffer. this takes the
+    /// renderer's dirty rects, so dont mix it with other presenters
+    pub fn sync(&mut self, renderer: &mut PortionRenderer<u8>) -> &[u32] {
+        let ipp = renderer.indices_per_pixel as usize;
+        for rect in renderer.take_dirty_rects() {
+            for y in rect.y..rect.y + rect.h {
+                let start = red_index(rect.x, y, renderer.width, renderer.indices_per_pixel);
+                let src = &renderer.pixel_buffer[start..start + rect.w as usize * ipp];
+                let dest_start = y as usize * self.width + rect.x as usize;
+                let dest = &mut self.buffer[dest_start..dest_start + rect.w as usize];
+                for (d, s) in dest.iter_mut().zip(src.chunks_exact(ipp)) {
+                    *d = self.format.read_0rgb(s);
+                }
+            }
+        }
+        &self.buffer
+    }
+
+    /// syncs the buffer and then shows it in the window
+    pub fn update_window(&mut self, renderer: &mut PortionRenderer<u8>, window: &mut Window) -> minifb::Result<()> {
+        let (width, height) = (self.width, self.height);
+        let buffer = self.sync(renderer);
+        window.update_with_buffer(buffer, width, height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sync_converts_dirty_portions() {
+        let mut p = PortionRenderer::<u8>::new_ex(
+            8, 8, 2, 2, PixelFormatEnum::RGBA8888
+        );
+        let mut adapter = MinifbAdapter::new(&p);
+        assert!(adapter.sync(&mut p).iter().all(|p| *p == 0));
+        p.create_object_from_color(0, Rect { x: 5, y: 5, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        let buffer = adapter.sync(&mut p);
+        assert_eq!(buffer[5 * 8 + 5], 0xff0000);
+        assert_eq!(buffer.iter().filter(|p| **p != 0).count(), 1);
+    }
+
+    #[test]
+    fn sync_converts_from_the_pixel_format() {
+        let mut p = PortionRenderer::<u8>::new_ex(
+            4, 4, 2, 2, PixelFormatEnum::BGRA8888
+        );
+        let mut adapter = MinifbAdapter::new(&p);
+        // the texture is already in the renderer's format, so it is copied as is
+        p.create_object_from_texture(0, Rect { x: 1, y: 0, w: 2, h: 1 }, vec![
+            0x33, 0x22, 0x11, 255,
+            255, 0, 0, 255,
+        ], 2, 1);
+        p.draw_all_layers();
+        let buffer = adapter.sync(&mut p);
+        assert_eq!(&buffer[0..4], &[0, 0x112233, 0x0000ff, 0]);
+    }
+}
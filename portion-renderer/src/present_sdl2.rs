@@ -0,0 +1,20 @@
+use sdl2::render::{Texture, UpdateTextureError};
+
+use super::*;
+
+impl PortionRenderer<u8> {
+    /// copies the portions that changed since the last call into
+    /// the texture, one Texture::update per dirty rectangle.
+    /// the texture must be the same size as the renderer, and
+    /// use the same pixel format, eg: PixelFormatEnum::RGBA32 in sdl2
+    /// is RGBA8888 in byte order
+    pub fn present_to_texture(&mut self, texture: &mut Texture) -> Result<(), UpdateTextureError> {
+        let pitch = self.pitch;
+        for rect in self.take_dirty_rects() {
+            let start = red_index(rect.x, rect.y, self.width, self.indices_per_pixel);
+            let sdl_rect = sdl2::rect::Rect::new(rect.x as i32, rect.y as i32, rect.w, rect.h);
+            texture.update(sdl_rect, &self.pixel_buffer[start..], pitch)?;
+        }
+        Ok(())
+    }
+}
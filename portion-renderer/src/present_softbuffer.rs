@@ -0,0 +1,135 @@
+use std::collections::VecDeque;
+use std::num::NonZeroU32;
+
+use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
+use softbuffer::{Context, SoftBufferError, Surface};
+
+use super::*;
+
+/// how many frames of damage are kept for buffers that are
+/// reused after more than one frame, eg: double/triple buffering
+const DAMAGE_HISTORY: usize = 3;
+
+/// owns a softbuffer surface for a window, and copies only the dirty
+/// portions of the renderer into it each frame. if the window is a
+/// different size than the renderer, the renderer is drawn in the top left
+pub struct SoftbufferPresenter<D, W> {
+    surface: Surface<D, W>,
+    width: u32,
+    height: u32,
+    /// the damage of the last few frames, most recent first
+    history: VecDeque<Vec<Rect>>,
+}
+
+impl<D: HasDisplayHandle, W: HasWindowHandle> SoftbufferPresenter<D, W> {
+    pub fn new(context: &Context<D>, window: W, width: u32, height: u32) -> Result<Self, SoftBufferError> {
+        let mut presenter = SoftbufferPresenter {
+            surface: Surface::new(context, window)?,
+            width: 0,
+            height: 0,
+            history: VecDeque::with_capacity(DAMAGE_HISTORY),
+        };
+        presenter.resize(width, height)?;
+        Ok(presenter)
+    }
+
+    pub fn window(&self) -> &W {
+        self.surface.window()
+    }
+
+    /// call this when the window is resized. the next present copies everything
+    pub fn resize(&mut self, width: u32, height: u32) -> Result<(), SoftBufferError> {
+        let (w, h) = match (NonZeroU32::new(width), NonZeroU32::new(height)) {
+            (Some(w), Some(h)) => (w, h),
+            // minimized windows can be 0x0, theres nothing to draw to
+            _ => return Ok(()),
+        };
+        self.surface.resize(w, h)?;
+        self.width = width;
+        self.height = height;
+        self.history.clear();
+        Ok(())
+    }
+
+    /// copies the dirty portions of the renderer to the window.
+    /// this takes the renderer's dirty rects, so dont mix it with other presenters
+    pub fn present(&mut self, renderer: &mut PortionRenderer<u8>) -> Result<(), SoftBufferError> {
+        if self.width == 0 || self.height == 0 {
+            return Ok(());
+        }
+        let visible = Rect {
+            x: 0, y: 0,
+            w: cmp::min(self.width, renderer.width),
+            h: cmp::min(self.height, renderer.height),
+        };
+        let dirty: Vec<Rect> = renderer.take_dirty_rects().iter()
+            .filter_map(|r| r.intersection(visible)).collect();
+        let mut buffer = self.surface.buffer_mut()?;
+
+        // the buffer might be from a few frames ago, in which case
+        // it is missing the damage of every frame since then
+        let age = buffer.age() as usize;
+        let copy: Vec<Rect> = if age == 0 || age > self.history.len() + 1 {
+            vec![visible]
+        } else {
+            self.history.iter().take(age - 1).flatten().chain(dirty.iter()).copied().collect()
+        };
+        self.history.push_front(dirty.clone());
+        self.history.truncate(DAMAGE_HISTORY);
+
+        for rect in copy.iter() {
+            copy_rect(renderer, &mut buffer, self.width, *rect);
+        }
+
+        let damage: Vec<softbuffer::Rect> = copy.iter().filter_map(|r| {
+            Some(softbuffer::Rect {
+                x: r.x, y: r.y,
+                width: NonZeroU32::new(r.w)?,
+                height: NonZeroU32::new(r.h)?,
+            })
+        }).collect();
+        if damage.is_empty() {
+            return Ok(());
+        }
+        buffer.present_with_damage(&damage)
+    }
+}
+
+/// converts the rect of the renderer from its pixel format
+/// into the 0RGB pixels of a buffer that is width pixels wide
+fn copy_rect(renderer: &PortionRenderer<u8>, buffer: &mut [u32], width: u32, rect: Rect) {
+    let ipp = renderer.indices_per_pixel as usize;
+    let format = renderer.pixel_format;
+    for y in rect.y..rect.y + rect.h {
+        let start = red_index(rect.x, y, renderer.width, renderer.indices_per_pixel);
+        let src = &renderer.pixel_buffer[start..start + rect.w as usize * ipp];
+        let dest_start = y as usize * width as usize + rect.x as usize;
+        let dest = &mut buffer[dest_start..dest_start + rect.w as usize];
+        for (d, s) in dest.iter_mut().zip(src.chunks_exact(ipp)) {
+            *d = format.read_0rgb(s);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copy_rect_converts_from_the_pixel_format() {
+        let mut p = PortionRenderer::<u8>::new_ex(
+            4, 4, 2, 2, PixelFormatEnum::BGRA8888
+        );
+        // the texture is already in the renderer's format, so it is copied as is
+        p.create_object_from_texture(0, Rect { x: 1, y: 0, w: 2, h: 1 }, vec![
+            0x33, 0x22, 0x11, 255,
+            255, 0, 0, 255,
+        ], 2, 1);
+        p.draw_all_layers();
+        // the window is wider than the renderer
+        let mut buffer = vec![9; 6 * 4];
+        copy_rect(&p, &mut buffer, 6, Rect { x: 0, y: 0, w: 4, h: 1 });
+        assert_eq!(&buffer[0..6], &[0, 0x112233, 0x0000ff, 0, 9, 9]);
+        assert_eq!(&buffer[6..12], &[9; 6]);
+    }
+}
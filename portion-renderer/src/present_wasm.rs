@@ -0,0 +1,82 @@
+use wasm_bindgen::{Clamped, JsValue};
+use web_sys::{CanvasRenderingContext2d, ImageData};
+
+use super::*;
+
+impl PortionRenderer<u8> {
+    /// the pixel buffer, which for RGBA8888 has the same layout as
+    /// `ImageData.data`: rows of RGBA bytes with no padding between them
+    pub fn image_data_bytes(&self) -> &[u8] {
+        &self.pixel_buffer
+    }
+
+    /// takes the dirty rects as a flat list of [x, y, w, h, x, y, w, h, ...],
+    /// which is simple to pass to javascript as a Uint32Array
+    pub fn take_dirty_rect_list(&mut self) -> Vec<u32> {
+        self.take_dirty_rects().iter().flat_map(|r| vec![r.x, r.y, r.w, r.h]).collect()
+    }
+
+    /// calls putImageData once for every dirty rect, so only the damaged
+    /// pixels are copied to javascript. this takes the renderer's
+    /// dirty rects, so dont mix it with other presenters
+    pub fn put_dirty_image_data(&mut self, context: &CanvasRenderingContext2d) -> Result<(), JsValue> {
+        let mut rect_pixels = vec![];
+        for rect in self.take_dirty_rects() {
+            let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+            let rect = match rect.intersection(screen) {
+                Some(rect) => rect,
+                None => continue,
+            };
+            self.rect_image_data(rect, &mut rect_pixels);
+            let image_data = ImageData::new_with_u8_clamped_array_and_sh(
+                Clamped(&rect_pixels), rect.w, rect.h,
+            )?;
+            context.put_image_data(&image_data, rect.x as f64, rect.y as f64)?;
+        }
+        Ok(())
+    }
+
+    /// the pixels of the rect as the RGBA bytes of `ImageData.data`,
+    /// converted from the renderer's pixel format
+    fn rect_image_data(&self, rect: Rect, rect_pixels: &mut Vec<u8>) {
+        rect_pixels.clear();
+        let ipp = self.indices_per_pixel as usize;
+        let format = self.pixel_format;
+        for y in rect.y..rect.y + rect.h {
+            let start = red_index(rect.x, y, self.width, self.indices_per_pixel);
+            let src = &self.pixel_buffer[start..start + rect.w as usize * ipp];
+            rect_pixels.extend(src.chunks_exact(ipp).flat_map(|s| format.read_rgba(s)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_rect_list_is_flat() {
+        let mut p = PortionRenderer::<u8>::new_ex(8, 8, 4, 4, PixelFormatEnum::RGBA8888);
+        p.take_dirty_rects();
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, PIXEL_RED);
+        p.create_object_from_color(0, Rect { x: 6, y: 6, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        assert_eq!(p.take_dirty_rect_list(), vec![0, 0, 2, 2, 6, 6, 2, 2]);
+        assert!(p.take_dirty_rect_list().is_empty());
+        assert_eq!(p.image_data_bytes().len(), 8 * 8 * 4);
+    }
+
+    #[test]
+    fn rect_image_data_converts_from_the_pixel_format() {
+        let mut p = PortionRenderer::<u8>::new_ex(4, 4, 2, 2, PixelFormatEnum::BGRA8888);
+        // the texture is already in the renderer's format, so it is copied as is
+        p.create_object_from_texture(0, Rect { x: 1, y: 1, w: 2, h: 1 }, vec![
+            3, 2, 1, 255,
+            255, 0, 0, 128,
+        ], 2, 1);
+        p.draw_all_layers();
+        let mut rect_pixels = vec![9];
+        p.rect_image_data(Rect { x: 1, y: 1, w: 2, h: 1 }, &mut rect_pixels);
+        assert_eq!(rect_pixels, vec![1, 2, 3, 255, 0, 0, 255, 128]);
+    }
+}
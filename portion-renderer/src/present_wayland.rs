@@ -0,0 +1,108 @@
+use wayland_client::protocol::wl_buffer::WlBuffer;
+use wayland_client::protocol::wl_surface::WlSurface;
+
+use super::*;
+
+/// copies the dirty portions of a renderer into a wl_shm buffer
+/// of format Xrgb8888 or Argb8888, converting from the renderer's
+/// pixel format, and damages only those portions
+/// of the surface. the shm buffer must be at least as large as the
+/// renderer, and should only be written to after the compositor
+/// releases it. if several buffers are swapped between, each one
+/// also needs the damage of the frames it missed, so the
+/// simplest use is a single buffer that is waited on
+pub struct ShmPresenter {
+    /// bytes per row of the shm buffer, as given to wl_shm_pool.create_buffer
+    pub stride: usize,
+}
+
+impl ShmPresenter {
+    pub fn new(stride: usize) -> ShmPresenter {
+        ShmPresenter { stride }
+    }
+
+    /// copies every pixel, eg: for a newly created buffer
+    pub fn write_all(&self, renderer: &PortionRenderer<u8>, shm: &mut [u8]) {
+        let screen = Rect { x: 0, y: 0, w: renderer.width, h: renderer.height };
+        self.write_rect(renderer, shm, screen);
+    }
+
+    /// copies the dirty portions into shm, and returns them for damaging.
+    /// this takes the renderer's dirty rects, so dont mix it with other presenters
+    pub fn write_dirty(&self, renderer: &mut PortionRenderer<u8>, shm: &mut [u8]) -> Vec<Rect> {
+        let dirty = renderer.take_dirty_rects();
+        for rect in dirty.iter() {
+            self.write_rect(renderer, shm, *rect);
+        }
+        dirty
+    }
+
+    fn write_rect(&self, renderer: &PortionRenderer<u8>, shm: &mut [u8], rect: Rect) {
+        let ipp = renderer.indices_per_pixel as usize;
+        let format = renderer.pixel_format;
+        for y in rect.y..rect.y + rect.h {
+            let start = red_index(rect.x, y, renderer.width, renderer.indices_per_pixel);
+            let src = &renderer.pixel_buffer[start..start + rect.w as usize * ipp];
+            let dest_start = y as usize * self.stride + rect.x as usize * 4;
+            let dest = &mut shm[dest_start..dest_start + rect.w as usize * 4];
+            for (d, s) in dest.chunks_exact_mut(4).zip(src.chunks_exact(ipp)) {
+                // wl_shm formats are little endian
+                d.copy_from_slice(&format.read_0rgb(s).to_le_bytes());
+            }
+        }
+    }
+
+    /// writes the dirty portions, then attaches the buffer,
+    /// damages the dirty portions, and commits the surface.
+    /// nothing is committed if nothing changed
+    pub fn present(
+        &self, renderer: &mut PortionRenderer<u8>, shm: &mut [u8],
+        surface: &WlSurface, buffer: &WlBuffer,
+    ) {
+        let dirty = self.write_dirty(renderer, shm);
+        if dirty.is_empty() {
+            return;
+        }
+        surface.attach(Some(buffer), 0, 0);
+        for rect in dirty {
+            surface.damage_buffer(rect.x as i32, rect.y as i32, rect.w as i32, rect.h as i32);
+        }
+        surface.commit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_dirty_uses_the_stride() {
+        let mut p = PortionRenderer::<u8>::new_ex(4, 4, 2, 2, PixelFormatEnum::RGBA8888);
+        p.take_dirty_rects();
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, RgbaPixel { r: 1, g: 2, b: 3, a: 255 });
+        p.draw_all_layers();
+        // 2 bytes of padding at the end of each row
+        let presenter = ShmPresenter::new(4 * 4 + 2);
+        let mut shm = vec![9; 18 * 4];
+        let dirty = presenter.write_dirty(&mut p, &mut shm);
+        assert_eq!(dirty, vec![Rect { x: 0, y: 0, w: 2, h: 2 }]);
+        assert_eq!(&shm[0..8], &[3, 2, 1, 0, 0, 0, 0, 0]);
+        assert_eq!(&shm[8..18], &[9; 10]);
+        assert_eq!(&shm[18..26], &[0; 8]);
+        assert_eq!(shm[36], 9);
+    }
+
+    #[test]
+    fn write_all_converts_from_the_pixel_format() {
+        let mut p = PortionRenderer::<u8>::new_ex(2, 1, 1, 1, PixelFormatEnum::ARGB8888);
+        // the texture is already in the renderer's format, so it is copied as is
+        p.create_object_from_texture(0, Rect { x: 0, y: 0, w: 2, h: 1 }, vec![
+            255, 1, 2, 3,
+            255, 0, 0, 255,
+        ], 2, 1);
+        p.draw_all_layers();
+        let mut shm = vec![9; 8];
+        ShmPresenter::new(8).write_all(&p, &mut shm);
+        assert_eq!(&shm, &[3, 2, 1, 0, 255, 0, 0, 0]);
+    }
+}
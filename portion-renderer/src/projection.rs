@@ -200,13 +200,17 @@ impl From<&Matrix> for UnitMatrix {
     }
 }
 
+/// every matrix is a special case of this one, so any of them can be converted
 impl From<&Matrix> for RotateScaleTranslateMatrix {
     fn from(orig: &Matrix) -> Self {
         match orig {
             Matrix::RotateAndScaleAndTranslate(a0, a1, b0, b1, tx, ty) => {
                 RotateScaleTranslateMatrix { a0: *a0, a1: *a1, b0: *b0, b1: *b1, tx: *tx, ty: *ty }
             },
-            _ => panic!("Tried converting to the wrong matrix"),
+            _ => {
+                let m: [f32; 9] = orig.into();
+                RotateScaleTranslateMatrix { a0: m[0], a1: m[1], tx: m[2], b0: m[3], b1: m[4], ty: m[5] }
+            }
         }
     }
 }
@@ -325,6 +329,7 @@ macro_rules! match_matrix {
 }
 
 #[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Matrix {
     Unit,
     Scale(f32, f32),
@@ -354,6 +359,18 @@ impl Matrix {
         Matrix::Rotate(cos, sin)
     }
 
+    /// mirrors the x axis of something that is `width` pixels wide,
+    /// such that pixel 0 becomes pixel width - 1, and vice versa
+    pub fn flip_horizontal(width: f32) -> Matrix {
+        Matrix::ScaleAndTranslate(-1.0, 1.0, width - 1.0, 0.0)
+    }
+
+    /// mirrors the y axis of something that is `height` pixels tall,
+    /// such that pixel 0 becomes pixel height - 1, and vice versa
+    pub fn flip_vertical(height: f32) -> Matrix {
+        Matrix::ScaleAndTranslate(1.0, -1.0, 0.0, height - 1.0)
+    }
+
     #[inline(always)]
     pub fn mul_tuple(&self, xy: (f32, f32)) -> (f32, f32) {
         self.mul_point(xy.0, xy.1)
@@ -478,7 +495,10 @@ impl From<[f32; 9]> for Matrix {
             }
         };
 
-        let is_sin_and_cos = multiplied[0] == multiplied[4] && multiplied[1] == -multiplied[3];
+        // a uniform scale (or the unit matrix) also has equal diagonals
+        // so we only consider it a rotation if it actually rotates
+        let is_sin_and_cos = has_rotate.is_some()
+            && multiplied[0] == multiplied[4] && multiplied[1] == -multiplied[3];
         // check if its just rotate:
         if is_sin_and_cos && has_translate.is_none() {
             return Matrix::Rotate(multiplied[0], multiplied[3]);
@@ -526,6 +546,77 @@ impl Mul<Matrix> for Matrix {
     }
 }
 
+/// A convenience for composing matrices in the order that
+/// you read them, rather than the 'backwards' order of
+/// matrix multiplication. eg:
+/// `MatrixBuilder::new().translate(1.0, 0.0).rotate_deg(90.0).build()`
+/// will first translate, and then rotate.
+/// if `about(cx, cy)` is set, the whole transformation is applied
+/// relative to the point (cx, cy) instead of the origin, which is
+/// what you want when rotating/scaling an object about its center.
+/// The resulting matrix will be the most specific Matrix variant
+/// that can represent the transformation.
+#[derive(Debug, Copy, Clone)]
+pub struct MatrixBuilder {
+    matrix: Matrix,
+    about: Option<(f32, f32)>,
+}
+
+impl Default for MatrixBuilder {
+    fn default() -> Self {
+        MatrixBuilder::new()
+    }
+}
+
+impl MatrixBuilder {
+    pub fn new() -> MatrixBuilder {
+        MatrixBuilder {
+            matrix: Matrix::Unit,
+            about: None,
+        }
+    }
+
+    /// applies `m` after all of the previously added transformations
+    pub fn then(mut self, m: Matrix) -> MatrixBuilder {
+        self.matrix = m * self.matrix;
+        self
+    }
+
+    pub fn translate(self, tx: f32, ty: f32) -> MatrixBuilder {
+        self.then(Matrix::TranslateXY(tx, ty))
+    }
+
+    pub fn rotate_deg(self, angle: f32) -> MatrixBuilder {
+        self.then(Matrix::rotate_degrees(angle))
+    }
+
+    pub fn rotate_rad(self, radians: f32) -> MatrixBuilder {
+        self.then(Matrix::rotate_radians(radians))
+    }
+
+    pub fn scale(self, sx: f32, sy: f32) -> MatrixBuilder {
+        self.then(Matrix::Scale(sx, sy))
+    }
+
+    /// sets the point that the transformation should be relative to.
+    /// only the last call to about() is used.
+    pub fn about(mut self, cx: f32, cy: f32) -> MatrixBuilder {
+        self.about = Some((cx, cy));
+        self
+    }
+
+    pub fn build(&self) -> Matrix {
+        match self.about {
+            Some((cx, cy)) => {
+                Matrix::TranslateXY(cx, cy) * self.matrix * Matrix::TranslateXY(-cx, -cy)
+            }
+            // multiplying by unit gives us the most specific variant
+            // in case the user only called then() with a general matrix
+            None => Matrix::Unit * self.matrix,
+        }
+    }
+}
+
 #[cfg(test)]
 mod projection_tests {
     use super::*;
@@ -686,4 +777,81 @@ mod projection_tests {
         assert_f_eq(out_x, 2.0);
         assert_f_eq(out_y, 1.0);
     }
+
+    #[test]
+    fn matrix_builder_applies_in_reading_order() {
+        let (x, y) = (1.0, 0.0);
+        // same as the inverse_work test, but written in the order
+        // that the transformations happen:
+        // (1, 0) -> (2, 1) -> (4, 2) -> (-2, 4)
+        let m = MatrixBuilder::new()
+            .translate(1.0, 1.0)
+            .scale(2.0, 2.0)
+            .rotate_deg(90.0)
+            .build();
+        let (out_x, out_y) = m.mul_point(x, y);
+        assert_f_eq(out_x, -2.0);
+        assert_f_eq(out_y, 4.0);
+    }
+
+    #[test]
+    fn matrix_builder_can_rotate_about_point() {
+        // same as can_rotate_about_arbitrary_point
+        let m = MatrixBuilder::new().rotate_deg(90.0).about(1.0, 1.0).build();
+        let (out_x, out_y) = m * (1.0, 0.0);
+        assert_f_eq(out_x, 2.0);
+        assert_f_eq(out_y, 1.0);
+    }
+
+    #[test]
+    fn matrix_builder_gives_most_specific_variant() {
+        let m = MatrixBuilder::new().build();
+        assert!(matches!(m, Matrix::Unit));
+
+        let m = MatrixBuilder::new().scale(2.0, 2.0).build();
+        assert!(matches!(m, Matrix::Scale(_, _)));
+
+        let m = MatrixBuilder::new().translate(1.0, 2.0).translate(1.0, 0.0).build();
+        assert!(matches!(m, Matrix::TranslateXY(_, _)));
+
+        let m = MatrixBuilder::new().scale(2.0, 3.0).translate(1.0, 2.0).build();
+        assert!(matches!(m, Matrix::ScaleAndTranslate(_, _, _, _)));
+
+        let m = MatrixBuilder::new().rotate_deg(30.0).build();
+        assert!(matches!(m, Matrix::Rotate(_, _)));
+
+        let m = MatrixBuilder::new().rotate_deg(30.0).about(5.0, 5.0).build();
+        assert!(matches!(m, Matrix::RotateAndTranslate(_, _, _, _)));
+    }
+
+    #[test]
+    fn flips_work() {
+        let m = Matrix::flip_horizontal(10.0);
+        assert_eq!(m * (0.0, 3.0), (9.0, 3.0));
+        assert_eq!(m * (9.0, 3.0), (0.0, 3.0));
+
+        let m = Matrix::flip_vertical(4.0);
+        assert_eq!(m * (2.0, 0.0), (2.0, 3.0));
+        assert_eq!(m * (2.0, 3.0), (2.0, 0.0));
+    }
+
+    #[test]
+    fn flips_compose_correctly() {
+        // flipping twice should do nothing
+        let m = Matrix::flip_horizontal(10.0) * Matrix::flip_horizontal(10.0);
+        assert!(matches!(m, Matrix::Unit));
+
+        // flipping both axes is just a scale and translate
+        let m = Matrix::flip_horizontal(10.0) * Matrix::flip_vertical(4.0);
+        assert!(matches!(m, Matrix::ScaleAndTranslate(_, _, _, _)));
+        assert_eq!(m * (0.0, 0.0), (9.0, 3.0));
+
+        // a flip combined with a rotation is not a rotation
+        // because it cannot be represented by a cos/sin pair
+        let m = Matrix::rotate_degrees(90.0) * Matrix::flip_horizontal(1.0);
+        assert!(matches!(m, Matrix::RotateAndScaleAndTranslate(_, _, _, _, _, _)));
+        let (out_x, out_y) = m * (1.0, 0.0);
+        assert_f_eq(out_x, 0.0);
+        assert_f_eq(out_y, -1.0);
+    }
 }
@@ -0,0 +1,159 @@
+use std::io::Write;
+use std::time::Duration;
+
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::{Delay, Frame, ImageResult, RgbaImage};
+
+use super::*;
+
+/// a part of a frame that changed since the previous frame
+struct Patch {
+    bounds: Rect,
+    image: RgbaImage,
+}
+
+struct RecordedFrame {
+    delay: Duration,
+    /// a keyframe has a single patch that covers the whole screen
+    patches: Vec<Patch>,
+}
+
+/// records frames of a renderer so they can be encoded as an
+/// animated GIF or APNG, eg: for demo recordings. to save memory,
+/// frames can be captured as only their dirty regions, in which
+/// case every keyframe_interval'th frame is stored whole anyway
+pub struct Recorder {
+    width: u32,
+    height: u32,
+    keyframe_interval: usize,
+    frames: Vec<RecordedFrame>,
+}
+
+impl Recorder {
+    pub fn new(renderer: &PortionRenderer<u8>) -> Recorder {
+        Recorder::with_keyframe_interval(renderer, 30)
+    }
+
+    pub fn with_keyframe_interval(renderer: &PortionRenderer<u8>, keyframe_interval: usize) -> Recorder {
+        Recorder {
+            width: renderer.width,
+            height: renderer.height,
+            keyframe_interval: keyframe_interval.max(1),
+            frames: vec![],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.frames.clear();
+    }
+
+    /// stores the whole screen as the next frame, which is shown for delay
+    pub fn capture(&mut self, renderer: &PortionRenderer<u8>, delay: Duration) {
+        let bounds = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        self.frames.push(RecordedFrame {
+            delay,
+            patches: vec![Patch { bounds, image: renderer.region_to_image(bounds) }],
+        });
+    }
+
+    /// stores only the dirty rects as the next frame. the dirty rects are
+    /// whatever changed since the previous capture, eg: from take_dirty_rects
+    pub fn capture_dirty(&mut self, renderer: &PortionRenderer<u8>, dirty: &[Rect], delay: Duration) {
+        if self.frames.len().is_multiple_of(self.keyframe_interval) {
+            return self.capture(renderer, delay);
+        }
+        let patches = dirty.iter().map(|bounds| Patch {
+            bounds: *bounds,
+            image: renderer.region_to_image(*bounds),
+        }).collect();
+        self.frames.push(RecordedFrame { delay, patches });
+    }
+
+    /// calls f with every frame in full, in order
+    fn for_each_frame<E>(&self, mut f: impl FnMut(&RgbaImage, Duration) -> Result<(), E>) -> Result<(), E> {
+        let mut canvas = RgbaImage::new(self.width, self.height);
+        for frame in self.frames.iter() {
+            for patch in frame.patches.iter() {
+                image::imageops::replace(&mut canvas, &patch.image, patch.bounds.x as i64, patch.bounds.y as i64);
+            }
+            f(&canvas, frame.delay)?;
+        }
+        Ok(())
+    }
+
+    /// the GIF loops forever
+    pub fn encode_gif<W: Write>(&self, writer: W) -> ImageResult<()> {
+        let mut encoder = GifEncoder::new(writer);
+        encoder.set_repeat(Repeat::Infinite)?;
+        self.for_each_frame(|canvas, delay| {
+            let delay = Delay::from_saturating_duration(delay);
+            encoder.encode_frame(Frame::from_parts(canvas.clone(), 0, 0, delay))
+        })
+    }
+
+    /// the APNG loops forever
+    pub fn encode_apng<W: Write>(&self, writer: W) -> Result<(), png::EncodingError> {
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_animated(self.frames.len() as u32, 0)?;
+        let mut writer = encoder.write_header()?;
+        self.for_each_frame(|canvas, delay| {
+            let ms = delay.as_millis().min(u16::MAX as u128) as u16;
+            writer.set_frame_delay(ms, 1000)?;
+            writer.write_image_data(canvas.as_raw())
+        })?;
+        writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_captures_rebuild_whole_frames() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let red = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+        p.draw_all_layers();
+        let mut recorder = Recorder::with_keyframe_interval(&p, 2);
+        let dirty = p.take_dirty_rects();
+        recorder.capture_dirty(&p, &dirty, Duration::from_millis(10));
+        for _ in 0..2 {
+            p.move_object_x_by(red, 2);
+            p.draw_all_layers();
+            let dirty = p.take_dirty_rects();
+            recorder.capture_dirty(&p, &dirty, Duration::from_millis(10));
+        }
+        assert_eq!(recorder.len(), 3);
+        // only the second frame is a patch
+        assert_eq!(recorder.frames[1].patches[0].bounds, Rect { x: 0, y: 0, w: 6, h: 4 });
+        assert_eq!(recorder.frames[2].patches[0].bounds, Rect { x: 0, y: 0, w: 8, h: 8 });
+
+        let mut frames = vec![];
+        recorder.for_each_frame::<()>(|canvas, _| {
+            frames.push(canvas.clone());
+            Ok(())
+        }).unwrap();
+        for (i, frame) in frames.iter().enumerate() {
+            let x = i as u32 * 2;
+            assert_eq!(frame.get_pixel(x, 0).0, [255, 0, 0, 255]);
+            assert_eq!(frame.get_pixel(x + 2, 0).0[3], 0);
+        }
+
+        let mut gif = vec![];
+        recorder.encode_gif(&mut gif).unwrap();
+        assert!(gif.starts_with(b"GIF89a"));
+        let mut apng = vec![];
+        recorder.encode_apng(&mut apng).unwrap();
+        assert!(apng.windows(4).any(|w| w == b"acTL"));
+    }
+}
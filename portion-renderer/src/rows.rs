@@ -0,0 +1,69 @@
+use super::*;
+
+impl<T: Clone> PortionRenderer<T> {
+    /// calls f(y, x_start, pixels) for every row of the dirty portions, top
+    /// to bottom, with the pixels straight from the pixel buffer, so in the
+    /// renderer's pixel format. dirty portions that touch on a row are
+    /// joined into one span, eg: for a SPI display (ST7789, ILI9341), where
+    /// each call is one column/row address window and a write of the
+    /// pixels. returns the dirty rects like take_dirty_rects, so dont mix
+    /// it with other presenters
+    pub fn for_each_dirty_row(&mut self, mut f: impl FnMut(u32, u32, &[T])) -> Vec<Rect> {
+        let dirty = self.take_dirty_rects();
+        let mut spans = vec![];
+        for rect in dirty.iter() {
+            let end = (rect.x + rect.w).min(self.width);
+            if rect.x >= end {
+                continue;
+            }
+            spans.extend((rect.y..(rect.y + rect.h).min(self.height)).map(|y| (y, rect.x, end)));
+        }
+        spans.sort_unstable();
+        let ipp = self.indices_per_pixel as usize;
+        let mut call = |(y, start, end): (u32, u32, u32)| {
+            let index = red_index(start, y, self.width, self.indices_per_pixel);
+            f(y, start, &self.pixel_buffer[index..index + (end - start) as usize * ipp]);
+        };
+        let mut spans = spans.into_iter();
+        let mut current = match spans.next() {
+            Some(span) => span,
+            None => return dirty,
+        };
+        for (y, start, end) in spans {
+            if y == current.0 && start <= current.2 {
+                current.2 = current.2.max(end);
+                continue;
+            }
+            call(current);
+            current = (y, start, end);
+        }
+        call(current);
+        dirty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_row_of_the_dirty_portions_is_passed_once() {
+        let mut p = PortionRenderer::<u8>::new_ex(8, 8, 2, 2, PixelFormatEnum::RGBA8888);
+        p.draw_all_layers();
+        p.take_dirty_rects();
+        p.create_object_from_color(0, Rect { x: 1, y: 1, w: 2, h: 1 }, PIXEL_RED);
+        p.create_object_from_color(0, Rect { x: 6, y: 0, w: 1, h: 1 }, PIXEL_BLUE);
+        p.draw_all_layers();
+        let mut rows = vec![];
+        let dirty = p.for_each_dirty_row(|y, x, pixels| rows.push((y, x, pixels.to_vec())));
+        let rows_of_dirty: u32 = dirty.iter().map(|r| r.h).sum();
+        assert_eq!(rows.len() as u32, rows_of_dirty);
+        assert!(rows.windows(2).all(|w| w[0].0 < w[1].0));
+        let (y, x, pixels) = &rows[1];
+        assert_eq!((*y, *x), (1, 0));
+        assert_eq!(pixels[4..12], [255, 0, 0, 255].repeat(2));
+        let (_, x, pixels) = &rows[0];
+        assert_eq!(pixels[(6 - *x as usize) * 4..(7 - *x as usize) * 4], [0, 0, 255, 255]);
+        assert!(p.for_each_dirty_row(|_, _, _| panic!("nothing is dirty")).is_empty());
+    }
+}
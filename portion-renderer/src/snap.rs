@@ -0,0 +1,92 @@
+use super::*;
+
+impl<T> PortionRenderer<T> {
+    pub fn grid_snapping(&self) -> bool {
+        self.snap_to_grid
+    }
+
+    /// the (col_width, row_height) of the portions, when snapping is on
+    pub(crate) fn snap_cell(&self) -> Option<(u32, u32)> {
+        if self.snap_to_grid {
+            Some((self.portioner.col_width, self.portioner.row_height))
+        } else {
+            None
+        }
+    }
+
+    /// the screen position moved up and left to the corner of its portion
+    pub(crate) fn snap_position(&self, x: i32, y: i32) -> (i32, i32) {
+        match self.snap_cell() {
+            Some((w, h)) => {
+                let (w, h) = (w as i32, h as i32);
+                (x.div_euclid(w) * w, y.div_euclid(h) * h)
+            }
+            None => (x, y),
+        }
+    }
+
+    /// the size rounded up to whole portions
+    pub(crate) fn snap_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.snap_cell() {
+            Some((w, h)) => (width.div_ceil(w) * w, height.div_ceil(h) * h),
+            None => (width, height),
+        }
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// for tile based scenes, eg: terminals or roguelikes, where everything
+    /// is on a grid of cells the size of a portion. when enabled, objects
+    /// are created with their size rounded up to whole portions, and are
+    /// placed at the top left corner of the portion that their position
+    /// is in. since every object then covers whole portions, exactly
+    /// the portions that objects are on get marked as dirty, and opaque
+    /// color objects are drawn by copying whole rows. objects that
+    /// already exist are moved onto the grid, but keep their size
+    pub fn set_grid_snapping(&mut self, enabled: bool) {
+        self.log_command(|| RenderCommand::SetGridSnapping(enabled));
+        if self.snap_to_grid == enabled {
+            return;
+        }
+        self.snap_to_grid = enabled;
+        let objects: Vec<usize> = self.objects.used_indices().collect();
+        for object_index in objects {
+            self.move_object_to_world_position(object_index);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snapped_objects_cover_whole_portions() {
+        let mut p = PortionRenderer::<u8>::new_ex(16, 16, 4, 4, PixelFormatEnum::RGBA8888);
+        p.set_grid_snapping(true);
+        p.take_dirty_rects();
+        let o = p.create_object_from_color(0, Rect { x: 5, y: 6, w: 3, h: 5 }, PIXEL_RED);
+        p.draw_all_layers();
+        let object = p.get_object(o).unwrap();
+        assert_eq!(object.current_bounds, Rect { x: 4, y: 4, w: 4, h: 8 });
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 4, y: 4, w: 4, h: 8 }]);
+        assert_eq!(RgbaPixel::from(&p[(4, 4)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(7, 11)]), PIXEL_RED);
+        assert_eq!(&p[(8, 4)], &[0, 0, 0, 0]);
+
+        // moving by less than a portion stays in the same portion
+        p.move_object_x_by(o, 2);
+        p.draw_all_layers();
+        assert_eq!(p.get_object(o).unwrap().current_bounds.x, 4);
+        p.move_object_x_by(o, 3);
+        p.draw_all_layers();
+        assert_eq!(p.get_object(o).unwrap().current_bounds.x, 8);
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 4, y: 4, w: 8, h: 8 }]);
+        assert_eq!(&p[(4, 4)], &[0, 0, 0, 0]);
+        assert_eq!(RgbaPixel::from(&p[(11, 11)]), PIXEL_RED);
+
+        p.set_grid_snapping(false);
+        p.draw_all_layers();
+        assert_eq!(p.get_object(o).unwrap().current_bounds.x, 10);
+    }
+}
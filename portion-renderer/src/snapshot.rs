@@ -0,0 +1,129 @@
+use super::*;
+
+/// the scene of a renderer at one point in time, see snapshot.
+/// the textures are shared with the renderer until either side changes
+/// them, so a snapshot mostly costs a copy of the pixel buffer
+#[derive(Clone)]
+pub struct RendererSnapshot<T> {
+    width: u32,
+    height: u32,
+    pixel_buffer: Vec<T>,
+    clear: ClearSource<T>,
+    textures: TightVec<Arc<Texture<T>>>,
+    texture_refs: HashMap<usize, usize>,
+    objects: TightVec<Object>,
+    layers: Vec<Layer>,
+    removed_regions: Vec<Rect>,
+    camera_x: i32,
+    camera_y: i32,
+    zoom: f32,
+    clip: Option<Rect>,
+    motions: HashMap<usize, Motion>,
+    lighting: Option<Lighting>,
+    masks: HashMap<usize, Mask>,
+    layer_groups: TightVec<LayerGroup>,
+}
+
+impl<T> RendererSnapshot<T> {
+    /// the size of the renderer that the snapshot was taken of
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// captures the objects, layers, textures, camera, and pixels, eg: for
+    /// the undo of an editor, or the save state of a game. the settings,
+    /// like the pixel format or the sampling filter, the viewports,
+    /// and the event callbacks are not part of it
+    pub fn snapshot(&self) -> RendererSnapshot<T> {
+        RendererSnapshot {
+            width: self.width,
+            height: self.height,
+            pixel_buffer: self.pixel_buffer.clone(),
+            clear: self.clear.clone(),
+            textures: self.textures.clone(),
+            texture_refs: self.texture_refs.clone(),
+            objects: self.objects.clone(),
+            layers: self.layers.clone(),
+            removed_regions: self.removed_regions.clone(),
+            camera_x: self.camera_x,
+            camera_y: self.camera_y,
+            zoom: self.zoom,
+            clip: self.clip,
+            motions: self.motions.clone(),
+            lighting: self.lighting.clone(),
+            masks: self.masks.clone(),
+            layer_groups: self.layer_groups.clone(),
+        }
+    }
+
+    /// puts the renderer back to how it was when the snapshot was taken,
+    /// with the same object and texture indices, and pixels. the whole
+    /// screen is marked as dirty, and the viewports are drawn again on
+    /// the next draw_all_layers. textures from a provider that were created
+    /// after the snapshot are forgotten. panics if the snapshot was
+    /// taken of a renderer of a different size
+    pub fn restore(&mut self, snapshot: RendererSnapshot<T>) {
+        if snapshot.size() != (self.width, self.height) {
+            panic!("Cannot restore a {}x{} snapshot because the renderer is {}x{}", snapshot.width, snapshot.height, self.width, self.height);
+        }
+        self.pixel_buffer = snapshot.pixel_buffer;
+        self.clear = snapshot.clear;
+        self.textures = snapshot.textures;
+        self.opaque_textures.clear();
+        self.texture_refs = snapshot.texture_refs;
+        self.objects = snapshot.objects;
+        self.layers = snapshot.layers;
+        self.removed_regions = snapshot.removed_regions;
+        self.camera_x = snapshot.camera_x;
+        self.camera_y = snapshot.camera_y;
+        self.zoom = snapshot.zoom;
+        self.clip = snapshot.clip;
+        self.motions = snapshot.motions;
+        self.lighting = snapshot.lighting;
+        self.masks = snapshot.masks;
+        self.layer_groups = snapshot.layer_groups;
+        let textures = &self.textures;
+        self.lazy_textures.retain(|texture_index, _| textures.get(*texture_index).is_some());
+        self.redraw_viewports();
+        self.portioner.take_region((0, 0), (self.width - 1, self.height - 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn restore_goes_back_to_the_snapshot() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let red = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+        let texture = p.create_texture(Texture::new([0, 0, 255, 255].repeat(4), 2, 2));
+        p.create_object_from_texture_index(1, Rect { x: 4, y: 4, w: 2, h: 2 }, texture);
+        p.draw_all_layers();
+        let snapshot = p.snapshot();
+        let pixels = p.pixel_buffer.clone();
+        // the texture isnt copied
+        assert!(std::ptr::eq(snapshot.textures[texture].data.as_ptr(), p.get_texture(texture).unwrap().data.as_ptr()));
+
+        p.move_object_x_by(red, 3);
+        p.remove_object(red);
+        p.adjust_texture(texture, ColorAdjustment::Invert);
+        p.create_object_from_color(2, Rect { x: 0, y: 0, w: 8, h: 8 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        assert_ne!(p.pixel_buffer, pixels);
+
+        p.take_dirty_rects();
+        p.restore(snapshot);
+        assert_eq!(p.pixel_buffer, pixels);
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 8, h: 8 }]);
+        assert_eq!(p.get_texture(texture).unwrap().data, [0, 0, 255, 255].repeat(4));
+        // the objects carry on from where they were
+        p.move_object_y_by(red, 2);
+        p.draw_all_layers();
+        assert_eq!(RgbaPixel::from(&p[(0, 0)]), PIXEL_BLANK);
+        assert_eq!(RgbaPixel::from(&p[(1, 3)]), PIXEL_RED);
+        assert_eq!(RgbaPixel::from(&p[(5, 5)]), PIXEL_BLUE);
+    }
+}
@@ -0,0 +1,165 @@
+use std::collections::HashMap;
+
+use super::Rect;
+
+/// the width and height in pixels of a single cell of the index
+pub const SPATIAL_CELL_SIZE: u32 = 64;
+
+/// the inclusive range of cells that an object's bounds touch
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct CellRange {
+    col_start: u32,
+    col_end: u32,
+    row_start: u32,
+    row_end: u32,
+}
+
+/// a uniform grid of buckets of object indices, so that finding the
+/// objects near some region only needs to look at the objects
+/// in the cells that the region touches, instead of every object.
+/// the cells are created lazily, so this does not need to know
+/// the dimensions of the screen
+#[derive(Debug, Default, Clone)]
+pub struct SpatialIndex {
+    cells: HashMap<(u32, u32), Vec<usize>>,
+    object_cells: HashMap<usize, CellRange>,
+}
+
+impl CellRange {
+    fn of(bounds: &Rect) -> Option<CellRange> {
+        if bounds.w == 0 || bounds.h == 0 {
+            return None;
+        }
+        Some(CellRange {
+            col_start: bounds.x / SPATIAL_CELL_SIZE,
+            col_end: (bounds.x + bounds.w - 1) / SPATIAL_CELL_SIZE,
+            row_start: bounds.y / SPATIAL_CELL_SIZE,
+            row_end: (bounds.y + bounds.h - 1) / SPATIAL_CELL_SIZE,
+        })
+    }
+
+    fn cells(&self) -> impl Iterator<Item = (u32, u32)> {
+        let (col_start, col_end) = (self.col_start, self.col_end);
+        (self.row_start..=self.row_end).flat_map(move |row| {
+            (col_start..=col_end).map(move |col| (col, row))
+        })
+    }
+}
+
+impl SpatialIndex {
+    pub fn new() -> SpatialIndex {
+        SpatialIndex::default()
+    }
+
+    /// adds the object to the index, or moves it
+    /// if it is already in the index
+    pub fn insert(&mut self, object_index: usize, bounds: Rect) {
+        let range = CellRange::of(&bounds);
+        if range.is_some() && self.object_cells.get(&object_index) == range.as_ref() {
+            return;
+        }
+        self.remove(object_index);
+        let range = match range {
+            Some(range) => range,
+            // nothing can intersect an empty rect
+            None => return,
+        };
+        for cell in range.cells() {
+            self.cells.entry(cell).or_default().push(object_index);
+        }
+        self.object_cells.insert(object_index, range);
+    }
+
+    pub fn remove(&mut self, object_index: usize) {
+        let range = match self.object_cells.remove(&object_index) {
+            Some(range) => range,
+            None => return,
+        };
+        for cell in range.cells() {
+            if let Some(objects) = self.cells.get_mut(&cell) {
+                objects.retain(|o| *o != object_index);
+                if objects.is_empty() {
+                    self.cells.remove(&cell);
+                }
+            }
+        }
+    }
+
+    /// fills out with every object whose cells touch the bounds,
+    /// sorted by object index. these objects might not actually
+    /// intersect the bounds, but every object that does is included
+    pub fn query(&self, bounds: &Rect, out: &mut Vec<usize>) {
+        out.clear();
+        let range = match CellRange::of(bounds) {
+            Some(range) => range,
+            None => return,
+        };
+        for cell in range.cells() {
+            if let Some(objects) = self.cells.get(&cell) {
+                out.extend_from_slice(objects);
+            }
+        }
+        out.sort_unstable();
+        out.dedup();
+    }
+
+    pub fn len(&self) -> usize {
+        self.object_cells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.object_cells.is_empty()
+    }
+
+    /// roughly how many bytes the index has allocated
+    pub fn heap_size(&self) -> usize {
+        let cell = std::mem::size_of::<((u32, u32), Vec<usize>)>();
+        let objects: usize = self.cells.values().map(|o| o.capacity() * std::mem::size_of::<usize>()).sum();
+        self.cells.capacity() * cell + objects
+            + self.object_cells.capacity() * std::mem::size_of::<(usize, CellRange)>()
+    }
+
+    pub fn shrink_to_fit(&mut self) {
+        self.cells.values_mut().for_each(|objects| objects.shrink_to_fit());
+        self.cells.shrink_to_fit();
+        self.object_cells.shrink_to_fit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_only_returns_nearby_objects() {
+        let mut index = SpatialIndex::new();
+        index.insert(0, Rect { x: 0, y: 0, w: 10, h: 10 });
+        index.insert(1, Rect { x: 500, y: 500, w: 10, h: 10 });
+        index.insert(2, Rect { x: 60, y: 0, w: 100, h: 10 });
+        let mut out = vec![];
+        index.query(&Rect { x: 5, y: 5, w: 2, h: 2 }, &mut out);
+        assert_eq!(out, vec![0, 2]);
+        index.query(&Rect { x: 400, y: 400, w: 200, h: 200 }, &mut out);
+        assert_eq!(out, vec![1]);
+        index.query(&Rect { x: 0, y: 0, w: 0, h: 0 }, &mut out);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn moving_an_object_updates_its_cells() {
+        let mut index = SpatialIndex::new();
+        index.insert(3, Rect { x: 0, y: 0, w: 10, h: 10 });
+        index.insert(3, Rect { x: 300, y: 0, w: 10, h: 10 });
+        assert_eq!(index.len(), 1);
+        let mut out = vec![];
+        index.query(&Rect { x: 0, y: 0, w: 10, h: 10 }, &mut out);
+        assert!(out.is_empty());
+        index.query(&Rect { x: 300, y: 0, w: 10, h: 10 }, &mut out);
+        assert_eq!(out, vec![3]);
+
+        index.remove(3);
+        index.query(&Rect { x: 300, y: 0, w: 10, h: 10 }, &mut out);
+        assert!(out.is_empty());
+        assert!(index.is_empty());
+    }
+}
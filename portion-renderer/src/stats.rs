@@ -0,0 +1,154 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use super::{PIXEL_BLUE, PIXEL_GREEN, PIXEL_RED};
+
+/// how long one object took to draw during a frame,
+/// and how many pixels it touched
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectStats {
+    pub object_index: usize,
+    /// includes clearing the object's previous bounds
+    pub time: Duration,
+    pub pixels_written: usize,
+    pub pixels_cleared: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FrameStats {
+    pub draw_time: Duration,
+    pub pixels_written: usize,
+    pub pixels_cleared: usize,
+    /// one entry per object update, in the order they were drawn.
+    /// this is empty for frames drawn in parallel, because the
+    /// objects are drawn at the same time in each band
+    pub objects: Vec<ObjectStats>,
+}
+
+impl FrameStats {
+    pub fn slowest_object(&self) -> Option<&ObjectStats> {
+        self.objects.iter().max_by_key(|o| o.time)
+    }
+}
+
+/// counts the pixels touched while drawing. atomic so that
+/// it can be shared by bands that are drawn in parallel.
+/// the draw loops count locally, and only add to this once per call
+#[derive(Debug, Default)]
+pub struct PixelCounter {
+    written: AtomicUsize,
+    cleared: AtomicUsize,
+}
+
+impl PixelCounter {
+    #[inline(always)]
+    pub fn add_written(&self, n: usize) {
+        self.written.fetch_add(n, Ordering::Relaxed);
+    }
+
+    #[inline(always)]
+    pub fn add_cleared(&self, n: usize) {
+        self.cleared.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /// returns (written, cleared) and resets both to 0
+    pub fn take(&self) -> (usize, usize) {
+        (self.written.swap(0, Ordering::Relaxed), self.cleared.swap(0, Ordering::Relaxed))
+    }
+}
+
+/// how many times each pixel was written to during a frame,
+/// see PortionRenderer::set_overdraw_tracking. atomic for the
+/// same reason as the PixelCounter
+#[derive(Debug)]
+pub struct OverdrawMap {
+    width: u32,
+    height: u32,
+    counts: Vec<AtomicU32>,
+}
+
+impl OverdrawMap {
+    pub fn new(width: u32, height: u32) -> OverdrawMap {
+        let counts = (0..width as usize * height as usize).map(|_| AtomicU32::new(0)).collect();
+        OverdrawMap { width, height, counts }
+    }
+
+    /// counts a write to every pixel of row y between [x_start, x_end)
+    #[inline(always)]
+    pub fn add_span(&self, y: u32, x_start: u32, x_end: u32) {
+        let row = y as usize * self.width as usize;
+        for count in &self.counts[row + x_start as usize..row + x_end as usize] {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub fn count(&self, x: u32, y: u32) -> u32 {
+        self.counts[y as usize * self.width as usize + x as usize].load(Ordering::Relaxed)
+    }
+
+    /// the most times that any one pixel was written to
+    pub fn max(&self) -> u32 {
+        self.counts.iter().map(|c| c.load(Ordering::Relaxed)).max().unwrap_or(0)
+    }
+
+    pub fn reset(&self) {
+        self.counts.iter().for_each(|c| c.store(0, Ordering::Relaxed));
+    }
+
+    /// the counts as an RGBA8888 image the size of the screen. pixels
+    /// that were not written to are transparent, pixels written once
+    /// are blue, and pixels written to more often go through
+    /// green to red, which is max_count writes or more
+    pub fn heatmap(&self, max_count: u32) -> Vec<u8> {
+        let max_count = max_count.max(2);
+        let mut image = vec![0; self.width as usize * self.height as usize * 4];
+        for (pixel, count) in image.chunks_exact_mut(4).zip(self.counts.iter()) {
+            let count = count.load(Ordering::Relaxed);
+            if count == 0 {
+                continue;
+            }
+            // blue at 1 write, green halfway, and red at max_count
+            let t = (count.min(max_count) - 1) as f32 / (max_count - 1) as f32;
+            let color = if t < 0.5 {
+                PIXEL_BLUE.lerp(PIXEL_GREEN, t * 2.0)
+            } else {
+                PIXEL_GREEN.lerp(PIXEL_RED, t * 2.0 - 1.0)
+            };
+            pixel.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pixel_counter_take_resets() {
+        let counter = PixelCounter::default();
+        counter.add_written(3);
+        counter.add_written(4);
+        counter.add_cleared(2);
+        assert_eq!(counter.take(), (7, 2));
+        assert_eq!(counter.take(), (0, 0));
+    }
+
+    #[test]
+    fn heatmap_goes_from_blue_to_red() {
+        let overdraw = OverdrawMap::new(4, 1);
+        overdraw.add_span(0, 1, 4);
+        overdraw.add_span(0, 2, 4);
+        overdraw.add_span(0, 3, 4);
+        assert_eq!(overdraw.count(3, 0), 3);
+        assert_eq!(overdraw.max(), 3);
+        assert_eq!(overdraw.heatmap(3), vec![
+            0, 0, 0, 0,
+            0, 0, 255, 255,
+            0, 255, 0, 255,
+            255, 0, 0, 255,
+        ]);
+        overdraw.reset();
+        assert_eq!(overdraw.max(), 0);
+    }
+}
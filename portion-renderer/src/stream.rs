@@ -0,0 +1,326 @@
+use std::borrow::Cow;
+use std::convert::TryInto;
+use std::io;
+
+use super::*;
+
+/// how the pixels of each rect are packed in a frame
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Compression {
+    /// the pixels as they are in the pixel buffer
+    None,
+    /// runs of the same pixel as a count byte and the pixel,
+    /// which is small for flat colors, eg: UIs and pixel art
+    Rle,
+    /// the pixels, compressed with zlib
+    #[cfg(feature = "zlib")]
+    Zlib,
+}
+
+impl Compression {
+    fn to_byte(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Rle => 1,
+            #[cfg(feature = "zlib")]
+            Compression::Zlib => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> io::Result<Compression> {
+        match byte {
+            0 => Ok(Compression::None),
+            1 => Ok(Compression::Rle),
+            #[cfg(feature = "zlib")]
+            2 => Ok(Compression::Zlib),
+            _ => Err(invalid_data(format!("Unknown compression {}", byte))),
+        }
+    }
+}
+
+/// packs the dirty rects of a renderer and their pixels into frames that
+/// a StreamDecoder can apply to its own copy of the pixel buffer, eg: to
+/// show a renderer on another machine. every number is a little endian u32.
+/// a frame is: width, height, indices per pixel, the compression byte,
+/// the number of rects, and then for each rect its x, y, w, h,
+/// the length of its packed pixels, and the packed pixels
+pub struct StreamEncoder {
+    compression: Compression,
+}
+
+impl StreamEncoder {
+    pub fn new(compression: Compression) -> StreamEncoder {
+        StreamEncoder { compression }
+    }
+
+    /// a frame of the renderer's dirty rects. this takes the renderer's
+    /// dirty rects, so dont mix it with other presenters
+    pub fn encode_frame(&self, renderer: &mut PortionRenderer<u8>) -> Vec<u8> {
+        let dirty = renderer.take_dirty_rects();
+        self.encode_rects(renderer, &dirty)
+    }
+
+    /// a frame of the whole pixel buffer, eg: for a
+    /// decoder that just connected
+    pub fn encode_all(&self, renderer: &PortionRenderer<u8>) -> Vec<u8> {
+        self.encode_rects(renderer, &[Rect { x: 0, y: 0, w: renderer.width, h: renderer.height }])
+    }
+
+    /// a frame of the rects, which must be on screen
+    pub fn encode_rects(&self, renderer: &PortionRenderer<u8>, rects: &[Rect]) -> Vec<u8> {
+        let mut frame = vec![];
+        for value in [renderer.width, renderer.height, renderer.indices_per_pixel].iter() {
+            frame.extend_from_slice(&value.to_le_bytes());
+        }
+        frame.push(self.compression.to_byte());
+        frame.extend_from_slice(&(rects.len() as u32).to_le_bytes());
+        let ipp = renderer.indices_per_pixel as usize;
+        let mut pixels = vec![];
+        for rect in rects.iter() {
+            pixels.clear();
+            for y in rect.y..rect.y + rect.h {
+                let start = red_index(rect.x, y, renderer.width, renderer.indices_per_pixel);
+                pixels.extend_from_slice(&renderer.pixel_buffer[start..start + rect.w as usize * ipp]);
+            }
+            let packed = match self.compression {
+                Compression::None => Cow::Borrowed(&pixels[..]),
+                Compression::Rle => Cow::Owned(rle_encode(&pixels, ipp)),
+                #[cfg(feature = "zlib")]
+                Compression::Zlib => Cow::Owned(zlib_encode(&pixels)),
+            };
+            for value in [rect.x, rect.y, rect.w, rect.h, packed.len() as u32].iter() {
+                frame.extend_from_slice(&value.to_le_bytes());
+            }
+            frame.extend_from_slice(&packed);
+        }
+        frame
+    }
+}
+
+/// the most pixels that a StreamDecoder makes a buffer for, which is
+/// 8192x8192, so that a bad frame cant make it allocate gigabytes
+pub const MAX_FRAME_PIXELS: usize = 8192 * 8192;
+
+/// a copy of a renderer's pixel buffer that is kept up to date
+/// by applying the frames of a StreamEncoder
+pub struct StreamDecoder {
+    pub width: u32,
+    pub height: u32,
+    pub indices_per_pixel: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl StreamDecoder {
+    /// the buffer is empty until the first frame
+    pub fn new() -> StreamDecoder {
+        StreamDecoder { width: 0, height: 0, indices_per_pixel: 4, pixels: vec![] }
+    }
+
+    /// copies the rects of the frame into the pixels, and returns them.
+    /// if the frame is for a different size of renderer, the pixels
+    /// are resized and cleared first. returns an InvalidData error
+    /// if the frame is not a valid frame, or has more than
+    /// MAX_FRAME_PIXELS, in which case some of its
+    /// rects might have been applied already
+    pub fn apply(&mut self, frame: &[u8]) -> io::Result<Vec<Rect>> {
+        let mut reader = FrameReader { data: frame };
+        let (width, height, ipp) = (reader.u32()?, reader.u32()?, reader.u32()?);
+        let compression = Compression::from_byte(reader.bytes(1)?[0])?;
+        let rect_count = reader.u32()?;
+        // every renderer that encodes frames has 4 values per pixel
+        if ipp != 4 {
+            return Err(invalid_data(format!("A frame cant have {} values per pixel", ipp)));
+        }
+        if (width, height, ipp) != (self.width, self.height, self.indices_per_pixel) {
+            let len = checked_buffer_len(width, height, 1)
+                .filter(|pixels| *pixels <= MAX_FRAME_PIXELS)
+                .and_then(|_| checked_buffer_len(width, height, ipp))
+                .ok_or_else(|| invalid_data(format!("A {}x{} frame is too big", width, height)))?;
+            self.width = width;
+            self.height = height;
+            self.indices_per_pixel = ipp;
+            self.pixels = vec![0; len];
+        }
+        let screen = Rect { x: 0, y: 0, w: width, h: height };
+        let mut rects = Vec::with_capacity(rect_count.min(1024) as usize);
+        for _ in 0..rect_count {
+            let rect = Rect { x: reader.u32()?, y: reader.u32()?, w: reader.u32()?, h: reader.u32()? };
+            let packed_len = reader.u32()? as usize;
+            let packed = reader.bytes(packed_len)?;
+            if rect.is_empty() {
+                continue;
+            }
+            if rect.intersection(screen) != Some(rect) {
+                return Err(invalid_data(format!("{:?} is not inside of the {}x{} frame", rect, width, height)));
+            }
+            let row_len = rect.w as usize * ipp as usize;
+            let len = row_len * rect.h as usize;
+            let pixels = match compression {
+                Compression::None => Cow::Borrowed(packed),
+                Compression::Rle => Cow::Owned(rle_decode(packed, ipp as usize, len)?),
+                #[cfg(feature = "zlib")]
+                Compression::Zlib => Cow::Owned(zlib_decode(packed, len)?),
+            };
+            if pixels.len() != len {
+                return Err(invalid_data(format!("{:?} has {} pixel values instead of {}", rect, pixels.len(), len)));
+            }
+            for (row, src) in pixels.chunks_exact(row_len).enumerate() {
+                let start = red_index(rect.x, rect.y + row as u32, width, ipp);
+                self.pixels[start..start + row_len].copy_from_slice(src);
+            }
+            rects.push(rect);
+        }
+        Ok(rects)
+    }
+}
+
+impl Default for StreamDecoder {
+    fn default() -> StreamDecoder {
+        StreamDecoder::new()
+    }
+}
+
+struct FrameReader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> FrameReader<'a> {
+    fn bytes(&mut self, len: usize) -> io::Result<&'a [u8]> {
+        if self.data.len() < len {
+            return Err(invalid_data("The frame ends too early".to_string()));
+        }
+        let (bytes, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(bytes)
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// runs of up to 255 of the same pixel, as the length of the run and then the pixel
+fn rle_encode(pixels: &[u8], ipp: usize) -> Vec<u8> {
+    let mut packed = vec![];
+    let mut chunks = pixels.chunks_exact(ipp).peekable();
+    while let Some(pixel) = chunks.next() {
+        let mut run = 1u8;
+        while run < u8::MAX && chunks.peek() == Some(&pixel) {
+            chunks.next();
+            run += 1;
+        }
+        packed.push(run);
+        packed.extend_from_slice(pixel);
+    }
+    packed
+}
+
+fn rle_decode(packed: &[u8], ipp: usize, len: usize) -> io::Result<Vec<u8>> {
+    let mut pixels = Vec::with_capacity(len);
+    for run in packed.chunks(ipp + 1) {
+        if run.len() != ipp + 1 || run[0] == 0 {
+            return Err(invalid_data("Invalid run length encoding".to_string()));
+        }
+        for _ in 0..run[0] {
+            pixels.extend_from_slice(&run[1..]);
+        }
+        if pixels.len() > len {
+            break;
+        }
+    }
+    Ok(pixels)
+}
+
+#[cfg(feature = "zlib")]
+fn zlib_encode(pixels: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    let mut encoder = flate2::write::ZlibEncoder::new(vec![], flate2::Compression::default());
+    // writing to a vec cant fail
+    encoder.write_all(pixels).unwrap();
+    encoder.finish().unwrap()
+}
+
+#[cfg(feature = "zlib")]
+fn zlib_decode(packed: &[u8], len: usize) -> io::Result<Vec<u8>> {
+    use std::io::Read;
+    let mut pixels = Vec::with_capacity(len);
+    // one byte more than expected is enough to tell that it is too long
+    flate2::read::ZlibDecoder::new(packed).take(len as u64 + 1).read_to_end(&mut pixels)?;
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene() -> PortionRenderer<u8> {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 8, h: 8 }, PIXEL_BLUE);
+        p.create_object_from_texture(1, Rect { x: 1, y: 1, w: 2, h: 1 }, vec![1, 2, 3, 4, 5, 6, 7, 8], 2, 1);
+        p.draw_all_layers();
+        p
+    }
+
+    #[test]
+    fn decoded_frames_match_the_renderer() {
+        let compressions = [
+            Compression::None,
+            Compression::Rle,
+            #[cfg(feature = "zlib")]
+            Compression::Zlib,
+        ];
+        for compression in compressions {
+            let mut p = scene();
+            let encoder = StreamEncoder::new(compression);
+            let mut decoder = StreamDecoder::new();
+            assert!(!decoder.apply(&encoder.encode_frame(&mut p)).unwrap().is_empty());
+            assert_eq!((decoder.width, decoder.height), (8, 8));
+            assert_eq!(decoder.pixels, p.pixel_buffer);
+
+            p.move_object_x_by(1, 4);
+            p.draw_all_layers();
+            assert!(!decoder.apply(&encoder.encode_frame(&mut p)).unwrap().is_empty());
+            assert_eq!(decoder.pixels, p.pixel_buffer);
+            // nothing changed, so nothing is sent
+            assert!(decoder.apply(&encoder.encode_frame(&mut p)).unwrap().is_empty());
+        }
+    }
+
+    #[test]
+    fn invalid_frames_are_errors() {
+        let p = scene();
+        let frame = StreamEncoder::new(Compression::Rle).encode_all(&p);
+        let mut decoder = StreamDecoder::new();
+        let error = decoder.apply(&frame[..frame.len() - 1]).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let mut unknown = frame.clone();
+        unknown[12] = 9;
+        assert!(decoder.apply(&unknown).is_err());
+        assert!(decoder.apply(&frame).is_ok());
+    }
+
+    #[test]
+    fn malformed_frames_are_errors_and_keep_the_pixels() {
+        let p = scene();
+        let frame = StreamEncoder::new(Compression::None).encode_all(&p);
+        let mut decoder = StreamDecoder::new();
+        decoder.apply(&frame).unwrap();
+        let with_header = |width: u32, height: u32, ipp: u32| {
+            let mut malformed = frame.clone();
+            for (i, value) in [width, height, ipp].iter().enumerate() {
+                malformed[i * 4..i * 4 + 4].copy_from_slice(&value.to_le_bytes());
+            }
+            malformed
+        };
+        for malformed in [with_header(8, 8, 0), with_header(8, 8, 3), with_header(u32::MAX, u32::MAX, 4), with_header(8193, 8192, 4)] {
+            let error = decoder.apply(&malformed).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+            assert_eq!((decoder.width, decoder.height, decoder.indices_per_pixel), (8, 8, 4));
+            assert_eq!(decoder.pixels, p.pixel_buffer);
+        }
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::{self, Write};
+
+use super::*;
+
+/// the kitty graphics protocol only allows this many
+/// bytes of base64 in a single escape code
+const KITTY_CHUNK_SIZE: usize = 4096;
+
+const BASE64_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+fn base64_encode(data: &[u8]) -> String {
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [chunk[0], *chunk.get(1).unwrap_or(&0), *chunk.get(2).unwrap_or(&0)];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64_ALPHABET[(n >> (18 - i * 6)) as usize & 63] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// writes the pixels of the renderer to a terminal, eg: for looking at
+/// what was drawn in a headless test. the terminal needs 24 bit color
+/// for the half blocks, and kitty graphics support for write_kitty
+impl PortionRenderer<u8> {
+    fn rgba_at(&self, x: u32, y: u32) -> [u8; 4] {
+        let red_index = red_index(x, y, self.width, self.indices_per_pixel);
+        self.pixel_format.read_rgba(&self.pixel_buffer[red_index..red_index + self.indices_per_pixel as usize])
+    }
+
+    fn screen_rect(&self) -> Rect {
+        Rect { x: 0, y: 0, w: self.width, h: self.height }
+    }
+
+    fn write_half_block_rows<W: Write>(&self, out: &mut W, region: Rect, move_cursor: bool) -> io::Result<()> {
+        let region = match region.intersection(self.screen_rect()) {
+            Some(region) => region,
+            None => return Ok(()),
+        };
+        // every character is 2 pixels tall, so start on an even row
+        let y_start = region.y - region.y % 2;
+        for y in (y_start..region.y + region.h).step_by(2) {
+            if move_cursor {
+                write!(out, "\x1b[{};{}H", y / 2 + 1, region.x + 1)?;
+            }
+            for x in region.x..region.x + region.w {
+                let top = self.rgba_at(x, y);
+                let bottom = if y + 1 < self.height { self.rgba_at(x, y + 1) } else { [0; 4] };
+                write!(
+                    out, "\x1b[38;2;{};{};{}m\x1b[48;2;{};{};{}m\u{2580}",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2],
+                )?;
+            }
+            out.write_all(b"\x1b[0m")?;
+            if !move_cursor {
+                out.write_all(b"\n")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// writes region using ANSI half block characters, one character
+    /// for every 2 vertically stacked pixels, at the cursor
+    pub fn write_half_blocks<W: Write>(&self, out: &mut W, region: Rect) -> io::Result<()> {
+        self.write_half_block_rows(out, region, false)
+    }
+
+    /// rewrites only the dirty regions of a frame that was previously
+    /// written with write_half_blocks at the top left of the terminal.
+    /// this takes the renderer's dirty rects, so dont mix it with other presenters
+    pub fn write_dirty_half_blocks<W: Write>(&mut self, out: &mut W) -> io::Result<()> {
+        for rect in self.take_dirty_rects() {
+            self.write_half_block_rows(out, rect, true)?;
+        }
+        out.flush()
+    }
+
+    /// writes region as an image using the kitty graphics protocol
+    pub fn write_kitty<W: Write>(&self, out: &mut W, region: Rect) -> io::Result<()> {
+        let region = match region.intersection(self.screen_rect()) {
+            Some(region) => region,
+            None => return Ok(()),
+        };
+        let mut rgba = Vec::with_capacity(region.w as usize * region.h as usize * 4);
+        for y in region.y..region.y + region.h {
+            for x in region.x..region.x + region.w {
+                rgba.extend_from_slice(&self.rgba_at(x, y));
+            }
+        }
+        let encoded = base64_encode(&rgba);
+        let chunks: Vec<&[u8]> = encoded.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+        for (i, chunk) in chunks.iter().enumerate() {
+            let more = if i + 1 < chunks.len() { 1 } else { 0 };
+            if i == 0 {
+                write!(out, "\x1b_Gf=32,s={},v={},a=T,m={};", region.w, region.h, more)?;
+            } else {
+                write!(out, "\x1b_Gm={};", more)?;
+            }
+            out.write_all(chunk)?;
+            out.write_all(b"\x1b\\")?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base64_encode_pads() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn half_blocks_stack_two_rows() {
+        let mut p = PortionRenderer::<u8>::new(4, 4);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        let mut out = vec![];
+        p.write_half_blocks(&mut out, Rect { x: 0, y: 0, w: 1, h: 2 }).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out, "\x1b[38;2;255;0;0m\x1b[48;2;0;0;0m\u{2580}\x1b[0m\n");
+
+        let mut out = vec![];
+        p.write_kitty(&mut out, Rect { x: 0, y: 0, w: 1, h: 1 }).unwrap();
+        assert_eq!(out, b"\x1b_Gf=32,s=1,v=1,a=T,m=0;/wAA/w==\x1b\\");
+    }
+}
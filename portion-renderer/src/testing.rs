@@ -0,0 +1,127 @@
+use super::*;
+
+/// compares the pixels of a renderer to a map of chars, one char per
+/// pixel, starting at the top left. eg: with the default mappings
+///
+/// ```
+/// # use portion_renderer::*;
+/// # use portion_renderer::testing::PixelMap;
+/// let mut p = PortionRenderer::<u8>::new(4, 4);
+/// p.create_object_from_color(0, Rect { x: 1, y: 0, w: 2, h: 1 }, PIXEL_RED);
+/// p.draw_all_layers();
+/// PixelMap::default().assert_matches(&p, &[
+///     'x', 'r', 'r', 'x',
+///     'x', 'x', '?', '?',
+/// ], 4);
+/// ```
+///
+/// '?' matches any pixel
+#[derive(Debug, Clone)]
+pub struct PixelMap {
+    mappings: Vec<(char, RgbaPixel)>,
+}
+
+/// the char shown for pixels that arent in the mappings
+pub const UNKNOWN_PIXEL_CHAR: char = '?';
+
+impl Default for PixelMap {
+    /// x for blank, r for red, g for green and b for blue
+    fn default() -> PixelMap {
+        PixelMap::new()
+            .with('x', PIXEL_BLANK)
+            .with('r', PIXEL_RED)
+            .with('g', PIXEL_GREEN)
+            .with('b', PIXEL_BLUE)
+    }
+}
+
+impl PixelMap {
+    /// a map without any mappings
+    pub fn new() -> PixelMap {
+        PixelMap { mappings: vec![] }
+    }
+
+    /// maps c to pixel, replacing the pixel if c was already mapped
+    pub fn with(mut self, c: char, pixel: RgbaPixel) -> PixelMap {
+        if c == UNKNOWN_PIXEL_CHAR {
+            panic!("'{}' is reserved for pixels that can be anything", c);
+        }
+        self.mappings.retain(|(mapped, _)| *mapped != c);
+        self.mappings.push((c, pixel));
+        self
+    }
+
+    fn pixel_of(&self, c: char) -> Option<RgbaPixel> {
+        self.mappings.iter().find(|(mapped, _)| *mapped == c).map(|(_, pixel)| *pixel)
+    }
+
+    fn char_of(&self, pixel: RgbaPixel) -> char {
+        self.mappings.iter().find(|(_, mapped)| *mapped == pixel)
+            .map(|(c, _)| *c)
+            .unwrap_or(UNKNOWN_PIXEL_CHAR)
+    }
+
+    /// returns the expected and actual maps as strings if
+    /// any pixel in map is different than the renderer's
+    pub fn check(&self, p: &PortionRenderer<u8>, map: &[char], width: u32) -> Result<(), String> {
+        let mut expected_string = String::from("[");
+        let mut actual_string = String::from("[");
+        let mut matches = true;
+        for (i, c) in map.iter().enumerate() {
+            let x = i as u32 % width;
+            let y = i as u32 / width;
+            let actual: RgbaPixel = p[(x, y)].into();
+            let actual_char = match self.pixel_of(*c) {
+                Some(expected) => {
+                    matches &= expected == actual;
+                    self.char_of(actual)
+                }
+                None if *c == UNKNOWN_PIXEL_CHAR => UNKNOWN_PIXEL_CHAR,
+                None => panic!("Found undefined char in map: {}", c),
+            };
+            expected_string.push(*c);
+            expected_string.push_str(", ");
+            actual_string.push(actual_char);
+            actual_string.push_str(", ");
+            if x + 1 == width {
+                expected_string.push_str("\n ");
+                actual_string.push_str("\n ");
+            }
+        }
+        if matches {
+            Ok(())
+        } else {
+            Err(format!("\n\nExpected\n{}\nActual\n{}\n", expected_string, actual_string))
+        }
+    }
+
+    /// panics with the expected and actual maps if any pixel
+    /// in map is different than the renderer's
+    pub fn assert_matches(&self, p: &PortionRenderer<u8>, map: &[char], width: u32) {
+        if let Err(e) = self.check(p, map, width) {
+            panic!("{}", e);
+        }
+    }
+}
+
+/// PixelMap::default().assert_matches
+pub fn assert_pixels_in_map(p: &PortionRenderer<u8>, map: &[char], width: u32) {
+    PixelMap::default().assert_matches(p, map, width);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn check_reports_both_maps() {
+        let mut p = PortionRenderer::<u8>::new(4, 4);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        let map = PixelMap::default().with('w', PIXEL_BLACK);
+        assert!(map.check(&p, &['r', 'x', '?', 'x'], 4).is_ok());
+        let e = map.check(&p, &['w', 'x'], 4).unwrap_err();
+        assert!(e.contains("[w, x, "));
+        assert!(e.contains("[r, x, "));
+    }
+}
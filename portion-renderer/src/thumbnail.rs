@@ -0,0 +1,104 @@
+use super::*;
+
+impl<T> PortionRenderer<T> {
+    /// the width and height of a thumbnail that is scale times smaller.
+    /// a partial block of pixels at the right/bottom edge still gets a pixel
+    pub fn thumbnail_size(&self, scale: u32) -> (u32, u32) {
+        if scale == 0 {
+            panic!("Thumbnail scale must be more than 0");
+        }
+        (self.width.div_ceil(scale), self.height.div_ceil(scale))
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// a copy of the pixel buffer that is scale times smaller, eg: for a
+    /// minimap or a live preview. every pixel of the thumbnail is the
+    /// average of the scale x scale block of pixels that it covers.
+    /// see thumbnail_size for its dimensions
+    pub fn render_thumbnail(&self, scale: u32) -> Vec<u8> {
+        let (width, height) = self.thumbnail_size(scale);
+        let mut thumbnail = vec![0; checked_buffer_len(width, height, self.indices_per_pixel)
+            .expect("Thumbnail dimensions overflow usize")];
+        let whole = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        self.update_thumbnail(&mut thumbnail, scale, &[whole]);
+        thumbnail
+    }
+
+    /// averages the pixels of a thumbnail from render_thumbnail again, but only
+    /// the ones whose block of pixels overlaps one of the dirty rects,
+    /// eg: the rects from take_dirty_rects.
+    /// panics if the thumbnail is not the size of a thumbnail at scale
+    pub fn update_thumbnail(&self, thumbnail: &mut [u8], scale: u32, dirty: &[Rect]) {
+        let (width, height) = self.thumbnail_size(scale);
+        let ipp = self.indices_per_pixel as usize;
+        let expected_len = width as usize * height as usize * ipp;
+        if thumbnail.len() != expected_len {
+            panic!("Thumbnail has length {} but a thumbnail at scale {} has length {}", thumbnail.len(), scale, expected_len);
+        }
+        let screen = Rect { x: 0, y: 0, w: self.width, h: self.height };
+        for rect in dirty.iter().filter_map(|r| r.intersection(screen)) {
+            // the blocks that the first and last pixel of the rect are in
+            let (x_start, y_start) = (rect.x / scale, rect.y / scale);
+            let (x_end, y_end) = ((rect.x + rect.w - 1) / scale, (rect.y + rect.h - 1) / scale);
+            for y in y_start..=y_end {
+                for x in x_start..=x_end {
+                    let index = red_index(x, y, width, self.indices_per_pixel);
+                    let block = Rect { x: x * scale, y: y * scale, w: scale, h: scale }.clamp_to(&screen);
+                    thumbnail[index..index + ipp].copy_from_slice(&self.average_of(block));
+                }
+            }
+        }
+    }
+
+    /// the average of every pixel in block, which must be on screen
+    fn average_of(&self, block: Rect) -> [u8; 4] {
+        let mut sums = [0u64; 4];
+        for y in block.y..block.y + block.h {
+            let row_start = red_index(block.x, y, self.width, self.indices_per_pixel);
+            let row_end = row_start + block.w as usize * self.indices_per_pixel as usize;
+            for pixel in self.pixel_buffer[row_start..row_end].chunks_exact(4) {
+                sums.iter_mut().zip(pixel).for_each(|(sum, v)| *sum += *v as u64);
+            }
+        }
+        let count = block.area().max(1);
+        let mut average = [0u8; 4];
+        average.iter_mut().zip(sums.iter()).for_each(|(a, sum)| *a = (sum / count) as u8);
+        average
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thumbnail_pixels_are_the_average_of_their_block() {
+        // 5 pixels wide, so that the last column of blocks is partial
+        let mut p = PortionRenderer::<u8>::new_ex(5, 4, 1, 1, PixelFormatEnum::RGBA8888);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 5, h: 4 }, PIXEL_BLUE);
+        p.create_object_from_color(1, Rect { x: 0, y: 0, w: 1, h: 2 }, PIXEL_RED);
+        p.draw_all_layers();
+        assert_eq!(p.thumbnail_size(2), (3, 2));
+
+        let mut thumbnail = p.render_thumbnail(2);
+        assert_eq!(thumbnail.len(), 3 * 2 * 4);
+        // half of the top left block is red
+        assert_eq!(&thumbnail[0..4], &[127, 0, 127, 255]);
+        assert_eq!(&thumbnail[4..8], &[0, 0, 255, 255]);
+        // the last column is a block of 1x2 pixels
+        assert_eq!(&thumbnail[8..12], &[0, 0, 255, 255]);
+
+        // only the blocks that overlap the dirty rect are averaged again
+        let green = p.create_object_from_color(1, Rect { x: 2, y: 2, w: 3, h: 2 }, PIXEL_GREEN);
+        p.draw_all_layers();
+        p.take_dirty_rects();
+        let before = thumbnail.clone();
+        p.update_thumbnail(&mut thumbnail, 2, &[Rect { x: 4, y: 3, w: 1, h: 1 }]);
+        assert_eq!(&thumbnail[20..24], &[0, 255, 0, 255]);
+        assert_eq!(&thumbnail[..20], &before[..20]);
+
+        p.update_thumbnail(&mut thumbnail, 2, &[p.get_object(green).unwrap().current_bounds]);
+        assert_eq!(thumbnail, p.render_thumbnail(2));
+    }
+}
@@ -0,0 +1,251 @@
+use super::*;
+
+/// what a Timeline animates
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Property {
+    /// the world x of the top left of the object, in pixels
+    X,
+    /// the world y of the top left of the object, in pixels
+    Y,
+    /// in degrees
+    Rotation,
+    /// the alpha of the color of a color object, from 0 to 255.
+    /// textured objects dont have an opacity
+    Opacity,
+}
+
+/// how the value changes on the way to a keyframe
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Easing {
+    Linear,
+    /// starts slow
+    EaseIn,
+    /// ends slow
+    EaseOut,
+    /// starts and ends slow
+    EaseInOut,
+    /// keeps the previous value until the keyframe's time
+    Step,
+}
+
+impl Easing {
+    /// t is how far along, from 0 to 1
+    fn apply(self, t: f32) -> f32 {
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Step => if t >= 1.0 { 1.0 } else { 0.0 },
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keyframe {
+    /// in seconds from the start of the timeline
+    pub time: f32,
+    pub value: f32,
+    /// how the value gets to this keyframe from the one before it
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct Track {
+    object_index: usize,
+    property: Property,
+    /// ordered by time
+    keyframes: Vec<Keyframe>,
+}
+
+impl Track {
+    /// the value at time, which is the first/last value
+    /// before the first/after the last keyframe
+    fn value_at(&self, time: f32) -> f32 {
+        let next = self.keyframes.iter().position(|k| k.time > time);
+        match next {
+            Some(0) => self.keyframes[0].value,
+            Some(next) => {
+                let (from, to) = (&self.keyframes[next - 1], &self.keyframes[next]);
+                let t = to.easing.apply((time - from.time) / (to.time - from.time));
+                from.value + (to.value - from.value) * t
+            }
+            None => self.keyframes.last().map(|k| k.value).unwrap_or(0.0),
+        }
+    }
+}
+
+/// keyframes for the properties of objects, eg: for a cutscene.
+/// advance moves the timeline forward and sets the properties
+/// of the objects to their values at that time. only the properties
+/// that changed are set, so only what moved gets drawn again.
+///
+/// ```
+/// # use portion_renderer::*;
+/// let mut p = PortionRenderer::<u8>::new(16, 16);
+/// let o = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+/// let mut timeline = Timeline::new()
+///     .position(o, 0.0, 0, 0, Easing::Linear)
+///     .position(o, 1.0, 8, 4, Easing::EaseInOut)
+///     .keyframe(o, Property::Opacity, 1.0, 255.0, Easing::Linear)
+///     .keyframe(o, Property::Opacity, 1.5, 0.0, Easing::Linear);
+/// timeline.advance(&mut p, 0.5);
+/// p.draw_all_layers();
+/// ```
+#[derive(Debug, Default, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timeline {
+    time: f32,
+    looping: bool,
+    tracks: Vec<Track>,
+}
+
+impl Timeline {
+    pub fn new() -> Timeline {
+        Timeline::default()
+    }
+
+    /// the property of the object is value at time seconds.
+    /// a keyframe at the same time as another one of the
+    /// same property of the same object replaces it
+    pub fn keyframe(mut self, object_index: usize, property: Property, time: f32, value: f32, easing: Easing) -> Self {
+        let keyframe = Keyframe { time, value, easing };
+        let track = match self.tracks.iter().position(|t| t.object_index == object_index && t.property == property) {
+            Some(track) => &mut self.tracks[track],
+            None => {
+                self.tracks.push(Track { object_index, property, keyframes: vec![] });
+                self.tracks.last_mut().unwrap()
+            }
+        };
+        match track.keyframes.iter().position(|k| k.time >= time) {
+            Some(i) if track.keyframes[i].time == time => track.keyframes[i] = keyframe,
+            Some(i) => track.keyframes.insert(i, keyframe),
+            None => track.keyframes.push(keyframe),
+        }
+        self
+    }
+
+    /// a keyframe for both X and Y
+    pub fn position(self, object_index: usize, time: f32, x: i32, y: i32, easing: Easing) -> Self {
+        self.keyframe(object_index, Property::X, time, x as f32, easing)
+            .keyframe(object_index, Property::Y, time, y as f32, easing)
+    }
+
+    /// when looping, the timeline starts over once it is past its duration
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    /// the time of the last keyframe
+    pub fn duration(&self) -> f32 {
+        self.tracks.iter()
+            .filter_map(|t| t.keyframes.last())
+            .map(|k| k.time)
+            .fold(0.0, f32::max)
+    }
+
+    /// in seconds since the start
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// true once a timeline that isnt looping is past its last keyframe
+    pub fn is_finished(&self) -> bool {
+        !self.looping && self.time >= self.duration()
+    }
+
+    /// moves the timeline forward by dt seconds, see seek
+    pub fn advance(&mut self, renderer: &mut PortionRenderer<u8>, dt: f32) {
+        let time = self.time + dt;
+        self.seek(renderer, time);
+    }
+
+    /// sets the properties of the objects to their values at time.
+    /// the tracks of objects that dont exist anymore are skipped.
+    /// panics if an object with an Opacity track has a texture
+    pub fn seek(&mut self, renderer: &mut PortionRenderer<u8>, time: f32) {
+        let duration = self.duration();
+        self.time = if self.looping && duration > 0.0 { time % duration } else { time.max(0.0) };
+        for track in self.tracks.iter() {
+            let object = match renderer.get_object(track.object_index) {
+                Some(object) => object,
+                None => continue,
+            };
+            let value = track.value_at(self.time);
+            match track.property {
+                Property::X | Property::Y => {
+                    let (mut x, mut y) = (object.world_x, object.world_y);
+                    if track.property == Property::X {
+                        x = value.round() as i32;
+                    } else {
+                        y = value.round() as i32;
+                    }
+                    if (x, y) != (object.world_x, object.world_y) {
+                        renderer.set_object_position(track.object_index, x, y);
+                    }
+                }
+                Property::Rotation => {
+                    if value != object.rotation {
+                        renderer.set_object_rotation(track.object_index, value);
+                    }
+                }
+                Property::Opacity => {
+                    let mut color = match object.texture_color {
+                        Some(color) => color,
+                        None => panic!("Cannot animate the opacity of object {} because it has a texture", track.object_index),
+                    };
+                    let alpha = value.round().clamp(0.0, 255.0) as u8;
+                    if alpha != color.a {
+                        color.a = alpha;
+                        renderer.set_object_color(track.object_index, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn advance_sets_the_eased_values() {
+        let mut p = PortionRenderer::<u8>::new(16, 16);
+        let o = p.create_object_from_color(0, Rect { x: 0, y: 0, w: 2, h: 2 }, PIXEL_RED);
+        let mut timeline = Timeline::new()
+            .position(o, 1.0, 10, 0, Easing::Linear)
+            .position(o, 0.0, 0, 0, Easing::Linear)
+            .keyframe(o, Property::Rotation, 0.0, 0.0, Easing::Linear)
+            .keyframe(o, Property::Rotation, 2.0, 90.0, Easing::Step)
+            .keyframe(o, Property::Opacity, 1.0, 255.0, Easing::Linear)
+            .keyframe(o, Property::Opacity, 2.0, 0.0, Easing::EaseIn);
+        assert_eq!(timeline.duration(), 2.0);
+
+        timeline.advance(&mut p, 0.5);
+        assert_eq!(p.get_object(o).unwrap().world_x, 5);
+        assert_eq!(p.get_object(o).unwrap().rotation, 0.0);
+        timeline.advance(&mut p, 1.0);
+        assert_eq!(p.get_object(o).unwrap().world_x, 10);
+        // a quarter of the way to 0, because of the easing
+        assert_eq!(p.get_object(o).unwrap().texture_color.unwrap().a, 191);
+        assert!(!timeline.is_finished());
+
+        timeline.advance(&mut p, 1.0);
+        assert!(timeline.is_finished());
+        assert_eq!(p.get_object(o).unwrap().rotation, 90.0);
+        assert_eq!(p.get_object(o).unwrap().texture_color.unwrap().a, 0);
+
+        // nothing changes past the end, so nothing is drawn again
+        p.draw_all_layers();
+        p.take_dirty_rects();
+        timeline.advance(&mut p, 1.0);
+        p.draw_all_layers();
+        assert!(p.take_dirty_rects().is_empty());
+    }
+}
@@ -1,7 +1,7 @@
 use std::f64::consts::PI;
 
 use super::RgbaPixel;
-use super::get_red_index;
+use super::red_index;
 use super::Matrix;
 
 macro_rules! rotate_point {
@@ -117,13 +117,37 @@ pub fn interpolate_nearest(
         default
     } else {
         // TODO: pixel format!
-        let red_index = get_red_index!(rx as u32, ry as u32, texture_width, 4) as usize;
+        let red_index = red_index(rx as u32, ry as u32, texture_width, 4);
         // println!("red index: {}", red_index);
         RgbaPixel {
             r: texture[red_index],
             g: texture[red_index + 1],
             b: texture[red_index + 2],
-            a: texture[red_index + 2],
+            a: texture[red_index + 3],
+        }
+    }
+}
+
+/// samples the texture at (x, y) with the given filter. pixels outside of
+/// the texture are the default. AreaAverage is sampled as Bilinear,
+/// since a single point doesnt cover an area
+pub fn interpolate_filtered(
+    filter: Filter,
+    texture: &[u8],
+    texture_width: u32,
+    texture_height: u32,
+    x: f32,
+    y: f32,
+    default: RgbaPixel
+) -> RgbaPixel {
+    match filter {
+        Filter::Nearest => interpolate_nearest(texture, texture_width, texture_height, x, y, default),
+        Filter::Bilinear | Filter::AreaAverage => {
+            let (rx, ry) = (x.round(), y.round());
+            if rx < 0f32 || rx >= texture_width as f32 || ry < 0f32 || ry >= texture_height as f32 {
+                return default;
+            }
+            (&sample_bilinear_clamped(texture, texture_width, texture_height, x, y)[..]).into()
         }
     }
 }
@@ -175,15 +199,10 @@ fn interpolate_bilinear(
     let right_u32 = right as u32;
     let bottom_u32 = bottom as u32;
 
-    let top_left_red_index = get_red_index!(left_u32, top_u32, texture_width, indices_per_pixel);
-    let top_right_red_index = get_red_index!(right_u32, top_u32, texture_width, indices_per_pixel);
-    let bottom_left_red_index = get_red_index!(left_u32, bottom_u32, texture_width, indices_per_pixel);
-    let bottom_right_red_index = get_red_index!(right_u32, bottom_u32, texture_width, indices_per_pixel);
-
-    let top_left_red_index = top_left_red_index as usize;
-    let top_right_red_index = top_right_red_index as usize;
-    let bottom_left_red_index = bottom_left_red_index as usize;
-    let bottom_right_red_index = bottom_right_red_index as usize;
+    let top_left_red_index = red_index(left_u32, top_u32, texture_width, indices_per_pixel);
+    let top_right_red_index = red_index(right_u32, top_u32, texture_width, indices_per_pixel);
+    let bottom_left_red_index = red_index(left_u32, bottom_u32, texture_width, indices_per_pixel);
+    let bottom_right_red_index = red_index(right_u32, bottom_u32, texture_width, indices_per_pixel);
 
     let top_left = &texture[top_left_red_index..top_left_red_index+indices_per_pixel_usize];
     let top_right = &texture[top_right_red_index..top_right_red_index+indices_per_pixel_usize];
@@ -250,6 +269,245 @@ pub fn transform_texture(
 }
 
 
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Filter {
+    /// picks the closest source pixel. fast, and keeps
+    /// hard edges, which is usually what you want for pixel art
+    Nearest,
+    /// blends the 4 closest source pixels
+    Bilinear,
+    /// averages every source pixel that the destination pixel covers,
+    /// weighted by how much it covers. best quality for downscaling
+    AreaAverage,
+}
+
+/// scales an RGBA8888 texture of size texture_width x texture_height
+/// into a new texture of size new_width x new_height
+pub fn scale_texture(
+    texture: &[u8],
+    texture_width: u32,
+    texture_height: u32,
+    new_width: u32,
+    new_height: u32,
+    filter: Filter,
+) -> Vec<u8> {
+    let indices_per_pixel = 4;
+    let mut dest = vec![0; indices_per_pixel * (new_width * new_height) as usize];
+    if texture_width == 0 || texture_height == 0 || new_width == 0 || new_height == 0 {
+        return dest;
+    }
+
+    // how many source pixels correspond to one destination pixel
+    let ratio_x = texture_width as f32 / new_width as f32;
+    let ratio_y = texture_height as f32 / new_height as f32;
+    let pitch = indices_per_pixel * new_width as usize;
+
+    dest.chunks_mut(pitch).enumerate().for_each(|(y, row)| {
+        for (x, slice) in row.chunks_mut(indices_per_pixel).enumerate() {
+            let pixel = match filter {
+                Filter::Nearest => {
+                    let sx = ((x as f32 + 0.5) * ratio_x) as u32;
+                    let sy = ((y as f32 + 0.5) * ratio_y) as u32;
+                    let sx = sx.min(texture_width - 1);
+                    let sy = sy.min(texture_height - 1);
+                    let red_index = red_index(sx, sy, texture_width, 4);
+                    [texture[red_index], texture[red_index + 1], texture[red_index + 2], texture[red_index + 3]]
+                }
+                Filter::Bilinear => sample_bilinear_clamped(
+                    texture, texture_width, texture_height,
+                    (x as f32 + 0.5) * ratio_x - 0.5,
+                    (y as f32 + 0.5) * ratio_y - 0.5,
+                ),
+                Filter::AreaAverage => sample_area(
+                    texture, texture_width, texture_height,
+                    x as f32 * ratio_x, y as f32 * ratio_y,
+                    ratio_x, ratio_y,
+                ),
+            };
+            slice.copy_from_slice(&pixel);
+        }
+    });
+
+    dest
+}
+
+/// like interpolate_bilinear, but instead of returning a default
+/// pixel at the edges, the edge pixels get repeated. also blends the alpha channel
+fn sample_bilinear_clamped(
+    texture: &[u8],
+    texture_width: u32,
+    texture_height: u32,
+    x: f32,
+    y: f32,
+) -> [u8; 4] {
+    let max_x = (texture_width - 1) as f32;
+    let max_y = (texture_height - 1) as f32;
+    let x = x.max(0.0).min(max_x);
+    let y = y.max(0.0).min(max_y);
+    let left = x.floor();
+    let top = y.floor();
+    let right = (left + 1.0).min(max_x);
+    let bottom = (top + 1.0).min(max_y);
+    let right_weight = x - left;
+    let bottom_weight = y - top;
+
+    let tl = red_index(left as u32, top as u32, texture_width, 4);
+    let tr = red_index(right as u32, top as u32, texture_width, 4);
+    let bl = red_index(left as u32, bottom as u32, texture_width, 4);
+    let br = red_index(right as u32, bottom as u32, texture_width, 4);
+
+    let mut out = [0; 4];
+    for i in 0..4 {
+        let top = (1.0 - right_weight) * texture[tl + i] as f32 + right_weight * texture[tr + i] as f32;
+        let bottom = (1.0 - right_weight) * texture[bl + i] as f32 + right_weight * texture[br + i] as f32;
+        let value = (1.0 - bottom_weight) * top + bottom_weight * bottom;
+        out[i] = value.round() as u8;
+    }
+    out
+}
+
+/// averages the source pixels in the box starting at (x, y)
+/// of size (w, h). pixels that are partially covered by the box
+/// contribute proportionally to how much of them is covered.
+fn sample_area(
+    texture: &[u8],
+    texture_width: u32,
+    texture_height: u32,
+    x: f32,
+    y: f32,
+    w: f32,
+    h: f32,
+) -> [u8; 4] {
+    let mut sum = [0f32; 4];
+    let mut total_weight = 0f32;
+    // rounding errors could put the end just past the texture
+    let x_end = (x + w).min(texture_width as f32);
+    let y_end = (y + h).min(texture_height as f32);
+    let mut sy = y.floor();
+    while sy < y_end {
+        let y_weight = (sy + 1.0).min(y_end) - sy.max(y);
+        let mut sx = x.floor();
+        while sx < x_end {
+            let x_weight = (sx + 1.0).min(x_end) - sx.max(x);
+            let weight = x_weight * y_weight;
+            let red_index = red_index(sx as u32, sy as u32, texture_width, 4);
+            for i in 0..4 {
+                sum[i] += texture[red_index + i] as f32 * weight;
+            }
+            total_weight += weight;
+            sx += 1.0;
+        }
+        sy += 1.0;
+    }
+
+    let mut out = [0; 4];
+    for i in 0..4 {
+        out[i] = (sum[i] / total_weight).round() as u8;
+    }
+    out
+}
+
+/// A grid of control points used by warp_mesh.
+/// The output texture is divided into cols x rows equally sized cells,
+/// and each cell corner has a control point which is the position in the
+/// source texture that this corner should sample from. Pixels inside a cell
+/// bilinearly interpolate their sample position from the 4 corners of their cell.
+/// So there are (cols + 1) * (rows + 1) points, stored row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mesh {
+    pub cols: u32,
+    pub rows: u32,
+    pub points: Vec<(f32, f32)>,
+}
+
+impl Mesh {
+    /// creates a mesh that does not deform anything, ie:
+    /// warping a texture with this mesh just scales it to the output size.
+    /// start with this, and then move the points around to deform the texture
+    pub fn identity(cols: u32, rows: u32, texture_width: u32, texture_height: u32) -> Mesh {
+        let cols = cols.max(1);
+        let rows = rows.max(1);
+        let mut points = Vec::with_capacity(((cols + 1) * (rows + 1)) as usize);
+        for j in 0..=rows {
+            for i in 0..=cols {
+                points.push((
+                    i as f32 * texture_width as f32 / cols as f32,
+                    j as f32 * texture_height as f32 / rows as f32,
+                ));
+            }
+        }
+        Mesh { cols, rows, points }
+    }
+
+    #[inline(always)]
+    pub fn point(&self, col: u32, row: u32) -> (f32, f32) {
+        self.points[(row * (self.cols + 1) + col) as usize]
+    }
+
+    #[inline(always)]
+    pub fn point_mut(&mut self, col: u32, row: u32) -> &mut (f32, f32) {
+        &mut self.points[(row * (self.cols + 1) + col) as usize]
+    }
+}
+
+/// deforms the texture by the control points of the mesh, and writes
+/// the result into out_texture. pixels that sample from outside of the
+/// texture will be set to the default_pixel. does nothing if the mesh
+/// has no cells, or fewer than (cols + 1) * (rows + 1) points
+pub fn warp_mesh(
+    texture: &[u8],
+    texture_width: u32,
+    texture_height: u32,
+    mesh: &Mesh,
+    default_pixel: RgbaPixel,
+    out_texture: &mut [u8],
+    out_width: u32,
+) {
+    let indices_per_pixel = 4;
+    let pitch = indices_per_pixel * out_width as usize;
+    if pitch == 0 || texture_width == 0 || texture_height == 0 {
+        return;
+    }
+    // the fields are public, so the mesh might not be from Mesh::identity
+    if mesh.cols == 0 || mesh.rows == 0 || mesh.points.len() < ((mesh.cols + 1) * (mesh.rows + 1)) as usize {
+        return;
+    }
+    let out_height = out_texture.len() / pitch;
+    let cell_w = out_width as f32 / mesh.cols as f32;
+    let cell_h = out_height as f32 / mesh.rows as f32;
+    let max_x = (texture_width - 1) as f32;
+    let max_y = (texture_height - 1) as f32;
+    let default = [default_pixel.r, default_pixel.g, default_pixel.b, default_pixel.a];
+
+    out_texture.chunks_mut(pitch).enumerate().for_each(|(y, row)| {
+        let gy = y as f32 / cell_h;
+        let cell_row = (gy as u32).min(mesh.rows - 1);
+        let v = gy - cell_row as f32;
+        for (x, slice) in row.chunks_mut(indices_per_pixel).enumerate() {
+            let gx = x as f32 / cell_w;
+            let cell_col = (gx as u32).min(mesh.cols - 1);
+            let u = gx - cell_col as f32;
+
+            let p00 = mesh.point(cell_col, cell_row);
+            let p10 = mesh.point(cell_col + 1, cell_row);
+            let p01 = mesh.point(cell_col, cell_row + 1);
+            let p11 = mesh.point(cell_col + 1, cell_row + 1);
+            let top = (p00.0 + (p10.0 - p00.0) * u, p00.1 + (p10.1 - p00.1) * u);
+            let bottom = (p01.0 + (p11.0 - p01.0) * u, p01.1 + (p11.1 - p01.1) * u);
+            let px = top.0 + (bottom.0 - top.0) * v;
+            let py = top.1 + (bottom.1 - top.1) * v;
+
+            let pixel = if px < 0.0 || px > max_x || py < 0.0 || py > max_y {
+                default
+            } else {
+                sample_bilinear_clamped(texture, texture_width, texture_height, px, py)
+            };
+            slice.copy_from_slice(&pixel);
+        }
+    });
+}
+
 #[cfg(test)]
 mod transform_tests {
     use super::*;
@@ -315,4 +573,83 @@ mod transform_tests {
         assert_eq!(new_width, 4);
         assert_eq!(new_height, 4);
     }
+
+    #[test]
+    fn scale_texture_nearest_upscales() {
+        let texture = [
+            [1, 1, 1, 1], [2, 2, 2, 2],
+            [3, 3, 3, 3], [4, 4, 4, 4],
+        ].concat();
+        let scaled = scale_texture(&texture, 2, 2, 4, 4, Filter::Nearest);
+        let expected = [
+            [1, 1, 1, 1], [1, 1, 1, 1], [2, 2, 2, 2], [2, 2, 2, 2],
+            [1, 1, 1, 1], [1, 1, 1, 1], [2, 2, 2, 2], [2, 2, 2, 2],
+            [3, 3, 3, 3], [3, 3, 3, 3], [4, 4, 4, 4], [4, 4, 4, 4],
+            [3, 3, 3, 3], [3, 3, 3, 3], [4, 4, 4, 4], [4, 4, 4, 4],
+        ].concat();
+        assert_eq!(scaled, expected);
+    }
+
+    #[test]
+    fn scale_texture_area_average_downscales() {
+        let texture = [
+            [0, 0, 0, 255], [100, 100, 100, 255], [8, 8, 8, 8], [8, 8, 8, 8],
+            [0, 0, 0, 255], [100, 100, 100, 255], [8, 8, 8, 8], [8, 8, 8, 8],
+        ].concat();
+        let scaled = scale_texture(&texture, 4, 2, 2, 1, Filter::AreaAverage);
+        let expected = [
+            [50, 50, 50, 255], [8, 8, 8, 8],
+        ].concat();
+        assert_eq!(scaled, expected);
+    }
+
+    #[test]
+    fn scale_texture_bilinear_blends() {
+        let texture = [
+            [0, 0, 0, 0], [100, 100, 100, 100],
+        ].concat();
+        // same size should be identical
+        let scaled = scale_texture(&texture, 2, 1, 2, 1, Filter::Bilinear);
+        assert_eq!(scaled, texture);
+
+        // the middle pixel of 3 should be half way between the two
+        let scaled = scale_texture(&texture, 2, 1, 3, 1, Filter::Bilinear);
+        assert_eq!(&scaled[4..8], &[50, 50, 50, 50]);
+    }
+
+    #[test]
+    fn warp_mesh_identity_does_nothing() {
+        let texture = [
+            [1, 1, 1, 1], [2, 2, 2, 2], [3, 3, 3, 3],
+            [4, 4, 4, 4], [5, 5, 5, 5], [6, 6, 6, 6],
+        ].concat();
+        let mesh = Mesh::identity(3, 2, 3, 2);
+        let mut out = vec![0; texture.len()];
+        warp_mesh(&texture, 3, 2, &mesh, PIXEL_BLACK, &mut out, 3);
+        assert_eq!(out, texture);
+    }
+
+    #[test]
+    fn warp_mesh_can_shift_cells() {
+        let texture = [
+            [1, 1, 1, 1], [2, 2, 2, 2], [3, 3, 3, 3],
+        ].concat();
+        let mut mesh = Mesh::identity(1, 1, 3, 1);
+        // sample everything one pixel to the left, so the
+        // output is shifted right by one
+        for point in mesh.points.iter_mut() {
+            point.0 -= 1.0;
+        }
+        let mut out = vec![0; texture.len()];
+        warp_mesh(&texture, 3, 1, &mesh, PIXEL_BLACK, &mut out, 3);
+        let expected = [
+            [0, 0, 0, 255], [1, 1, 1, 1], [2, 2, 2, 2],
+        ].concat();
+        assert_eq!(out, expected);
+
+        // a mesh without cells leaves out_texture as it was
+        let mesh = Mesh { cols: 0, rows: 1, points: vec![(0.0, 0.0), (0.0, 1.0)] };
+        warp_mesh(&texture, 3, 1, &mesh, PIXEL_BLACK, &mut out, 3);
+        assert_eq!(out, expected);
+    }
 }
@@ -0,0 +1,221 @@
+use super::*;
+
+/// which way the edge of a Wipe moves
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum WipeDirection {
+    Right,
+    Left,
+    Down,
+    Up,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TransitionEffect {
+    /// every pixel blends from the old screen to the new one
+    Crossfade,
+    /// the new screen slides in behind an edge that moves across the screen
+    Wipe(WipeDirection),
+    /// the pixels switch to the new screen one at a time, in an
+    /// order that looks random, but is the same for the same seed
+    Dissolve { seed: u32 },
+}
+
+/// goes from what is on screen now to another full screen of pixels,
+/// eg: the next level drawn by a second renderer, or a background
+/// image. each step only writes, and marks as dirty, the
+/// pixels that changed since the last step.
+///
+/// ```
+/// # use portion_renderer::*;
+/// let mut p = PortionRenderer::<u8>::new(16, 16);
+/// let next_scene = vec![255; 16 * 16 * 4];
+/// let mut transition = Transition::new(&p, next_scene, TransitionEffect::Crossfade, 0.5);
+/// while !transition.is_finished() {
+///     transition.step(&mut p, 1.0 / 60.0);
+///     let dirty = p.take_dirty_rects();
+///     // present the dirty rects
+/// }
+/// ```
+///
+/// the transition writes to the pixel buffer directly, so drawing
+/// objects during it draws them over it
+pub struct Transition {
+    effect: TransitionEffect,
+    duration: f32,
+    time: f32,
+    from: Vec<u8>,
+    to: Vec<u8>,
+    width: u32,
+    /// the pixels that differ between from and to. nothing outside of it
+    /// changes, or None if the two screens are the same
+    changed: Option<Rect>,
+}
+
+impl Transition {
+    /// a transition of duration seconds from the renderer's pixel buffer
+    /// to target, which is in the same format and the same size.
+    /// panics if target isnt the same size as the pixel buffer
+    pub fn new(renderer: &PortionRenderer<u8>, target: Vec<u8>, effect: TransitionEffect, duration: f32) -> Transition {
+        if target.len() != renderer.pixel_buffer.len() {
+            panic!("Cannot transition to {} pixel values because the pixel buffer has {}", target.len(), renderer.pixel_buffer.len());
+        }
+        let from = renderer.pixel_buffer.clone();
+        let (width, height) = (renderer.width, renderer.height);
+        let mut changed: Option<Rect> = None;
+        for y in 0..height {
+            let row = red_index(0, y, width, 4)..red_index(0, y + 1, width, 4);
+            let (old, new) = (&from[row.clone()], &target[row]);
+            if old == new {
+                continue;
+            }
+            let first = old.chunks_exact(4).zip(new.chunks_exact(4)).position(|(a, b)| a != b).unwrap() as u32;
+            let last = old.chunks_exact(4).zip(new.chunks_exact(4)).rposition(|(a, b)| a != b).unwrap() as u32;
+            let row_changed = Rect { x: first, y, w: last + 1 - first, h: 1 };
+            changed = Some(changed.map(|r| r.union(&row_changed)).unwrap_or(row_changed));
+        }
+        Transition { effect, duration, time: 0.0, from, to: target, width, changed }
+    }
+
+    /// in seconds since the start
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+
+    /// how far along the transition is, from 0 to 1
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            (self.time / self.duration).min(1.0)
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// moves the transition forward by dt seconds, and writes the pixels
+    /// that changed because of it to the renderer's pixel buffer
+    pub fn step(&mut self, renderer: &mut PortionRenderer<u8>, dt: f32) {
+        // a transition with no duration is finished before it starts,
+        // but the first step still has to write the target
+        let before = if self.time > 0.0 { self.progress() } else { 0.0 };
+        if before >= 1.0 {
+            return;
+        }
+        self.time += dt.max(0.0);
+        let now = self.progress();
+        let changed = match self.changed {
+            Some(changed) => changed,
+            None => return,
+        };
+        match self.effect {
+            TransitionEffect::Crossfade => {
+                for y in changed.y..changed.y + changed.h {
+                    let start = red_index(changed.x, y, self.width, 4);
+                    let end = red_index(changed.x + changed.w, y, self.width, 4);
+                    let (from, to) = (&self.from[start..end], &self.to[start..end]);
+                    let pixels = from.iter().zip(to.iter())
+                        .map(|(a, b)| (*a as f32 + (*b as f32 - *a as f32) * now).round() as u8);
+                    renderer.pixel_buffer[start..end].iter_mut().zip(pixels).for_each(|(p, v)| *p = v);
+                }
+                renderer.portioner.take_rect(changed);
+            }
+            TransitionEffect::Wipe(direction) => {
+                // the part of the screen that the edge moved over since the last step
+                let (w, h) = (renderer.width, renderer.height);
+                let at = |progress: f32, len: u32| (progress * len as f32).round() as u32;
+                let swept = match direction {
+                    WipeDirection::Right => Rect { x: at(before, w), y: 0, w: at(now, w) - at(before, w), h },
+                    WipeDirection::Left => Rect { x: w - at(now, w), y: 0, w: at(now, w) - at(before, w), h },
+                    WipeDirection::Down => Rect { x: 0, y: at(before, h), w, h: at(now, h) - at(before, h) },
+                    WipeDirection::Up => Rect { x: 0, y: h - at(now, h), w, h: at(now, h) - at(before, h) },
+                };
+                let swept = match swept.intersection(changed) {
+                    Some(swept) if !swept.is_empty() => swept,
+                    _ => return,
+                };
+                for y in swept.y..swept.y + swept.h {
+                    let start = red_index(swept.x, y, self.width, 4);
+                    let end = red_index(swept.x + swept.w, y, self.width, 4);
+                    renderer.pixel_buffer[start..end].copy_from_slice(&self.to[start..end]);
+                }
+                renderer.portioner.take_rect(swept);
+            }
+            TransitionEffect::Dissolve { seed } => {
+                for y in changed.y..changed.y + changed.h {
+                    for x in changed.x..changed.x + changed.w {
+                        let threshold = dissolve_threshold(x, y, seed);
+                        if threshold < before || (threshold >= now && now < 1.0) {
+                            continue;
+                        }
+                        let i = red_index(x, y, self.width, 4);
+                        renderer.pixel_buffer[i..i + 4].copy_from_slice(&self.to[i..i + 4]);
+                        renderer.portioner.take_pixel(x, y);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// when the pixel at (x, y) switches, from 0 to 1
+fn dissolve_threshold(x: u32, y: u32, seed: u32) -> f32 {
+    let mut h = x.wrapping_mul(0x9e37_79b9) ^ y.wrapping_mul(0x85eb_ca6b) ^ seed;
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7feb_352d);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846c_a68b);
+    h ^= h >> 16;
+    (h >> 8) as f32 / (1 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn red_screen() -> Vec<u8> {
+        [255, 0, 0, 255].repeat(8 * 8)
+    }
+
+    #[test]
+    fn wipe_only_marks_what_the_edge_moved_over() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        p.take_dirty_rects();
+        let mut transition = Transition::new(&p, red_screen(), TransitionEffect::Wipe(WipeDirection::Right), 1.0);
+        transition.step(&mut p, 0.5);
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 4, h: 8 }]);
+        assert_eq!(RgbaPixel::from(&p[(3, 7)]), PIXEL_RED);
+        assert_eq!(&p[(4, 0)], &[0, 0, 0, 0]);
+        transition.step(&mut p, 0.5);
+        assert!(transition.is_finished());
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 4, y: 0, w: 4, h: 8 }]);
+        assert_eq!(p.pixel_buffer, red_screen());
+    }
+
+    #[test]
+    fn crossfade_and_dissolve_end_on_the_target() {
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let mut transition = Transition::new(&p, red_screen(), TransitionEffect::Crossfade, 1.0);
+        transition.step(&mut p, 0.5);
+        assert_eq!(&p[(5, 5)], &[128, 0, 0, 128]);
+        transition.step(&mut p, 1.0);
+        assert_eq!(p.pixel_buffer, red_screen());
+
+        let mut p = PortionRenderer::<u8>::new(8, 8);
+        let mut transition = Transition::new(&p, red_screen(), TransitionEffect::Dissolve { seed: 7 }, 1.0);
+        transition.step(&mut p, 0.5);
+        let switched = p.pixel_buffer.chunks_exact(4).filter(|px| px[0] == 255).count();
+        assert!(switched > 8 && switched < 56);
+        transition.step(&mut p, 0.5);
+        assert_eq!(p.pixel_buffer, red_screen());
+
+        // nothing changes when the screens are the same
+        p.take_dirty_rects();
+        let mut transition = Transition::new(&p, red_screen(), TransitionEffect::Crossfade, 1.0);
+        transition.step(&mut p, 1.0);
+        assert!(p.take_dirty_rects().is_empty());
+    }
+}
@@ -0,0 +1,177 @@
+use super::*;
+
+/// presents a renderer at a whole number multiple of its size,
+/// eg: a 320x180 renderer into a 1280x720 buffer at scale 4. every
+/// pixel becomes a scale x scale block of the same color, so pixel
+/// art stays sharp. only the dirty portions are copied each frame
+pub struct Upscaler {
+    scale: u32,
+    crt: Option<CrtEffect>,
+}
+
+/// darkens the target like an old CRT screen, see Upscaler::set_crt_effect.
+/// each one is in [0, 1], where 0 turns it off
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CrtEffect {
+    /// how much darker the bottom row of every block of pixels is,
+    /// which leaves a dark line between every row of the renderer.
+    /// there are no scanlines at scale 1
+    pub scanlines: f32,
+    /// how much darker the two channels of every pixel that dont
+    /// match its column are, where the columns of the target
+    /// go red, green, blue, red, etc. like the phosphors of a CRT
+    pub phosphor_mask: f32,
+}
+
+impl CrtEffect {
+    /// darkens the pixels of one row of the target, which is
+    /// row_in_block rows into its block, and starts at column x.
+    /// order is the pixel format's channel order
+    fn apply(&self, pixels: &mut [u8], ipp: usize, order: [usize; 4], scale: usize, row_in_block: usize, x: usize) {
+        let dim = if scale > 1 && row_in_block == scale - 1 { 1.0 - self.scanlines } else { 1.0 };
+        for (i, pixel) in pixels.chunks_exact_mut(ipp).enumerate() {
+            let lit = (x + i) % 3;
+            for (channel, index) in order.iter().take(3).enumerate() {
+                let weight = if channel == lit { dim } else { dim * (1.0 - self.phosphor_mask) };
+                pixel[*index] = (pixel[*index] as f32 * weight).round() as u8;
+            }
+        }
+    }
+}
+
+impl Upscaler {
+    /// panics if scale is 0
+    pub fn new(scale: u32) -> Upscaler {
+        if scale == 0 {
+            panic!("Upscaler scale must be more than 0");
+        }
+        Upscaler { scale, crt: None }
+    }
+
+    /// darkens the scaled up pixels like a CRT screen, or None to
+    /// copy them as they are. it is applied as pixels are copied, so
+    /// call present_all to apply it to what was already presented
+    pub fn set_crt_effect(&mut self, crt: Option<CrtEffect>) {
+        self.crt = crt.map(|crt| CrtEffect {
+            scanlines: crt.scanlines.clamp(0.0, 1.0),
+            phosphor_mask: crt.phosphor_mask.clamp(0.0, 1.0),
+        });
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    /// the size of the buffer that a renderer of
+    /// width x height gets presented into
+    pub fn target_size(&self, width: u32, height: u32) -> (u32, u32) {
+        (width * self.scale, height * self.scale)
+    }
+
+    /// the rects of the target buffer that the rects of the renderer cover
+    pub fn scale_rects(&self, rects: &[Rect]) -> Vec<Rect> {
+        let s = self.scale;
+        rects.iter().map(|r| Rect { x: r.x * s, y: r.y * s, w: r.w * s, h: r.h * s }).collect()
+    }
+
+    /// copies the dirty portions of the renderer into target, scaled up.
+    /// target has the renderer's pixel format and is target_size big.
+    /// returns the dirty rects of target, eg: to pass on as the damage
+    /// of a window. this takes the renderer's dirty rects, so dont
+    /// mix it with other presenters. panics if target is too small
+    pub fn present(&self, renderer: &mut PortionRenderer<u8>, target: &mut [u8]) -> Vec<Rect> {
+        let dirty = renderer.take_dirty_rects();
+        for rect in dirty.iter() {
+            self.copy_rect(renderer, *rect, target);
+        }
+        self.scale_rects(&dirty)
+    }
+
+    /// copies the whole renderer into target, eg: for the first frame,
+    /// or after the target was lost. the dirty rects are left as they are
+    pub fn present_all(&self, renderer: &PortionRenderer<u8>, target: &mut [u8]) {
+        let screen = Rect { x: 0, y: 0, w: renderer.width, h: renderer.height };
+        self.copy_rect(renderer, screen, target);
+    }
+
+    fn copy_rect(&self, renderer: &PortionRenderer<u8>, rect: Rect, target: &mut [u8]) {
+        let scale = self.scale as usize;
+        let ipp = renderer.indices_per_pixel as usize;
+        let (target_width, target_height) = self.target_size(renderer.width, renderer.height);
+        let target_pitch = target_width as usize * ipp;
+        let target_len = target_pitch * target_height as usize;
+        if target.len() < target_len {
+            panic!("Upscaler target has length {} but needs {}", target.len(), target_len);
+        }
+        let row_len = rect.w as usize * scale * ipp;
+        let order = renderer.pixel_format.channel_order();
+        let mut scaled_row = Vec::with_capacity(row_len);
+        let mut crt_row = vec![];
+        for y in rect.y..rect.y + rect.h {
+            let start = red_index(rect.x, y, renderer.width, renderer.indices_per_pixel);
+            let src = &renderer.pixel_buffer[start..start + rect.w as usize * ipp];
+            scaled_row.clear();
+            for pixel in src.chunks_exact(ipp) {
+                for _ in 0..scale {
+                    scaled_row.extend_from_slice(pixel);
+                }
+            }
+            let dest_x = rect.x as usize * scale * ipp;
+            for row in 0..scale {
+                let dest_start = (y as usize * scale + row) * target_pitch + dest_x;
+                let dest = &mut target[dest_start..dest_start + row_len];
+                match &self.crt {
+                    Some(crt) => {
+                        crt_row.clear();
+                        crt_row.extend_from_slice(&scaled_row);
+                        crt.apply(&mut crt_row, ipp, order, scale, row, rect.x as usize * scale);
+                        dest.copy_from_slice(&crt_row);
+                    }
+                    None => dest.copy_from_slice(&scaled_row),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_pixel_becomes_a_block() {
+        let mut p = PortionRenderer::<u8>::new_ex(4, 4, 1, 1, PixelFormatEnum::RGBA8888);
+        p.create_object_from_color(0, Rect { x: 1, y: 2, w: 1, h: 1 }, PIXEL_RED);
+        p.draw_all_layers();
+        let upscaler = Upscaler::new(3);
+        assert_eq!(upscaler.target_size(4, 4), (12, 12));
+        let mut target = vec![0; 12 * 12 * 4];
+        let damage = upscaler.present(&mut p, &mut target);
+        assert_eq!(damage, vec![Rect { x: 0, y: 0, w: 12, h: 12 }]);
+        let pixel = |x: usize, y: usize| -> RgbaPixel { (&target[(y * 12 + x) * 4..]).into() };
+        for y in 0..12 {
+            for x in 0..12 {
+                let expected = if (3..6).contains(&x) && (6..9).contains(&y) { PIXEL_RED } else { PIXEL_BLANK };
+                assert_eq!(pixel(x, y), expected, "({}, {})", x, y);
+            }
+        }
+        assert!(upscaler.present(&mut p, &mut target).is_empty());
+    }
+
+    #[test]
+    fn crt_effect_darkens_scanlines_and_masks_channels() {
+        let mut p = PortionRenderer::<u8>::new_ex(4, 4, 1, 1, PixelFormatEnum::RGBA8888);
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 4, h: 4 }, PIXEL_WHITE);
+        p.draw_all_layers();
+        let mut upscaler = Upscaler::new(2);
+        upscaler.set_crt_effect(Some(CrtEffect { scanlines: 0.5, phosphor_mask: 2.0 }));
+        let mut target = vec![0; 8 * 8 * 4];
+        upscaler.present(&mut p, &mut target);
+        let pixel = |x: usize, y: usize| &target[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), &[255, 0, 0, 255]);
+        assert_eq!(pixel(1, 0), &[0, 255, 0, 255]);
+        assert_eq!(pixel(5, 2), &[0, 0, 255, 255]);
+        assert_eq!(pixel(3, 1), &[128, 0, 0, 255]);
+    }
+}
@@ -0,0 +1,344 @@
+use super::*;
+
+/// a region of the screen that shows the world from its own camera,
+/// eg: one half of a split screen. viewports are drawn on top of the
+/// main camera's view, and viewports with a higher index are drawn on
+/// top of viewports with a lower index. they share the camera zoom
+#[derive(Debug, Clone)]
+pub struct Viewport {
+    /// where on the screen the viewport is drawn
+    pub screen: Rect,
+    /// the world coordinate that is drawn at the top left of the viewport
+    pub camera_x: i32,
+    pub camera_y: i32,
+    /// the part of the viewport that each object was drawn to,
+    /// for every object that is visible in the viewport
+    drawn: HashMap<usize, Rect>,
+    /// parts of the viewport to redraw that no object update covers,
+    /// eg: where a removed object was
+    damage: Vec<Rect>,
+    full_redraw: bool,
+}
+
+/// what changed since the last frame, as far as the viewports are concerned.
+/// it has to be collected before the updates get drawn
+#[derive(Debug, Default)]
+pub(crate) struct ViewportDamage {
+    /// the parts of the screen that the main camera draws over
+    screen: Vec<Rect>,
+    updated: Vec<usize>,
+    full: bool,
+}
+
+impl<T> PortionRenderer<T> {
+    pub fn viewport(&self, viewport_index: usize) -> Option<&Viewport> {
+        self.viewports.get(viewport_index)
+    }
+
+    /// the whole of every viewport is drawn again next frame
+    pub(crate) fn redraw_viewports(&mut self) {
+        for (_, viewport) in self.viewports.iter_mut() {
+            viewport.full_redraw = true;
+        }
+    }
+
+    /// the object's pixels are no longer in any viewport
+    pub(crate) fn remove_object_from_viewports(&mut self, object_index: usize) {
+        for (_, viewport) in self.viewports.iter_mut() {
+            if let Some(rect) = viewport.drawn.remove(&object_index) {
+                viewport.damage.push(rect);
+            }
+        }
+    }
+
+    pub(crate) fn remap_viewport_objects(&mut self, remap: &HashMap<usize, usize>) {
+        for (_, viewport) in self.viewports.iter_mut() {
+            viewport.drawn = viewport.drawn.drain()
+                .map(|(index, rect)| (*remap.get(&index).unwrap_or(&index), rect))
+                .collect();
+        }
+    }
+}
+
+impl<T: Clone> PortionRenderer<T> {
+    /// how far the object has to move such that the world coordinate
+    /// (camera_x, camera_y) is at the screen coordinate (origin_x, origin_y)
+    fn layout_offset(&self, object_index: usize, camera_x: i32, camera_y: i32, origin_x: i32, origin_y: i32) -> (i32, i32) {
+        let object = &self.objects[object_index];
+        let (x, y) = self.layer_to_screen(object.layer_index, object.world_x, object.world_y, camera_x, camera_y);
+        (origin_x + x - object.position.x, origin_y + y - object.position.y)
+    }
+
+    /// moves the objects such that the world coordinate
+    /// (camera_x, camera_y) is at the screen coordinate (origin_x, origin_y)
+    fn layout_objects_at(&mut self, object_indices: &[usize], camera_x: i32, camera_y: i32, origin_x: i32, origin_y: i32) {
+        for object_index in object_indices.iter().copied() {
+            let (dx, dy) = self.layout_offset(object_index, camera_x, camera_y, origin_x, origin_y);
+            if dx != 0 || dy != 0 {
+                self.move_object_on_screen_by(object_index, dx, dy);
+                self.update_spatial_index(object_index);
+            }
+        }
+    }
+
+    /// lays out the objects that the viewport shows, and the objects that
+    /// are inside of screen now, which get moved out of the way. the rest
+    /// are left where the main camera sees them, so that their spatial
+    /// index isnt updated for every viewport. masks are always laid out,
+    /// since the objects they mask might be shown. returns the moved objects
+    fn layout_objects_in_viewport(&mut self, camera_x: i32, camera_y: i32, screen: Rect) -> Vec<usize> {
+        let (origin_x, origin_y) = (screen.x as i32, screen.y as i32);
+        let mut moved = vec![];
+        let object_indices: Vec<usize> = self.objects.used_indices().collect();
+        for object_index in object_indices {
+            let (dx, dy) = self.layout_offset(object_index, camera_x, camera_y, origin_x, origin_y);
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+            let in_the_way = self.objects[object_index].get_bounds().intersection(screen).is_some();
+            // moving only changes the position and shape, so
+            // it is undone by moving back if it isnt needed
+            self.move_object_on_screen_by(object_index, dx, dy);
+            let shown = self.objects[object_index].get_bounds().intersection(screen).is_some();
+            if shown || in_the_way || self.masks.contains_key(&object_index) {
+                self.update_spatial_index(object_index);
+                moved.push(object_index);
+            } else {
+                self.move_object_on_screen_by(object_index, -dx, -dy);
+            }
+        }
+        moved
+    }
+}
+
+impl PortionRenderer<u8> {
+    /// adds a viewport that shows the world from (camera_x, camera_y)
+    /// at the screen rect. it is drawn on the next draw_all_layers.
+    /// panics if the rect is not on the screen
+    pub fn add_viewport(&mut self, screen: Rect, camera_x: i32, camera_y: i32) -> usize {
+        self.log_command(|| RenderCommand::AddViewport { screen, camera_x, camera_y });
+        if screen.x + screen.w > self.width || screen.y + screen.h > self.height {
+            panic!("Viewport {:?} is not inside of the {}x{} screen", screen, self.width, self.height);
+        }
+        self.viewports.insert(Viewport {
+            screen,
+            camera_x,
+            camera_y,
+            drawn: HashMap::new(),
+            damage: vec![],
+            full_redraw: true,
+        })
+    }
+
+    /// the main camera's view is drawn where the viewport was, right away
+    pub fn remove_viewport(&mut self, viewport_index: usize) {
+        self.log_command(|| RenderCommand::RemoveViewport(viewport_index));
+        let viewport = match self.viewports.take(viewport_index) {
+            Some(viewport) => viewport,
+            None => return,
+        };
+        if !viewport.screen.is_empty() {
+            self.composite_region(viewport.screen);
+        }
+        // the main camera's view might have covered other viewports
+        self.redraw_viewports();
+    }
+
+    /// moves the viewport's camera to (x, y) in world coordinates.
+    /// the whole viewport is drawn again on the next draw_all_layers
+    pub fn set_viewport_camera(&mut self, viewport_index: usize, x: i32, y: i32) {
+        self.log_command(|| RenderCommand::SetViewportCamera { viewport_index, x, y });
+        let viewport = &mut self.viewports[viewport_index];
+        viewport.camera_x = x;
+        viewport.camera_y = y;
+        viewport.full_redraw = true;
+    }
+
+    /// collects what the viewports need to redraw, before the updates
+    /// are drawn, since drawing them forgets where they were
+    pub(crate) fn take_viewport_damage(&mut self, full: bool) -> ViewportDamage {
+        if self.viewports.used_len() == 0 {
+            return ViewportDamage::default();
+        }
+        let mut damage = ViewportDamage { full, ..Default::default() };
+        damage.screen.extend(self.removed_regions.iter().copied());
+        for layer in self.layers.iter() {
+            for object_index in layer.updates.iter().copied() {
+                let object = &self.objects[object_index];
+                if !object.initial_render {
+                    damage.screen.push(object.previous_bounds);
+                }
+                damage.screen.push(object.get_bounds());
+                damage.updated.push(object_index);
+            }
+        }
+        damage
+    }
+
+    /// draws the parts of each viewport that changed, with every
+    /// object moved to where that viewport's camera sees it.
+    /// only the pixels inside of a viewport are drawn to
+    pub(crate) fn draw_viewports(&mut self, damage: ViewportDamage) {
+        if self.viewports.used_len() == 0 {
+            return;
+        }
+        // whatever was drawn so far has to be drawn over
+        // by every viewport that it overlaps
+        let mut written = damage.screen;
+        let viewport_indices: Vec<usize> = self.viewports.used_indices().collect();
+        for viewport_index in viewport_indices {
+            let (screen, camera_x, camera_y) = {
+                let v = &self.viewports[viewport_index];
+                (v.screen, v.camera_x, v.camera_y)
+            };
+            if screen.is_empty() {
+                continue;
+            }
+            let moved = self.layout_objects_in_viewport(camera_x, camera_y, screen);
+            let objects = &self.objects;
+            let viewport = &mut self.viewports[viewport_index];
+            let mut redraw = vec![];
+            if damage.full || viewport.full_redraw {
+                redraw.push(screen);
+                viewport.drawn.clear();
+                for (object_index, object) in objects.iter() {
+                    if let Some(rect) = object.get_bounds().intersection(screen) {
+                        viewport.drawn.insert(object_index, rect);
+                    }
+                }
+            } else {
+                redraw.extend(written.iter().filter_map(|r| r.intersection(screen)));
+                redraw.append(&mut viewport.damage);
+                for object_index in damage.updated.iter().copied() {
+                    if let Some(rect) = viewport.drawn.remove(&object_index) {
+                        redraw.push(rect);
+                    }
+                    if let Some(rect) = objects[object_index].get_bounds().intersection(screen) {
+                        viewport.drawn.insert(object_index, rect);
+                        redraw.push(rect);
+                    }
+                }
+            }
+            viewport.damage.clear();
+            viewport.full_redraw = false;
+            for rect in redraw.iter() {
+                self.composite_region(*rect);
+            }
+            written.extend(redraw);
+            self.layout_objects_at(&moved, self.camera_x, self.camera_y, 0, 0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scene(p: &mut PortionRenderer<u8>) -> (usize, usize) {
+        p.create_object_from_color(0, Rect { x: 0, y: 0, w: 40, h: 40 }, PIXEL_BLUE);
+        let red = p.create_object_from_color(1, Rect { x: 2, y: 2, w: 3, h: 2 }, PIXEL_RED);
+        let green = p.create_object_from_color(1, Rect { x: 20, y: 1, w: 2, h: 3 }, PIXEL_GREEN);
+        (red, green)
+    }
+
+    /// the pixels of rect in p
+    fn pixels_in(p: &PortionRenderer<u8>, rect: Rect) -> Vec<RgbaPixel> {
+        (rect.y..rect.y + rect.h)
+            .flat_map(|y| (rect.x..rect.x + rect.w).map(move |x| (x, y)))
+            .map(|(x, y)| p[(x, y)].into())
+            .collect()
+    }
+
+    #[test]
+    fn each_viewport_shows_its_own_camera() {
+        let mut p = PortionRenderer::<u8>::new(12, 8);
+        let (red, green) = scene(&mut p);
+        let left = Rect { x: 0, y: 0, w: 6, h: 8 };
+        let right = Rect { x: 6, y: 0, w: 6, h: 8 };
+        p.add_viewport(left, 0, 0);
+        let follow_green = p.add_viewport(right, 19, 0);
+        p.draw_all_layers();
+        assert_eq!(p.take_dirty_rects(), vec![Rect { x: 0, y: 0, w: 12, h: 8 }]);
+
+        // the left half is what the main camera sees, and the right half
+        // is what a camera at (19, 0) would see on a 6x8 screen
+        let mut main = PortionRenderer::<u8>::new(12, 8);
+        scene(&mut main);
+        main.draw_all_layers();
+        assert_eq!(pixels_in(&p, left), pixels_in(&main, left));
+        let mut follow = PortionRenderer::<u8>::new(12, 8);
+        follow.set_camera_position(19, 0);
+        scene(&mut follow);
+        follow.draw_all_layers();
+        assert_eq!(pixels_in(&p, right), pixels_in(&follow, left));
+
+        // updates are only drawn where the viewports see them
+        p.move_object_x_by(green, 1);
+        p.move_object_y_by(red, 1);
+        p.set_viewport_camera(follow_green, 20, 1);
+        p.draw_all_layers();
+        main.move_object_y_by(red, 1);
+        main.move_object_x_by(green, 1);
+        main.draw_all_layers();
+        follow.move_object_y_by(red, 1);
+        follow.move_object_x_by(green, 1);
+        follow.set_camera_position(20, 1);
+        follow.draw_all_layers();
+        assert_eq!(pixels_in(&p, left), pixels_in(&main, left));
+        assert_eq!(pixels_in(&p, right), pixels_in(&follow, left));
+        // objects are back where the main camera sees them
+        assert_eq!(p.objects[green].position.x, 21);
+    }
+
+    #[test]
+    fn only_the_objects_in_a_viewport_are_laid_out() {
+        let mut p = PortionRenderer::<u8>::new(12, 8);
+        let (red, green) = scene(&mut p);
+        // inside of the viewport for the main camera, but not for its own
+        let in_the_way = p.create_object_from_color(1, Rect { x: 8, y: 5, w: 1, h: 1 }, PIXEL_RED);
+        let right = Rect { x: 6, y: 0, w: 6, h: 8 };
+        let mut moved = p.layout_objects_in_viewport(19, 0, right);
+        moved.sort();
+        // the blue background is the only other object that the viewport
+        // shows, and red is at (-11, 2) in the viewport, so it stays
+        assert_eq!(moved, vec![0, green, in_the_way]);
+        assert_eq!(p.objects[red].position.x, 2);
+        assert_eq!(p.objects[green].position.x, 7);
+        assert_eq!(p.objects[in_the_way].position.x, -5);
+        p.layout_objects_at(&moved, 0, 0, 0, 0);
+        assert_eq!(p.objects[in_the_way].position.x, 8);
+
+        p.add_viewport(right, 19, 0);
+        p.draw_all_layers();
+        let mut follow = PortionRenderer::<u8>::new(12, 8);
+        follow.set_camera_position(19, 0);
+        scene(&mut follow);
+        follow.draw_all_layers();
+        assert_eq!(pixels_in(&p, right), pixels_in(&follow, Rect { x: 0, y: 0, w: 6, h: 8 }));
+    }
+
+    #[test]
+    fn only_the_damaged_part_of_a_viewport_is_redrawn() {
+        let mut p = PortionRenderer::<u8>::new(12, 8);
+        let (red, _) = scene(&mut p);
+        p.add_viewport(Rect { x: 4, y: 4, w: 8, h: 4 }, 0, 0);
+        p.draw_all_layers();
+        p.take_dirty_rects();
+
+        // the red rect is at (6, 6) in the viewport, and at (2, 2)
+        // for the main camera, which doesnt overlap the viewport
+        p.remove_object(red);
+        p.draw_all_layers();
+        // new splits the 12x8 screen into 4x4 portions of 3x2 pixels. red
+        // was on the portions at columns 0-1 of row 1 for the main camera
+        // (x 2..5, y 2..4), and at column 2 of row 3 in the viewport
+        // (x 6..9, y 6..8). nothing else changed, so only those are dirty
+        assert_eq!(p.take_dirty_rects(), vec![
+            Rect { x: 0, y: 2, w: 6, h: 2 },
+            Rect { x: 6, y: 6, w: 3, h: 2 },
+        ]);
+        let blue = vec![PIXEL_BLUE; 32];
+        assert_eq!(pixels_in(&p, Rect { x: 4, y: 4, w: 8, h: 4 }), blue);
+        assert_eq!(p[(2, 2)], p[(0, 0)]);
+    }
+}
@@ -0,0 +1,101 @@
+use super::Texture;
+
+/// the three planes of a YUV 4:2:0 frame, eg: from a video decoder.
+/// the u and v planes are half the width and height of the y plane,
+/// rounded up. the strides are the bytes per row of each plane,
+/// which decoders often pad past the width
+#[derive(Debug, Copy, Clone)]
+pub struct Yuv420Planes<'a> {
+    pub y: &'a [u8],
+    pub u: &'a [u8],
+    pub v: &'a [u8],
+    pub y_stride: usize,
+    pub uv_stride: usize,
+}
+
+impl<'a> Yuv420Planes<'a> {
+    /// planes without any padding between rows
+    pub fn packed(y: &'a [u8], u: &'a [u8], v: &'a [u8], width: u32) -> Yuv420Planes<'a> {
+        Yuv420Planes {
+            y, u, v,
+            y_stride: width as usize,
+            uv_stride: (width as usize).div_ceil(2),
+        }
+    }
+}
+
+#[inline(always)]
+fn clamp_channel(value: i32) -> u8 {
+    value.clamp(0, 255) as u8
+}
+
+/// BT.601 limited range, in 8 bit fixed point. there are no branches
+/// other than the clamps, so this auto-vectorizes well
+#[inline(always)]
+fn yuv_to_rgba(y: u8, u: u8, v: u8, dest: &mut [u8]) {
+    let c = 298 * (y as i32 - 16);
+    let d = u as i32 - 128;
+    let e = v as i32 - 128;
+    dest[0] = clamp_channel((c + 409 * e + 128) >> 8);
+    dest[1] = clamp_channel((c - 100 * d - 208 * e + 128) >> 8);
+    dest[2] = clamp_channel((c + 516 * d + 128) >> 8);
+    dest[3] = 255;
+}
+
+impl Texture<u8> {
+    /// converts a YUV 4:2:0 frame to a RGBA8888 texture
+    pub fn from_yuv420(planes: Yuv420Planes, width: u32, height: u32) -> Texture<u8> {
+        let mut texture = Texture::new(vec![0; width as usize * height as usize * 4], width, height);
+        texture.write_yuv420(planes);
+        texture
+    }
+
+    /// converts a YUV 4:2:0 frame that is the same size as this
+    /// texture into it, so that each frame of a video
+    /// can reuse the same texture allocation
+    pub fn write_yuv420(&mut self, planes: Yuv420Planes) {
+        let width = self.width as usize;
+        for (row, dest_row) in self.data.chunks_exact_mut(width * 4).enumerate() {
+            let y_row = &planes.y[row * planes.y_stride..row * planes.y_stride + width];
+            let uv_start = (row / 2) * planes.uv_stride;
+            let u_row = &planes.u[uv_start..];
+            let v_row = &planes.v[uv_start..];
+            // each u and v sample covers 2 pixels of the row
+            let pairs = dest_row.chunks_exact_mut(8).zip(y_row.chunks_exact(2)).zip(u_row.iter().zip(v_row.iter()));
+            for ((dest, ys), (u, v)) in pairs {
+                yuv_to_rgba(ys[0], *u, *v, &mut dest[0..4]);
+                yuv_to_rgba(ys[1], *u, *v, &mut dest[4..8]);
+            }
+            if width % 2 == 1 {
+                let last = width - 1;
+                yuv_to_rgba(y_row[last], u_row[last / 2], v_row[last / 2], &mut dest_row[last * 4..]);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_yuv420_converts_each_pixel() {
+        // 3x2, so the last column has its own chroma sample
+        let y = [16, 235, 81, 16, 235, 81];
+        let u = [128, 90];
+        let v = [128, 240];
+        let texture = Texture::from_yuv420(Yuv420Planes::packed(&y, &u, &v, 3), 3, 2);
+        assert_eq!(&texture.data[0..4], &[0, 0, 0, 255]);
+        assert_eq!(&texture.data[4..8], &[255, 255, 255, 255]);
+        // bt.601 red
+        assert_eq!(&texture.data[8..12], &[255, 0, 0, 255]);
+        assert_eq!(&texture.data[12..16], &texture.data[0..4]);
+
+        // padded rows give the same result
+        let y = [16, 235, 81, 0, 16, 235, 81, 0];
+        let u = [128, 90, 0];
+        let v = [128, 240, 0];
+        let planes = Yuv420Planes { y: &y, u: &u, v: &v, y_stride: 4, uv_stride: 3 };
+        assert_eq!(Texture::from_yuv420(planes, 3, 2).data, texture.data);
+    }
+}
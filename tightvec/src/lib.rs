@@ -2,25 +2,41 @@ use std::ops::{IndexMut, Index};
 use std::collections::VecDeque;
 
 
-#[derive(Default)]
+/// a vec where removing a value leaves an empty slot instead of
+/// shifting everything after it, so indices stay valid as handles.
+/// empty slots are reused by the next inserts
+#[derive(Clone)]
 pub struct TightVec<T> {
-    buf: Vec<T>,
+    buf: Vec<Option<T>>,
     next: VecDeque<usize>,
 }
 
+impl<T> Default for TightVec<T> {
+    fn default() -> Self {
+        TightVec::new()
+    }
+}
+
+/// panics if the index is out of range, or its slot is empty
 impl<T> Index<usize> for TightVec<T> {
     type Output = T;
 
     #[inline(always)]
     fn index(&self, index: usize) -> &Self::Output {
-        &self.buf[index]
+        match &self.buf[index] {
+            Some(value) => value,
+            None => panic!("TightVec index {} was removed", index),
+        }
     }
 }
 
 impl<T> IndexMut<usize> for TightVec<T> {
     #[inline(always)]
     fn index_mut(&mut self, index: usize) -> &mut Self::Output {
-        &mut self.buf[index]
+        match &mut self.buf[index] {
+            Some(value) => value,
+            None => panic!("TightVec index {} was removed", index),
+        }
     }
 }
 
@@ -35,12 +51,12 @@ impl<T> TightVec<T> {
     pub fn insert(&mut self, value: T) -> usize {
         match self.next.pop_front() {
             Some(index) => {
-                self.buf[index] = value;
+                self.buf[index] = Some(value);
                 index
             }
             None => {
                 let index = self.buf.len();
-                self.buf.push(value);
+                self.buf.push(Some(value));
                 index
             }
         }
@@ -60,20 +76,116 @@ impl<T> TightVec<T> {
         self.next.len()
     }
 
-    pub fn replace_with(&mut self, index: usize, replace: T) {
-        if self.buf.len() > index {
-            self.buf[index] = replace;
-            self.next.push_back(index);
-        }
+    /// returns None if the index is out of range or was removed
+    #[inline(always)]
+    pub fn get(&self, index: usize) -> Option<&T> {
+        self.buf.get(index)?.as_ref()
+    }
+
+    #[inline(always)]
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut T> {
+        self.buf.get_mut(index)?.as_mut()
+    }
+
+    /// the used slots and their indices, in index order.
+    /// removed slots are skipped
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.buf.iter().enumerate().filter_map(|(i, value)| Some((i, value.as_ref()?)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (usize, &mut T)> {
+        self.buf.iter_mut().enumerate().filter_map(|(i, value)| Some((i, value.as_mut()?)))
+    }
+
+    pub fn used_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        self.iter().map(|(i, _)| i)
+    }
+
+    /// removes the value at index and returns it, leaving the slot
+    /// empty for the next insert. returns None if the index is
+    /// out of range or was already removed
+    pub fn take(&mut self, index: usize) -> Option<T> {
+        let value = self.buf.get_mut(index)?.take()?;
+        self.next.push_back(index);
+        Some(value)
     }
-}
 
-impl<T: Default> TightVec<T> {
     pub fn remove(&mut self, index: usize) {
-        if self.buf.len() > index {
-            self.buf[index] = T::default();
-            self.next.push_back(index);
+        self.take(index);
+    }
+
+    /// removes every value that keep returns false for.
+    /// the indices of the kept values dont change
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut keep: F) {
+        for (index, slot) in self.buf.iter_mut().enumerate() {
+            let remove = match slot {
+                Some(value) => !keep(value),
+                None => false,
+            };
+            if remove {
+                *slot = None;
+                self.next.push_back(index);
+            }
+        }
+    }
+
+    /// removes every value, and returns them with the index they had
+    pub fn drain_used(&mut self) -> Vec<(usize, T)> {
+        let mut drained = Vec::with_capacity(self.used_len());
+        for (index, slot) in self.buf.iter_mut().enumerate() {
+            if let Some(value) = slot.take() {
+                drained.push((index, value));
+                self.next.push_back(index);
+            }
         }
+        drained
+    }
+
+    /// moves the used values down into the removed slots, keeping their
+    /// order, and shrinks the buffer so that there are no removed slots.
+    /// moved is called with (old_index, new_index) for every value
+    /// whose index changed, so that handles to it can be updated
+    pub fn compact<F: FnMut(usize, usize)>(&mut self, mut moved: F) {
+        let mut new_index = 0;
+        for old_index in 0..self.buf.len() {
+            if self.buf[old_index].is_none() {
+                continue;
+            }
+            if old_index != new_index {
+                self.buf.swap(old_index, new_index);
+                moved(old_index, new_index);
+            }
+            new_index += 1;
+        }
+        self.buf.truncate(new_index);
+        self.buf.shrink_to_fit();
+        self.next.clear();
+    }
+
+    /// how many slots fit before the buffer has to grow
+    pub fn capacity(&self) -> usize {
+        self.buf.capacity()
+    }
+
+    /// drops the removed slots at the end of the buffer, and frees
+    /// the capacity that isnt used. unlike compact, no value moves,
+    /// so the removed slots between used values are kept
+    pub fn shrink_to_fit(&mut self) {
+        while let Some(None) = self.buf.last() {
+            self.buf.pop();
+        }
+        let len = self.buf.len();
+        self.next.retain(|index| *index < len);
+        self.buf.shrink_to_fit();
+        self.next.shrink_to_fit();
+    }
+
+    /// removed slots no longer hold a placeholder value,
+    /// so this is the same as remove, and replace is dropped
+    #[deprecated(note = "use take or remove instead")]
+    pub fn replace_with(&mut self, index: usize, replace: T) {
+        drop(replace);
+        self.remove(index);
     }
 }
 
@@ -111,7 +223,7 @@ mod tests {
         assert_eq!(t.len(), 3);
 
         t.remove(1);
-        assert_eq!(&t[1], &SimpleData::Empty);
+        assert!(t.take(1).is_none());
         assert_eq!(t.len(), 3);
         assert_eq!(t.used_len(), 2);
         assert_eq!(t.unused_len(), 1);
@@ -130,5 +242,107 @@ mod tests {
         t.remove(0);
         t.remove(1);
         t.remove(100000);
+        // removing twice doesnt free the slot twice
+        assert_eq!(t.unused_len(), 1);
+    }
+
+    #[test]
+    fn iter_skips_removed_slots() {
+        let mut t = TightVec::new();
+        for i in 0..5 {
+            t.insert(i * 10);
+        }
+        t.remove(1);
+        t.remove(3);
+        assert_eq!(t.iter().collect::<Vec<_>>(), vec![(0, &0), (2, &20), (4, &40)]);
+        assert_eq!(t.used_indices().collect::<Vec<_>>(), vec![0, 2, 4]);
+        for (_, value) in t.iter_mut() {
+            *value += 1;
+        }
+        assert_eq!(t[4], 41);
+        assert_eq!(t.iter().count(), t.used_len());
+    }
+
+    #[test]
+    fn retain_and_drain_free_slots() {
+        let mut t = TightVec::new();
+        for i in 0..6 {
+            t.insert(i);
+        }
+        t.remove(0);
+        t.retain(|v| v % 2 == 0);
+        assert_eq!(t.used_indices().collect::<Vec<_>>(), vec![2, 4]);
+        assert_eq!(t.unused_len(), 4);
+
+        assert_eq!(t.drain_used(), vec![(2, 2), (4, 4)]);
+        assert_eq!(t.used_len(), 0);
+        assert!(t.drain_used().is_empty());
+        // every slot gets reused before the buffer grows
+        for i in 0..6 {
+            t.insert(i);
+        }
+        assert_eq!(t.len(), 6);
+    }
+
+    #[test]
+    fn get_returns_none_for_bad_indices() {
+        let mut t = TightVec::new();
+        t.insert('a');
+        t.insert('b');
+        t.remove(0);
+        assert_eq!(t.get(0), None);
+        assert_eq!(t.get(1), Some(&'b'));
+        assert_eq!(t.get(2), None);
+        *t.get_mut(1).unwrap() = 'c';
+        assert_eq!(t[1], 'c');
+        assert!(t.get_mut(0).is_none());
+    }
+
+    #[test]
+    fn compact_fills_removed_slots() {
+        let mut t = TightVec::new();
+        for c in "abcde".chars() {
+            t.insert(c);
+        }
+        t.remove(0);
+        t.remove(2);
+        let mut moves = vec![];
+        t.compact(|old, new| moves.push((old, new)));
+        assert_eq!(moves, vec![(1, 0), (3, 1), (4, 2)]);
+        assert_eq!(t.iter().collect::<Vec<_>>(), vec![(0, &'b'), (1, &'d'), (2, &'e')]);
+        assert_eq!(t.len(), 3);
+        assert_eq!(t.unused_len(), 0);
+        assert_eq!(t.insert('f'), 3);
+    }
+
+    #[test]
+    fn shrink_to_fit_drops_trailing_removed_slots() {
+        let mut t = TightVec::new();
+        for c in "abcde".chars() {
+            t.insert(c);
+        }
+        t.remove(1);
+        t.remove(3);
+        t.remove(4);
+        t.shrink_to_fit();
+        assert_eq!(t.len(), 3);
+        assert_eq!(t.capacity(), 3);
+        assert_eq!(t.iter().collect::<Vec<_>>(), vec![(0, &'a'), (2, &'c')]);
+        assert_eq!(t.insert('f'), 1);
+        assert_eq!(t.insert('g'), 3);
+    }
+
+    #[test]
+    fn take_works_without_default() {
+        struct NoDefault(u32);
+        let mut t = TightVec::new();
+        let a = t.insert(NoDefault(1));
+        let b = t.insert(NoDefault(2));
+        assert_eq!(t.take(a).map(|v| v.0), Some(1));
+        assert!(t.take(a).is_none());
+        assert!(t.take(5).is_none());
+        assert_eq!(t[b].0, 2);
+        assert_eq!(t.insert(NoDefault(3)), a);
+        assert_eq!(t.used_len(), 2);
     }
 }